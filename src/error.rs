@@ -8,7 +8,6 @@ use std::result;
 pub type Result<T> = result::Result<T, RUSQLError>;
 
 #[derive(Error, Debug, PartialEq)]
-#[allow(dead_code)]
 pub enum RUSQLError {
     #[error("Not Implemented error: {0}")]
     NotImplemented(String),
@@ -20,4 +19,16 @@ pub enum RUSQLError {
     UnknownCommand(String),
     #[error("Parser error: {0:?}")]
     SqlError(#[from] ParserError),
+    #[error("IO error: {0}")]
+    Io(String),
+    /// A row would duplicate the value of a UNIQUE column or the key of a table-level
+    /// UNIQUE or PRIMARY KEY constraint.
+    #[error("Key violation: {0}")]
+    KeyViolation(String),
+}
+
+impl From<std::io::Error> for RUSQLError {
+    fn from(err: std::io::Error) -> Self {
+        RUSQLError::Io(err.to_string())
+    }
 }
@@ -10,7 +10,7 @@
 //! 1. **REPL Layer** - Interactive command-line interface
 //! 2. **Parser Layer** - SQL statement parsing using sqlparser-rs
 //! 3. **Execution Layer** - Statement execution and validation
-//! 4. **Storage Layer** - In-memory data structures (BTree-based)
+//! 4. **Storage Layer** - Column B+trees in a paged database file behind an LRU buffer pool, or in-memory BTreeMaps
 //!
 //! ## Core Components
 //!
@@ -59,3 +59,5 @@ pub mod util;
 pub use error::{RUSQLError, Result};
 pub use sql::db::database::Database;
 pub use sql::db::table::{Column, DataType, Table};
+pub use sql::db::value::Value;
+pub use sql::executor::result_set::ResultSet;
@@ -1,29 +1,24 @@
-extern crate clap;
-extern crate colored;
-extern crate prettytable;
-mod error;
-mod meta_command;
-mod repl;
-mod replloop;
-mod sql;
-mod util;
-
-use repl::{get_config, REPLHelper};
-use replloop::run_repl_loop;
-use sql::db::database::Database;
-use util::intro::print_intro;
+use rusql::repl::{get_config, REPLHelper};
+use rusql::replloop::run_repl_loop;
+use rusql::sql::db::database::Database;
+use rusql::util::intro::print_intro;
 
 use rustyline::Editor;
 
-use clap::{crate_authors, crate_description, crate_name, crate_version, Command};
+use clap::{crate_authors, crate_description, crate_name, crate_version, Arg, Command};
 
 fn main() -> rustyline::Result<()> {
     env_logger::init();
 
-    let _matches = Command::new(crate_name!())
+    let matches = Command::new(crate_name!())
         .version(crate_version!())
         .author(crate_authors!())
         .about(crate_description!())
+        .arg(
+            Arg::new("database")
+                .value_name("FILE")
+                .help("Database file to open; it is created on exit if it does not exist"),
+        )
         .get_matches();
 
     let config = get_config();
@@ -34,7 +29,7 @@ fn main() -> rustyline::Result<()> {
         Ok(editor) => editor,
         Err(e) => {
             eprintln!("Error initializing editor: {}", e);
-            return Err(From::from(e));
+            return Err(e);
         }
     };
 
@@ -46,7 +41,16 @@ fn main() -> rustyline::Result<()> {
 
     print_intro();
 
-    let db = Database::new("tempdbase".to_string());
+    let db = match matches.get_one::<String>("database") {
+        Some(path) => match Database::open(path) {
+            Ok(db) => db,
+            Err(err) => {
+                eprintln!("Error opening database {}: {}", path, err);
+                std::process::exit(1);
+            }
+        },
+        None => Database::new("tempdbase".to_string()),
+    };
 
     run_repl_loop(repl, db)?;
 
@@ -2,6 +2,7 @@
 
 use crate::error::{RUSQLError, Result};
 use crate::repl::REPLHelper;
+use crate::sql::db::database::Database;
 use rustyline::history::FileHistory;
 use rustyline::Editor;
 use std::fmt;
@@ -42,16 +43,19 @@ impl MetaCommand {
 pub fn handle_meta_command(
     command: MetaCommand,
     repl: &mut Editor<REPLHelper, FileHistory>,
+    db: &mut Database,
 ) -> Result<String> {
     match command {
         MetaCommand::Exit => {
+            // Stay in the REPL if the database could not be written, so nothing is lost.
+            db.close()?;
             repl.append_history("history").unwrap();
             std::process::exit(0);
         }
         MetaCommand::Help => Ok(format!(
             "{}{}{}{}{}{}",
             "Special Commands: \n",
-            ".exit: Exit the REPL\n",
+            ".exit: Roll back an open transaction, save the database file (if one was opened) and exit the REPL\n",
             ".help: Display this help message\n",
             ".cls:  Clear the screen\n",
             "RUSQL is a simple SQL database engine written in Rust.\n",
@@ -60,7 +64,7 @@ pub fn handle_meta_command(
         MetaCommand::Cls => {
             if cfg!(target_os = "windows") {
                 ProcessCommand::new("cmd")
-                    .args(&["/C", "cls"])
+                    .args(["/C", "cls"])
                     .status()
                     .unwrap();
             } else {
@@ -68,8 +72,8 @@ pub fn handle_meta_command(
             }
             Ok("".to_string())
         }
-        MetaCommand::Unknown => Err(RUSQLError::UnknownCommand(format!(
-            "Unknown command or Invalid syntax. Type .help for more information."
-        ))),
+        MetaCommand::Unknown => Err(RUSQLError::UnknownCommand(
+            "Unknown command or Invalid syntax. Type .help for more information.".to_string(),
+        )),
     }
 }
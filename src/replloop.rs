@@ -23,13 +23,13 @@ pub fn run_repl_loop(
                 }
                 match get_command_type(&command.trim().to_owned()) {
                     CommandType::MetaCommand(cmd) => {
-                        let _ = match handle_meta_command(cmd, &mut repl) {
+                        match handle_meta_command(cmd, &mut repl, &mut db) {
                             Ok(msg) => println!("{}", msg),
                             Err(err) => eprintln!("An error occured: {}", err),
                         };
                     }
                     CommandType::SQLCommand(_cmd) => {
-                        let _ = match process_command(&command, &mut db) {
+                        match process_command(&command, &mut db) {
                             Ok(msg) => println!("{}", msg),
                             Err(err) => eprintln!("An error occured: {}", err),
                         };
@@ -50,6 +50,9 @@ pub fn run_repl_loop(
             }
         }
     }
+    if let Err(err) = db.close() {
+        eprintln!("Error saving database: {}", err);
+    }
     if let Err(err) = repl.save_history("history") {
         println!("Error saving history: {:?}", err);
     }
@@ -0,0 +1,477 @@
+//! B+tree keyed by rowid, stored in pager pages.
+//!
+//! Leaves hold `(rowid, value)` cells in key order and are linked left to right for
+//! scans; interior pages hold separator keys and child pointers. Values longer than
+//! `MAX_INLINE` bytes are moved to a chain of overflow pages, so any value fits.
+//!
+//! ```text
+//! leaf:     | 1 | cell count (2) | next leaf (4) | key (8) len (2) bytes ... |
+//! interior: | 2 | key count (2)  | last child (4) | child (4) key (8) ...    |
+//! overflow: | next page (4) | bytes ... |
+//! ```
+//!
+//! A child left of separator `k` holds keys `< k`. The root page never moves: when it
+//! splits, its contents are copied to a new page and the root becomes their parent.
+//! Deletes do not merge pages, so a tree that shrinks keeps its underfull pages.
+
+use crate::error::{RUSQLError, Result};
+use crate::sql::db::pager::{read_u32, PageId, Pager, PAGE_SIZE};
+use serde::{Deserialize, Serialize};
+
+const LEAF: u8 = 1;
+const INTERIOR: u8 = 2;
+const NODE_HEADER: usize = 7;
+const LEAF_CELL_HEADER: usize = 10;
+const INTERIOR_CELL: usize = 12;
+/// Largest value stored inside a leaf; at least four cells fit in a page.
+pub const MAX_INLINE: usize = (PAGE_SIZE - NODE_HEADER) / 4 - LEAF_CELL_HEADER;
+/// Length marker of a cell whose value lives in overflow pages.
+const OVERFLOW: u16 = u16::MAX;
+const OVERFLOW_CHUNK: usize = PAGE_SIZE - 4;
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct BTree {
+    root: PageId,
+}
+
+#[derive(Debug, Clone)]
+enum Payload {
+    Inline(Vec<u8>),
+    Overflow { len: u32, first: PageId },
+}
+
+#[derive(Debug)]
+enum Node {
+    Leaf {
+        cells: Vec<(i64, Payload)>,
+        next: PageId,
+    },
+    /// `children.len() == keys.len() + 1`.
+    Interior {
+        keys: Vec<i64>,
+        children: Vec<PageId>,
+    },
+}
+
+impl Node {
+    fn decode(page: &[u8]) -> Result<Node> {
+        let count = u16::from_le_bytes([page[1], page[2]]) as usize;
+        let link = read_u32(page, 3);
+        let mut pos = NODE_HEADER;
+
+        match page[0] {
+            LEAF => {
+                let mut cells = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let key = read_i64(page, pos);
+                    let len = u16::from_le_bytes([page[pos + 8], page[pos + 9]]);
+                    pos += LEAF_CELL_HEADER;
+                    if len == OVERFLOW {
+                        cells.push((
+                            key,
+                            Payload::Overflow {
+                                len: read_u32(page, pos),
+                                first: read_u32(page, pos + 4),
+                            },
+                        ));
+                        pos += 8;
+                    } else {
+                        let len = len as usize;
+                        cells.push((key, Payload::Inline(page[pos..pos + len].to_vec())));
+                        pos += len;
+                    }
+                }
+                Ok(Node::Leaf { cells, next: link })
+            }
+            INTERIOR => {
+                let mut keys = Vec::with_capacity(count);
+                let mut children = Vec::with_capacity(count + 1);
+                for _ in 0..count {
+                    children.push(read_u32(page, pos));
+                    keys.push(read_i64(page, pos + 4));
+                    pos += INTERIOR_CELL;
+                }
+                children.push(link);
+                Ok(Node::Interior { keys, children })
+            }
+            kind => Err(RUSQLError::General(format!(
+                "Corrupt database file: unknown page type {}.",
+                kind
+            ))),
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut page = Vec::with_capacity(self.size());
+        match self {
+            Node::Leaf { cells, next } => {
+                page.push(LEAF);
+                page.extend_from_slice(&(cells.len() as u16).to_le_bytes());
+                page.extend_from_slice(&next.to_le_bytes());
+                for (key, payload) in cells {
+                    page.extend_from_slice(&key.to_le_bytes());
+                    match payload {
+                        Payload::Inline(bytes) => {
+                            page.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+                            page.extend_from_slice(bytes);
+                        }
+                        Payload::Overflow { len, first } => {
+                            page.extend_from_slice(&OVERFLOW.to_le_bytes());
+                            page.extend_from_slice(&len.to_le_bytes());
+                            page.extend_from_slice(&first.to_le_bytes());
+                        }
+                    }
+                }
+            }
+            Node::Interior { keys, children } => {
+                page.push(INTERIOR);
+                page.extend_from_slice(&(keys.len() as u16).to_le_bytes());
+                page.extend_from_slice(&children[keys.len()].to_le_bytes());
+                for (key, child) in keys.iter().zip(children) {
+                    page.extend_from_slice(&child.to_le_bytes());
+                    page.extend_from_slice(&key.to_le_bytes());
+                }
+            }
+        }
+        page
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Node::Leaf { cells, .. } => {
+                NODE_HEADER + cells.iter().map(|(_, p)| cell_size(p)).sum::<usize>()
+            }
+            Node::Interior { keys, .. } => NODE_HEADER + keys.len() * INTERIOR_CELL,
+        }
+    }
+}
+
+fn cell_size(payload: &Payload) -> usize {
+    LEAF_CELL_HEADER
+        + match payload {
+            Payload::Inline(bytes) => bytes.len(),
+            Payload::Overflow { .. } => 8,
+        }
+}
+
+fn read_i64(bytes: &[u8], offset: usize) -> i64 {
+    i64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_node(pager: &mut Pager, page: PageId) -> Result<Node> {
+    Node::decode(pager.read(page)?)
+}
+
+fn write_node(pager: &mut Pager, page: PageId, node: &Node) -> Result<()> {
+    pager.write(page, &node.encode())
+}
+
+impl BTree {
+    /// Allocates an empty tree.
+    pub fn create(pager: &mut Pager) -> Result<BTree> {
+        let root = pager.allocate()?;
+        write_node(
+            pager,
+            root,
+            &Node::Leaf {
+                cells: vec![],
+                next: 0,
+            },
+        )?;
+        Ok(BTree { root })
+    }
+
+    /// A tree whose root page is already in the file.
+    pub fn open(root: PageId) -> BTree {
+        BTree { root }
+    }
+
+    pub fn root(&self) -> PageId {
+        self.root
+    }
+
+    pub fn get(&self, pager: &mut Pager, key: i64) -> Result<Option<Vec<u8>>> {
+        let leaf = self.find_leaf(pager, key)?;
+        if let Node::Leaf { cells, .. } = read_node(pager, leaf)? {
+            if let Ok(idx) = cells.binary_search_by_key(&key, |(k, _)| *k) {
+                return read_payload(pager, &cells[idx].1).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn contains(&self, pager: &mut Pager, key: i64) -> Result<bool> {
+        let leaf = self.find_leaf(pager, key)?;
+        match read_node(pager, leaf)? {
+            Node::Leaf { cells, .. } => Ok(cells.binary_search_by_key(&key, |(k, _)| *k).is_ok()),
+            Node::Interior { .. } => Ok(false),
+        }
+    }
+
+    /// Inserts `value` under `key`, replacing any value already stored for it.
+    pub fn insert(&self, pager: &mut Pager, key: i64, value: &[u8]) -> Result<()> {
+        let payload = write_payload(pager, value)?;
+        if let Some((separator, right)) = self.insert_into(pager, self.root, key, payload)? {
+            // Keep the root page in place: move its left half to a new page.
+            let left = pager.allocate()?;
+            let node = read_node(pager, self.root)?;
+            write_node(pager, left, &node)?;
+            write_node(
+                pager,
+                self.root,
+                &Node::Interior {
+                    keys: vec![separator],
+                    children: vec![left, right],
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Inserts into the subtree at `page`. When the page had to split, its left half
+    /// stays at `page` and the separator and new right page are returned.
+    fn insert_into(
+        &self,
+        pager: &mut Pager,
+        page: PageId,
+        key: i64,
+        payload: Payload,
+    ) -> Result<Option<(i64, PageId)>> {
+        match read_node(pager, page)? {
+            Node::Leaf { mut cells, next } => {
+                match cells.binary_search_by_key(&key, |(k, _)| *k) {
+                    Ok(idx) => {
+                        let old = std::mem::replace(&mut cells[idx].1, payload);
+                        free_payload(pager, &old)?;
+                    }
+                    Err(idx) => cells.insert(idx, (key, payload)),
+                }
+
+                let node = Node::Leaf { cells, next };
+                if node.size() <= PAGE_SIZE {
+                    write_node(pager, page, &node)?;
+                    return Ok(None);
+                }
+                let mut cells = match node {
+                    Node::Leaf { cells, .. } => cells,
+                    Node::Interior { .. } => unreachable!(),
+                };
+
+                // Split by bytes so both halves fit whatever the cell sizes are.
+                let total = cells.iter().map(|(_, p)| cell_size(p)).sum::<usize>();
+                let mut left_size = 0;
+                let mut mid = 0;
+                while left_size < total / 2 {
+                    left_size += cell_size(&cells[mid].1);
+                    mid += 1;
+                }
+                let right_cells = cells.split_off(mid);
+                let separator = right_cells[0].0;
+                let right = pager.allocate()?;
+                write_node(
+                    pager,
+                    right,
+                    &Node::Leaf {
+                        cells: right_cells,
+                        next,
+                    },
+                )?;
+                write_node(pager, page, &Node::Leaf { cells, next: right })?;
+                Ok(Some((separator, right)))
+            }
+            Node::Interior {
+                mut keys,
+                mut children,
+            } => {
+                let idx = keys.partition_point(|k| *k <= key);
+                let split = self.insert_into(pager, children[idx], key, payload)?;
+                let (separator, right) = match split {
+                    Some(split) => split,
+                    None => return Ok(None),
+                };
+                keys.insert(idx, separator);
+                children.insert(idx + 1, right);
+
+                let node = Node::Interior { keys, children };
+                if node.size() <= PAGE_SIZE {
+                    write_node(pager, page, &node)?;
+                    return Ok(None);
+                }
+                let (mut keys, mut children) = match node {
+                    Node::Interior { keys, children } => (keys, children),
+                    Node::Leaf { .. } => unreachable!(),
+                };
+
+                let mid = keys.len() / 2;
+                let right_keys = keys.split_off(mid + 1);
+                let separator = keys.pop().unwrap();
+                let right_children = children.split_off(mid + 1);
+                let right = pager.allocate()?;
+                write_node(
+                    pager,
+                    right,
+                    &Node::Interior {
+                        keys: right_keys,
+                        children: right_children,
+                    },
+                )?;
+                write_node(pager, page, &Node::Interior { keys, children })?;
+                Ok(Some((separator, right)))
+            }
+        }
+    }
+
+    /// Removes `key`, returning whether it was present.
+    pub fn delete(&self, pager: &mut Pager, key: i64) -> Result<bool> {
+        let leaf = self.find_leaf(pager, key)?;
+        if let Node::Leaf { mut cells, next } = read_node(pager, leaf)? {
+            if let Ok(idx) = cells.binary_search_by_key(&key, |(k, _)| *k) {
+                let (_, payload) = cells.remove(idx);
+                free_payload(pager, &payload)?;
+                write_node(pager, leaf, &Node::Leaf { cells, next })?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Every key in ascending order.
+    pub fn keys(&self, pager: &mut Pager) -> Result<Vec<i64>> {
+        let mut keys = vec![];
+        let mut leaf = self.first_leaf(pager)?;
+        while leaf != 0 {
+            match read_node(pager, leaf)? {
+                Node::Leaf { cells, next } => {
+                    keys.extend(cells.iter().map(|(k, _)| *k));
+                    leaf = next;
+                }
+                Node::Interior { .. } => break,
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Every `(key, value)` pair in ascending key order.
+    pub fn entries(&self, pager: &mut Pager) -> Result<Vec<(i64, Vec<u8>)>> {
+        let mut entries = vec![];
+        let mut leaf = self.first_leaf(pager)?;
+        while leaf != 0 {
+            match read_node(pager, leaf)? {
+                Node::Leaf { cells, next } => {
+                    for (key, payload) in &cells {
+                        entries.push((*key, read_payload(pager, payload)?));
+                    }
+                    leaf = next;
+                }
+                Node::Interior { .. } => break,
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Frees every page of the tree, including its root.
+    pub fn destroy(self, pager: &mut Pager) -> Result<()> {
+        free_subtree(pager, self.root)
+    }
+
+    fn find_leaf(&self, pager: &mut Pager, key: i64) -> Result<PageId> {
+        let mut page = self.root;
+        loop {
+            match read_node(pager, page)? {
+                Node::Leaf { .. } => return Ok(page),
+                Node::Interior { keys, children } => {
+                    page = children[keys.partition_point(|k| *k <= key)];
+                }
+            }
+        }
+    }
+
+    fn first_leaf(&self, pager: &mut Pager) -> Result<PageId> {
+        let mut page = self.root;
+        loop {
+            match read_node(pager, page)? {
+                Node::Leaf { .. } => return Ok(page),
+                Node::Interior { children, .. } => page = children[0],
+            }
+        }
+    }
+}
+
+fn free_subtree(pager: &mut Pager, page: PageId) -> Result<()> {
+    match read_node(pager, page)? {
+        Node::Leaf { cells, .. } => {
+            for (_, payload) in &cells {
+                free_payload(pager, payload)?;
+            }
+        }
+        Node::Interior { children, .. } => {
+            for child in children {
+                free_subtree(pager, child)?;
+            }
+        }
+    }
+    pager.free(page)
+}
+
+fn write_payload(pager: &mut Pager, value: &[u8]) -> Result<Payload> {
+    if value.len() <= MAX_INLINE {
+        return Ok(Payload::Inline(value.to_vec()));
+    }
+
+    let len = u32::try_from(value.len())
+        .map_err(|_| RUSQLError::General("Value is too large to store.".to_string()))?;
+    let chunks = value.chunks(OVERFLOW_CHUNK).collect::<Vec<&[u8]>>();
+    let pages = chunks
+        .iter()
+        .map(|_| pager.allocate())
+        .collect::<Result<Vec<PageId>>>()?;
+
+    for (idx, chunk) in chunks.iter().enumerate() {
+        let next = pages.get(idx + 1).copied().unwrap_or(0);
+        let mut page = Vec::with_capacity(4 + chunk.len());
+        page.extend_from_slice(&next.to_le_bytes());
+        page.extend_from_slice(chunk);
+        pager.write(pages[idx], &page)?;
+    }
+    Ok(Payload::Overflow {
+        len,
+        first: pages[0],
+    })
+}
+
+fn read_payload(pager: &mut Pager, payload: &Payload) -> Result<Vec<u8>> {
+    match payload {
+        Payload::Inline(bytes) => Ok(bytes.clone()),
+        Payload::Overflow { len, first } => {
+            let len = *len as usize;
+            let mut value = Vec::with_capacity(len);
+            let mut page = *first;
+            while value.len() < len {
+                if page == 0 {
+                    return Err(RUSQLError::General(
+                        "Corrupt database file: overflow chain is too short.".to_string(),
+                    ));
+                }
+                let data = pager.read(page)?;
+                let take = (len - value.len()).min(OVERFLOW_CHUNK);
+                value.extend_from_slice(&data[4..4 + take]);
+                page = read_u32(data, 0);
+            }
+            Ok(value)
+        }
+    }
+}
+
+fn free_payload(pager: &mut Pager, payload: &Payload) -> Result<()> {
+    if let Payload::Overflow { len, first } = payload {
+        let mut remaining = *len as usize;
+        let mut page = *first;
+        while page != 0 && remaining > 0 {
+            let next = read_u32(pager.read(page)?, 0);
+            pager.free(page)?;
+            remaining = remaining.saturating_sub(OVERFLOW_CHUNK);
+            page = next;
+        }
+    }
+    Ok(())
+}
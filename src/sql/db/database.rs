@@ -1,15 +1,33 @@
 //! Database container for managing multiple tables.
-//! Provides O(1) table lookups using HashMap. All operations are currently in-memory.
+//! Provides O(1) table lookups using HashMap. A database opened from a file keeps its
+//! columns in that file, read through a pager with a bounded page cache; otherwise the
+//! tables live in memory. Changes made through `Database` are journaled while a statement
+//! runs or a transaction is open, so they can be rolled back (see `transaction.rs`).
 
 use crate::error::{RUSQLError, Result};
+use crate::sql::db::file_format::{self, LEGACY_JSON_VERSION, NARROW_NUMBERS_VERSION};
+use crate::sql::db::pager::{Pager, SharedPager, DEFAULT_CACHE_PAGES};
 use crate::sql::db::table::Table;
+use crate::sql::db::transaction::{Journal, Savepoint, UndoEntry};
+use crate::sql::db::value::Value;
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Database {
     pub db_name: String,
     pub tables: HashMap<String, Table>,
+    /// File the database was opened from, `None` for an in-memory database.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    /// Pager of the open file, `None` for an in-memory database.
+    #[serde(skip)]
+    pager: Option<SharedPager>,
+    #[serde(skip)]
+    journal: Journal,
 }
 
 impl Database {
@@ -17,14 +35,101 @@ impl Database {
         Database {
             db_name,
             tables: HashMap::new(),
+            path: None,
+            pager: None,
+            journal: Journal::default(),
         }
     }
 
+    /// Opens the database stored at `path` with the default page cache. A missing file
+    /// is created as an empty database named after the file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Database::open_with_cache(path, DEFAULT_CACHE_PAGES)
+    }
+
+    /// Opens the database stored at `path`, keeping at most `cache_pages` pages in
+    /// memory. Files in an older format are converted first.
+    pub fn open_with_cache(path: impl AsRef<Path>, cache_pages: usize) -> Result<Self> {
+        let path = path.as_ref();
+        let (pager, mut db) = if path.exists() {
+            match file_format::read_version(path)? {
+                LEGACY_JSON_VERSION => file_format::migrate_legacy(path)?,
+                NARROW_NUMBERS_VERSION => file_format::migrate_narrow_numbers(path)?,
+                _ => (),
+            }
+            let pager = Rc::new(RefCell::new(Pager::open(path, cache_pages)?));
+            let db = file_format::load(&pager)?;
+            (pager, db)
+        } else {
+            let db_name = path.file_stem().map_or(String::from("main"), |stem| {
+                stem.to_string_lossy().to_string()
+            });
+            let pager = file_format::create(path, cache_pages)?;
+            let db = Database::new(db_name);
+            file_format::store(&pager, &db)?;
+            pager.borrow_mut().checkpoint()?;
+            (pager, db)
+        };
+        db.path = Some(path.to_path_buf());
+        db.pager = Some(pager);
+        Ok(db)
+    }
+
+    /// Writes the database to `path`. Saving to the file the database was opened from
+    /// commits its pending changes to the write-ahead log; any other path receives a
+    /// full copy.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        match &self.pager {
+            Some(pager) if self.path.as_deref() == Some(path) => file_format::store(pager, self),
+            _ => file_format::export(self, path),
+        }
+    }
+
+    /// Writes the database back to the file it was opened from. Does nothing for an
+    /// in-memory database.
+    pub fn save_to_path(&self) -> Result<()> {
+        match &self.path {
+            Some(path) => self.save(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Rolls back a transaction left open, writes the database back to its file and
+    /// checkpoints the write-ahead log into it.
+    pub fn close(&mut self) -> Result<()> {
+        if self.in_transaction() {
+            self.rollback()?;
+        }
+        self.save_to_path()?;
+        match &self.pager {
+            Some(pager) => pager.borrow_mut().checkpoint(),
+            None => Ok(()),
+        }
+    }
+
+    /// Pager of the open file, `None` for an in-memory database.
+    pub fn pager(&self) -> Option<&SharedPager> {
+        self.pager.as_ref()
+    }
+
+    /// Adds a table, moving its columns into the database file when there is one.
+    pub fn add_table(&mut self, table: Table) -> Result<()> {
+        let table = match &self.pager {
+            Some(pager) => table.copy_into(pager)?,
+            None => table,
+        };
+        self.journal.record(UndoEntry::CreateTable {
+            table: table.tb_name.to_string(),
+        });
+        self.tables.insert(table.tb_name.to_string(), table);
+        Ok(())
+    }
+
     pub fn contains_table(&self, table_name: String) -> bool {
         self.tables.contains_key(&table_name)
     }
 
-    #[allow(dead_code)]
     pub fn get_table(&self, table_name: String) -> Result<&Table> {
         self.tables
             .get(&table_name)
@@ -38,17 +143,229 @@ impl Database {
     }
 
     pub fn drop_table(&mut self, table_name: String) -> Result<()> {
-        self.tables.remove(&table_name).map(|_| ()).ok_or_else(|| {
+        let table = self.tables.remove(&table_name).ok_or_else(|| {
             RUSQLError::General(format!("Cannot drop table '{}': not found", table_name))
-        })
+        })?;
+        if self.journal.is_recording() {
+            // The pages are freed on commit, a rollback puts the table back.
+            self.journal.record(UndoEntry::DropTable { table });
+            Ok(())
+        } else {
+            table.destroy_storage()
+        }
+    }
+
+    /// Inserts a row into a table (see `Table::insert_row`) and returns its rowid.
+    pub fn insert_row(
+        &mut self,
+        table_name: &str,
+        cols: &[String],
+        values: &[Value],
+    ) -> Result<i64> {
+        let table = self.get_table_mut(table_name.to_string())?;
+        let last_rowid = table.last_rowid;
+        let rowid = table.insert_row(cols, values)?;
+        self.journal.record(UndoEntry::InsertRow {
+            table: table_name.to_string(),
+            rowid,
+            last_rowid,
+        });
+        Ok(rowid)
+    }
+
+    /// Overwrites columns of a row (see `Table::update_row`) and returns its new rowid.
+    pub fn update_row(
+        &mut self,
+        table_name: &str,
+        rowid: i64,
+        assignments: &[(String, Value)],
+    ) -> Result<i64> {
+        let table = self.get_table_mut(table_name.to_string())?;
+        let values = table.get_row_values(rowid)?;
+        let last_rowid = table.last_rowid;
+        let new_rowid = table.update_row(rowid, assignments)?;
+        self.journal.record(UndoEntry::UpdateRow {
+            table: table_name.to_string(),
+            rowid,
+            new_rowid,
+            values,
+            last_rowid,
+        });
+        Ok(new_rowid)
+    }
+
+    /// Removes a row from a table. Returns whether a row with that rowid existed.
+    pub fn delete_row(&mut self, table_name: &str, rowid: i64) -> Result<bool> {
+        let table = self.get_table_mut(table_name.to_string())?;
+        let values = table.get_row_values(rowid)?;
+        let deleted = table.delete_row(rowid)?;
+        if deleted {
+            self.journal.record(UndoEntry::DeleteRow {
+                table: table_name.to_string(),
+                rowid,
+                values,
+            });
+        }
+        Ok(deleted)
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.journal.in_transaction()
+    }
+
+    /// Starts journaling the changes of a statement so that they can be undone if it fails.
+    pub fn begin_statement(&mut self) {
+        self.journal.set_statement(Some(self.journal.len()));
+    }
+
+    /// Ends the running statement. A failed statement is undone, leaving the database as
+    /// it was before the statement; outside a transaction a successful one is committed.
+    pub fn end_statement(&mut self, succeeded: bool) -> Result<()> {
+        let mark = self.journal.statement().unwrap_or(self.journal.len());
+        self.journal.set_statement(None);
+        if !succeeded {
+            self.undo_to(mark)?;
+        }
+        if !self.journal.in_transaction() {
+            self.finish_commit()?;
+        }
+        Ok(())
+    }
+
+    pub fn begin(&mut self) -> Result<()> {
+        if self.journal.in_transaction() {
+            return Err(RUSQLError::General(
+                "Cannot start a transaction within a transaction.".to_string(),
+            ));
+        }
+        self.journal.set_in_transaction(true);
+        Ok(())
+    }
+
+    pub fn commit(&mut self) -> Result<()> {
+        if !self.journal.in_transaction() {
+            return Err(RUSQLError::General(
+                "Cannot commit: no transaction is active.".to_string(),
+            ));
+        }
+        self.journal.set_in_transaction(false);
+        self.finish_commit()
+    }
+
+    pub fn rollback(&mut self) -> Result<()> {
+        if !self.journal.in_transaction() {
+            return Err(RUSQLError::General(
+                "Cannot rollback: no transaction is active.".to_string(),
+            ));
+        }
+        self.undo_to(0)?;
+        self.journal.set_in_transaction(false);
+        Ok(())
+    }
+
+    /// Marks a point the transaction can be rolled back to. Outside a transaction the
+    /// savepoint starts one, which releasing the savepoint commits.
+    pub fn savepoint(&mut self, name: &str) {
+        let starts_transaction = !self.journal.in_transaction();
+        self.journal.set_in_transaction(true);
+        self.journal.push_savepoint(Savepoint {
+            name: name.to_string(),
+            mark: self.journal.len(),
+            starts_transaction,
+        });
+    }
+
+    /// Forgets a savepoint and every savepoint taken after it, keeping their changes.
+    pub fn release(&mut self, name: &str) -> Result<()> {
+        let idx = self.find_savepoint(name)?;
+        let starts_transaction = self.journal.savepoint(idx).starts_transaction;
+        self.journal.truncate_savepoints(idx);
+        if starts_transaction {
+            self.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Undoes every change made since a savepoint. The savepoint itself stays active.
+    pub fn rollback_to(&mut self, name: &str) -> Result<()> {
+        let idx = self.find_savepoint(name)?;
+        self.undo_to(self.journal.savepoint(idx).mark)?;
+        self.journal.truncate_savepoints(idx + 1);
+        Ok(())
+    }
+
+    fn find_savepoint(&self, name: &str) -> Result<usize> {
+        self.journal
+            .find_savepoint(name)
+            .ok_or_else(|| RUSQLError::General(format!("No such savepoint: {}", name)))
+    }
+
+    /// Discards the journal, freeing the storage of tables dropped since the last commit,
+    /// and makes the changes durable in the database file.
+    fn finish_commit(&mut self) -> Result<()> {
+        if self.journal.is_empty() {
+            return Ok(());
+        }
+        for entry in self.journal.drain() {
+            if let UndoEntry::DropTable { table } = entry {
+                table.destroy_storage()?;
+            }
+        }
+        match &self.pager {
+            Some(pager) => file_format::store(pager, self),
+            None => Ok(()),
+        }
+    }
+
+    /// Reverts journaled changes, newest first, until `mark` entries remain.
+    fn undo_to(&mut self, mark: usize) -> Result<()> {
+        while self.journal.len() > mark {
+            match self.journal.pop().unwrap() {
+                UndoEntry::InsertRow {
+                    table,
+                    rowid,
+                    last_rowid,
+                } => {
+                    let table = self.get_table_mut(table)?;
+                    table.delete_row(rowid)?;
+                    table.last_rowid = last_rowid;
+                }
+                UndoEntry::UpdateRow {
+                    table,
+                    rowid,
+                    new_rowid,
+                    values,
+                    last_rowid,
+                } => {
+                    let table = self.get_table_mut(table)?;
+                    table.delete_row(new_rowid)?;
+                    table.restore_row(rowid, &values)?;
+                    table.last_rowid = last_rowid;
+                }
+                UndoEntry::DeleteRow {
+                    table,
+                    rowid,
+                    values,
+                } => {
+                    self.get_table_mut(table)?.restore_row(rowid, &values)?;
+                }
+                UndoEntry::CreateTable { table } => {
+                    if let Some(table) = self.tables.remove(&table) {
+                        table.destroy_storage()?;
+                    }
+                }
+                UndoEntry::DropTable { table } => {
+                    self.tables.insert(table.tb_name.to_string(), table);
+                }
+            }
+        }
+        Ok(())
     }
 
-    #[allow(dead_code)]
     pub fn table_count(&self) -> usize {
         self.tables.len()
     }
 
-    #[allow(dead_code)]
     pub fn list_table_names(&self) -> Vec<String> {
         self.tables.keys().cloned().collect()
     }
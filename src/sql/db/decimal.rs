@@ -0,0 +1,420 @@
+//! Exact decimal numbers.
+//! A `Decimal` is an integer mantissa scaled by a power of ten, so values such as 0.1
+//! are held exactly and sums of money do not pick up binary floating point error.
+//! Mantissas have at most `MAX_PRECISION` digits; the checked operations return `None`
+//! when a result would need more, which SQL reports as `overflow_error`.
+
+use crate::error::RUSQLError;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::{Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Neg;
+
+/// Most digits a decimal can have, as in `DECIMAL(38, s)`.
+pub const MAX_PRECISION: u32 = 38;
+/// Fewest digits after the point a quotient keeps.
+const DIVISION_SCALE: u32 = 6;
+const LIMIT: i128 = 10i128.pow(MAX_PRECISION);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Decimal {
+    mantissa: i128,
+    /// Digits after the decimal point; the value is `mantissa / 10^scale`.
+    scale: u32,
+}
+
+/// How to round away digits that do not fit the target scale.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RoundingMode {
+    /// Ties go away from zero. Used when storing into a column.
+    HalfUp,
+    /// Ties go to the even neighbour (banker's rounding).
+    HalfEven,
+    /// Ties go towards zero.
+    HalfDown,
+    /// Away from zero.
+    Up,
+    /// Towards zero.
+    Down,
+    /// Towards positive infinity.
+    Ceiling,
+    /// Towards negative infinity.
+    Floor,
+}
+
+impl RoundingMode {
+    /// Reads a mode by name, such as `'half_even'` or `'CEILING'`.
+    pub fn from_name(name: &str) -> Option<RoundingMode> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "half_up" => RoundingMode::HalfUp,
+            "half_even" => RoundingMode::HalfEven,
+            "half_down" => RoundingMode::HalfDown,
+            "up" => RoundingMode::Up,
+            "down" => RoundingMode::Down,
+            "ceiling" => RoundingMode::Ceiling,
+            "floor" => RoundingMode::Floor,
+            _ => return None,
+        })
+    }
+}
+
+impl Decimal {
+    pub fn new(mantissa: i128, scale: u32) -> Option<Decimal> {
+        (mantissa.abs() < LIMIT && scale <= MAX_PRECISION).then_some(Decimal { mantissa, scale })
+    }
+
+    pub fn from_i64(i: i64) -> Decimal {
+        Decimal {
+            mantissa: i as i128,
+            scale: 0,
+        }
+    }
+
+    /// The decimal a real prints as, so `0.1` becomes exactly 0.1. `None` for NaN,
+    /// infinities and reals that need more than `MAX_PRECISION` digits.
+    pub fn from_f64(r: f64) -> Option<Decimal> {
+        if r.is_finite() {
+            Decimal::parse(&r.to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Parses text such as `-12.50` or `1.5e3`, keeping the digits written after the
+    /// point: `1.50` has scale 2. Returns `None` for anything else.
+    pub fn parse(s: &str) -> Option<Decimal> {
+        let s = s.trim();
+        let (negative, s) = match s.as_bytes().first()? {
+            b'-' => (true, &s[1..]),
+            b'+' => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let (number, exponent) = match s.find(['e', 'E']) {
+            Some(idx) => (&s[..idx], s[idx + 1..].parse::<i32>().ok()?),
+            None => (s, 0),
+        };
+        let (int_part, frac_part) = number.split_once('.').unwrap_or((number, ""));
+        if int_part.is_empty() && frac_part.is_empty() {
+            return None;
+        }
+
+        let mut mantissa: i128 = 0;
+        for c in int_part.chars().chain(frac_part.chars()) {
+            let digit = c.to_digit(10)?;
+            mantissa = mantissa.checked_mul(10)?.checked_add(digit as i128)?;
+        }
+        if negative {
+            mantissa = -mantissa;
+        }
+        let scale = frac_part.len() as i64 - exponent as i64;
+        if scale < 0 {
+            let mantissa = mantissa.checked_mul(pow10(u32::try_from(-scale).ok()?)?)?;
+            Decimal::new(mantissa, 0)
+        } else {
+            Decimal::new(mantissa, u32::try_from(scale).ok()?)
+        }
+    }
+
+    /// Number of digits in the mantissa, at least 1.
+    pub fn precision(&self) -> u32 {
+        self.mantissa
+            .unsigned_abs()
+            .checked_ilog10()
+            .map_or(1, |log| log + 1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa == 0
+    }
+
+    /// The mantissa and scale, for storing the value.
+    pub fn to_parts(self) -> (i128, u32) {
+        (self.mantissa, self.scale)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        // Going through the digits gives the real nearest the exact value.
+        self.to_string().parse().unwrap()
+    }
+
+    /// The integer part, saturating at the ends of the `i64` range like a real does.
+    pub fn to_i64(self) -> i64 {
+        let int_part = self.mantissa / pow10(self.scale).unwrap();
+        int_part.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+    }
+
+    /// The value as an integer, if it has no fractional part and fits in an `i64`.
+    pub fn to_integer(self) -> Option<i64> {
+        let divisor = pow10(self.scale).unwrap();
+        if self.mantissa % divisor == 0 {
+            i64::try_from(self.mantissa / divisor).ok()
+        } else {
+            None
+        }
+    }
+
+    /// The value with exactly `scale` digits after the point, rounded with `mode` when
+    /// digits are dropped. `None` when it would need too many digits.
+    pub fn rescale(&self, scale: u32, mode: RoundingMode) -> Option<Decimal> {
+        if scale >= self.scale {
+            let mantissa = self.mantissa.checked_mul(pow10(scale - self.scale)?)?;
+            Decimal::new(mantissa, scale)
+        } else {
+            let mantissa = div_round(self.mantissa, pow10(self.scale - scale)?, mode);
+            Decimal::new(mantissa, scale)
+        }
+    }
+
+    /// Rounds to at most `digits` digits after the point; never adds digits.
+    pub fn round(&self, digits: u32, mode: RoundingMode) -> Decimal {
+        if digits >= self.scale {
+            *self
+        } else {
+            // Dropping digits only makes the mantissa smaller, so this always fits.
+            self.rescale(digits, mode).unwrap()
+        }
+    }
+
+    /// The value as stored in a `DECIMAL(precision, scale)` column: rounded half up
+    /// to `scale` digits after the point, `None` if it then has more than `precision`.
+    pub fn fit(&self, precision: u32, scale: u32) -> Option<Decimal> {
+        self.rescale(scale, RoundingMode::HalfUp)
+            .filter(|decimal| decimal.precision() <= precision)
+    }
+
+    pub fn abs(&self) -> Decimal {
+        Decimal {
+            mantissa: self.mantissa.abs(),
+            scale: self.scale,
+        }
+    }
+
+    pub fn checked_add(&self, other: &Decimal) -> Option<Decimal> {
+        let (a, b, scale) = self.aligned(other)?;
+        Decimal::new(a.checked_add(b)?, scale)
+    }
+
+    pub fn checked_sub(&self, other: &Decimal) -> Option<Decimal> {
+        let (a, b, scale) = self.aligned(other)?;
+        Decimal::new(a.checked_sub(b)?, scale)
+    }
+
+    /// The exact product. When it has more than `MAX_PRECISION` digits, in all or after
+    /// the point, digits after the point are rounded away half up until it fits; `None`
+    /// when the digits before the point alone are too many.
+    pub fn checked_mul(&self, other: &Decimal) -> Option<Decimal> {
+        // The product of two mantissas can exceed an i128, so multiply digit by digit.
+        let digits = digit_product(self.mantissa.unsigned_abs(), other.mantissa.unsigned_abs());
+        let scale = (self.scale + other.scale) as usize;
+        let max = MAX_PRECISION as usize;
+        let dropped = digits
+            .len()
+            .saturating_sub(max)
+            .max(scale.saturating_sub(max));
+        if dropped > scale {
+            return None;
+        }
+        let kept = digits.len().saturating_sub(dropped);
+        let mut mantissa = digits[..kept]
+            .iter()
+            .fold(0i128, |mantissa, digit| mantissa * 10 + *digit as i128);
+        let mut scale = (scale - dropped) as u32;
+        if dropped > 0 && dropped <= digits.len() && digits[kept] >= 5 {
+            mantissa += 1;
+            if mantissa == LIMIT {
+                // Rounding carried into a new digit: 99.99 became 100.0.
+                scale = scale.checked_sub(1)?;
+                mantissa /= 10;
+            }
+        }
+        let negative = (self.mantissa < 0) != (other.mantissa < 0);
+        Decimal::new(if negative { -mantissa } else { mantissa }, scale)
+    }
+
+    /// The quotient rounded half up to the larger scale of the operands, and to at
+    /// least `DIVISION_SCALE` digits after the point, or to fewer digits after the point
+    /// when the quotient would otherwise not fit. `None` for division by zero.
+    pub fn checked_div(&self, other: &Decimal) -> Option<Decimal> {
+        if other.is_zero() {
+            return None;
+        }
+        let mut scale = self.scale.max(other.scale).max(DIVISION_SCALE);
+        loop {
+            // mantissa / 10^scale = (self.mantissa / 10^self.scale) / (other.mantissa / 10^other.scale)
+            let quotient = (scale + other.scale)
+                .checked_sub(self.scale)
+                .and_then(pow10)
+                .and_then(|factor| self.mantissa.checked_mul(factor))
+                .and_then(|numerator| {
+                    let mantissa = div_round(numerator, other.mantissa, RoundingMode::HalfUp);
+                    Decimal::new(mantissa, scale)
+                });
+            if quotient.is_some() || scale == 0 {
+                return quotient;
+            }
+            scale -= 1;
+        }
+    }
+
+    /// The remainder of truncating division, with the sign of `self`. `None` for a
+    /// zero divisor.
+    pub fn checked_rem(&self, other: &Decimal) -> Option<Decimal> {
+        let (a, b, scale) = self.aligned(other)?;
+        Decimal::new(a.checked_rem(b)?, scale)
+    }
+
+    /// Both mantissas at the larger of the two scales.
+    fn aligned(&self, other: &Decimal) -> Option<(i128, i128, u32)> {
+        let scale = self.scale.max(other.scale);
+        let a = self.mantissa.checked_mul(pow10(scale - self.scale)?)?;
+        let b = other.mantissa.checked_mul(pow10(scale - other.scale)?)?;
+        Some((a, b, scale))
+    }
+}
+
+/// The error for a decimal result that needs more than `MAX_PRECISION` digits.
+pub fn overflow_error() -> RUSQLError {
+    RUSQLError::General("numeric overflow".to_string())
+}
+
+/// The decimal digits of `a * b`, most significant first, without leading zeros.
+fn digit_product(a: u128, b: u128) -> Vec<u8> {
+    let digits = |n: u128| -> Vec<u32> {
+        n.to_string()
+            .bytes()
+            .rev()
+            .map(|c| (c - b'0') as u32)
+            .collect()
+    };
+    let (a, b) = (digits(a), digits(b));
+    let mut product = vec![0u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            product[i + j] += x * y;
+        }
+    }
+    for i in 0..product.len() - 1 {
+        product[i + 1] += product[i] / 10;
+        product[i] %= 10;
+    }
+    while product.last() == Some(&0) {
+        product.pop();
+    }
+    product.iter().rev().map(|digit| *digit as u8).collect()
+}
+
+fn pow10(exp: u32) -> Option<i128> {
+    10i128.checked_pow(exp)
+}
+
+/// `n / d` rounded to an integer with `mode`.
+fn div_round(n: i128, d: i128, mode: RoundingMode) -> i128 {
+    let (quotient, remainder) = (n / d, n % d);
+    if remainder == 0 {
+        return quotient;
+    }
+    let positive = (n < 0) == (d < 0);
+    let (remainder, d) = (remainder.unsigned_abs(), d.unsigned_abs());
+    // How the dropped part compares with one half.
+    let half = remainder.cmp(&(d - remainder));
+    let away = match mode {
+        RoundingMode::HalfUp => half != Ordering::Less,
+        RoundingMode::HalfEven => {
+            half == Ordering::Greater || (half == Ordering::Equal && quotient % 2 != 0)
+        }
+        RoundingMode::HalfDown => half == Ordering::Greater,
+        RoundingMode::Up => true,
+        RoundingMode::Down => false,
+        RoundingMode::Ceiling => positive,
+        RoundingMode::Floor => !positive,
+    };
+    match (away, positive) {
+        (false, _) => quotient,
+        (true, true) => quotient + 1,
+        (true, false) => quotient - 1,
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal {
+            mantissa: -self.mantissa,
+            scale: self.scale,
+        }
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        let scaled = |d: &Decimal| d.mantissa.checked_mul(pow10(scale - d.scale)?);
+        match (scaled(self), scaled(other)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            // Only the side with fewer digits after the point can overflow, and then
+            // it is larger in magnitude than the other side.
+            (None, _) => self.mantissa.cmp(&0),
+            (_, None) => 0.cmp(&other.mantissa),
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// `1.5` equals `1.50`.
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let (mut mantissa, mut scale) = (self.mantissa, self.scale);
+        while scale > 0 && mantissa % 10 == 0 {
+            mantissa /= 10;
+            scale -= 1;
+        }
+        (mantissa, scale).hash(state);
+    }
+}
+
+/// Prints every digit of the scale, so `DECIMAL(10, 2)` values read `12.50`.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.mantissa < 0 {
+            f.write_str("-")?;
+        }
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return f.write_str(&digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (int_part, frac_part) = digits.split_at(digits.len() - scale);
+        write!(f, "{}.{}", int_part, frac_part)
+    }
+}
+
+/// Serialized as its text, which keeps the scale.
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Decimal::parse(&text).ok_or_else(|| de::Error::custom(format!("invalid decimal: {}", text)))
+    }
+}
@@ -0,0 +1,150 @@
+//! On-disk format of a RUSQL database file.
+//!
+//! Every file starts with an 8-byte magic number and a format version:
+//!
+//! ```text
+//! +----------------+--------------------+------------------------------------+
+//! | magic (8 B)    | format version     | rest of the file                   |
+//! | "RUSQLDB\0"    | u32, little endian |                                    |
+//! +----------------+--------------------+------------------------------------+
+//! ```
+//!
+//! - Version 1 followed the header with the whole database serialized as JSON. Such
+//!   files are converted to the current version when they are opened.
+//! - Version 2 is a page file (see `pager.rs`). The header fills page 0 and points to
+//!   the catalog, a B+tree whose single entry is the schema of every table, including
+//!   the root page of each column tree. Indexes are rebuilt from the columns on load.
+//!   Changes are committed to a write-ahead log next to the file before they reach it.
+//!   Integer and real cells were 32 bits wide; such files are rewritten when opened.
+//! - Version 3 has the layout of version 2 with 64-bit integer and real cells.
+//!
+//! A file with a version newer than `FORMAT_VERSION` is rejected instead of being misread.
+
+use crate::error::{RUSQLError, Result};
+use crate::sql::db::btree::BTree;
+use crate::sql::db::database::Database;
+use crate::sql::db::pager::{wal_path, Pager, SharedPager, DEFAULT_CACHE_PAGES};
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+pub const MAGIC: &[u8; 8] = b"RUSQLDB\0";
+pub const FORMAT_VERSION: u32 = 3;
+/// The JSON format, read only to migrate it.
+pub const LEGACY_JSON_VERSION: u32 = 1;
+/// The page format with 32-bit integer and real cells, read only to migrate it.
+pub const NARROW_NUMBERS_VERSION: u32 = 2;
+pub const HEADER_LEN: usize = MAGIC.len() + 4;
+
+/// Key of the schema entry in the catalog tree.
+const CATALOG_KEY: i64 = 0;
+
+/// Reads the format version from the header of the file at `path`.
+pub fn read_version(path: &Path) -> Result<u32> {
+    let mut header = [0; HEADER_LEN];
+    File::open(path)?
+        .read_exact(&mut header)
+        .map_err(|_| not_a_database())?;
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(not_a_database());
+    }
+    Ok(u32::from_le_bytes(
+        header[MAGIC.len()..].try_into().unwrap(),
+    ))
+}
+
+fn not_a_database() -> RUSQLError {
+    RUSQLError::General("File is not a RUSQL database.".to_string())
+}
+
+/// Decodes a version 1 file: the header followed by the database as JSON.
+pub fn decode_legacy(bytes: &[u8]) -> Result<Database> {
+    if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(not_a_database());
+    }
+    serde_json::from_slice(&bytes[HEADER_LEN..])
+        .map_err(|err| RUSQLError::General(format!("Corrupt database file: {}", err)))
+}
+
+/// Rewrites a version 1 file at `path` in the current format.
+pub fn migrate_legacy(path: &Path) -> Result<()> {
+    let db = decode_legacy(&fs::read(path)?)?;
+    export(&db, path)
+}
+
+/// Rewrites a version 2 file at `path` in the current format. The file's log is
+/// replayed first; the copy starts with an empty one.
+pub fn migrate_narrow_numbers(path: &Path) -> Result<()> {
+    let pager = Rc::new(RefCell::new(Pager::open_version(
+        path,
+        DEFAULT_CACHE_PAGES,
+        NARROW_NUMBERS_VERSION,
+    )?));
+    let db = load(&pager)?;
+    export(&db, path)
+}
+
+/// Creates an empty database file with an empty catalog.
+pub fn create(path: &Path, cache_pages: usize) -> Result<SharedPager> {
+    let mut pager = Pager::create(path, cache_pages)?;
+    let catalog = BTree::create(&mut pager)?;
+    pager.set_catalog_root(catalog.root());
+    Ok(Rc::new(RefCell::new(pager)))
+}
+
+/// Reads the catalog of an open database file and connects its tables to the pager.
+pub fn load(pager: &SharedPager) -> Result<Database> {
+    let bytes = {
+        let mut pager = pager.borrow_mut();
+        let catalog = BTree::open(pager.catalog_root());
+        catalog.get(&mut pager, CATALOG_KEY)?
+    };
+    let mut db: Database = match bytes {
+        Some(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|err| RUSQLError::General(format!("Corrupt database file: {}", err)))?,
+        None => {
+            return Err(RUSQLError::General(
+                "Corrupt database file: missing catalog.".to_string(),
+            ))
+        }
+    };
+
+    for table in db.tables.values_mut() {
+        table.attach_pager(pager);
+        table.rebuild_indexes()?;
+    }
+    Ok(db)
+}
+
+/// Writes the catalog of `db` and commits every modified page to the write-ahead log.
+pub fn store(pager: &SharedPager, db: &Database) -> Result<()> {
+    let catalog = serde_json::to_vec(db)
+        .map_err(|err| RUSQLError::General(format!("Cannot serialize database: {}", err)))?;
+    let mut pager = pager.borrow_mut();
+    BTree::open(pager.catalog_root()).insert(&mut pager, CATALOG_KEY, &catalog)?;
+    pager.commit()
+}
+
+/// Writes a complete copy of `db` to a new file, which replaces `path` once it is on
+/// disk, so a crash mid-export leaves any previous file intact.
+pub fn export(db: &Database, path: &Path) -> Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    {
+        let pager = create(&tmp_path, DEFAULT_CACHE_PAGES)?;
+        let mut copy = Database::new(db.db_name.to_string());
+        for (name, table) in &db.tables {
+            copy.tables
+                .insert(name.to_string(), table.copy_into(&pager)?);
+        }
+        store(&pager, &copy)?;
+        pager.borrow_mut().checkpoint()?;
+    }
+    fs::rename(&tmp_path, path)?;
+    fs::remove_file(wal_path(&tmp_path))?;
+    Ok(())
+}
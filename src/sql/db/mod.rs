@@ -1,2 +1,10 @@
+pub mod btree;
 pub mod database;
+pub mod decimal;
+pub mod file_format;
+pub mod json;
+pub mod paged_column;
+pub mod pager;
 pub mod table;
+pub mod transaction;
+pub mod value;
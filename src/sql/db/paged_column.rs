@@ -0,0 +1,179 @@
+//! Column storage backed by an on-disk B+tree.
+//! A file-backed table keeps each column in its own B+tree keyed by rowid, read through
+//! the database's pager, so the cells do not have to fit in memory.
+
+use crate::error::{RUSQLError, Result};
+use crate::sql::db::btree::BTree;
+use crate::sql::db::decimal::Decimal;
+use crate::sql::db::pager::{Pager, SharedPager};
+use crate::sql::db::table::DataType;
+use crate::sql::db::value::Value;
+use serde::{Deserialize, Serialize};
+use std::cell::RefMut;
+use std::fmt;
+use std::rc::Rc;
+
+const NULL_CELL: u8 = 0;
+const VALUE_CELL: u8 = 1;
+/// Payload length of the integer and real cells of version 2 files (`i32` and `f32`).
+const NARROW_CELL: usize = 4;
+
+/// Only the tree's root page is serialized; the pager is attached after loading.
+#[derive(Serialize, Deserialize)]
+pub struct PagedColumn {
+    pub datatype: DataType,
+    pub tree: BTree,
+    #[serde(skip)]
+    pager: Option<SharedPager>,
+}
+
+impl PagedColumn {
+    /// Creates an empty column tree.
+    pub fn create(datatype: DataType, pager: &SharedPager) -> Result<Self> {
+        let tree = BTree::create(&mut pager.borrow_mut())?;
+        Ok(PagedColumn {
+            datatype,
+            tree,
+            pager: Some(Rc::clone(pager)),
+        })
+    }
+
+    pub fn attach(&mut self, pager: &SharedPager) {
+        self.pager = Some(Rc::clone(pager));
+    }
+
+    fn pager(&self) -> Result<RefMut<'_, Pager>> {
+        self.pager
+            .as_ref()
+            .map(|pager| pager.borrow_mut())
+            .ok_or_else(|| RUSQLError::Internal("Column is not attached to a pager.".to_string()))
+    }
+
+    /// The cell stored for `rowid`, `None` if the column has no entry for it.
+    pub fn cell(&self, rowid: i64) -> Result<Option<Value>> {
+        match self.tree.get(&mut *self.pager()?, rowid)? {
+            Some(bytes) => self.decode(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn contains(&self, rowid: i64) -> Result<bool> {
+        self.tree.contains(&mut *self.pager()?, rowid)
+    }
+
+    pub fn insert(&self, rowid: i64, value: &Value) -> Result<()> {
+        let bytes = self.encode(value)?;
+        self.tree.insert(&mut *self.pager()?, rowid, &bytes)
+    }
+
+    pub fn remove(&self, rowid: i64) -> Result<Option<Value>> {
+        let value = self.cell(rowid)?;
+        if value.is_some() {
+            self.tree.delete(&mut *self.pager()?, rowid)?;
+        }
+        Ok(value)
+    }
+
+    pub fn rowids(&self) -> Result<Vec<i64>> {
+        self.tree.keys(&mut *self.pager()?)
+    }
+
+    /// Every cell in rowid order, read in one pass over the leaves.
+    pub fn values(&self) -> Result<Vec<Value>> {
+        let entries = self.tree.entries(&mut *self.pager()?)?;
+        entries
+            .iter()
+            .map(|(_, bytes)| self.decode(bytes))
+            .collect()
+    }
+
+    /// Frees the pages of the column tree.
+    pub fn destroy(&self) -> Result<()> {
+        self.tree.destroy(&mut *self.pager()?)
+    }
+
+    /// Cells use the same widths as the in-memory column maps, so a value reads back
+    /// the same whichever storage it went through. Integers and reals take 8 bytes, and
+    /// decimals a byte of scale followed by the 16-byte mantissa.
+    fn encode(&self, value: &Value) -> Result<Vec<u8>> {
+        let mut bytes = vec![VALUE_CELL];
+        match (&self.datatype, value) {
+            (_, Value::Null) => return Ok(vec![NULL_CELL]),
+            (DataType::Integer, Value::Integer(v)) => bytes.extend_from_slice(&v.to_le_bytes()),
+            (DataType::Real, Value::Real(v)) => bytes.extend_from_slice(&v.to_le_bytes()),
+            (DataType::Text | DataType::Json, Value::Text(v)) => {
+                bytes.extend_from_slice(v.as_bytes())
+            }
+            (DataType::Bool, Value::Bool(v)) => bytes.push(u8::from(*v)),
+            (DataType::Blob, Value::Blob(v)) => bytes.extend_from_slice(v),
+            (DataType::Decimal(_), Value::Decimal(v)) => {
+                let (mantissa, scale) = v.to_parts();
+                bytes.push(scale as u8);
+                bytes.extend_from_slice(&mantissa.to_le_bytes());
+            }
+            _ => {
+                return Err(RUSQLError::Internal(format!(
+                    "Cannot store {} in a {} column.",
+                    value, self.datatype
+                )))
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Also reads the 4-byte integer and real cells of version 2 files, so they can be
+    /// migrated (see `file_format.rs`).
+    fn decode(&self, bytes: &[u8]) -> Result<Value> {
+        let corrupt = || RUSQLError::General("Corrupt database file: bad cell.".to_string());
+        let (tag, payload) = bytes.split_first().ok_or_else(corrupt)?;
+        if *tag == NULL_CELL {
+            return Ok(Value::Null);
+        }
+        match self.datatype {
+            DataType::Integer => match payload.len() {
+                NARROW_CELL => Ok(Value::Integer(
+                    i32::from_le_bytes(payload.try_into().unwrap()) as i64,
+                )),
+                _ => Ok(Value::Integer(i64::from_le_bytes(
+                    payload.try_into().map_err(|_| corrupt())?,
+                ))),
+            },
+            DataType::Real => match payload.len() {
+                NARROW_CELL => Ok(Value::Real(
+                    f32::from_le_bytes(payload.try_into().unwrap()) as f64
+                )),
+                _ => Ok(Value::Real(f64::from_le_bytes(
+                    payload.try_into().map_err(|_| corrupt())?,
+                ))),
+            },
+            DataType::Text | DataType::Json => String::from_utf8(payload.to_vec())
+                .map(Value::Text)
+                .map_err(|_| corrupt()),
+            DataType::Bool => Ok(Value::Bool(payload.first().ok_or_else(corrupt)? != &0)),
+            DataType::Blob => Ok(Value::Blob(payload.to_vec())),
+            DataType::Decimal(_) => {
+                let (scale, mantissa) = payload.split_first().ok_or_else(corrupt)?;
+                let mantissa = i128::from_le_bytes(mantissa.try_into().map_err(|_| corrupt())?);
+                Decimal::new(mantissa, *scale as u32)
+                    .map(Value::Decimal)
+                    .ok_or_else(corrupt)
+            }
+            DataType::None | DataType::Invalid => Err(corrupt()),
+        }
+    }
+}
+
+impl PartialEq for PagedColumn {
+    fn eq(&self, other: &Self) -> bool {
+        self.datatype == other.datatype && self.tree == other.tree
+    }
+}
+
+impl fmt::Debug for PagedColumn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PagedColumn")
+            .field("datatype", &self.datatype)
+            .field("tree", &self.tree)
+            .finish()
+    }
+}
@@ -0,0 +1,542 @@
+//! Page-level access to a database file.
+//!
+//! The file is a sequence of fixed-size pages. Page 0 holds the file header, every other
+//! page belongs to a B+tree (see `btree.rs`), an overflow chain or the freelist. Pages
+//! are read through a buffer pool that keeps at most `capacity` pages in memory and
+//! evicts the least recently used one, writing it back first if it was modified.
+//!
+//! ```text
+//! page 0: | magic (8) | version (4) | page size (4) | page count (4) | freelist (4) | catalog (4) | file id (4) |
+//! ```
+//!
+//! Modified pages never go straight to the database file. They are appended to a
+//! write-ahead log next to it (`<file>-wal`) when they are evicted or committed:
+//!
+//! ```text
+//! log:   | magic (8) | file id (4) | salt (4) | page size (4) | frame | frame | ...
+//! frame: | page (4) | commit (4) | salt (4) | checksum (8) | page data (4096) |
+//! ```
+//!
+//! `commit` appends every modified page followed by a copy of the header page whose
+//! commit field holds the page count, then syncs the log. Reads look in the log before
+//! the database file. A checkpoint copies the newest committed version of each logged
+//! page into the database file, syncs it and starts a new log with a different salt.
+//!
+//! Opening a file replays its log: frames are read up to the first one that is
+//! incomplete or fails its checksum, everything after the last commit frame is
+//! discarded, and the committed frames are checkpointed. A crash therefore loses at
+//! most the transaction that was being written.
+
+use crate::error::{RUSQLError, Result};
+use crate::sql::db::file_format::{FORMAT_VERSION, MAGIC};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const PAGE_SIZE: usize = 4096;
+/// Number of pages the buffer pool holds when no size is given (1 MiB).
+pub const DEFAULT_CACHE_PAGES: usize = 256;
+
+/// Suffix appended to the database path to name its write-ahead log.
+pub const WAL_SUFFIX: &str = "-wal";
+/// Committing checkpoints the log once it holds this many frames.
+pub const CHECKPOINT_FRAMES: u64 = 1000;
+
+const WAL_MAGIC: &[u8; 8] = b"RUSQLWAL";
+const WAL_HEADER_LEN: u64 = 20;
+const FRAME_HEADER_LEN: u64 = 20;
+const FRAME_LEN: u64 = FRAME_HEADER_LEN + PAGE_SIZE as u64;
+
+/// Page number within the file. Page 0 is the header, so 0 doubles as "no page".
+pub type PageId = u32;
+
+/// A pager shared by the database and the column trees that read through it.
+pub type SharedPager = Rc<RefCell<Pager>>;
+
+struct Frame {
+    data: Vec<u8>,
+    dirty: bool,
+    /// Position of the frame in `Pager::lru`.
+    tick: u64,
+}
+
+pub struct Pager {
+    file: File,
+    path: PathBuf,
+    page_count: u32,
+    freelist_head: PageId,
+    catalog_root: PageId,
+    /// Identifies the database file, so a log left by another file is never replayed.
+    file_id: u32,
+    wal: File,
+    /// Changes with every new log, so frames of an earlier log are never replayed.
+    wal_salt: u32,
+    /// End of the last frame in the log.
+    wal_len: u64,
+    /// Offset in the log of the newest copy of each logged page.
+    wal_index: HashMap<PageId, u64>,
+    /// Whether the log holds frames written since the last commit.
+    uncommitted: bool,
+    capacity: usize,
+    frames: HashMap<PageId, Frame>,
+    /// Frames ordered by last use; the first entry is the eviction candidate.
+    lru: BTreeMap<u64, PageId>,
+    clock: u64,
+}
+
+impl Pager {
+    /// Creates a new, empty database file at `path`, replacing any existing file.
+    pub fn create(path: &Path, capacity: usize) -> Result<Pager> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let mut pager = Pager::new(file, open_wal(path)?, path, capacity);
+        pager.page_count = 1;
+        pager.file_id = new_salt();
+        let header = pager.header();
+        write_page_at(&mut pager.file, 0, &header)?;
+        pager.file.sync_all()?;
+        pager.reset_wal()?;
+        Ok(pager)
+    }
+
+    /// Opens an existing database file, validating its header and recovering the
+    /// changes committed to its log.
+    pub fn open(path: &Path, capacity: usize) -> Result<Pager> {
+        Pager::open_version(path, capacity, FORMAT_VERSION)
+    }
+
+    /// Opens a database file written in format `version`. Only migrations open files of
+    /// an older version; committing writes the current version into the header.
+    pub fn open_version(path: &Path, capacity: usize, expected: u32) -> Result<Pager> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        let mut header = vec![0; PAGE_SIZE];
+        file.read_exact(&mut header)
+            .map_err(|_| RUSQLError::General("File is not a RUSQL database.".to_string()))?;
+
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(RUSQLError::General(
+                "File is not a RUSQL database.".to_string(),
+            ));
+        }
+        let version = read_u32(&header, 8);
+        if version != expected {
+            return Err(RUSQLError::General(format!(
+                "Unsupported database file format version {} (this build reads version {}).",
+                version, FORMAT_VERSION
+            )));
+        }
+        if read_u32(&header, 12) as usize != PAGE_SIZE {
+            return Err(RUSQLError::General(format!(
+                "Unsupported page size {}.",
+                read_u32(&header, 12)
+            )));
+        }
+
+        let mut pager = Pager::new(file, open_wal(path)?, path, capacity);
+        pager.read_header(&header);
+        pager.file_id = read_u32(&header, 28);
+        pager.recover()?;
+
+        let file_len = pager.file.metadata()?.len();
+        if file_len < pager.page_count as u64 * PAGE_SIZE as u64 {
+            return Err(RUSQLError::General(
+                "Corrupt database file: file is shorter than its page count.".to_string(),
+            ));
+        }
+        Ok(pager)
+    }
+
+    fn new(file: File, wal: File, path: &Path, capacity: usize) -> Pager {
+        Pager {
+            file,
+            path: path.to_path_buf(),
+            page_count: 0,
+            freelist_head: 0,
+            catalog_root: 0,
+            file_id: 0,
+            wal,
+            wal_salt: new_salt(),
+            wal_len: 0,
+            wal_index: HashMap::new(),
+            uncommitted: false,
+            capacity: capacity.max(1),
+            frames: HashMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn page_count(&self) -> u32 {
+        self.page_count
+    }
+
+    /// Number of pages currently held by the buffer pool.
+    pub fn cached_pages(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn catalog_root(&self) -> PageId {
+        self.catalog_root
+    }
+
+    pub fn set_catalog_root(&mut self, root: PageId) {
+        self.catalog_root = root;
+    }
+
+    /// Returns the contents of a page, loading it into the buffer pool if needed.
+    pub fn read(&mut self, id: PageId) -> Result<&[u8]> {
+        self.load(id)?;
+        Ok(&self.frames[&id].data)
+    }
+
+    /// Replaces the contents of a page. `data` may be shorter than a page; the rest of
+    /// the page is zeroed.
+    pub fn write(&mut self, id: PageId, data: &[u8]) -> Result<()> {
+        if data.len() > PAGE_SIZE {
+            return Err(RUSQLError::Internal(format!(
+                "Page {} overflows: {} bytes",
+                id,
+                data.len()
+            )));
+        }
+        self.load(id)?;
+        let frame = self.frames.get_mut(&id).unwrap();
+        frame.data[..data.len()].copy_from_slice(data);
+        frame.data[data.len()..].fill(0);
+        frame.dirty = true;
+        Ok(())
+    }
+
+    /// Hands out a zeroed page, reusing a freed page when there is one.
+    pub fn allocate(&mut self) -> Result<PageId> {
+        if self.freelist_head != 0 {
+            let id = self.freelist_head;
+            self.freelist_head = read_u32(self.read(id)?, 0);
+            self.write(id, &[])?;
+            return Ok(id);
+        }
+
+        let id = self.page_count;
+        self.page_count += 1;
+        self.insert_frame(id, vec![0; PAGE_SIZE], true)?;
+        Ok(id)
+    }
+
+    /// Returns a page to the freelist.
+    pub fn free(&mut self, id: PageId) -> Result<()> {
+        let next = self.freelist_head;
+        self.write(id, &next.to_le_bytes())?;
+        self.freelist_head = id;
+        Ok(())
+    }
+
+    /// Makes every change since the last commit durable: appends the modified pages and
+    /// the header to the log and syncs it. Checkpoints once the log grows long.
+    pub fn commit(&mut self) -> Result<()> {
+        let mut dirty: Vec<PageId> = self
+            .frames
+            .iter()
+            .filter(|(_, frame)| frame.dirty)
+            .map(|(id, _)| *id)
+            .collect();
+        dirty.sort_unstable();
+        for id in dirty {
+            let frame = self.frames.get_mut(&id).unwrap();
+            frame.dirty = false;
+            let offset = append_frame(
+                &mut self.wal,
+                self.wal_len,
+                self.wal_salt,
+                id,
+                0,
+                &frame.data,
+            )?;
+            self.wal_index.insert(id, offset);
+            self.wal_len += FRAME_LEN;
+        }
+
+        let header = self.header();
+        let offset = append_frame(
+            &mut self.wal,
+            self.wal_len,
+            self.wal_salt,
+            0,
+            self.page_count,
+            &header,
+        )?;
+        self.wal_index.insert(0, offset);
+        self.wal_len += FRAME_LEN;
+        self.wal.sync_data()?;
+        self.uncommitted = false;
+
+        if self.wal_frames() >= CHECKPOINT_FRAMES {
+            self.checkpoint()?;
+        }
+        Ok(())
+    }
+
+    /// Copies the committed pages of the log into the database file and starts a new
+    /// log. Does nothing while there are uncommitted changes.
+    pub fn checkpoint(&mut self) -> Result<()> {
+        if self.uncommitted || self.frames.values().any(|frame| frame.dirty) {
+            return Ok(());
+        }
+        if !self.wal_index.is_empty() {
+            let mut pages: Vec<(PageId, u64)> = self
+                .wal_index
+                .iter()
+                .map(|(id, offset)| (*id, *offset))
+                .collect();
+            pages.sort_unstable();
+            let mut data = vec![0; PAGE_SIZE];
+            for (id, offset) in pages {
+                read_at(&mut self.wal, offset, &mut data)?;
+                write_page_at(&mut self.file, id, &data)?;
+            }
+            self.file.sync_all()?;
+        }
+        self.reset_wal()
+    }
+
+    /// Number of frames in the log.
+    pub fn wal_frames(&self) -> u64 {
+        self.wal_len.saturating_sub(WAL_HEADER_LEN) / FRAME_LEN
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let mut header = vec![0; PAGE_SIZE];
+        header[..MAGIC.len()].copy_from_slice(MAGIC);
+        header[8..12].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+        header[12..16].copy_from_slice(&(PAGE_SIZE as u32).to_le_bytes());
+        header[16..20].copy_from_slice(&self.page_count.to_le_bytes());
+        header[20..24].copy_from_slice(&self.freelist_head.to_le_bytes());
+        header[24..28].copy_from_slice(&self.catalog_root.to_le_bytes());
+        header[28..32].copy_from_slice(&self.file_id.to_le_bytes());
+        header
+    }
+
+    fn read_header(&mut self, header: &[u8]) {
+        self.page_count = read_u32(header, 16);
+        self.freelist_head = read_u32(header, 20);
+        self.catalog_root = read_u32(header, 24);
+    }
+
+    /// Replays the log: keeps the frames up to the last valid commit frame, then
+    /// checkpoints them into the database file.
+    fn recover(&mut self) -> Result<()> {
+        let wal_len = self.wal.metadata()?.len();
+        let mut header = vec![0; WAL_HEADER_LEN as usize];
+        let valid = wal_len >= WAL_HEADER_LEN
+            && read_at(&mut self.wal, 0, &mut header).is_ok()
+            && &header[..WAL_MAGIC.len()] == WAL_MAGIC
+            && read_u32(&header, 8) == self.file_id
+            && read_u32(&header, 16) as usize == PAGE_SIZE;
+        if !valid {
+            return self.reset_wal();
+        }
+        self.wal_salt = read_u32(&header, 12);
+
+        let mut pending = HashMap::new();
+        let mut frame = vec![0; FRAME_LEN as usize];
+        let mut offset = WAL_HEADER_LEN;
+        while offset + FRAME_LEN <= wal_len {
+            read_at(&mut self.wal, offset, &mut frame)?;
+            let (frame_header, data) = frame.split_at(FRAME_HEADER_LEN as usize);
+            let id = read_u32(frame_header, 0);
+            let commit = read_u32(frame_header, 4);
+            if read_u32(frame_header, 8) != self.wal_salt
+                || read_u64(frame_header, 12) != frame_checksum(self.wal_salt, id, commit, data)
+            {
+                break;
+            }
+            pending.insert(id, offset + FRAME_HEADER_LEN);
+            offset += FRAME_LEN;
+
+            if commit != 0 {
+                self.wal_index.extend(pending.drain());
+                self.wal_len = offset;
+                self.read_header(data);
+            }
+        }
+        self.checkpoint()
+    }
+
+    /// Empties the log and gives it a new salt.
+    fn reset_wal(&mut self) -> Result<()> {
+        self.wal_salt = self.wal_salt.wrapping_add(1);
+        let mut header = Vec::with_capacity(WAL_HEADER_LEN as usize);
+        header.extend_from_slice(WAL_MAGIC);
+        header.extend_from_slice(&self.file_id.to_le_bytes());
+        header.extend_from_slice(&self.wal_salt.to_le_bytes());
+        header.extend_from_slice(&(PAGE_SIZE as u32).to_le_bytes());
+
+        self.wal.set_len(0)?;
+        self.wal.seek(SeekFrom::Start(0))?;
+        self.wal.write_all(&header)?;
+        self.wal.sync_all()?;
+        self.wal_len = WAL_HEADER_LEN;
+        self.wal_index.clear();
+        Ok(())
+    }
+
+    fn load(&mut self, id: PageId) -> Result<()> {
+        if id == 0 || id >= self.page_count {
+            return Err(RUSQLError::General(format!(
+                "Corrupt database file: page {} is out of range.",
+                id
+            )));
+        }
+        if let Some(frame) = self.frames.get_mut(&id) {
+            self.lru.remove(&frame.tick);
+            self.clock += 1;
+            frame.tick = self.clock;
+            self.lru.insert(self.clock, id);
+            return Ok(());
+        }
+
+        let mut data = vec![0; PAGE_SIZE];
+        let read = match self.wal_index.get(&id) {
+            Some(offset) => read_at(&mut self.wal, *offset, &mut data),
+            None => read_at(&mut self.file, id as u64 * PAGE_SIZE as u64, &mut data),
+        };
+        read.map_err(|_| {
+            RUSQLError::General(format!("Corrupt database file: page {} is missing.", id))
+        })?;
+        self.insert_frame(id, data, false)
+    }
+
+    fn insert_frame(&mut self, id: PageId, data: Vec<u8>, dirty: bool) -> Result<()> {
+        while self.frames.len() >= self.capacity {
+            self.evict()?;
+        }
+        self.clock += 1;
+        self.lru.insert(self.clock, id);
+        self.frames.insert(
+            id,
+            Frame {
+                data,
+                dirty,
+                tick: self.clock,
+            },
+        );
+        Ok(())
+    }
+
+    fn evict(&mut self) -> Result<()> {
+        let (_, id) = match self.lru.pop_first() {
+            Some(entry) => entry,
+            None => return Ok(()),
+        };
+        let frame = self.frames.remove(&id).unwrap();
+        if frame.dirty {
+            let offset = append_frame(
+                &mut self.wal,
+                self.wal_len,
+                self.wal_salt,
+                id,
+                0,
+                &frame.data,
+            )?;
+            self.wal_index.insert(id, offset);
+            self.wal_len += FRAME_LEN;
+            self.uncommitted = true;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Pager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Pager")
+            .field("path", &self.path)
+            .field("page_count", &self.page_count)
+            .field("cached_pages", &self.frames.len())
+            .finish()
+    }
+}
+
+fn write_page_at(file: &mut File, id: PageId, data: &[u8]) -> Result<()> {
+    file.seek(SeekFrom::Start(id as u64 * PAGE_SIZE as u64))?;
+    file.write_all(data)?;
+    Ok(())
+}
+
+fn read_at(file: &mut File, offset: u64, data: &mut [u8]) -> Result<()> {
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(data)?;
+    Ok(())
+}
+
+/// Path of the write-ahead log of the database file at `path`.
+pub fn wal_path(path: &Path) -> PathBuf {
+    let mut wal_path = path.as_os_str().to_owned();
+    wal_path.push(WAL_SUFFIX);
+    PathBuf::from(wal_path)
+}
+
+fn open_wal(path: &Path) -> Result<File> {
+    Ok(OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(wal_path(path))?)
+}
+
+/// Writes a frame at `offset` in the log and returns the offset of its page data.
+fn append_frame(
+    wal: &mut File,
+    offset: u64,
+    salt: u32,
+    id: PageId,
+    commit: u32,
+    data: &[u8],
+) -> Result<u64> {
+    let mut frame = Vec::with_capacity(FRAME_LEN as usize);
+    frame.extend_from_slice(&id.to_le_bytes());
+    frame.extend_from_slice(&commit.to_le_bytes());
+    frame.extend_from_slice(&salt.to_le_bytes());
+    frame.extend_from_slice(&frame_checksum(salt, id, commit, data).to_le_bytes());
+    frame.extend_from_slice(data);
+    wal.seek(SeekFrom::Start(offset))?;
+    wal.write_all(&frame)?;
+    Ok(offset + FRAME_HEADER_LEN)
+}
+
+/// FNV-1a over the frame header fields and the page data.
+fn frame_checksum(salt: u32, id: PageId, commit: u32, data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let fields = [salt.to_le_bytes(), id.to_le_bytes(), commit.to_le_bytes()];
+    for byte in fields.iter().flatten().chain(data) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+fn new_salt() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos() ^ time.as_secs() as u32)
+}
+
+pub fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
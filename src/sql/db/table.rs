@@ -3,22 +3,33 @@
 //! Supports primary keys, unique constraints, and automatic indexing.
 
 use crate::error::{RUSQLError, Result};
+use crate::sql::db::decimal::{Decimal, MAX_PRECISION};
+use crate::sql::db::json::Json;
+use crate::sql::db::paged_column::PagedColumn;
+use crate::sql::db::pager::SharedPager;
+use crate::sql::db::value::{parse_number, real_to_integer, Value};
 use crate::sql::parser::create::CreateQuery;
 use colored::*;
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt;
 use std::rc::Rc;
 
 use prettytable::{row, Cell as PrintCell, Row as PrintRow, Table as PrintTable};
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub enum DataType {
     Integer,
     Text,
     Real,
     Bool,
+    /// JSON documents, stored as minified text.
+    Json,
+    Blob,
+    /// Exact decimals with at most `precision` digits, `scale` of them after the point.
+    /// Without a precision and scale, any decimal is kept at its own scale.
+    Decimal(Option<(u32, u32)>),
     None,
     Invalid,
 }
@@ -30,13 +41,82 @@ impl DataType {
             "text" => DataType::Text,
             "real" => DataType::Real,
             "bool" => DataType::Bool,
+            "json" => DataType::Json,
+            "blob" => DataType::Blob,
             "none" => DataType::None,
+            name if name.starts_with("decimal") => match decimal_spec(&name["decimal".len()..]) {
+                Some(spec) => DataType::Decimal(spec),
+                None => {
+                    eprintln!("Invalid data type given {}", cmd);
+                    DataType::Invalid
+                }
+            },
             _ => {
                 eprintln!("Invalid data type given {}", cmd);
-                return DataType::Invalid;
+                DataType::Invalid
             }
         }
     }
+
+    /// Applies the type affinity of a column of this type to `value`, returning the value
+    /// as it would be stored, or `None` when it cannot be represented. NULL passes through.
+    ///
+    /// Text only converts to a number when the whole string is numeric, reals convert to
+    /// integers when they have no fractional part and fit in 64 bits, and BOOL accepts true/false and 0/1.
+    /// JSON only accepts well-formed JSON, which it stores minified. BLOB accepts blobs,
+    /// and text as its UTF-8 bytes. DECIMAL reads text digit by digit, so `'0.1'` is
+    /// exactly 0.1, rounds half up to the column's scale and rejects values with more
+    /// digits than its precision.
+    pub fn coerce(&self, value: &Value) -> Option<Value> {
+        let numeric = match value {
+            Value::Text(s) => parse_number(s),
+            Value::Blob(_) => None,
+            _ => Some(value.to_numeric()),
+        };
+
+        match (self, value) {
+            (_, Value::Null) => Some(Value::Null),
+            (DataType::Integer, _) => match numeric? {
+                Value::Integer(i) => Some(Value::Integer(i)),
+                Value::Real(r) => real_to_integer(r).map(Value::Integer),
+                Value::Decimal(d) => d.to_integer().map(Value::Integer),
+                _ => None,
+            },
+            (DataType::Real, _) => numeric?.as_f64().map(Value::Real),
+            (DataType::Text, Value::Blob(_)) => None,
+            (DataType::Text, _) => Some(Value::Text(value.to_string())),
+            (DataType::Bool, Value::Bool(b)) => Some(Value::Bool(*b)),
+            (DataType::Bool, Value::Text(s)) => match s.to_lowercase().as_str() {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            (DataType::Bool, _) => match numeric? {
+                Value::Integer(0) => Some(Value::Bool(false)),
+                Value::Integer(1) => Some(Value::Bool(true)),
+                _ => None,
+            },
+            (DataType::Json, Value::Blob(_)) => None,
+            (DataType::Json, _) => {
+                Json::parse(&value.to_string()).map(|json| Value::Text(json.to_string()))
+            }
+            (DataType::Blob, Value::Blob(b)) => Some(Value::Blob(b.clone())),
+            (DataType::Blob, Value::Text(s)) => Some(Value::Blob(s.as_bytes().to_vec())),
+            (DataType::Blob, _) => None,
+            (DataType::Decimal(spec), _) => {
+                let decimal = match value {
+                    Value::Text(s) => Decimal::parse(s),
+                    _ => numeric?.as_decimal(),
+                }?;
+                match spec {
+                    Some((precision, scale)) => decimal.fit(*precision, *scale),
+                    None => Some(decimal),
+                }
+                .map(Value::Decimal)
+            }
+            (DataType::None | DataType::Invalid, _) => None,
+        }
+    }
 }
 
 impl fmt::Display for DataType {
@@ -46,6 +126,12 @@ impl fmt::Display for DataType {
             DataType::Text => f.write_str("Text"),
             DataType::Real => f.write_str("Real"),
             DataType::Bool => f.write_str("Boolean"),
+            DataType::Json => f.write_str("Json"),
+            DataType::Blob => f.write_str("Blob"),
+            DataType::Decimal(None) => f.write_str("Decimal"),
+            DataType::Decimal(Some((precision, scale))) => {
+                write!(f, "Decimal({},{})", precision, scale)
+            }
             DataType::None => f.write_str("None"),
             DataType::Invalid => f.write_str("Invalid"),
         }
@@ -60,6 +146,23 @@ pub struct Table {
     pub indexes: HashMap<String, String>,
     pub last_rowid: i64,
     pub primary_key: String,
+    /// Constraints over several columns. Absent from files written before table-level
+    /// constraints existed, which read as none.
+    #[serde(default)]
+    pub unique_keys: Vec<UniqueKey>,
+}
+
+/// Reads the `(precision,scale)` suffix of a decimal type name: empty for a decimal of
+/// any scale, `(p)` for whole numbers of up to `p` digits.
+fn decimal_spec(suffix: &str) -> Option<Option<(u32, u32)>> {
+    if suffix.is_empty() {
+        return Some(None);
+    }
+    let args = suffix.strip_prefix('(')?.strip_suffix(')')?;
+    let (precision, scale) = args.split_once(',').unwrap_or((args, "0"));
+    let (precision, scale) = (precision.trim().parse().ok()?, scale.trim().parse().ok()?);
+    (1..=MAX_PRECISION).contains(&precision).then_some(())?;
+    (scale <= precision).then_some(Some((precision, scale)))
 }
 
 pub fn rusql_insert_datatype_based_row(
@@ -75,12 +178,18 @@ pub fn rusql_insert_datatype_based_row(
         DataType::Real => {
             table_rows_mut.insert(col_name, Row::Real(BTreeMap::new()));
         }
-        DataType::Text => {
+        DataType::Text | DataType::Json => {
             table_rows_mut.insert(col_name, Row::Text(BTreeMap::new()));
         }
         DataType::Bool => {
             table_rows_mut.insert(col_name, Row::Bool(BTreeMap::new()));
         }
+        DataType::Blob => {
+            table_rows_mut.insert(col_name, Row::Blob(BTreeMap::new()));
+        }
+        DataType::Decimal(_) => {
+            table_rows_mut.insert(col_name, Row::Decimal(BTreeMap::new()));
+        }
         DataType::Invalid | DataType::None => {
             table_rows_mut.insert(col_name, Row::None);
         }
@@ -91,34 +200,54 @@ pub fn create_error(message: &str) -> Result<()> {
     Err(RUSQLError::General(String::from(message)))
 }
 
-pub fn validate_column_unique_constraint(column: &mut Column, name: &str, val: &str) -> Result<()> {
-    if !column.is_unique {
+/// Checks that no row already holds `value` in a UNIQUE column. NULLs never conflict.
+pub fn validate_column_unique_constraint(column: &Column, value: &Value) -> Result<()> {
+    if !column.is_unique || value.is_null() {
         return Ok(());
     }
 
-    let col_idx = &column.index;
-    match col_idx {
-        Index::Integer(index) => {
-            if index.contains_key(&val.parse::<i32>().unwrap()) {
-                return create_error(&format!(
-                    "Error: Unique constraint violation for column {}. Value {} already exists.",
-                    name, val
-                ));
-            }
+    if let Index::None = column.index {
+        return create_error(&format!(
+            "Error: Cannot find index for column {}. ",
+            column.column_name
+        ));
+    }
+    if column.index.rowids_for(value).is_some() {
+        return Err(RUSQLError::KeyViolation(format!(
+            "Error: Unique constraint violation for column {}. Value {} already exists.",
+            column.column_name, value
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that `value` may be stored in `column` for row `rowid`: NOT NULL columns
+/// reject NULL and UNIQUE columns reject a value held by any other row. A row that has
+/// no rowid yet conflicts with every holder.
+pub fn validate_column_constraints(
+    column: &Column,
+    rowid: Option<i64>,
+    value: &Value,
+) -> Result<()> {
+    if value.is_null() {
+        if column.not_null {
+            return create_error(&format!(
+                "Error: NOT NULL constraint violation for column {}.",
+                column.column_name
+            ));
         }
+        return Ok(());
+    }
 
-        Index::Text(index) => {
-            if index.contains_key(val) {
-                return create_error(&format!(
+    if column.is_unique {
+        if let Some(rowids) = column.index.rowids_for(value) {
+            if rowids.iter().any(|holder| Some(*holder) != rowid) {
+                return Err(RUSQLError::KeyViolation(format!(
                     "Error: Unique constraint violation for column {}. Value {} already exists.",
-                    name, val
-                ));
+                    column.column_name, value
+                )));
             }
         }
-
-        Index::None => {
-            return create_error(&format!("Error: Cannot find index for column {}. ", name));
-        }
     }
     Ok(())
 }
@@ -137,13 +266,15 @@ impl Table {
                 primary_key = col_name.to_string();
             }
 
-            table_cols.push(Column::new(
+            let mut column = Column::new(
                 col_name.to_string(),
                 col.datatype.to_string(),
                 col.is_pk,
                 col.not_null,
                 col.is_unique,
-            ));
+            );
+            column.default = col.default.clone();
+            table_cols.push(column);
 
             rusql_insert_datatype_based_row(
                 DataType::new(col.datatype.to_string()),
@@ -152,13 +283,25 @@ impl Table {
             );
         }
 
+        let unique_keys = create_query
+            .constraints
+            .into_iter()
+            .map(|constraint| UniqueKey {
+                name: constraint.name,
+                columns: constraint.columns,
+                is_primary: constraint.is_primary,
+                index: BTreeMap::new(),
+            })
+            .collect();
+
         Table {
             tb_name: table_name,
             columns: table_cols,
             rows: table_rows,
             indexes: HashMap::new(),
             last_rowid: 0,
-            primary_key: primary_key,
+            primary_key,
+            unique_keys,
         }
     }
 
@@ -166,7 +309,6 @@ impl Table {
         self.columns.iter().any(|col| col.column_name == column)
     }
 
-    #[allow(dead_code)]
     pub fn get_column(&self, column_name: String) -> Result<&Column> {
         if let Some(column) = self
             .columns
@@ -181,7 +323,7 @@ impl Table {
         }
     }
 
-    pub fn get_column_mut<'a>(&mut self, column_name: String) -> Result<&mut Column> {
+    pub fn get_column_mut(&mut self, column_name: String) -> Result<&mut Column> {
         for elem in self.columns.iter_mut() {
             if elem.column_name == column_name {
                 return Ok(elem);
@@ -190,159 +332,390 @@ impl Table {
         Err(RUSQLError::General(String::from("Column not found.")))
     }
 
-    pub fn validate_unique_constraint(
-        &mut self,
-        cols: &Vec<String>,
-        values: &Vec<String>,
-    ) -> Result<()> {
-        for (idx, name) in cols.iter().enumerate() {
-            let column = self.get_column_mut(name.to_string()).unwrap();
-            let val = &values[idx];
-            validate_column_unique_constraint(column, name, val)?;
+    /// Checks the values about to be inserted against the UNIQUE columns they target and
+    /// against the table-level UNIQUE and PRIMARY KEY constraints.
+    pub fn validate_unique_constraint(&self, cols: &[String], values: &[Value]) -> Result<()> {
+        for (name, value) in cols.iter().zip(values) {
+            let column = self.get_column(name.to_string())?;
+            let value = column.coerce_value(value)?;
+            validate_column_unique_constraint(column, &value)?;
+        }
+        if !self.unique_keys.is_empty() {
+            let row = self.handle_missing_columns(cols, values)?;
+            self.validate_unique_keys(None, &row)?;
+        }
+        Ok(())
+    }
+
+    /// Checks a full row, in schema order, against the table-level constraints. `rowid`
+    /// is the row being written, which may keep its own key.
+    pub fn validate_unique_keys(&self, rowid: Option<i64>, row: &[Value]) -> Result<()> {
+        for key in &self.unique_keys {
+            key.validate(&self.columns, rowid, row)?;
+        }
+        Ok(())
+    }
+
+    /// Inserts a row given as parallel lists of column names and values. Columns may be
+    /// listed in any order and omitted ones are stored as NULL. Every value is converted
+    /// to its column type and checked against the NOT NULL and UNIQUE constraints first,
+    /// so a rejected row leaves the table untouched. Returns the rowid of the new row.
+    /// Conversion errors are reported before key violations, which are
+    /// `RUSQLError::KeyViolation`.
+    pub fn insert_row(&mut self, cols: &[String], values: &[Value]) -> Result<i64> {
+        let mut row = self.handle_missing_columns(cols, values)?;
+        let next_rowid = self.handle_primary_key(&mut row)?;
+
+        for (column, value) in self.columns.iter().zip(&row) {
+            validate_column_constraints(column, None, value)?;
+        }
+        self.validate_unique_keys(None, &row)?;
+        for (col_idx, value) in row.iter().enumerate() {
+            self.insert_value_into_column(col_idx, value, next_rowid)?;
+        }
+        for key in self.unique_keys.iter_mut() {
+            key.insert(&self.columns, &row, next_rowid);
+        }
+        self.last_rowid = self.last_rowid.max(next_rowid);
+        Ok(next_rowid)
+    }
+
+    /// Writes a row back at `rowid` exactly as it was read, without converting or
+    /// checking its values. Used to undo changes.
+    pub fn restore_row(&mut self, rowid: i64, values: &[Value]) -> Result<()> {
+        for (col_idx, value) in values.iter().enumerate() {
+            self.insert_value_into_column(col_idx, value, rowid)?;
+        }
+        for key in self.unique_keys.iter_mut() {
+            key.insert(&self.columns, values, rowid);
         }
         Ok(())
     }
 
-    pub fn insert_row(&mut self, cols: &Vec<String>, values: &Vec<String>) {
-        let mut next_rowid = self.last_rowid + i64::from(1);
+    /// Picks the rowid of a new row. An INTEGER primary key is the rowid: an explicit
+    /// value is used as is, a missing or NULL one is assigned the next free rowid.
+    pub fn handle_primary_key(&self, row: &mut [Value]) -> Result<i64> {
+        let next_rowid = self.last_rowid.checked_add(1).ok_or_else(|| {
+            RUSQLError::General(format!(
+                "Error: Table {} has no rowids left after {}.",
+                self.tb_name, self.last_rowid
+            ))
+        });
+        let pk_idx = self
+            .columns
+            .iter()
+            .position(|col| col.column_name == self.primary_key);
 
-        if self.primary_key != "-1" {
-            next_rowid = self.handle_primary_key(cols, values, next_rowid);
+        match pk_idx {
+            Some(idx) if self.columns[idx].datatype == DataType::Integer => match row[idx] {
+                Value::Integer(rowid) => Ok(rowid),
+                _ => {
+                    let next_rowid = next_rowid?;
+                    row[idx] = Value::Integer(next_rowid);
+                    Ok(next_rowid)
+                }
+            },
+            _ => next_rowid,
+        }
+    }
+
+    /// Lays the given values out in schema order, converted to their column types, with
+    /// NULL for every column that was not named.
+    pub fn handle_missing_columns(&self, cols: &[String], values: &[Value]) -> Result<Vec<Value>> {
+        if cols.len() != values.len() {
+            return Err(RUSQLError::General(format!(
+                "Column count and value count mismatch. Columns: {}, Values: {}",
+                cols.len(),
+                values.len()
+            )));
+        }
+        for (idx, name) in cols.iter().enumerate() {
+            if !self.contains_column(name.to_string()) {
+                return Err(RUSQLError::General(format!("Column {} not found.", name)));
+            }
+            if cols[..idx].contains(name) {
+                return Err(RUSQLError::General(format!(
+                    "Column {} specified more than once.",
+                    name
+                )));
+            }
         }
 
-        self.handle_missing_columns(cols, values, next_rowid);
-        self.last_rowid = next_rowid;
+        self.columns
+            .iter()
+            .map(
+                |column| match cols.iter().position(|col| col == &column.column_name) {
+                    Some(idx) => column.coerce_value(&values[idx]),
+                    None => Ok(Value::Null),
+                },
+            )
+            .collect()
     }
 
-    pub fn handle_primary_key(
+    /// Stores an already converted value in the column at `col_idx` and indexes it.
+    pub fn insert_value_into_column(
         &mut self,
-        cols: &Vec<String>,
-        values: &Vec<String>,
+        col_idx: usize,
+        value: &Value,
         next_rowid: i64,
-    ) -> i64 {
-        let mut next_rowid = next_rowid;
-        if !cols.iter().any(|col| col == &self.primary_key) {
-            next_rowid = self.auto_assign_primary_key(next_rowid);
-        } else {
-            next_rowid = self.assign_primary_key_from_values(cols, values, next_rowid);
+    ) -> Result<()> {
+        let column = &mut self.columns[col_idx];
+        let rows_clone = Rc::clone(&self.rows);
+        let mut row_data = rows_clone.as_ref().borrow_mut();
+        if let Some(row) = row_data.get_mut(&column.column_name) {
+            row.insert_value(next_rowid, value)?;
+            column.index_value(value, next_rowid);
         }
-        next_rowid
+        Ok(())
     }
 
-    pub fn auto_assign_primary_key(&mut self, next_rowid: i64) -> i64 {
+    /// Overwrites columns of an existing row. `assignments` pairs column names with their
+    /// new values; every value is converted to its column type and checked against the
+    /// column constraints before anything is written, so a rejected update leaves the row
+    /// untouched. Assigning a new primary key moves the row to the matching rowid, which
+    /// is returned.
+    pub fn update_row(&mut self, rowid: i64, assignments: &[(String, Value)]) -> Result<i64> {
+        let old_row = if self.unique_keys.is_empty() {
+            vec![]
+        } else {
+            self.get_row_values(rowid)?
+        };
         let rows_clone = Rc::clone(&self.rows);
         let mut row_data = rows_clone.as_ref().borrow_mut();
-        let mut table_col_data = row_data.get_mut(&self.primary_key).unwrap();
-        let column_headers = self.get_column_mut(self.primary_key.to_string()).unwrap();
-        let col_index = column_headers.get_mut_index();
-
-        match &mut table_col_data {
-            Row::Integer(tree) => {
-                let val = next_rowid as i32;
-                tree.insert(next_rowid.clone(), val);
-                if let Index::Integer(index) = col_index {
-                    index.insert(val, next_rowid.clone());
+
+        let mut converted: Vec<(usize, Value)> = vec![];
+        for (name, value) in assignments {
+            let col_idx = self
+                .columns
+                .iter()
+                .position(|col| &col.column_name == name)
+                .ok_or_else(|| RUSQLError::General(format!("Column {} not found.", name)))?;
+            let column = &self.columns[col_idx];
+            let value = column.coerce_value(value)?;
+            validate_column_constraints(column, Some(rowid), &value)?;
+            converted.retain(|(idx, _)| *idx != col_idx);
+            converted.push((col_idx, value));
+        }
+
+        let mut new_rowid = rowid;
+        for (col_idx, value) in &converted {
+            let column = &self.columns[*col_idx];
+            // Only an INTEGER primary key is the rowid.
+            if column.column_name == self.primary_key && column.datatype == DataType::Integer {
+                new_rowid = match value {
+                    Value::Integer(pk) => *pk,
+                    _ => {
+                        return Err(RUSQLError::General(format!(
+                            "Error: Primary key {} must be an integer.",
+                            self.primary_key
+                        )))
+                    }
+                };
+            }
+        }
+        let mut taken = false;
+        if new_rowid != rowid {
+            for row in row_data.values() {
+                taken = taken || row.contains(new_rowid)?;
+            }
+        }
+        if taken {
+            return Err(RUSQLError::KeyViolation(format!(
+                "Error: Unique constraint violation for column {}. Value {} already exists.",
+                self.primary_key, new_rowid
+            )));
+        }
+
+        let mut new_row = old_row.clone();
+        if !self.unique_keys.is_empty() {
+            for (col_idx, value) in &converted {
+                new_row[*col_idx] = value.clone();
+            }
+            self.validate_unique_keys(Some(rowid), &new_row)?;
+        }
+
+        for (col_idx, value) in converted {
+            let column = &mut self.columns[col_idx];
+            let row = row_data.get_mut(&column.column_name).unwrap();
+            if let Some(old) = row.remove_value(rowid)? {
+                column.unindex_value(&old, rowid);
+            }
+            row.insert_value(rowid, &value)?;
+            column.index_value(&value, rowid);
+        }
+
+        if new_rowid != rowid {
+            for column in self.columns.iter_mut() {
+                let row = row_data.get_mut(&column.column_name).unwrap();
+                if let Some(value) = row.remove_value(rowid)? {
+                    column.unindex_value(&value, rowid);
+                    row.insert_value(new_rowid, &value)?;
+                    column.index_value(&value, new_rowid);
                 }
             }
-            _ => {}
+            self.last_rowid = self.last_rowid.max(new_rowid);
         }
-        next_rowid
+
+        for key in self.unique_keys.iter_mut() {
+            key.remove(&self.columns, &old_row, rowid);
+            key.insert(&self.columns, &new_row, new_rowid);
+        }
+        Ok(new_rowid)
     }
 
-    pub fn assign_primary_key_from_values(
-        &mut self,
-        cols: &Vec<String>,
-        values: &Vec<String>,
-        next_rowid: i64,
-    ) -> i64 {
-        let mut next_rowid = next_rowid;
+    /// Removes a row from every column tree and drops its entries from the column indexes.
+    /// Returns whether a row with that rowid existed.
+    pub fn delete_row(&mut self, rowid: i64) -> Result<bool> {
         let rows_clone = Rc::clone(&self.rows);
         let mut row_data = rows_clone.as_ref().borrow_mut();
-        let mut table_col_data = row_data.get_mut(&self.primary_key).unwrap();
-
-        match &mut table_col_data {
-            Row::Integer(_) => {
-                for i in 0..cols.len() {
-                    let key = &cols[i];
-                    if key == &self.primary_key {
-                        let val = &values[i];
-                        next_rowid = val.parse::<i64>().unwrap();
-                    }
+        let mut deleted = false;
+        let mut removed = vec![];
+
+        for column in self.columns.iter_mut() {
+            let row = match row_data.get_mut(&column.column_name) {
+                Some(row) => row,
+                None => {
+                    removed.push(Value::Null);
+                    continue;
+                }
+            };
+            match row.remove_value(rowid)? {
+                Some(value) => {
+                    column.unindex_value(&value, rowid);
+                    removed.push(value);
+                    deleted = true;
                 }
+                None => removed.push(Value::Null),
             }
-            _ => {}
         }
-        next_rowid
+        if deleted {
+            for key in self.unique_keys.iter_mut() {
+                key.remove(&self.columns, &removed, rowid);
+            }
+        }
+        Ok(deleted)
     }
 
-    pub fn handle_missing_columns(
-        &mut self,
-        cols: &Vec<String>,
-        values: &Vec<String>,
-        next_rowid: i64,
-    ) {
-        let column_names = self
+    /// Returns every rowid stored in the table in ascending order.
+    pub fn rowids(&self) -> Result<Vec<i64>> {
+        let row_data = self.rows.as_ref().borrow();
+        // Every row has a cell in every column, so one column lists all rowids.
+        match self
             .columns
             .iter()
-            .map(|col| col.column_name.to_string())
-            .collect::<Vec<String>>();
+            .filter_map(|col| row_data.get(&col.column_name))
+            .find(|row| !matches!(row, Row::None))
+        {
+            Some(row) => row.rowids(),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Reads the values of a single row, one per table column in schema order.
+    pub fn get_row_values(&self, rowid: i64) -> Result<Vec<Value>> {
+        let row_data = self.rows.as_ref().borrow();
+        self.columns
+            .iter()
+            .map(|col| {
+                row_data
+                    .get(&col.column_name)
+                    .map_or(Ok(Value::Null), |row| row.get_value(rowid))
+            })
+            .collect()
+    }
 
-        let mut j: usize = 0;
+    /// Reads one column of every row, in rowid order, without touching the other
+    /// columns. Since every row has a cell in every column, the values of different
+    /// columns line up position by position.
+    pub fn column_values(&self, column_idx: usize) -> Result<Vec<Value>> {
+        let row_data = self.rows.as_ref().borrow();
+        match row_data.get(&self.columns[column_idx].column_name) {
+            Some(row) => row.values(),
+            None => Ok(vec![]),
+        }
+    }
 
-        for i in 0..column_names.len() {
-            let mut val = String::from("Null");
-            let key = &column_names[i];
+    /// Builds a copy of this table whose columns are stored in B+trees of `pager`.
+    pub fn copy_into(&self, pager: &SharedPager) -> Result<Table> {
+        let rowids = self.rowids()?;
+        let row_data = self.rows.as_ref().borrow();
+        let mut paged_rows = HashMap::new();
 
-            if let Some(key) = &cols.get(j) {
-                if &key.to_string() == &column_names[i] {
-                    val = values[j].to_string();
-                    j += 1;
-                } else {
-                    if &self.primary_key == &column_names[i] {
-                        continue;
+        for column in &self.columns {
+            let row = match row_data.get(&column.column_name) {
+                Some(Row::None) | None => Row::None,
+                Some(row) => {
+                    let paged = PagedColumn::create(column.datatype.clone(), pager)?;
+                    for rowid in &rowids {
+                        paged.insert(*rowid, &row.get_value(*rowid)?)?;
                     }
+                    Row::Paged(paged)
                 }
-            } else {
-                if &self.primary_key == &column_names[i] {
-                    continue;
-                }
+            };
+            paged_rows.insert(column.column_name.to_string(), row);
+        }
+
+        Ok(Table {
+            tb_name: self.tb_name.to_string(),
+            columns: self.columns.clone(),
+            rows: Rc::new(RefCell::new(paged_rows)),
+            indexes: self.indexes.clone(),
+            last_rowid: self.last_rowid,
+            primary_key: self.primary_key.to_string(),
+            unique_keys: self.unique_keys.clone(),
+        })
+    }
+
+    /// Connects the column trees of a table loaded from a database file to its pager.
+    pub fn attach_pager(&mut self, pager: &SharedPager) {
+        for row in self.rows.borrow_mut().values_mut() {
+            if let Row::Paged(column) = row {
+                column.attach(pager);
             }
+        }
+    }
 
-            self.insert_value_into_column(key, val, next_rowid);
+    /// Frees the pages of the column trees of a file-backed table.
+    pub fn destroy_storage(&self) -> Result<()> {
+        for row in self.rows.borrow().values() {
+            if let Row::Paged(column) = row {
+                column.destroy()?;
+            }
         }
+        Ok(())
     }
 
-    pub fn insert_value_into_column(&mut self, key: &String, val: String, next_rowid: i64) {
-        let rows_clone = Rc::clone(&self.rows);
-        let mut row_data = rows_clone.as_ref().borrow_mut();
-        let mut table_col_data = row_data.get_mut(key).unwrap();
-        let column_headers = self.get_column_mut(key.to_string()).unwrap();
-        let col_index = column_headers.get_mut_index();
-
-        match &mut table_col_data {
-            Row::Integer(tree) => {
-                let val = val.parse::<i32>().unwrap();
-                tree.insert(next_rowid.clone(), val);
-                if let Index::Integer(index) = col_index {
-                    index.insert(val, next_rowid.clone());
-                }
+    /// Recreates every column index from the stored cells. Indexes are not part of the
+    /// database file, so this runs whenever a table is loaded.
+    pub fn rebuild_indexes(&mut self) -> Result<()> {
+        let rowids = self.rowids()?;
+        let row_data = self.rows.as_ref().borrow();
+        for column in self.columns.iter_mut() {
+            column.index = Index::for_datatype(&column.datatype);
+            column.nulls.clear();
+            if matches!(column.index, Index::None) {
+                continue;
             }
-            Row::Text(tree) => {
-                tree.insert(next_rowid.clone(), val.to_string());
-                if let Index::Text(index) = col_index {
-                    index.insert(val.to_string(), next_rowid.clone());
+            if let Some(row) = row_data.get(&column.column_name) {
+                for rowid in &rowids {
+                    column.index_value(&row.get_value(*rowid)?, *rowid);
                 }
             }
-            Row::Real(tree) => {
-                let val = val.parse::<f32>().unwrap();
-                tree.insert(next_rowid.clone(), val);
-            }
-            Row::Bool(tree) => {
-                let val = val.parse::<bool>().unwrap();
-                tree.insert(next_rowid.clone(), val);
+        }
+        drop(row_data);
+
+        for key in self.unique_keys.iter_mut() {
+            key.index.clear();
+        }
+        if !self.unique_keys.is_empty() {
+            for rowid in rowids {
+                let row = self.get_row_values(rowid)?;
+                for key in self.unique_keys.iter_mut() {
+                    key.insert(&self.columns, &row, rowid);
+                }
             }
-            Row::None => panic!("None Data found"),
         }
+        Ok(())
     }
 
     /// Print the table schema to standard output in a pretty formatted way
@@ -389,6 +762,17 @@ impl Table {
         let table_string = format!("{}", table).blue();
 
         println!("{}", table_string);
+        for key in &self.unique_keys {
+            let kind = if key.is_primary {
+                "PRIMARY KEY"
+            } else {
+                "UNIQUE"
+            };
+            println!(
+                "{}",
+                format!("{} ({})", kind, key.columns.join(", ")).blue()
+            );
+        }
         Ok(self.columns.len())
     }
     /// Print the table data to standard output in a pretty formatted way
@@ -411,8 +795,7 @@ impl Table {
     ///     +----+---------+------------------------+
     /// ```
     ///
-
-    pub fn print_table_data(&self) {
+    pub fn print_table_data(&self) -> Result<()> {
         let mut print_table = PrintTable::new();
 
         let column_names = self
@@ -424,31 +807,20 @@ impl Table {
         let header_row = PrintRow::new(
             column_names
                 .iter()
-                .map(|col| PrintCell::new(&col))
+                .map(|col| PrintCell::new(col))
                 .collect::<Vec<PrintCell>>(),
         );
 
-        let rows_clone = Rc::clone(&self.rows);
-        let row_data = rows_clone.as_ref().borrow();
-        let first_col_data = row_data
-            .get(&self.columns.first().unwrap().column_name)
-            .unwrap();
-        let num_rows = first_col_data.count();
-        let mut print_table_rows: Vec<PrintRow> = vec![PrintRow::new(vec![]); num_rows];
-
-        for col_name in &column_names {
-            let col_val = row_data
-                .get(col_name)
-                .expect("Column not found in table rows");
-            let columns: Vec<String> = col_val.get_serialized_col_data();
-
-            for i in 0..num_rows {
-                if let Some(cell) = &columns.get(i) {
-                    print_table_rows[i].add_cell(PrintCell::new(cell));
-                } else {
-                    print_table_rows[i].add_cell(PrintCell::new(""));
-                }
-            }
+        // Cells are looked up by rowid, so a NULL in one column cannot shift the values
+        // of the other columns into the wrong row.
+        let mut print_table_rows = vec![];
+        for rowid in self.rowids()? {
+            print_table_rows.push(PrintRow::new(
+                self.get_row_values(rowid)?
+                    .iter()
+                    .map(|value| PrintCell::new(&value.to_string()))
+                    .collect::<Vec<PrintCell>>(),
+            ));
         }
 
         print_table.add_row(header_row);
@@ -459,10 +831,11 @@ impl Table {
         let table_string = format!("{}", print_table).blue();
 
         println!("{}", table_string);
+        Ok(())
     }
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct Column {
     pub column_name: String,
     pub datatype: DataType,
@@ -470,7 +843,17 @@ pub struct Column {
     pub not_null: bool,
     pub is_unique: bool,
     pub is_indexed: bool,
+    /// SQL text of the DEFAULT expression. Absent from files written before defaults
+    /// existed, which read as no default.
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Not stored in database files; rebuilt from the column data on load.
+    #[serde(skip)]
     pub index: Index,
+    /// Rowids whose value is NULL in an indexed column, which the index has no key for.
+    /// Rebuilt on load along with `index`.
+    #[serde(skip)]
+    pub nulls: BTreeSet<i64>,
 }
 
 impl Column {
@@ -482,14 +865,7 @@ impl Column {
         is_unique: bool,
     ) -> Self {
         let dt = DataType::new(datatype);
-        let index = match dt {
-            DataType::Integer => Index::Integer(BTreeMap::new()),
-            DataType::Bool => Index::None,
-            DataType::Text => Index::Text(BTreeMap::new()),
-            DataType::Real => Index::None,
-            DataType::Invalid => Index::None,
-            DataType::None => Index::None,
-        };
+        let index = Index::for_datatype(&dt);
 
         Column {
             column_name: name,
@@ -497,50 +873,420 @@ impl Column {
             is_pk,
             not_null,
             is_unique,
-            is_indexed: if is_pk { true } else { false },
+            is_indexed: is_pk,
+            default: None,
             index,
+            nulls: BTreeSet::new(),
         }
     }
 
+    /// Records `value` of the row `rowid` in the column index, NULLs included.
+    pub fn index_value(&mut self, value: &Value, rowid: i64) {
+        match value {
+            Value::Null if !matches!(self.index, Index::None) => {
+                self.nulls.insert(rowid);
+            }
+            _ => self.index.insert(value, rowid),
+        }
+    }
+
+    /// Drops `value` of the row `rowid` from the column index.
+    pub fn unindex_value(&mut self, value: &Value, rowid: i64) {
+        match value {
+            Value::Null => {
+                self.nulls.remove(&rowid);
+            }
+            _ => self.index.remove(value, rowid),
+        }
+    }
+
+    /// Converts `value` to this column's type, see [`DataType::coerce`].
+    pub fn coerce_value(&self, value: &Value) -> Result<Value> {
+        self.datatype.coerce(value).ok_or_else(|| {
+            RUSQLError::General(format!(
+                "Error: Cannot store {} in column {} of type {}.",
+                value, self.column_name, self.datatype
+            ))
+        })
+    }
+
     pub fn get_mut_index(&mut self) -> &mut Index {
-        return &mut self.index;
+        &mut self.index
     }
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// Secondary index of a column: maps each stored value to the rowids holding it.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
 pub enum Index {
-    Integer(BTreeMap<i32, i64>),
-    Text(BTreeMap<String, i64>),
+    Integer(BTreeMap<i64, BTreeSet<i64>>),
+    Text(BTreeMap<String, BTreeSet<i64>>),
+    Blob(BTreeMap<Vec<u8>, BTreeSet<i64>>),
+    Decimal(BTreeMap<Decimal, BTreeSet<i64>>),
+    #[default]
     None,
 }
 
+impl Index {
+    /// An empty index of the kind used for columns of `datatype`.
+    pub fn for_datatype(datatype: &DataType) -> Index {
+        match datatype {
+            DataType::Integer => Index::Integer(BTreeMap::new()),
+            DataType::Text | DataType::Json => Index::Text(BTreeMap::new()),
+            DataType::Blob => Index::Blob(BTreeMap::new()),
+            DataType::Decimal(_) => Index::Decimal(BTreeMap::new()),
+            DataType::Bool | DataType::Real | DataType::Invalid | DataType::None => Index::None,
+        }
+    }
+
+    /// Rowids currently holding `value`, if the column is indexed and the value is present.
+    pub fn rowids_for(&self, value: &Value) -> Option<&BTreeSet<i64>> {
+        match (self, value) {
+            (Index::Integer(index), Value::Integer(v)) => index.get(v),
+            (Index::Text(index), Value::Text(v)) => index.get(v),
+            (Index::Blob(index), Value::Blob(v)) => index.get(v),
+            (Index::Decimal(index), Value::Decimal(v)) => index.get(v),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, value: &Value, rowid: i64) {
+        match (self, value) {
+            (Index::Integer(index), Value::Integer(v)) => {
+                index.entry(*v).or_default().insert(rowid);
+            }
+            (Index::Text(index), Value::Text(v)) => {
+                index.entry(v.to_string()).or_default().insert(rowid);
+            }
+            (Index::Blob(index), Value::Blob(v)) => {
+                index.entry(v.clone()).or_default().insert(rowid);
+            }
+            (Index::Decimal(index), Value::Decimal(v)) => {
+                index.entry(*v).or_default().insert(rowid);
+            }
+            _ => (),
+        }
+    }
+
+    /// Drops the `value -> rowid` entry, removing the key once no rows hold the value.
+    pub fn remove(&mut self, value: &Value, rowid: i64) {
+        match (self, value) {
+            (Index::Integer(index), Value::Integer(v)) => {
+                if let Some(rowids) = index.get_mut(v) {
+                    rowids.remove(&rowid);
+                    if rowids.is_empty() {
+                        index.remove(v);
+                    }
+                }
+            }
+            (Index::Text(index), Value::Text(v)) => {
+                if let Some(rowids) = index.get_mut(v) {
+                    rowids.remove(&rowid);
+                    if rowids.is_empty() {
+                        index.remove(v);
+                    }
+                }
+            }
+            (Index::Blob(index), Value::Blob(v)) => {
+                if let Some(rowids) = index.get_mut(v) {
+                    rowids.remove(&rowid);
+                    if rowids.is_empty() {
+                        index.remove(v);
+                    }
+                }
+            }
+            (Index::Decimal(index), Value::Decimal(v)) => {
+                if let Some(rowids) = index.get_mut(v) {
+                    rowids.remove(&rowid);
+                    if rowids.is_empty() {
+                        index.remove(v);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// One value of a composite key. Values of every column type are ordered so that the
+/// whole key can be used in a B-tree; reals compare by their bit pattern, with `-0.0`
+/// folded into `0.0`.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+pub enum KeyPart {
+    Integer(i64),
+    Text(String),
+    Real(u64),
+    Bool(bool),
+    Blob(Vec<u8>),
+    Decimal(Decimal),
+}
+
+impl KeyPart {
+    /// The key part for a stored value, `None` for NULL.
+    pub fn from_value(value: &Value) -> Option<KeyPart> {
+        Some(match value {
+            Value::Null => return None,
+            Value::Integer(v) => KeyPart::Integer(*v),
+            Value::Text(v) => KeyPart::Text(v.to_string()),
+            Value::Real(v) => KeyPart::Real((v + 0.0).to_bits()),
+            Value::Bool(v) => KeyPart::Bool(*v),
+            Value::Blob(v) => KeyPart::Blob(v.clone()),
+            Value::Decimal(v) => KeyPart::Decimal(*v),
+        })
+    }
+}
+
+/// A table-level `UNIQUE (a, b, ...)` or `PRIMARY KEY (a, b, ...)` constraint. A
+/// constraint on a single column is stored on that column when its type has a column
+/// index; on REAL and BOOL columns, which have none, it becomes a one-column key here.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct UniqueKey {
+    pub name: Option<String>,
+    pub columns: Vec<String>,
+    pub is_primary: bool,
+    /// Maps each combination of values to the rowids holding it. Rows with a NULL in any
+    /// of the columns are left out, as they never conflict. Not stored in database files;
+    /// rebuilt from the column data on load.
+    #[serde(skip)]
+    pub index: BTreeMap<Vec<KeyPart>, BTreeSet<i64>>,
+}
+
+impl UniqueKey {
+    /// The key of a row given in schema order, `None` if any of its columns is NULL.
+    pub fn key_for(&self, columns: &[Column], row: &[Value]) -> Option<Vec<KeyPart>> {
+        self.columns
+            .iter()
+            .map(|name| {
+                let idx = columns.iter().position(|col| &col.column_name == name)?;
+                KeyPart::from_value(&row[idx])
+            })
+            .collect()
+    }
+
+    /// Checks that no row other than `rowid` holds the key of `row`. A row that has no
+    /// rowid yet conflicts with every holder.
+    pub fn validate(&self, columns: &[Column], rowid: Option<i64>, row: &[Value]) -> Result<()> {
+        let holders = match self.key_for(columns, row) {
+            Some(key) => self.index.get(&key),
+            None => None,
+        };
+        if holders.is_some_and(|holders| holders.iter().any(|holder| Some(*holder) != rowid)) {
+            let values: Vec<String> = self
+                .columns
+                .iter()
+                .filter_map(|name| columns.iter().position(|col| &col.column_name == name))
+                .map(|idx| row[idx].to_string())
+                .collect();
+            return Err(RUSQLError::KeyViolation(format!(
+                "Error: Unique constraint violation for columns ({}). Values ({}) already exist.",
+                self.columns.join(", "),
+                values.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn insert(&mut self, columns: &[Column], row: &[Value], rowid: i64) {
+        if let Some(key) = self.key_for(columns, row) {
+            self.index.entry(key).or_default().insert(rowid);
+        }
+    }
+
+    /// Drops the `key -> rowid` entry, removing the key once no rows hold it.
+    pub fn remove(&mut self, columns: &[Column], row: &[Value], rowid: i64) {
+        if let Some(key) = self.key_for(columns, row) {
+            if let Some(rowids) = self.index.get_mut(&key) {
+                rowids.remove(&rowid);
+                if rowids.is_empty() {
+                    self.index.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+/// Values of one column keyed by rowid. Every row of the table has an entry in every
+/// column; `None` is a NULL cell. Tables of a file-backed database keep their columns
+/// in on-disk B+trees instead (`Row::Paged`).
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub enum Row {
-    Integer(BTreeMap<i64, i32>),
-    Text(BTreeMap<i64, String>),
-    Real(BTreeMap<i64, f32>),
-    Bool(BTreeMap<i64, bool>),
+    Integer(BTreeMap<i64, Option<i64>>),
+    Text(BTreeMap<i64, Option<String>>),
+    Real(BTreeMap<i64, Option<f64>>),
+    Bool(BTreeMap<i64, Option<bool>>),
+    Blob(BTreeMap<i64, Option<Vec<u8>>>),
+    Decimal(BTreeMap<i64, Option<Decimal>>),
+    Paged(PagedColumn),
     None,
 }
 
 impl Row {
-    fn get_serialized_col_data(&self) -> Vec<String> {
+    /// Returns the value stored for `rowid`, or `Value::Null` for a NULL cell or a rowid
+    /// the column has no entry for.
+    pub fn get_value(&self, rowid: i64) -> Result<Value> {
+        Ok(self.cell(rowid)?.unwrap_or(Value::Null))
+    }
+
+    /// The cell stored for `rowid`, `None` if the column has no entry for it.
+    fn cell(&self, rowid: i64) -> Result<Option<Value>> {
+        Ok(match self {
+            Row::Integer(cd) => cd
+                .get(&rowid)
+                .map(|v| v.map_or(Value::Null, Value::Integer)),
+            Row::Real(cd) => cd.get(&rowid).map(|v| v.map_or(Value::Null, Value::Real)),
+            Row::Text(cd) => cd.get(&rowid).map(|v| {
+                v.as_ref()
+                    .map_or(Value::Null, |v| Value::Text(v.to_string()))
+            }),
+            Row::Bool(cd) => cd.get(&rowid).map(|v| v.map_or(Value::Null, Value::Bool)),
+            Row::Blob(cd) => cd
+                .get(&rowid)
+                .map(|v| v.clone().map_or(Value::Null, Value::Blob)),
+            Row::Decimal(cd) => cd
+                .get(&rowid)
+                .map(|v| v.map_or(Value::Null, Value::Decimal)),
+            Row::Paged(column) => column.cell(rowid)?,
+            Row::None => None,
+        })
+    }
+
+    fn contains(&self, rowid: i64) -> Result<bool> {
         match self {
-            Row::Integer(cd) => cd.iter().map(|(_i, v)| v.to_string()).collect(),
-            Row::Real(cd) => cd.iter().map(|(_i, v)| v.to_string()).collect(),
-            Row::Text(cd) => cd.iter().map(|(_i, v)| v.to_string()).collect(),
-            Row::Bool(cd) => cd.iter().map(|(_i, v)| v.to_string()).collect(),
-            Row::None => panic!("Data not found"),
+            Row::Paged(column) => column.contains(rowid),
+            _ => Ok(self.cell(rowid)?.is_some()),
+        }
+    }
+
+    /// Stores an already converted value for `rowid`; `Value::Null` stores a NULL cell.
+    /// A value of another type is an error.
+    fn insert_value(&mut self, rowid: i64, value: &Value) -> Result<()> {
+        match (self, value) {
+            (Row::Integer(cd), Value::Integer(v)) => {
+                cd.insert(rowid, Some(*v));
+            }
+            (Row::Real(cd), Value::Real(v)) => {
+                cd.insert(rowid, Some(*v));
+            }
+            (Row::Text(cd), Value::Text(v)) => {
+                cd.insert(rowid, Some(v.to_string()));
+            }
+            (Row::Bool(cd), Value::Bool(v)) => {
+                cd.insert(rowid, Some(*v));
+            }
+            (Row::Blob(cd), Value::Blob(v)) => {
+                cd.insert(rowid, Some(v.clone()));
+            }
+            (Row::Decimal(cd), Value::Decimal(v)) => {
+                cd.insert(rowid, Some(*v));
+            }
+            (Row::Integer(cd), Value::Null) => {
+                cd.insert(rowid, None);
+            }
+            (Row::Real(cd), Value::Null) => {
+                cd.insert(rowid, None);
+            }
+            (Row::Text(cd), Value::Null) => {
+                cd.insert(rowid, None);
+            }
+            (Row::Bool(cd), Value::Null) => {
+                cd.insert(rowid, None);
+            }
+            (Row::Blob(cd), Value::Null) => {
+                cd.insert(rowid, None);
+            }
+            (Row::Decimal(cd), Value::Null) => {
+                cd.insert(rowid, None);
+            }
+            (Row::Paged(column), value) => column.insert(rowid, value)?,
+            (Row::None, Value::Null) => (),
+            // Values are converted before they get here, so this is a bug rather than bad
+            // input; report it instead of dropping the value.
+            (_, value) => {
+                return Err(RUSQLError::Internal(format!(
+                    "Value {} does not match the type of its column.",
+                    value
+                )))
+            }
         }
+        Ok(())
     }
 
-    fn count(&self) -> usize {
+    /// Removes the entry for `rowid`, returning the value it held (`Value::Null` for a
+    /// NULL cell).
+    fn remove_value(&mut self, rowid: i64) -> Result<Option<Value>> {
+        if let Row::Paged(column) = self {
+            return column.remove(rowid);
+        }
+
+        let value = self.cell(rowid)?;
         match self {
-            Row::Integer(cd) => cd.len(),
-            Row::Real(cd) => cd.len(),
-            Row::Text(cd) => cd.len(),
-            Row::Bool(cd) => cd.len(),
-            Row::None => panic!("Data not found"),
+            Row::Integer(cd) => {
+                cd.remove(&rowid);
+            }
+            Row::Real(cd) => {
+                cd.remove(&rowid);
+            }
+            Row::Text(cd) => {
+                cd.remove(&rowid);
+            }
+            Row::Bool(cd) => {
+                cd.remove(&rowid);
+            }
+            Row::Blob(cd) => {
+                cd.remove(&rowid);
+            }
+            Row::Decimal(cd) => {
+                cd.remove(&rowid);
+            }
+            Row::Paged(_) | Row::None => (),
         }
+        Ok(value)
+    }
+
+    /// Every value of the column in rowid order.
+    fn values(&self) -> Result<Vec<Value>> {
+        Ok(match self {
+            Row::Integer(cd) => cd
+                .values()
+                .map(|v| v.map_or(Value::Null, Value::Integer))
+                .collect(),
+            Row::Real(cd) => cd
+                .values()
+                .map(|v| v.map_or(Value::Null, Value::Real))
+                .collect(),
+            Row::Text(cd) => cd
+                .values()
+                .map(|v| {
+                    v.as_ref()
+                        .map_or(Value::Null, |v| Value::Text(v.to_string()))
+                })
+                .collect(),
+            Row::Bool(cd) => cd
+                .values()
+                .map(|v| v.map_or(Value::Null, Value::Bool))
+                .collect(),
+            Row::Blob(cd) => cd
+                .values()
+                .map(|v| v.clone().map_or(Value::Null, Value::Blob))
+                .collect(),
+            Row::Decimal(cd) => cd
+                .values()
+                .map(|v| v.map_or(Value::Null, Value::Decimal))
+                .collect(),
+            Row::Paged(column) => column.values()?,
+            Row::None => vec![],
+        })
+    }
+
+    fn rowids(&self) -> Result<Vec<i64>> {
+        Ok(match self {
+            Row::Integer(cd) => cd.keys().copied().collect(),
+            Row::Real(cd) => cd.keys().copied().collect(),
+            Row::Text(cd) => cd.keys().copied().collect(),
+            Row::Bool(cd) => cd.keys().copied().collect(),
+            Row::Blob(cd) => cd.keys().copied().collect(),
+            Row::Decimal(cd) => cd.keys().copied().collect(),
+            Row::Paged(column) => column.rowids()?,
+            Row::None => vec![],
+        })
     }
 }
@@ -0,0 +1,130 @@
+//! Undo journal behind transactions and savepoints.
+//!
+//! While a statement runs or a transaction is open, every change made through `Database`
+//! records how to reverse it. Undoing replays the entries backwards: a failed statement
+//! is rolled back to where it started, ROLLBACK to the start of the transaction and
+//! ROLLBACK TO to the savepoint. Entries are discarded on commit.
+
+use crate::sql::db::table::Table;
+use crate::sql::db::value::Value;
+
+#[derive(Debug)]
+pub enum UndoEntry {
+    /// A row was inserted at `rowid`; `last_rowid` is the table's counter before it.
+    InsertRow {
+        table: String,
+        rowid: i64,
+        last_rowid: i64,
+    },
+    /// The row at `rowid` held `values` and now lives at `new_rowid`.
+    UpdateRow {
+        table: String,
+        rowid: i64,
+        new_rowid: i64,
+        values: Vec<Value>,
+        last_rowid: i64,
+    },
+    /// The row at `rowid` held `values` and was deleted.
+    DeleteRow {
+        table: String,
+        rowid: i64,
+        values: Vec<Value>,
+    },
+    CreateTable {
+        table: String,
+    },
+    /// A dropped table, kept whole until commit so it can be put back.
+    DropTable {
+        table: Table,
+    },
+}
+
+#[derive(Debug)]
+pub struct Savepoint {
+    pub name: String,
+    /// Journal length when the savepoint was taken.
+    pub mark: usize,
+    /// Whether this savepoint opened the transaction, so releasing it commits.
+    pub starts_transaction: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Journal {
+    entries: Vec<UndoEntry>,
+    savepoints: Vec<Savepoint>,
+    /// True between BEGIN (or an outermost SAVEPOINT) and COMMIT or ROLLBACK.
+    in_transaction: bool,
+    /// Journal length when the running statement started.
+    statement: Option<usize>,
+}
+
+impl Journal {
+    /// Changes are journaled inside a statement or a transaction; outside of both they
+    /// take effect immediately.
+    pub fn is_recording(&self) -> bool {
+        self.in_transaction || self.statement.is_some()
+    }
+
+    pub fn record(&mut self, entry: UndoEntry) {
+        if self.is_recording() {
+            self.entries.push(entry);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes and returns the most recent entry.
+    pub fn pop(&mut self) -> Option<UndoEntry> {
+        self.entries.pop()
+    }
+
+    /// Removes every entry, in the order they were recorded.
+    pub fn drain(&mut self) -> Vec<UndoEntry> {
+        std::mem::take(&mut self.entries)
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.in_transaction
+    }
+
+    pub fn set_in_transaction(&mut self, in_transaction: bool) {
+        self.in_transaction = in_transaction;
+        if !in_transaction {
+            self.savepoints.clear();
+        }
+    }
+
+    pub fn statement(&self) -> Option<usize> {
+        self.statement
+    }
+
+    pub fn set_statement(&mut self, mark: Option<usize>) {
+        self.statement = mark;
+    }
+
+    pub fn push_savepoint(&mut self, savepoint: Savepoint) {
+        self.savepoints.push(savepoint);
+    }
+
+    /// Position of the most recent savepoint called `name`. Names are case-insensitive.
+    pub fn find_savepoint(&self, name: &str) -> Option<usize> {
+        self.savepoints
+            .iter()
+            .rposition(|savepoint| savepoint.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn savepoint(&self, idx: usize) -> &Savepoint {
+        &self.savepoints[idx]
+    }
+
+    /// Forgets every savepoint from `idx` on.
+    pub fn truncate_savepoints(&mut self, idx: usize) {
+        self.savepoints.truncate(idx);
+    }
+}
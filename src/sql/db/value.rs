@@ -0,0 +1,217 @@
+//! Typed cell values.
+//! A `Value` is what INSERT hands to the storage layer, what the query executor hands
+//! back for every cell of a result set, and what the expression evaluator operates on.
+
+use crate::sql::db::decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Bool(bool),
+    Blob(Vec<u8>),
+    Decimal(Decimal),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// Truth value of a cell following SQLite: numbers are true when non-zero, text is
+    /// converted to a number first, and NULL is unknown (`None`).
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.to_numeric() {
+            Value::Null => None,
+            Value::Integer(i) => Some(i != 0),
+            Value::Real(r) => Some(r != 0.0),
+            Value::Decimal(d) => Some(!d.is_zero()),
+            _ => unreachable!("to_numeric only returns NULL, Integer, Real or Decimal"),
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.to_numeric() {
+            Value::Integer(i) => Some(i as f64),
+            Value::Real(r) => Some(r),
+            Value::Decimal(d) => Some(d.to_f64()),
+            _ => None,
+        }
+    }
+
+    /// The value as an exact decimal. Reals convert through the digits they print as.
+    pub fn as_decimal(&self) -> Option<Decimal> {
+        match self.to_numeric() {
+            Value::Integer(i) => Some(Decimal::from_i64(i)),
+            Value::Real(r) => Decimal::from_f64(r),
+            Value::Decimal(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Converts the value to an Integer or Real the way SQLite does for arithmetic:
+    /// booleans become 0/1 and text is read up to its longest numeric prefix (or 0).
+    /// Decimals stay decimal.
+    pub fn to_numeric(&self) -> Value {
+        match self {
+            Value::Null => Value::Null,
+            Value::Integer(i) => Value::Integer(*i),
+            Value::Real(r) => Value::Real(*r),
+            Value::Decimal(d) => Value::Decimal(*d),
+            Value::Bool(b) => Value::Integer(i64::from(*b)),
+            Value::Text(s) => parse_numeric_prefix(s),
+            Value::Blob(b) => parse_numeric_prefix(&String::from_utf8_lossy(b)),
+        }
+    }
+
+    /// Compares two values, returning `None` when either side is NULL.
+    ///
+    /// Numbers (including booleans) compare numerically and sort before text, which sorts
+    /// before blobs. Text that is entirely numeric is compared numerically against a number.
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        if self.is_null() || other.is_null() {
+            return None;
+        }
+
+        match (self.numeric_view(), other.numeric_view()) {
+            (Some(Value::Integer(a)), Some(Value::Integer(b))) => Some(a.cmp(&b)),
+            // A decimal compares exactly with integers, decimals and most reals.
+            (Some(a), Some(b))
+                if matches!(a, Value::Decimal(_)) || matches!(b, Value::Decimal(_)) =>
+            {
+                match (a.as_decimal(), b.as_decimal()) {
+                    (Some(a), Some(b)) => Some(a.cmp(&b)),
+                    _ => Some(
+                        (a.as_f64().unwrap())
+                            .partial_cmp(&b.as_f64().unwrap())
+                            .unwrap_or(Ordering::Equal),
+                    ),
+                }
+            }
+            (Some(a), Some(b)) => {
+                let (a, b) = (a.as_f64().unwrap(), b.as_f64().unwrap());
+                Some(a.partial_cmp(&b).unwrap_or(Ordering::Equal))
+            }
+            (Some(_), None) => Some(Ordering::Less),
+            (None, Some(_)) => Some(Ordering::Greater),
+            (None, None) => match (self, other) {
+                (Value::Blob(a), Value::Blob(b)) => Some(a.cmp(b)),
+                (Value::Blob(_), _) => Some(Ordering::Greater),
+                (_, Value::Blob(_)) => Some(Ordering::Less),
+                _ => Some(self.to_string().cmp(&other.to_string())),
+            },
+        }
+    }
+
+    /// Total order used to sort values, as in SQLite's ORDER BY: NULL first, then numbers
+    /// (including booleans) by value, then text by bytes, then blobs. Unlike `compare`,
+    /// text is never read as a number.
+    pub fn sort_cmp(&self, other: &Value) -> Ordering {
+        fn class(value: &Value) -> u8 {
+            match value {
+                Value::Null => 0,
+                Value::Integer(_) | Value::Real(_) | Value::Bool(_) | Value::Decimal(_) => 1,
+                Value::Text(_) => 2,
+                Value::Blob(_) => 3,
+            }
+        }
+
+        match (self, other) {
+            (Value::Text(a), Value::Text(b)) => a.cmp(b),
+            (Value::Blob(a), Value::Blob(b)) => a.cmp(b),
+            (a, b) if class(a) == 1 && class(b) == 1 => a.compare(b).unwrap(),
+            (a, b) => class(a).cmp(&class(b)),
+        }
+    }
+
+    /// The numeric interpretation of the value when it has one without loss.
+    fn numeric_view(&self) -> Option<Value> {
+        match self {
+            Value::Integer(_) | Value::Real(_) | Value::Bool(_) | Value::Decimal(_) => {
+                Some(self.to_numeric())
+            }
+            Value::Text(s) => parse_number(s),
+            Value::Null | Value::Blob(_) => None,
+        }
+    }
+}
+
+/// The integer a real is equal to, if it has no fractional part and fits in an `i64`.
+pub fn real_to_integer(r: f64) -> Option<i64> {
+    // 2^63 is exact as an f64, unlike i64::MAX.
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+    (r.fract() == 0.0 && (-LIMIT..LIMIT).contains(&r)).then_some(r as i64)
+}
+
+/// Parses text that is entirely a number (surrounding whitespace allowed).
+pub fn parse_number(s: &str) -> Option<Value> {
+    let s = s.trim();
+    if let Ok(i) = s.parse::<i64>() {
+        return Some(Value::Integer(i));
+    }
+    match s.parse::<f64>() {
+        Ok(r) if !s.is_empty() && !s.chars().any(|c| c.is_alphabetic() && c != 'e' && c != 'E') => {
+            Some(Value::Real(r))
+        }
+        _ => None,
+    }
+}
+
+fn parse_numeric_prefix(s: &str) -> Value {
+    let s = s.trim_start();
+    let bytes = s.as_bytes();
+    let mut end = 0;
+    if end < bytes.len() && (bytes[end] == b'+' || bytes[end] == b'-') {
+        end += 1;
+    }
+    while end < bytes.len() && bytes[end].is_ascii_digit() {
+        end += 1;
+    }
+    let int_end = end;
+    if end < bytes.len() && bytes[end] == b'.' {
+        end += 1;
+        while end < bytes.len() && bytes[end].is_ascii_digit() {
+            end += 1;
+        }
+    }
+    if end < bytes.len() && (bytes[end] == b'e' || bytes[end] == b'E') {
+        let mut exp_end = end + 1;
+        if exp_end < bytes.len() && (bytes[exp_end] == b'+' || bytes[exp_end] == b'-') {
+            exp_end += 1;
+        }
+        if exp_end < bytes.len() && bytes[exp_end].is_ascii_digit() {
+            while exp_end < bytes.len() && bytes[exp_end].is_ascii_digit() {
+                exp_end += 1;
+            }
+            end = exp_end;
+        }
+    }
+
+    if end == int_end {
+        if let Ok(i) = s[..end].parse::<i64>() {
+            return Value::Integer(i);
+        }
+    }
+    s[..end]
+        .parse::<f64>()
+        .map_or(Value::Integer(0), Value::Real)
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("NULL"),
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Real(r) => write!(f, "{}", r),
+            Value::Text(s) => f.write_str(s),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Blob(b) => b.iter().try_for_each(|byte| write!(f, "{:02X}", byte)),
+            Value::Decimal(d) => write!(f, "{}", d),
+        }
+    }
+}
@@ -0,0 +1,747 @@
+//! Aggregate queries: GROUP BY, HAVING and the aggregate functions COUNT, SUM, AVG,
+//! TOTAL, MIN, MAX, GROUP_CONCAT, JSON_GROUP_ARRAY and JSON_GROUP_OBJECT.
+//!
+//! An aggregate query reads only the columns it references, each in a single column scan,
+//! and feeds the rows to one accumulator per aggregate call and group. Groups come out in
+//! GROUP BY key order; without GROUP BY the whole table is one group, even when it has no
+//! rows. The select list, HAVING and ORDER BY are then evaluated once per group, with
+//! each aggregate call replaced by its result. As in SQLite, a column used outside an
+//! aggregate takes its value from the last row of the group, or from the row holding the
+//! result when the query has a single MIN or MAX. SUM and AVG over decimals are exact
+//! and return a decimal.
+//!
+//! Window calls in an aggregate query are computed after HAVING, over one row per group,
+//! and may take aggregate calls as arguments or in their PARTITION BY and ORDER BY.
+
+use crate::error::{RUSQLError, Result};
+use crate::sql::db::decimal::{overflow_error, Decimal};
+use crate::sql::db::json::Json;
+use crate::sql::db::value::Value;
+use crate::sql::executor::expr::{
+    contains_subquery, evaluate, is_true, walk_expr, ColumnBinding, Env, Scope,
+};
+use crate::sql::executor::json::{returns_json, to_json};
+use crate::sql::executor::order::{sort_values, Limit, SortKey, SortTerm, Sorter};
+use crate::sql::executor::result_set::ResultSet;
+use crate::sql::executor::select::{project, Projection, RowSource};
+use crate::sql::executor::window::{compute_windows, window_calls, window_operands};
+use sqlparser::ast::Value as SqlValue;
+use sqlparser::ast::{Expr, Function, FunctionArg, FunctionArgExpr, GroupByExpr, Select};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Whether `function` is a call to an aggregate function. MIN and MAX with more than one
+/// argument are the scalar functions of the same name.
+pub fn is_aggregate(function: &Function) -> bool {
+    if function.over.is_some() {
+        return false;
+    }
+    match function.name.to_string().to_lowercase().as_str() {
+        "count" | "sum" | "avg" | "total" | "group_concat" | "json_group_array"
+        | "json_group_object" => true,
+        "min" | "max" => function.args.len() == 1,
+        _ => false,
+    }
+}
+
+/// Whether `expr` calls an aggregate function anywhere.
+pub fn contains_aggregate(expr: &Expr) -> bool {
+    let mut found = false;
+    walk_expr(expr, &mut |expr| {
+        if matches!(expr, Expr::Function(function) if is_aggregate(function)) {
+            found = true;
+        }
+        !found
+    });
+    found
+}
+
+/// Whether `select` has to be executed as an aggregate query.
+pub fn is_aggregate_query(select: &Select, projections: &[Projection], keys: &[SortKey]) -> bool {
+    !matches!(&select.group_by, GroupByExpr::Expressions(exprs) if exprs.is_empty())
+        || select.having.is_some()
+        || projections.iter().any(
+            |projection| matches!(projection, Projection::Expr(expr) if contains_aggregate(expr)),
+        )
+        || keys
+            .iter()
+            .any(|key| matches!(key.term, SortTerm::Expr(expr) if contains_aggregate(expr)))
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Kind {
+    /// `COUNT(*)`: every row.
+    CountRows,
+    Count,
+    Sum,
+    Total,
+    Avg,
+    Min,
+    Max,
+    GroupConcat,
+    JsonGroupArray,
+    JsonGroupObject,
+}
+
+/// One aggregate call of the query.
+pub struct Aggregate<'a> {
+    call: &'a Expr,
+    kind: Kind,
+    arg: Option<&'a Expr>,
+    /// The separator argument of GROUP_CONCAT, or the value of JSON_GROUP_OBJECT.
+    separator: Option<&'a Expr>,
+    filter: Option<&'a Expr>,
+    distinct: bool,
+    /// Whether the JSON values are JSON text to embed as is, see `json::returns_json`.
+    json_input: bool,
+}
+
+impl<'a> Aggregate<'a> {
+    /// An aggregate call over rows with the columns of `bindings`.
+    pub fn new(call: &'a Expr, bindings: &[ColumnBinding]) -> Result<Self> {
+        let function = match call {
+            Expr::Function(function) => function,
+            _ => unreachable!("aggregate calls are function calls"),
+        };
+        let name = function.name.to_string().to_lowercase();
+        if !function.order_by.is_empty() {
+            return Err(RUSQLError::NotImplemented(format!(
+                "ORDER BY inside {}() is not supported.",
+                name
+            )));
+        }
+
+        let mut args = vec![];
+        let mut wildcard = false;
+        for arg in &function.args {
+            match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr))
+                | FunctionArg::Named {
+                    arg: FunctionArgExpr::Expr(expr),
+                    ..
+                } => args.push(expr),
+                FunctionArg::Unnamed(FunctionArgExpr::Wildcard) if name == "count" => {
+                    wildcard = true
+                }
+                _ => {
+                    return Err(RUSQLError::NotImplemented(format!(
+                        "Unsupported argument to {}(): {}",
+                        name, arg
+                    )))
+                }
+            }
+        }
+
+        let kind = match (name.as_str(), args.len()) {
+            ("count", 0) => Kind::CountRows,
+            ("count", 1) => Kind::Count,
+            ("sum", 1) => Kind::Sum,
+            ("total", 1) => Kind::Total,
+            ("avg", 1) => Kind::Avg,
+            ("min", 1) => Kind::Min,
+            ("max", 1) => Kind::Max,
+            ("group_concat", 1 | 2) => Kind::GroupConcat,
+            ("json_group_array", 1) => Kind::JsonGroupArray,
+            ("json_group_object", 2) => Kind::JsonGroupObject,
+            _ => {
+                return Err(RUSQLError::General(format!(
+                    "wrong number of arguments to function {}()",
+                    name
+                )))
+            }
+        };
+        if function.distinct && (wildcard || args.len() != 1) {
+            return Err(RUSQLError::General(format!(
+                "DISTINCT aggregates must have exactly one argument: {}()",
+                name
+            )));
+        }
+
+        let json_value = match kind {
+            Kind::JsonGroupArray => args.first(),
+            Kind::JsonGroupObject => args.get(1),
+            _ => None,
+        };
+        Ok(Aggregate {
+            call,
+            kind,
+            arg: args.first().copied(),
+            separator: args.get(1).copied(),
+            filter: function.filter.as_deref(),
+            distinct: function.distinct,
+            json_input: json_value.is_some_and(|expr| returns_json(expr, bindings)),
+        })
+    }
+
+    /// Whether the call is a MIN or MAX, whose result row bare columns are read from.
+    fn is_extreme(&self) -> bool {
+        matches!(self.kind, Kind::Min | Kind::Max)
+    }
+
+    /// Evaluates the inputs of the call for the row in `scope`, or `None` when the row
+    /// is left out by the FILTER clause.
+    pub fn inputs(&self, scope: &Scope) -> Result<Option<Inputs>> {
+        if let Some(filter) = self.filter {
+            if !is_true(filter, scope)? {
+                return Ok(None);
+            }
+        }
+        let value = match self.arg {
+            Some(arg) => evaluate(arg, scope)?,
+            None => Value::Null,
+        };
+        let separator = match self.separator {
+            Some(separator) => Some(evaluate(separator, scope)?),
+            None => None,
+        };
+        Ok(Some((value, separator)))
+    }
+
+    pub fn accumulator(&self) -> Accumulator {
+        Accumulator::new(self)
+    }
+
+    /// Adds one row's inputs. Returns true when the value became the new MIN or MAX.
+    pub fn step(&self, accumulator: &mut Accumulator, (value, separator): Inputs) -> Result<bool> {
+        accumulator.step(self.kind, value, separator)
+    }
+
+    pub fn finish(&self, accumulator: &Accumulator) -> Value {
+        accumulator.finish(self.kind)
+    }
+}
+
+/// Values ordered with `Value::sort_cmp`, so they can key groups and DISTINCT sets.
+/// Numbers compare by value, so `1` and `1.0` fall into the same group.
+#[derive(Debug)]
+pub struct GroupKey(pub Vec<Value>);
+
+impl Ord for GroupKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .map(|(a, b)| a.sort_cmp(b))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for GroupKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for GroupKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for GroupKey {}
+
+/// The argument and GROUP_CONCAT separator one row feeds to an aggregate call.
+pub type Inputs = (Value, Option<Value>);
+
+/// Running state of one aggregate call within one group.
+#[derive(Default)]
+pub struct Accumulator {
+    /// Non-NULL inputs seen (every row for `COUNT(*)`).
+    count: i64,
+    /// Integer sum, until a non-integer input turns the sum into a float.
+    int_sum: Option<i64>,
+    real_sum: f64,
+    /// Exact sum while the inputs are integers and decimals and the sum fits.
+    decimal_sum: Option<Decimal>,
+    /// Whether any input was a decimal, which makes SUM and AVG decimal.
+    has_decimal: bool,
+    /// Current MIN or MAX.
+    best: Option<Value>,
+    concat: Option<String>,
+    /// Elements of JSON_GROUP_ARRAY, or members of JSON_GROUP_OBJECT.
+    json: Vec<(String, Json)>,
+    /// Whether the JSON values are JSON text to embed as is, see `json::returns_json`.
+    json_input: bool,
+    /// Inputs already seen by a DISTINCT aggregate.
+    seen: Option<BTreeSet<GroupKey>>,
+}
+
+impl Accumulator {
+    fn new(aggregate: &Aggregate) -> Self {
+        Accumulator {
+            int_sum: Some(0),
+            decimal_sum: Some(Decimal::from_i64(0)),
+            seen: aggregate.distinct.then(BTreeSet::new),
+            json_input: aggregate.json_input,
+            ..Default::default()
+        }
+    }
+
+    /// Adds one input. Returns true when it became the new MIN or MAX.
+    fn step(&mut self, kind: Kind, value: Value, separator: Option<Value>) -> Result<bool> {
+        if kind == Kind::CountRows {
+            self.count += 1;
+            return Ok(false);
+        }
+        // JSON_GROUP_ARRAY keeps NULLs, as JSON nulls.
+        if value.is_null() && kind != Kind::JsonGroupArray {
+            return Ok(false);
+        }
+        if let Some(seen) = &mut self.seen {
+            if !seen.insert(GroupKey(vec![value.clone()])) {
+                return Ok(false);
+            }
+        }
+        self.count += 1;
+
+        match kind {
+            Kind::Sum | Kind::Total | Kind::Avg => {
+                self.int_sum = match (self.int_sum, &value) {
+                    (Some(sum), Value::Integer(_) | Value::Bool(_)) => {
+                        let Value::Integer(i) = value.to_numeric() else {
+                            unreachable!("integers and booleans are integral")
+                        };
+                        match sum.checked_add(i) {
+                            Some(sum) => Some(sum),
+                            None if kind == Kind::Sum => {
+                                return Err(RUSQLError::General("integer overflow".to_string()))
+                            }
+                            None => None,
+                        }
+                    }
+                    _ => None,
+                };
+                self.has_decimal |= matches!(value, Value::Decimal(_));
+                self.decimal_sum = match (self.decimal_sum, &value) {
+                    (Some(sum), Value::Integer(_) | Value::Bool(_) | Value::Decimal(_)) => {
+                        match value.as_decimal().and_then(|d| sum.checked_add(&d)) {
+                            Some(sum) => Some(sum),
+                            // An exact sum of decimals never turns into a float.
+                            None if self.has_decimal && kind != Kind::Total => {
+                                return Err(overflow_error())
+                            }
+                            None => None,
+                        }
+                    }
+                    _ => None,
+                };
+                self.real_sum += value.as_f64().unwrap_or(0.0);
+            }
+            Kind::Min | Kind::Max => {
+                let better = match &self.best {
+                    None => true,
+                    Some(best) if kind == Kind::Min => value.sort_cmp(best) == Ordering::Less,
+                    Some(best) => value.sort_cmp(best) == Ordering::Greater,
+                };
+                if better {
+                    self.best = Some(value);
+                }
+                return Ok(better);
+            }
+            Kind::GroupConcat => {
+                let text = value.to_string();
+                self.concat = Some(match self.concat.take() {
+                    None => text,
+                    Some(concat) => {
+                        let separator = separator.map_or(",".to_string(), |separator| {
+                            if separator.is_null() {
+                                String::new()
+                            } else {
+                                separator.to_string()
+                            }
+                        });
+                        concat + &separator + &text
+                    }
+                });
+            }
+            Kind::JsonGroupArray => {
+                let element = to_json(&value, self.json_input)?;
+                self.json.push((String::new(), element));
+            }
+            Kind::JsonGroupObject => {
+                let member = to_json(&separator.unwrap_or(Value::Null), self.json_input)?;
+                self.json.push((value.to_string(), member));
+            }
+            Kind::CountRows | Kind::Count => (),
+        }
+        Ok(false)
+    }
+
+    /// The decimal sum, when decimals were summed and it stayed exact.
+    fn exact_sum(&self) -> Option<Decimal> {
+        self.decimal_sum.filter(|_| self.has_decimal)
+    }
+
+    fn finish(&self, kind: Kind) -> Value {
+        match kind {
+            Kind::CountRows | Kind::Count => Value::Integer(self.count),
+            Kind::Sum if self.count == 0 => Value::Null,
+            Kind::Sum => match (self.int_sum, self.exact_sum()) {
+                (Some(sum), _) => Value::Integer(sum),
+                (None, Some(sum)) => Value::Decimal(sum),
+                (None, None) => Value::Real(self.real_sum),
+            },
+            Kind::Total => Value::Real(self.real_sum),
+            Kind::Avg if self.count == 0 => Value::Null,
+            Kind::Avg => match self.exact_sum() {
+                // Dividing by a count only drops digits after the point, so this always fits.
+                Some(sum) => {
+                    Value::Decimal(sum.checked_div(&Decimal::from_i64(self.count)).unwrap())
+                }
+                None => Value::Real(self.real_sum / self.count as f64),
+            },
+            Kind::Min | Kind::Max => self.best.clone().unwrap_or(Value::Null),
+            Kind::GroupConcat => self.concat.clone().map_or(Value::Null, Value::Text),
+            Kind::JsonGroupArray => Value::Text(
+                Json::Array(
+                    self.json
+                        .iter()
+                        .map(|(_, element)| element.clone())
+                        .collect(),
+                )
+                .to_string(),
+            ),
+            Kind::JsonGroupObject => Value::Text(Json::Object(self.json.clone()).to_string()),
+        }
+    }
+}
+
+struct Group {
+    accumulators: Vec<Accumulator>,
+    /// The row bare columns are read from.
+    row: Vec<Value>,
+}
+
+/// Executes an aggregate SELECT. `bindings` and `projections` refer to every column of
+/// the source rows; only the ones the query uses are read. The window calls of the
+/// query are computed over the groups that pass HAVING.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_aggregate<'a>(
+    select: &'a Select,
+    source: RowSource,
+    bindings: &[ColumnBinding],
+    labels: Vec<String>,
+    projections: &[Projection<'a>],
+    keys: &[SortKey<'a>],
+    windows: &[&'a Expr],
+    limit: &Limit,
+    env: Env,
+) -> Result<ResultSet> {
+    let group_by = resolve_group_by(select, &labels, projections, bindings)?;
+    let window_operands = window_operands(windows, select)?;
+
+    let mut calls = vec![];
+    let having = select.having.as_ref();
+    let key_exprs = keys.iter().filter_map(|key| match key.term {
+        SortTerm::Expr(expr) => Some(expr),
+        SortTerm::Output(_) => None,
+    });
+    for expr in projection_exprs(projections)
+        .chain(having)
+        .chain(key_exprs.clone())
+        .chain(window_operands.iter().copied())
+    {
+        walk_expr(expr, &mut |expr| match expr {
+            Expr::Function(function) if is_aggregate(function) => {
+                if !calls.contains(&expr) {
+                    calls.push(expr);
+                }
+                false
+            }
+            _ => true,
+        });
+    }
+    let aggregates = calls
+        .into_iter()
+        .map(|call| Aggregate::new(call, bindings))
+        .collect::<Result<Vec<_>>>()?;
+    // With a single MIN or MAX, bare columns come from the row holding its result.
+    let extreme = match aggregates
+        .iter()
+        .filter(|aggregate| aggregate.is_extreme())
+        .count()
+    {
+        1 => aggregates
+            .iter()
+            .position(|aggregate| aggregate.is_extreme()),
+        _ => None,
+    };
+
+    // Narrow the row down to the columns the query references.
+    let mut used = BTreeSet::new();
+    let all_exprs = projection_exprs(projections)
+        .chain(projection_exprs(&group_by))
+        .chain(select.selection.as_ref())
+        .chain(having)
+        .chain(key_exprs)
+        .chain(window_operands.iter().copied());
+    for expr in all_exprs {
+        if contains_subquery(expr) {
+            // A correlated subquery may read any column of the row.
+            used.extend(0..bindings.len());
+        }
+        used.extend(referenced_columns(expr, bindings));
+    }
+    for projection in projections.iter().chain(&group_by) {
+        if let Projection::Column(idx) = projection {
+            used.insert(*idx);
+        }
+    }
+    let used: Vec<usize> = used.into_iter().collect();
+    let narrowed: Vec<ColumnBinding> = used.iter().map(|idx| bindings[*idx].clone()).collect();
+    let (projections, group_by) = (narrow(projections, &used), narrow(&group_by, &used));
+
+    let (columns, row_count) = match source {
+        RowSource::Table(table) => {
+            let columns = used
+                .iter()
+                .map(|idx| table.column_values(*idx))
+                .collect::<Result<Vec<_>>>()?;
+            let row_count = match columns.iter().map(Vec::len).max() {
+                Some(row_count) => row_count,
+                None => table.rowids()?.len(),
+            };
+            (columns, row_count)
+        }
+        RowSource::Rows(rows) => {
+            let columns = used
+                .iter()
+                .map(|idx| rows.iter().map(|row| row[*idx].clone()).collect())
+                .collect();
+            (columns, rows.len())
+        }
+        RowSource::Empty => (vec![], 1),
+    };
+
+    let mut groups: BTreeMap<GroupKey, Group> = BTreeMap::new();
+    for position in 0..row_count {
+        let values: Vec<Value> = columns
+            .iter()
+            .map(|column| column.get(position).cloned().unwrap_or(Value::Null))
+            .collect();
+        let scope = Scope::new(&narrowed, &values).with_env(env);
+        if let Some(selection) = &select.selection {
+            if !is_true(selection, &scope)? {
+                continue;
+            }
+        }
+
+        let key = GroupKey(project(&group_by, &scope)?);
+        let group = groups.entry(key).or_insert_with(|| Group {
+            accumulators: aggregates.iter().map(Aggregate::accumulator).collect(),
+            row: vec![],
+        });
+        let mut holds_extreme = false;
+        for (idx, aggregate) in aggregates.iter().enumerate() {
+            if let Some(inputs) = aggregate.inputs(&scope)? {
+                let improved = aggregate.step(&mut group.accumulators[idx], inputs)?;
+                holds_extreme |= improved && extreme == Some(idx);
+            }
+        }
+        if extreme.is_none() || holds_extreme || group.row.is_empty() {
+            group.row = values;
+        }
+    }
+    if groups.is_empty() && group_by.is_empty() {
+        groups.insert(
+            GroupKey(vec![]),
+            Group {
+                accumulators: aggregates.iter().map(Aggregate::accumulator).collect(),
+                row: vec![Value::Null; narrowed.len()],
+            },
+        );
+    }
+
+    // HAVING can refer to output columns by an alias that is not a table column name,
+    // except to window results, which are computed after HAVING.
+    let aliases: Vec<usize> = (0..labels.len())
+        .filter(|idx| {
+            Scope::new(bindings, &[])
+                .resolve(None, &labels[*idx])
+                .is_err()
+                && !matches!(projections[*idx], Projection::Expr(expr)
+                    if !window_calls(std::iter::once(expr)).is_empty())
+        })
+        .collect();
+    let mut having_bindings = narrowed.clone();
+    having_bindings.extend(aliases.iter().map(|idx| ColumnBinding {
+        table: None,
+        name: labels[*idx].clone(),
+        hidden: false,
+        json: false,
+    }));
+
+    // Groups that pass HAVING, with the results of their aggregate calls.
+    let mut outputs = vec![];
+    for group in groups.into_values() {
+        let computed: Vec<(&Expr, Value)> = aggregates
+            .iter()
+            .zip(&group.accumulators)
+            .map(|(aggregate, accumulator)| (aggregate.call, aggregate.finish(accumulator)))
+            .collect();
+        if let Some(having) = having {
+            let scope = Scope::new(&narrowed, &group.row)
+                .with_computed(&computed)
+                .with_env(env);
+            let mut values = group.row.clone();
+            for idx in &aliases {
+                values.push(match projections[*idx] {
+                    Projection::Column(column) => group.row[column].clone(),
+                    Projection::Expr(expr) => evaluate(expr, &scope)?,
+                });
+            }
+            let scope = Scope::new(&having_bindings, &values)
+                .with_computed(&computed)
+                .with_env(env);
+            if !is_true(having, &scope)? {
+                continue;
+            }
+        }
+        outputs.push((group.row, computed));
+    }
+
+    // Window calls see one row per group, with the aggregates of the group computed.
+    if !windows.is_empty() {
+        let scope = |idx: usize| {
+            let (row, computed) = &outputs[idx];
+            Scope::new(&narrowed, row)
+                .with_computed(computed)
+                .with_env(env)
+        };
+        let results = compute_windows(windows, select, &narrowed, outputs.len(), &scope)?;
+        for (idx, (_, computed)) in outputs.iter_mut().enumerate() {
+            computed.extend(
+                windows
+                    .iter()
+                    .zip(&results)
+                    .map(|(call, values)| (*call, values[idx].clone())),
+            );
+        }
+    }
+
+    let mut result_set = ResultSet::new(labels);
+    let mut sorter = (!keys.is_empty()).then(|| Sorter::new(keys, limit.end()));
+    for (row, computed) in &outputs {
+        let scope = Scope::new(&narrowed, row)
+            .with_computed(computed)
+            .with_env(env);
+        let row = project(&projections, &scope)?;
+        match &mut sorter {
+            Some(sorter) => sorter.push(sort_values(keys, &scope, &row)?, row),
+            None => {
+                result_set.rows.push(row);
+                if limit.end().is_some_and(|end| result_set.rows.len() >= end) {
+                    break;
+                }
+            }
+        }
+    }
+
+    if let Some(sorter) = sorter {
+        result_set.rows = sorter.finish();
+    }
+    result_set.rows = limit.apply(result_set.rows);
+    Ok(result_set)
+}
+
+/// Resolves GROUP BY terms. Like ORDER BY, a term may name an output column by its
+/// 1-based position or, when no table column has that name, by its alias.
+fn resolve_group_by<'a>(
+    select: &'a Select,
+    labels: &[String],
+    projections: &[Projection<'a>],
+    bindings: &[ColumnBinding],
+) -> Result<Vec<Projection<'a>>> {
+    let exprs = match &select.group_by {
+        GroupByExpr::Expressions(exprs) => exprs,
+        GroupByExpr::All => {
+            return Err(RUSQLError::NotImplemented(
+                "GROUP BY ALL is not supported.".to_string(),
+            ))
+        }
+    };
+
+    exprs
+        .iter()
+        .map(|expr| {
+            let output = match expr {
+                Expr::Value(SqlValue::Number(position, _)) => match position.parse::<usize>() {
+                    Ok(position) if (1..=labels.len()).contains(&position) => Some(position - 1),
+                    _ => {
+                        return Err(RUSQLError::General(format!(
+                            "GROUP BY term out of range - should be between 1 and {}",
+                            labels.len()
+                        )))
+                    }
+                },
+                Expr::Identifier(ident)
+                    if Scope::new(bindings, &[])
+                        .resolve(None, &ident.value)
+                        .is_err() =>
+                {
+                    labels
+                        .iter()
+                        .position(|label| label.eq_ignore_ascii_case(&ident.value))
+                }
+                _ => None,
+            };
+            let term = match output {
+                Some(idx) => match &projections[idx] {
+                    Projection::Column(column) => Projection::Column(*column),
+                    Projection::Expr(expr) => Projection::Expr(expr),
+                },
+                None => Projection::Expr(expr),
+            };
+            if matches!(term, Projection::Expr(expr) if contains_aggregate(expr)) {
+                return Err(RUSQLError::General(
+                    "aggregate functions are not allowed in the GROUP BY clause".to_string(),
+                ));
+            }
+            Ok(term)
+        })
+        .collect()
+}
+
+/// Renumbers column projections for a row holding only the `used` columns.
+fn narrow<'a>(projections: &[Projection<'a>], used: &[usize]) -> Vec<Projection<'a>> {
+    projections
+        .iter()
+        .map(|projection| match projection {
+            Projection::Column(idx) => Projection::Column(used.binary_search(idx).unwrap()),
+            Projection::Expr(expr) => Projection::Expr(expr),
+        })
+        .collect()
+}
+
+pub fn projection_exprs<'a>(
+    projections: &'a [Projection<'a>],
+) -> impl Iterator<Item = &'a Expr> + 'a {
+    projections
+        .iter()
+        .filter_map(|projection| match projection {
+            Projection::Expr(expr) => Some(*expr),
+            Projection::Column(_) => None,
+        })
+}
+
+/// Positions of the columns `expr` refers to. Unknown names are left for evaluation to
+/// report.
+fn referenced_columns(expr: &Expr, bindings: &[ColumnBinding]) -> Vec<usize> {
+    let scope = Scope::new(bindings, &[]);
+    let mut columns = vec![];
+    walk_expr(expr, &mut |expr| {
+        let resolved = match expr {
+            Expr::Identifier(ident) => scope.resolve(None, &ident.value).ok(),
+            Expr::CompoundIdentifier(idents) => {
+                let (column, qualifier) = idents.split_last().unwrap();
+                let qualifier = qualifier.last().map(|ident| ident.value.as_str());
+                scope.resolve(qualifier, &column.value).ok()
+            }
+            _ => None,
+        };
+        columns.extend(resolved);
+        true
+    });
+    columns
+}
@@ -0,0 +1,123 @@
+//! Compound queries: UNION, UNION ALL, INTERSECT and EXCEPT.
+//!
+//! Both sides are evaluated in full and must have the same number of columns; the
+//! result takes its column names from the left side. Rows compare as GROUP BY keys do,
+//! so NULLs equal each other and `1` equals `1.0`. UNION, INTERSECT and EXCEPT return
+//! each distinct row once, in the order it first appears; the ALL forms keep duplicates,
+//! INTERSECT ALL as many times as the row is on both sides and EXCEPT ALL as many times
+//! as it is on the left beyond its count on the right. An ORDER BY and LIMIT after the
+//! last query apply to the compound as a whole.
+//!
+//! As in standard SQL, INTERSECT binds tighter than UNION and EXCEPT, which are applied
+//! left to right.
+
+use crate::error::{RUSQLError, Result};
+use crate::sql::db::value::Value;
+use crate::sql::executor::aggregate::GroupKey;
+use crate::sql::executor::expr::Env;
+use crate::sql::executor::order::Limit;
+use crate::sql::executor::result_set::ResultSet;
+use crate::sql::executor::select::execute_set_expr;
+use sqlparser::ast::{SetExpr, SetOperator, SetQuantifier};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub fn execute_compound(
+    op: &SetOperator,
+    set_quantifier: &SetQuantifier,
+    left: &SetExpr,
+    right: &SetExpr,
+    env: Env,
+) -> Result<ResultSet> {
+    let all = match set_quantifier {
+        SetQuantifier::All => true,
+        SetQuantifier::None | SetQuantifier::Distinct => false,
+        _ => {
+            return Err(RUSQLError::NotImplemented(format!(
+                "{} {} is not supported.",
+                op, set_quantifier
+            )))
+        }
+    };
+
+    let left = execute_set_expr(left, env, &[], &Limit::default())?;
+    let right = execute_set_expr(right, env, &[], &Limit::default())?;
+    if left.columns.len() != right.columns.len() {
+        return Err(RUSQLError::General(format!(
+            "SELECTs to the left and right of {} do not have the same number of result columns",
+            op
+        )));
+    }
+
+    let rows = match (op, all) {
+        (SetOperator::Union, true) => {
+            let mut rows = left.rows;
+            rows.extend(right.rows);
+            rows
+        }
+        (SetOperator::Union, false) => distinct(left.rows.into_iter().chain(right.rows)),
+        (SetOperator::Intersect, false) => {
+            let right = keys(right.rows);
+            distinct(
+                left.rows
+                    .into_iter()
+                    .filter(|row| right.contains(&GroupKey(row.clone()))),
+            )
+        }
+        (SetOperator::Except, false) => {
+            let right = keys(right.rows);
+            distinct(
+                left.rows
+                    .into_iter()
+                    .filter(|row| !right.contains(&GroupKey(row.clone()))),
+            )
+        }
+        (SetOperator::Intersect, true) => {
+            let mut counts = counts(right.rows);
+            left.rows
+                .into_iter()
+                .filter(|row| take_one(&mut counts, row))
+                .collect()
+        }
+        (SetOperator::Except, true) => {
+            let mut counts = counts(right.rows);
+            left.rows
+                .into_iter()
+                .filter(|row| !take_one(&mut counts, row))
+                .collect()
+        }
+    };
+    Ok(ResultSet {
+        columns: left.columns,
+        rows,
+    })
+}
+
+/// The first occurrence of every distinct row, in order.
+fn distinct(rows: impl Iterator<Item = Vec<Value>>) -> Vec<Vec<Value>> {
+    let mut seen = BTreeSet::new();
+    rows.filter(|row| seen.insert(GroupKey(row.clone())))
+        .collect()
+}
+
+fn keys(rows: Vec<Vec<Value>>) -> BTreeSet<GroupKey> {
+    rows.into_iter().map(GroupKey).collect()
+}
+
+fn counts(rows: Vec<Vec<Value>>) -> BTreeMap<GroupKey, usize> {
+    let mut counts = BTreeMap::new();
+    for row in rows {
+        *counts.entry(GroupKey(row)).or_default() += 1;
+    }
+    counts
+}
+
+/// Uses up one of the remaining copies of `row`, if there are any.
+fn take_one(counts: &mut BTreeMap<GroupKey, usize>, row: &[Value]) -> bool {
+    match counts.get_mut(&GroupKey(row.to_vec())) {
+        Some(count) if *count > 0 => {
+            *count -= 1;
+            true
+        }
+        _ => false,
+    }
+}
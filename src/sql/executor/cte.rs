@@ -0,0 +1,387 @@
+//! Common table expressions: the tables a WITH clause defines for its query.
+//!
+//! Each table is evaluated once, in order, before the query runs, so later tables, the
+//! query and its subqueries all read the same materialised rows. A recursive table
+//! `anchor UNION [ALL] step` starts from the anchor's rows and runs the step against the
+//! rows added by the previous round until a round adds none. UNION drops rows that were
+//! already produced, which ends cycles; a LIMIT on the table stops the recursion once
+//! that many rows exist. So does the LIMIT of a query that does nothing but scan the
+//! table, and a recursion that reaches `MAX_RECURSIVE_ROWS` rows is an error. The step
+//! may read the table only in its own FROM clause, not from a subquery.
+
+use crate::error::{RUSQLError, Result};
+use crate::sql::executor::aggregate::{is_aggregate, GroupKey};
+use crate::sql::executor::expr::{walk_expr, Env, SubqueryCache};
+use crate::sql::executor::order::Limit;
+use crate::sql::executor::result_set::ResultSet;
+use crate::sql::executor::select::{column_names, execute_query_in, execute_set_expr};
+use sqlparser::ast::{
+    Cte, Expr, GroupByExpr, JoinConstraint, JoinOperator, Query, Select, SelectItem, SetExpr,
+    SetOperator, SetQuantifier, TableFactor, TableWithJoins, With,
+};
+use std::cell::Cell;
+use std::collections::BTreeSet;
+
+/// Most rows a recursive table may produce. A recursion that gets this far has almost
+/// certainly no condition that ends it, so it fails instead of running out of memory.
+pub const MAX_RECURSIVE_ROWS: usize = 1_000_000;
+
+struct CommonTable {
+    name: String,
+    result_set: ResultSet,
+    /// Set when a query reads the table, which tells a recursive step from a plain one.
+    read: Cell<bool>,
+}
+
+/// The tables of a WITH clause, and through `parent` those of the enclosing queries.
+pub struct CommonTables<'a> {
+    tables: Vec<CommonTable>,
+    parent: Option<&'a CommonTables<'a>>,
+}
+
+impl<'a> CommonTables<'a> {
+    /// Evaluates every table of `with` in order. `scan` names the table the query only
+    /// scans, see [`plain_scan`], with the number of its rows the query's LIMIT and
+    /// OFFSET keep; a recursive table stops once it has that many.
+    pub fn define(
+        with: &With,
+        scan: Option<(&str, usize)>,
+        env: Env<'a>,
+    ) -> Result<CommonTables<'a>> {
+        let mut tables = CommonTables {
+            tables: vec![],
+            parent: env.tables,
+        };
+        // Only the last table of a name is visible to the query.
+        let scanned = scan.and_then(|(name, needed)| {
+            let idx = with
+                .cte_tables
+                .iter()
+                .rposition(|cte| cte.alias.name.value.eq_ignore_ascii_case(name))?;
+            Some((idx, needed))
+        });
+        for (idx, cte) in with.cte_tables.iter().enumerate() {
+            let result_set = match recursive_parts(cte, with.recursive)? {
+                Some((anchor, step, distinct)) => {
+                    let needed = scanned.filter(|(scanned, _)| *scanned == idx);
+                    let needed = needed.map(|(_, needed)| needed);
+                    tables.evaluate_recursive(cte, anchor, step, distinct, needed, env)?
+                }
+                None => {
+                    let cache = SubqueryCache::default();
+                    let result_set = execute_query_in(&cte.query, tables.env(env, &cache))?;
+                    renamed(result_set, cte)?
+                }
+            };
+            tables.push(&cte.alias.name.value, result_set);
+        }
+        Ok(tables)
+    }
+
+    /// The rows of the table called `name`, innermost WITH first.
+    pub fn get(&self, name: &str) -> Option<&ResultSet> {
+        let table = self
+            .tables
+            .iter()
+            .rev()
+            .find(|table| table.name.eq_ignore_ascii_case(name));
+        match table {
+            Some(table) => {
+                table.read.set(true);
+                Some(&table.result_set)
+            }
+            None => self.parent.and_then(|parent| parent.get(name)),
+        }
+    }
+
+    fn push(&mut self, name: &str, result_set: ResultSet) {
+        self.tables.push(CommonTable {
+            name: name.to_string(),
+            result_set,
+            read: Cell::new(false),
+        });
+    }
+
+    /// `env` with these tables in scope. Every evaluation gets its own subquery cache, as
+    /// the tables it sees differ from one round of a recursion to the next.
+    fn env<'b>(&'b self, env: Env<'b>, cache: &'b SubqueryCache) -> Env<'b> {
+        Env {
+            cache,
+            tables: Some(self),
+            ..env
+        }
+    }
+
+    fn evaluate_recursive(
+        &mut self,
+        cte: &Cte,
+        anchor: &SetExpr,
+        step: &SetExpr,
+        distinct: bool,
+        needed: Option<usize>,
+        env: Env,
+    ) -> Result<ResultSet> {
+        let limit = Limit::new(cte.query.limit.as_ref(), cte.query.offset.as_ref())?;
+        // Rows to produce: those the table's own LIMIT keeps, and no more than the query
+        // reading it needs.
+        let end = [
+            limit.end(),
+            needed.map(|needed| needed.saturating_add(limit.offset)),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        let cache = SubqueryCache::default();
+        let anchor = renamed(
+            execute_set_expr(anchor, self.env(env, &cache), &[], &Limit::default())?,
+            cte,
+        )?;
+
+        let mut result_set = ResultSet::new(anchor.columns);
+        let mut seen = BTreeSet::new();
+        let mut added = anchor.rows;
+        loop {
+            if distinct {
+                added.retain(|row| seen.insert(GroupKey(row.clone())));
+            }
+            result_set.rows.extend(added.iter().cloned());
+            let done = end.is_some_and(|end| result_set.rows.len() >= end);
+            if added.is_empty() || done {
+                break;
+            }
+            if result_set.rows.len() >= MAX_RECURSIVE_ROWS {
+                return Err(RUSQLError::General(format!(
+                    "Recursive table {} produced more than {} rows; give it a WHERE condition or LIMIT that ends the recursion.",
+                    cte.alias.name, MAX_RECURSIVE_ROWS
+                )));
+            }
+
+            self.push(
+                &cte.alias.name.value,
+                ResultSet {
+                    columns: result_set.columns.clone(),
+                    rows: added,
+                },
+            );
+            let cache = SubqueryCache::default();
+            let step_result = execute_set_expr(step, self.env(env, &cache), &[], &Limit::default());
+            let working = self.tables.pop().unwrap();
+            let step_result = step_result?;
+            if step_result.columns.len() != result_set.columns.len() {
+                return Err(RUSQLError::General(format!(
+                    "SELECTs to the left and right of UNION do not have the same number of result columns in {}",
+                    cte.alias.name
+                )));
+            }
+            added = step_result.rows;
+            if !working.read.get() {
+                // The step does not refer to the table, so one round is all there is.
+                if distinct {
+                    added.retain(|row| seen.insert(GroupKey(row.clone())));
+                }
+                result_set.rows.extend(added);
+                break;
+            }
+        }
+        result_set.rows = limit.apply(result_set.rows);
+        Ok(result_set)
+    }
+}
+
+/// The anchor, the step and whether duplicates are dropped, for a table of a
+/// `WITH RECURSIVE` clause written as `anchor UNION [ALL] step`.
+fn recursive_parts(cte: &Cte, recursive: bool) -> Result<Option<(&SetExpr, &SetExpr, bool)>> {
+    let (left, right, set_quantifier) = match cte.query.body.as_ref() {
+        SetExpr::SetOperation {
+            op: SetOperator::Union,
+            set_quantifier,
+            left,
+            right,
+        } if recursive => (left, right, set_quantifier),
+        _ => return Ok(None),
+    };
+    if cte.query.with.is_some() || !cte.query.order_by.is_empty() {
+        return Err(RUSQLError::NotImplemented(
+            "WITH and ORDER BY are not supported inside a recursive table.".to_string(),
+        ));
+    }
+    let name = &cte.alias.name.value;
+    let nested = match right.as_ref() {
+        SetExpr::Select(select) => select_reads(select, name, true),
+        step => set_expr_reads(step, name),
+    };
+    if nested {
+        return Err(RUSQLError::General(format!(
+            "recursive reference in a subquery: {}",
+            name
+        )));
+    }
+    let distinct = match set_quantifier {
+        SetQuantifier::All => false,
+        SetQuantifier::None | SetQuantifier::Distinct => true,
+        _ => {
+            return Err(RUSQLError::NotImplemented(format!(
+                "UNION {} is not supported.",
+                set_quantifier
+            )))
+        }
+    };
+    Ok(Some((left, right, distinct)))
+}
+
+/// Applies the column names of `name(a, b) AS (...)`.
+fn renamed(mut result_set: ResultSet, cte: &Cte) -> Result<ResultSet> {
+    result_set.columns = column_names(result_set.columns, &cte.alias)?;
+    Ok(result_set)
+}
+
+/// The table a query reads when it is nothing but `SELECT ... FROM table` with an
+/// optional LIMIT: every row of the table yields one row of the result, so the query
+/// needs no more of the table's rows than its LIMIT and OFFSET keep.
+pub fn plain_scan(query: &Query) -> Option<&str> {
+    let SetExpr::Select(select) = query.body.as_ref() else {
+        return None;
+    };
+    let [TableWithJoins {
+        relation: TableFactor::Table {
+            name, args: None, ..
+        },
+        joins,
+    }] = select.from.as_slice()
+    else {
+        return None;
+    };
+    let plain = query.order_by.is_empty()
+        && joins.is_empty()
+        && select.distinct.is_none()
+        && select.selection.is_none()
+        && select.having.is_none()
+        && matches!(&select.group_by, GroupByExpr::Expressions(exprs) if exprs.is_empty())
+        && select.projection.iter().all(|item| match item {
+            SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                is_row_wise(expr)
+            }
+            _ => true,
+        });
+    match name.0.as_slice() {
+        [table] if plain => Some(&table.value),
+        _ => None,
+    }
+}
+
+/// Whether `expr` is computed from its own row alone: it calls no aggregate or window
+/// function and has no subquery.
+fn is_row_wise(expr: &Expr) -> bool {
+    let mut row_wise = true;
+    walk_expr(expr, &mut |expr| {
+        match expr {
+            Expr::Function(function) if function.over.is_some() || is_aggregate(function) => {
+                row_wise = false
+            }
+            Expr::Subquery(_) | Expr::Exists { .. } | Expr::InSubquery { .. } => row_wise = false,
+            _ => (),
+        }
+        row_wise
+    });
+    row_wise
+}
+
+/// Whether `select` reads the table `name`. With `scan_allowed`, the tables of its own
+/// FROM clause do not count, only subqueries do.
+fn select_reads(select: &Select, name: &str, scan_allowed: bool) -> bool {
+    let exprs = select
+        .projection
+        .iter()
+        .filter_map(|item| match item {
+            SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => Some(expr),
+            _ => None,
+        })
+        .chain(&select.selection)
+        .chain(&select.having);
+    let group_by = match &select.group_by {
+        GroupByExpr::Expressions(exprs) => exprs.as_slice(),
+        GroupByExpr::All => &[],
+    };
+    select
+        .from
+        .iter()
+        .any(|table| table_reads(table, name, scan_allowed))
+        || exprs.chain(group_by).any(|expr| expr_reads(expr, name))
+}
+
+fn table_reads(table: &TableWithJoins, name: &str, scan_allowed: bool) -> bool {
+    factor_reads(&table.relation, name, scan_allowed)
+        || table.joins.iter().any(|join| {
+            let constraint = match &join.join_operator {
+                JoinOperator::Inner(JoinConstraint::On(expr))
+                | JoinOperator::LeftOuter(JoinConstraint::On(expr))
+                | JoinOperator::RightOuter(JoinConstraint::On(expr))
+                | JoinOperator::FullOuter(JoinConstraint::On(expr)) => Some(expr),
+                _ => None,
+            };
+            factor_reads(&join.relation, name, scan_allowed)
+                || constraint.is_some_and(|expr| expr_reads(expr, name))
+        })
+}
+
+fn factor_reads(factor: &TableFactor, name: &str, scan_allowed: bool) -> bool {
+    match factor {
+        TableFactor::Table { name: table, .. } => {
+            !scan_allowed && table.to_string().eq_ignore_ascii_case(name)
+        }
+        TableFactor::Derived { subquery, .. } => query_reads(subquery, name),
+        TableFactor::NestedJoin {
+            table_with_joins, ..
+        } => table_reads(table_with_joins, name, scan_allowed),
+        _ => false,
+    }
+}
+
+fn query_reads(query: &Query, name: &str) -> bool {
+    let with_reads = query.with.as_ref().is_some_and(|with| {
+        with.cte_tables
+            .iter()
+            .any(|cte| query_reads(&cte.query, name))
+    });
+    with_reads
+        || set_expr_reads(&query.body, name)
+        || query
+            .order_by
+            .iter()
+            .any(|order| expr_reads(&order.expr, name))
+}
+
+fn set_expr_reads(body: &SetExpr, name: &str) -> bool {
+    match body {
+        SetExpr::Select(select) => select_reads(select, name, false),
+        SetExpr::Query(query) => query_reads(query, name),
+        SetExpr::SetOperation { left, right, .. } => {
+            set_expr_reads(left, name) || set_expr_reads(right, name)
+        }
+        SetExpr::Values(values) => values
+            .rows
+            .iter()
+            .flatten()
+            .any(|expr| expr_reads(expr, name)),
+        _ => false,
+    }
+}
+
+/// Whether a subquery of `expr` reads the table `name`.
+fn expr_reads(expr: &Expr, name: &str) -> bool {
+    let mut found = false;
+    walk_expr(expr, &mut |expr| {
+        if let Expr::Subquery(query)
+        | Expr::Exists {
+            subquery: query, ..
+        }
+        | Expr::InSubquery {
+            subquery: query, ..
+        } = expr
+        {
+            found = found || query_reads(query, name);
+        }
+        !found
+    });
+    found
+}
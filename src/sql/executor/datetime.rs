@@ -0,0 +1,494 @@
+//! Date and time functions: `date`, `time`, `datetime`, `julianday`, `unixepoch` and
+//! `strftime`, and `CURRENT_DATE`, `CURRENT_TIME` and `CURRENT_TIMESTAMP`.
+//!
+//! As in SQLite there is no date type: dates are stored as ISO-8601 text
+//! (`'2024-03-01 12:30:00'`), which sorts chronologically, or as numbers. Every function
+//! reads a time value, applies its modifiers in order and formats the result. A moment
+//! is kept as milliseconds since noon of November 24, 4714 BC, the Julian day number
+//! times 86 400 000, so that date arithmetic is integer arithmetic. All times are UTC.
+//!
+//! A time value is one of
+//! - `YYYY-MM-DD`, optionally followed by ` HH:MM`, `:SS` and `.SSS`, with `T` allowed
+//!   in place of the space and an optional `Z` or `±HH:MM` zone after the time;
+//! - `HH:MM[:SS[.SSS]]` alone, which is that time on 2000-01-01;
+//! - `now`;
+//! - a number, which is a Julian day number, or seconds since 1970 when the first
+//!   modifier is `unixepoch`.
+//!
+//! The modifiers are `±N days`, `hours`, `minutes`, `seconds`, `months` and `years`
+//! (singular too, N may have a fraction), `start of day`, `start of month`,
+//! `start of year`, `weekday N` (the next day that is weekday N, Sunday being 0),
+//! `unixepoch`, `utc` (a no-op) and `subsec`, which keeps milliseconds in the output.
+//! Anything that cannot be read gives NULL, as in SQLite.
+
+use crate::error::Result;
+use crate::sql::db::value::{parse_number, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MS_PER_DAY: i64 = 86_400_000;
+/// The moment 1970-01-01 00:00:00.
+const UNIX_EPOCH_MS: i64 = 210_866_760_000_000;
+/// The last millisecond of 9999-12-31; earlier than 0000-01-01 is out of range too.
+const MAX_MS: i64 = 464_269_060_799_999;
+
+/// A moment, in milliseconds of the Julian day.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Moment(i64);
+
+/// A moment broken down into calendar fields.
+struct Fields {
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    /// Seconds including milliseconds.
+    second: f64,
+}
+
+impl Moment {
+    fn from_fields(fields: &Fields) -> Moment {
+        let (mut year, mut month) = (fields.year, fields.month);
+        if month <= 2 {
+            year -= 1;
+            month += 12;
+        }
+        let a = year / 100;
+        let b = 2 - a + a / 4;
+        let x1 = 36525 * (year + 4716) / 100;
+        let x2 = 306001 * (month + 1) / 10000;
+        let days = ((x1 + x2 + fields.day + b) as f64 - 1524.5) * MS_PER_DAY as f64;
+        let time = fields.hour * 3_600_000
+            + fields.minute * 60_000
+            + (fields.second * 1000.0).round() as i64;
+        Moment(days as i64 + time)
+    }
+
+    fn fields(self) -> Fields {
+        let z = (self.0 + MS_PER_DAY / 2) / MS_PER_DAY;
+        let alpha = ((z as f64 + 32044.75) / 36524.25) as i64 - 52;
+        let a = z + 1 + alpha - (alpha + 100) / 4 + 25;
+        let b = a + 1524;
+        let c = ((b as f64 - 122.1) / 365.25) as i64;
+        let d = (36525 * (c & 32767)) / 100;
+        let e = ((b - d) as f64 / 30.6001) as i64;
+        let x1 = (30.6001 * e as f64) as i64;
+        let month = if e < 14 { e - 1 } else { e - 13 };
+
+        let ms = (self.0 + MS_PER_DAY / 2) % MS_PER_DAY;
+        Fields {
+            year: if month > 2 { c - 4716 } else { c - 4715 },
+            month,
+            day: b - d - x1,
+            hour: ms / 3_600_000,
+            minute: ms / 60_000 % 60,
+            second: (ms % 60_000) as f64 / 1000.0,
+        }
+    }
+
+    fn now() -> Moment {
+        let ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as i64);
+        Moment(UNIX_EPOCH_MS + ms)
+    }
+
+    fn in_range(self) -> bool {
+        (0..=MAX_MS).contains(&self.0)
+    }
+
+    /// Day of the week, Sunday being 0.
+    fn weekday(self) -> i64 {
+        ((self.0 + MS_PER_DAY + MS_PER_DAY / 2) / MS_PER_DAY) % 7
+    }
+
+    fn unix_seconds(self) -> i64 {
+        (self.0 - UNIX_EPOCH_MS).div_euclid(1000)
+    }
+
+    fn julian_day(self) -> f64 {
+        self.0 as f64 / MS_PER_DAY as f64
+    }
+}
+
+/// The moment a time value and its modifiers describe, and whether `subsec` asked for
+/// milliseconds in the output. `None` when any part cannot be read.
+fn evaluate(args: &[Value]) -> Option<(Moment, bool)> {
+    let modifiers = args
+        .iter()
+        .skip(1)
+        .map(|modifier| match modifier {
+            Value::Null => None,
+            modifier => Some(modifier.to_string().trim().to_lowercase()),
+        })
+        .collect::<Option<Vec<String>>>()?;
+    let unix = modifiers.first().is_some_and(|first| first == "unixepoch");
+
+    let number = match args.first() {
+        Some(Value::Null) => return None,
+        Some(Value::Integer(i)) => Some(*i as f64),
+        Some(Value::Real(r)) => Some(*r),
+        Some(value) => parse_number(&value.to_string()).and_then(|number| number.as_f64()),
+        None => None,
+    };
+    let mut moment = match (args.first(), number) {
+        (None, _) => Moment::now(),
+        (Some(_), Some(seconds)) if unix => {
+            Moment(UNIX_EPOCH_MS.saturating_add((seconds * 1000.0).round() as i64))
+        }
+        (Some(_), Some(days)) => Moment((days * MS_PER_DAY as f64).round() as i64),
+        (Some(value), None) => parse_time_value(&value.to_string())?,
+    };
+
+    let mut subsec = false;
+    for (idx, modifier) in modifiers.iter().enumerate() {
+        if !moment.in_range() {
+            return None;
+        }
+        match modifier.as_str() {
+            "unixepoch" if idx == 0 && number.is_some() => (),
+            "utc" => (),
+            "subsec" | "subsecond" => subsec = true,
+            _ => moment = apply_modifier(moment, modifier)?,
+        }
+    }
+    moment.in_range().then_some((moment, subsec))
+}
+
+fn apply_modifier(moment: Moment, modifier: &str) -> Option<Moment> {
+    if let Some(unit) = modifier.strip_prefix("start of ") {
+        let mut fields = moment.fields();
+        match unit {
+            "year" => (fields.month, fields.day) = (1, 1),
+            "month" => fields.day = 1,
+            "day" => (),
+            _ => return None,
+        }
+        (fields.hour, fields.minute, fields.second) = (0, 0, 0.0);
+        return Some(Moment::from_fields(&fields));
+    }
+    if let Some(weekday) = modifier.strip_prefix("weekday ") {
+        let weekday: i64 = weekday
+            .trim()
+            .parse()
+            .ok()
+            .filter(|day| (0..7).contains(day))?;
+        let mut ahead = weekday - moment.weekday();
+        if ahead < 0 {
+            ahead += 7;
+        }
+        return Some(Moment(moment.0 + ahead * MS_PER_DAY));
+    }
+
+    let (amount, unit) = modifier.split_once(' ')?;
+    let amount: f64 = amount.parse().ok()?;
+    let unit = unit.trim();
+    let unit = unit.strip_suffix('s').unwrap_or(unit);
+    let (moment, ms) = match unit {
+        "day" => (moment, amount * MS_PER_DAY as f64),
+        "hour" => (moment, amount * 3_600_000.0),
+        "minute" => (moment, amount * 60_000.0),
+        "second" => (moment, amount * 1000.0),
+        "month" | "year" => {
+            // Whole months and years move the calendar fields and keep the day of the
+            // month, so one month after January 31 is March 3 (or 2), as in SQLite. A
+            // fraction is added as 30 days a month or 365 days a year.
+            let whole = amount.trunc();
+            if whole.abs() > 10_000.0 {
+                return None;
+            }
+            let (months, days_per_unit) = if unit == "year" {
+                (whole as i64 * 12, 365.0)
+            } else {
+                (whole as i64, 30.0)
+            };
+            let mut fields = moment.fields();
+            let month = fields.month - 1 + months;
+            fields.year += month.div_euclid(12);
+            fields.month = month.rem_euclid(12) + 1;
+            (
+                Moment::from_fields(&fields),
+                (amount - whole) * days_per_unit * MS_PER_DAY as f64,
+            )
+        }
+        _ => return None,
+    };
+    moment.0.checked_add(ms.round() as i64).map(Moment)
+}
+
+/// Reads the text forms of a time value.
+fn parse_time_value(text: &str) -> Option<Moment> {
+    let text = text.trim();
+    if text.eq_ignore_ascii_case("now") {
+        return Some(Moment::now());
+    }
+
+    let mut reader = Reader::new(text);
+    let (year, month, day) = if text.len() >= 10 && text.as_bytes()[4] == b'-' {
+        let year = reader.number(4)?;
+        reader.expect('-')?;
+        let month = reader.number(2)?;
+        reader.expect('-')?;
+        let day = reader.number(2)?;
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+        if reader.done() {
+            return Some(Moment::from_fields(&Fields {
+                year,
+                month,
+                day,
+                hour: 0,
+                minute: 0,
+                second: 0.0,
+            }));
+        }
+        reader.expect('T').or_else(|| reader.expect(' '))?;
+        reader.skip_spaces();
+        (year, month, day)
+    } else {
+        (2000, 1, 1)
+    };
+
+    let hour = reader.number(2)?;
+    reader.expect(':')?;
+    let minute = reader.number(2)?;
+    let mut second = 0.0;
+    if reader.expect(':').is_some() {
+        second = reader.number(2)? as f64;
+        if reader.expect('.').is_some() {
+            second += reader.fraction()?;
+        }
+    }
+    if hour > 23 || minute > 59 || second >= 60.0 {
+        return None;
+    }
+    let moment = Moment::from_fields(&Fields {
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+    });
+
+    // A zone gives the offset of the local time from UTC.
+    reader.skip_spaces();
+    let offset = if reader.done() || reader.expect('Z').or_else(|| reader.expect('z')).is_some() {
+        0
+    } else {
+        let sign = if reader.expect('+').is_some() {
+            1
+        } else {
+            reader.expect('-')?;
+            -1
+        };
+        let hours = reader.number(2)?;
+        reader.expect(':')?;
+        let minutes = reader.number(2)?;
+        sign * (hours * 60 + minutes) * 60_000
+    };
+    reader.done().then_some(Moment(moment.0 - offset))
+}
+
+struct Reader<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(text: &'a str) -> Self {
+        Reader {
+            text: text.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.pos == self.text.len()
+    }
+
+    fn expect(&mut self, c: char) -> Option<()> {
+        if self.text.get(self.pos) == Some(&(c as u8)) {
+            self.pos += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.text.get(self.pos) == Some(&b' ') {
+            self.pos += 1;
+        }
+    }
+
+    /// Exactly `digits` decimal digits.
+    fn number(&mut self, digits: usize) -> Option<i64> {
+        let end = self.pos + digits;
+        let digits = self.text.get(self.pos..end)?;
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        self.pos = end;
+        std::str::from_utf8(digits).ok()?.parse().ok()
+    }
+
+    /// The digits after a decimal point, as a fraction.
+    fn fraction(&mut self) -> Option<f64> {
+        let start = self.pos;
+        while self.text.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return None;
+        }
+        format!(
+            "0.{}",
+            std::str::from_utf8(&self.text[start..self.pos]).ok()?
+        )
+        .parse()
+        .ok()
+    }
+}
+
+fn format_date(fields: &Fields) -> String {
+    format!("{:04}-{:02}-{:02}", fields.year, fields.month, fields.day)
+}
+
+fn format_time(fields: &Fields, subsec: bool) -> String {
+    if subsec {
+        format!(
+            "{:02}:{:02}:{:06.3}",
+            fields.hour, fields.minute, fields.second
+        )
+    } else {
+        format!(
+            "{:02}:{:02}:{:02}",
+            fields.hour,
+            fields.minute,
+            fields.second.trunc() as i64
+        )
+    }
+}
+
+fn text_or_null(value: Option<String>) -> Result<Value> {
+    Ok(value.map_or(Value::Null, Value::Text))
+}
+
+pub fn date(args: &[Value]) -> Result<Value> {
+    text_or_null(evaluate(args).map(|(moment, _)| format_date(&moment.fields())))
+}
+
+pub fn time(args: &[Value]) -> Result<Value> {
+    text_or_null(evaluate(args).map(|(moment, subsec)| format_time(&moment.fields(), subsec)))
+}
+
+pub fn datetime(args: &[Value]) -> Result<Value> {
+    text_or_null(evaluate(args).map(|(moment, subsec)| {
+        let fields = moment.fields();
+        format!("{} {}", format_date(&fields), format_time(&fields, subsec))
+    }))
+}
+
+pub fn julianday(args: &[Value]) -> Result<Value> {
+    Ok(evaluate(args).map_or(Value::Null, |(moment, _)| Value::Real(moment.julian_day())))
+}
+
+/// Seconds since 1970-01-01, with milliseconds as a real under `subsec`.
+pub fn unixepoch(args: &[Value]) -> Result<Value> {
+    Ok(match evaluate(args) {
+        Some((moment, true)) => Value::Real((moment.0 - UNIX_EPOCH_MS) as f64 / 1000.0),
+        Some((moment, false)) => Value::Integer(moment.unix_seconds()),
+        None => Value::Null,
+    })
+}
+
+pub fn current_date(_args: &[Value]) -> Result<Value> {
+    date(&[])
+}
+
+pub fn current_time(_args: &[Value]) -> Result<Value> {
+    time(&[])
+}
+
+pub fn current_timestamp(_args: &[Value]) -> Result<Value> {
+    datetime(&[])
+}
+
+/// `strftime(format, time value, modifiers...)`. The substitutions are `%d` day of
+/// month, `%e` the same without padding, `%f` seconds with milliseconds, `%F` the date,
+/// `%H` hour, `%I` 12-hour clock hour, `%j` day of year, `%J` Julian day number, `%k`
+/// and `%l` the two hours padded with spaces, `%m` month, `%M` minute, `%p`/`%P`
+/// AM/PM and am/pm, `%R` and `%T` the time to minutes and seconds, `%s` seconds since
+/// 1970, `%S` seconds, `%u` ISO weekday (Monday 1), `%w` weekday (Sunday 0), `%W` week
+/// of the year starting on Mondays, `%Y` year and `%%`.
+pub fn strftime(args: &[Value]) -> Result<Value> {
+    if args[0].is_null() {
+        return Ok(Value::Null);
+    }
+    let format = args[0].to_string();
+    let (moment, _) = match evaluate(&args[1..]) {
+        Some(evaluated) => evaluated,
+        None => return Ok(Value::Null),
+    };
+    let fields = moment.fields();
+    let hour12 = match fields.hour % 12 {
+        0 => 12,
+        hour => hour,
+    };
+    let day_of_year = {
+        let january_first = Moment::from_fields(&Fields {
+            month: 1,
+            day: 1,
+            hour: 0,
+            minute: 0,
+            second: 0.0,
+            ..fields
+        });
+        (moment.0 - january_first.0) / MS_PER_DAY
+    };
+
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let substitution = match chars.next() {
+            Some('d') => format!("{:02}", fields.day),
+            Some('e') => format!("{:2}", fields.day),
+            Some('f') => format!("{:06.3}", fields.second),
+            Some('F') => format_date(&fields),
+            Some('H') => format!("{:02}", fields.hour),
+            Some('I') => format!("{:02}", hour12),
+            Some('j') => format!("{:03}", day_of_year + 1),
+            Some('J') => format!("{}", moment.julian_day()),
+            Some('k') => format!("{:2}", fields.hour),
+            Some('l') => format!("{:2}", hour12),
+            Some('m') => format!("{:02}", fields.month),
+            Some('M') => format!("{:02}", fields.minute),
+            Some('p') => (if fields.hour < 12 { "AM" } else { "PM" }).to_string(),
+            Some('P') => (if fields.hour < 12 { "am" } else { "pm" }).to_string(),
+            Some('R') => format!("{:02}:{:02}", fields.hour, fields.minute),
+            Some('s') => moment.unix_seconds().to_string(),
+            Some('S') => format!("{:02}", fields.second.trunc() as i64),
+            Some('T') => format_time(&fields, false),
+            Some('u') => match moment.weekday() {
+                0 => "7".to_string(),
+                weekday => weekday.to_string(),
+            },
+            Some('w') => moment.weekday().to_string(),
+            Some('W') => {
+                let monday_based = (moment.weekday() + 6) % 7;
+                format!("{:02}", (day_of_year + 7 - monday_based) / 7)
+            }
+            Some('Y') => format!("{:04}", fields.year),
+            Some('%') => "%".to_string(),
+            _ => return Ok(Value::Null),
+        };
+        out.push_str(&substitution);
+    }
+    Ok(Value::Text(out))
+}
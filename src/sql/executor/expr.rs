@@ -0,0 +1,872 @@
+//! Expression evaluator over sqlparser's `Expr`.
+//!
+//! Evaluation follows SQLite semantics, including three-valued logic: any comparison
+//! involving NULL yields NULL, `AND`/`OR` only short-circuit on a definite result, and a
+//! WHERE clause keeps a row only when its condition is definitely true.
+//!
+//! Subqueries run against the database in the scope's `Env`. A subquery that reads no
+//! column of the enclosing row gives the same result every time, so it runs once per
+//! statement and is cached; one that does is correlated and runs again for every row.
+
+use crate::error::{RUSQLError, Result};
+use crate::sql::db::database::Database;
+use crate::sql::db::decimal::overflow_error;
+use crate::sql::db::table::{DataType, Table};
+use crate::sql::db::value::{parse_number, Value};
+use crate::sql::executor::aggregate::is_aggregate;
+use crate::sql::executor::cte::CommonTables;
+use crate::sql::executor::functions::{self, call_function, cast};
+use crate::sql::executor::json::{
+    call_json_function, is_json_function, json_access, returns_json, Arg,
+};
+use crate::sql::executor::result_set::ResultSet;
+use crate::sql::executor::select::execute_query_in;
+use sqlparser::ast::Value as SqlValue;
+use sqlparser::ast::{
+    BinaryOperator, Expr, FunctionArg, FunctionArgExpr, JsonOperator, Query, TrimWhereField,
+    UnaryOperator,
+};
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Name under which a value can be referenced in an expression: `name` or `table.name`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ColumnBinding {
+    pub table: Option<String>,
+    pub name: String,
+    /// Only reachable by its qualified name and left out of `*`, as the right table's
+    /// copy of a column joined with USING.
+    pub hidden: bool,
+    /// Whether the column has type JSON, so that functions building JSON embed its
+    /// values as JSON rather than as strings.
+    pub json: bool,
+}
+
+impl ColumnBinding {
+    /// Bindings for every column of `table`, qualified by `qualifier` (a table name or alias).
+    pub fn for_table(table: &Table, qualifier: &str) -> Vec<ColumnBinding> {
+        table
+            .columns
+            .iter()
+            .map(|col| ColumnBinding {
+                table: Some(qualifier.to_string()),
+                name: col.column_name.clone(),
+                hidden: false,
+                json: col.datatype == DataType::Json,
+            })
+            .collect()
+    }
+
+    fn matches(&self, qualifier: Option<&str>, name: &str) -> bool {
+        if !self.name.eq_ignore_ascii_case(name) {
+            return false;
+        }
+        match (qualifier, &self.table) {
+            (None, _) => !self.hidden,
+            (Some(qualifier), Some(table)) => table.eq_ignore_ascii_case(qualifier),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// Results of the uncorrelated subqueries of a statement, keyed by their address in the
+/// statement's syntax tree.
+pub type SubqueryCache = RefCell<HashMap<usize, Rc<ResultSet>>>;
+
+/// What a query runs in: the database its subqueries read and, inside a subquery, the
+/// row of the enclosing query.
+#[derive(Clone, Copy)]
+pub struct Env<'a> {
+    pub db: &'a Database,
+    pub cache: &'a SubqueryCache,
+    /// The enclosing query's row, which correlated subqueries refer to.
+    pub outer: Option<&'a Scope<'a>>,
+    /// Set once a name is resolved in `outer`.
+    pub correlated: Option<&'a Cell<bool>>,
+    /// Tables defined by the WITH clauses of this query and the enclosing ones.
+    pub tables: Option<&'a CommonTables<'a>>,
+}
+
+impl<'a> Env<'a> {
+    pub fn new(db: &'a Database, cache: &'a SubqueryCache) -> Self {
+        Env {
+            db,
+            cache,
+            outer: None,
+            correlated: None,
+            tables: None,
+        }
+    }
+}
+
+/// The row an expression is evaluated against: column bindings and their current values.
+pub struct Scope<'a> {
+    pub bindings: &'a [ColumnBinding],
+    pub values: &'a [Value],
+    /// Function calls whose value was computed ahead of time, such as the aggregates of
+    /// a group. Evaluation uses these values instead of calling the function.
+    pub computed: &'a [(&'a Expr, Value)],
+    /// Without an environment, subqueries cannot be evaluated.
+    pub env: Option<Env<'a>>,
+}
+
+impl<'a> Scope<'a> {
+    pub fn new(bindings: &'a [ColumnBinding], values: &'a [Value]) -> Self {
+        Scope {
+            bindings,
+            values,
+            computed: &[],
+            env: None,
+        }
+    }
+
+    pub fn with_computed(mut self, computed: &'a [(&'a Expr, Value)]) -> Self {
+        self.computed = computed;
+        self
+    }
+
+    pub fn with_env(mut self, env: Env<'a>) -> Self {
+        self.env = Some(env);
+        self
+    }
+
+    /// Finds the position of a column reference, rejecting unknown and ambiguous names.
+    pub fn resolve(&self, qualifier: Option<&str>, name: &str) -> Result<usize> {
+        self.find(qualifier, name)?
+            .ok_or_else(|| no_such_column(qualifier, name))
+    }
+
+    /// The value of a column reference. Names not bound in this row are looked up in
+    /// the rows of the enclosing queries, innermost first.
+    pub fn lookup(&self, qualifier: Option<&str>, name: &str) -> Result<Value> {
+        if let Some(idx) = self.find(qualifier, name)? {
+            return Ok(self.values[idx].clone());
+        }
+        match self.env {
+            Some(Env {
+                outer: Some(outer),
+                correlated,
+                ..
+            }) => {
+                let value = outer.lookup(qualifier, name)?;
+                if let Some(correlated) = correlated {
+                    correlated.set(true);
+                }
+                Ok(value)
+            }
+            _ => Err(no_such_column(qualifier, name)),
+        }
+    }
+
+    /// Position of the binding `name` refers to, `None` if there is none.
+    fn find(&self, qualifier: Option<&str>, name: &str) -> Result<Option<usize>> {
+        let mut matches = self
+            .bindings
+            .iter()
+            .enumerate()
+            .filter(|(_, binding)| binding.matches(qualifier, name));
+
+        match (matches.next(), matches.next()) {
+            (Some((idx, _)), None) => Ok(Some(idx)),
+            (Some(_), Some(_)) => Err(RUSQLError::General(format!(
+                "Ambiguous column name: {}",
+                display_name(qualifier, name)
+            ))),
+            (None, _) => Ok(None),
+        }
+    }
+}
+
+fn no_such_column(qualifier: Option<&str>, name: &str) -> RUSQLError {
+    RUSQLError::General(format!("No such column: {}", display_name(qualifier, name)))
+}
+
+fn display_name(qualifier: Option<&str>, name: &str) -> String {
+    match qualifier {
+        Some(qualifier) => format!("{}.{}", qualifier, name),
+        None => name.to_string(),
+    }
+}
+
+/// Evaluates `expr` as a filter condition. Only a definitely true result keeps the row;
+/// both false and NULL reject it.
+pub fn is_true(expr: &Expr, scope: &Scope) -> Result<bool> {
+    Ok(evaluate(expr, scope)?.as_bool() == Some(true))
+}
+
+pub fn evaluate(expr: &Expr, scope: &Scope) -> Result<Value> {
+    match expr {
+        Expr::Identifier(ident) => scope.lookup(None, &ident.value),
+        Expr::CompoundIdentifier(idents) => {
+            let (column, qualifier) = idents.split_last().unwrap();
+            let qualifier = qualifier.last().map(|ident| ident.value.as_str());
+            scope.lookup(qualifier, &column.value)
+        }
+        Expr::Value(value) => literal_value(value),
+        Expr::Nested(expr) => evaluate(expr, scope),
+        Expr::UnaryOp {
+            op: UnaryOperator::Minus,
+            expr,
+        } if is_min_integer_magnitude(expr) => Ok(Value::Integer(i64::MIN)),
+        Expr::UnaryOp { op, expr } => unary_op(op, evaluate(expr, scope)?),
+        Expr::BinaryOp { left, op, right } => match op {
+            BinaryOperator::And => logical_and(left, right, scope),
+            BinaryOperator::Or => logical_or(left, right, scope),
+            _ => binary_op(op, evaluate(left, scope)?, evaluate(right, scope)?),
+        },
+        Expr::IsNull(expr) => Ok(Value::Bool(evaluate(expr, scope)?.is_null())),
+        Expr::IsNotNull(expr) => Ok(Value::Bool(!evaluate(expr, scope)?.is_null())),
+        Expr::IsTrue(expr) => Ok(Value::Bool(evaluate(expr, scope)?.as_bool() == Some(true))),
+        Expr::IsNotTrue(expr) => Ok(Value::Bool(evaluate(expr, scope)?.as_bool() != Some(true))),
+        Expr::IsFalse(expr) => Ok(Value::Bool(evaluate(expr, scope)?.as_bool() == Some(false))),
+        Expr::IsNotFalse(expr) => Ok(Value::Bool(evaluate(expr, scope)?.as_bool() != Some(false))),
+        Expr::IsDistinctFrom(left, right) => Ok(Value::Bool(!is_not_distinct(
+            &evaluate(left, scope)?,
+            &evaluate(right, scope)?,
+        ))),
+        Expr::IsNotDistinctFrom(left, right) => Ok(Value::Bool(is_not_distinct(
+            &evaluate(left, scope)?,
+            &evaluate(right, scope)?,
+        ))),
+        Expr::InList {
+            expr,
+            list,
+            negated,
+        } => {
+            let value = evaluate(expr, scope)?;
+            let candidates = list
+                .iter()
+                .map(|item| evaluate(item, scope))
+                .collect::<Result<Vec<Value>>>()?;
+            Ok(negate_if(in_list(&value, &candidates), *negated))
+        }
+        Expr::Between {
+            expr,
+            negated,
+            low,
+            high,
+        } => {
+            let value = evaluate(expr, scope)?;
+            let above_low = compare_op(&BinaryOperator::GtEq, &value, &evaluate(low, scope)?);
+            let below_high = compare_op(&BinaryOperator::LtEq, &value, &evaluate(high, scope)?);
+            Ok(negate_if(and3(above_low, below_high), *negated))
+        }
+        Expr::Subquery(query) => {
+            let result_set = run_subquery(query, scope)?;
+            single_column(&result_set)?;
+            Ok(result_set
+                .rows
+                .first()
+                .map_or(Value::Null, |row| row[0].clone()))
+        }
+        Expr::Exists { subquery, negated } => {
+            let result_set = run_subquery(subquery, scope)?;
+            Ok(Value::Bool(result_set.rows.is_empty() == *negated))
+        }
+        Expr::InSubquery {
+            expr,
+            subquery,
+            negated,
+        } => {
+            let value = evaluate(expr, scope)?;
+            let result_set = run_subquery(subquery, scope)?;
+            single_column(&result_set)?;
+            let candidates: Vec<Value> = result_set.rows.iter().map(|row| row[0].clone()).collect();
+            Ok(negate_if(in_list(&value, &candidates), *negated))
+        }
+        Expr::Like {
+            negated,
+            expr,
+            pattern,
+            escape_char,
+        } => {
+            let matched = match (evaluate(expr, scope)?, evaluate(pattern, scope)?) {
+                (Value::Null, _) | (_, Value::Null) => Value::Null,
+                (value, pattern) => Value::Bool(like_match(
+                    &pattern.to_string(),
+                    &value.to_string(),
+                    *escape_char,
+                )),
+            };
+            Ok(negate_if(matched, *negated))
+        }
+        Expr::Function(function) => {
+            if let Some((_, value)) = scope.computed.iter().find(|(call, _)| *call == expr) {
+                return Ok(value.clone());
+            }
+            let name = function.name.to_string().to_lowercase();
+            if is_aggregate(function) {
+                return Err(RUSQLError::General(format!(
+                    "misuse of aggregate function {}()",
+                    name
+                )));
+            }
+            if function.over.is_some() {
+                return Err(RUSQLError::General(format!(
+                    "misuse of window function {}()",
+                    name
+                )));
+            }
+            let arg_exprs = function
+                .args
+                .iter()
+                .map(|arg| match arg {
+                    FunctionArg::Unnamed(FunctionArgExpr::Expr(expr))
+                    | FunctionArg::Named {
+                        arg: FunctionArgExpr::Expr(expr),
+                        ..
+                    } => Ok(expr),
+                    _ => Err(RUSQLError::NotImplemented(format!(
+                        "Unsupported argument to {}(): {}",
+                        name, arg
+                    ))),
+                })
+                .collect::<Result<Vec<&Expr>>>()?;
+            if is_json_function(&name) {
+                let args = arg_exprs
+                    .into_iter()
+                    .map(|expr| {
+                        Ok(Arg {
+                            value: evaluate(expr, scope)?,
+                            is_json: returns_json(expr, scope.bindings),
+                        })
+                    })
+                    .collect::<Result<Vec<Arg>>>()?;
+                return call_json_function(&name, &args);
+            }
+            let args = arg_exprs
+                .into_iter()
+                .map(|expr| evaluate(expr, scope))
+                .collect::<Result<Vec<Value>>>()?;
+            call_function(&name, &args)
+        }
+        Expr::JsonAccess {
+            left,
+            operator: operator @ (JsonOperator::Arrow | JsonOperator::LongArrow),
+            right,
+        } => json_access(
+            &evaluate(left, scope)?,
+            &evaluate(right, scope)?,
+            *operator == JsonOperator::LongArrow,
+        ),
+        Expr::Case {
+            operand,
+            conditions,
+            results,
+            else_result,
+        } => {
+            // A simple CASE compares its operand with each WHEN value as `=` does, so a
+            // NULL operand matches nothing.
+            let operand = match operand {
+                Some(operand) => Some(evaluate(operand, scope)?),
+                None => None,
+            };
+            for (condition, result) in conditions.iter().zip(results) {
+                let matched = match &operand {
+                    Some(operand) => {
+                        operand.compare(&evaluate(condition, scope)?) == Some(Ordering::Equal)
+                    }
+                    None => is_true(condition, scope)?,
+                };
+                if matched {
+                    return evaluate(result, scope);
+                }
+            }
+            match else_result {
+                Some(else_result) => evaluate(else_result, scope),
+                None => Ok(Value::Null),
+            }
+        }
+        Expr::Cast {
+            expr,
+            data_type,
+            format: None,
+        } => cast(evaluate(expr, scope)?, data_type),
+        // `TRIM([BOTH | LEADING | TRAILING] [chars FROM] x)`
+        Expr::Trim {
+            expr,
+            trim_where,
+            trim_what,
+            trim_characters: None,
+        } => {
+            let name = match trim_where {
+                Some(TrimWhereField::Leading) => "ltrim",
+                Some(TrimWhereField::Trailing) => "rtrim",
+                Some(TrimWhereField::Both) | None => "trim",
+            };
+            let mut args = vec![evaluate(expr, scope)?];
+            if let Some(chars) = trim_what {
+                args.push(evaluate(chars, scope)?);
+            }
+            call_function(name, &args)
+        }
+        // `SUBSTRING(x FROM start FOR length)` and `SUBSTRING(x, start, length)`
+        Expr::Substring {
+            expr,
+            substring_from,
+            substring_for,
+            ..
+        } => {
+            let mut args = vec![evaluate(expr, scope)?];
+            args.push(match substring_from {
+                Some(start) => evaluate(start, scope)?,
+                None => Value::Integer(1),
+            });
+            if let Some(length) = substring_for {
+                args.push(evaluate(length, scope)?);
+            }
+            call_function("substr", &args)
+        }
+        _ => Err(RUSQLError::NotImplemented(format!(
+            "Unsupported expression: {}",
+            expr
+        ))),
+    }
+}
+
+/// Runs a subquery with `scope` as its enclosing row. Uncorrelated results are cached
+/// for the rest of the statement.
+fn run_subquery(query: &Query, scope: &Scope) -> Result<Rc<ResultSet>> {
+    let env = scope.env.ok_or_else(|| {
+        RUSQLError::NotImplemented("Subqueries are not supported here.".to_string())
+    })?;
+    let key = query as *const Query as usize;
+    if let Some(result_set) = env.cache.borrow().get(&key) {
+        return Ok(Rc::clone(result_set));
+    }
+
+    let correlated = Cell::new(false);
+    let inner = Env {
+        outer: Some(scope),
+        correlated: Some(&correlated),
+        ..env
+    };
+    let result_set = Rc::new(execute_query_in(query, inner)?);
+    if !correlated.get() {
+        env.cache.borrow_mut().insert(key, Rc::clone(&result_set));
+    }
+    Ok(result_set)
+}
+
+fn single_column(result_set: &ResultSet) -> Result<()> {
+    match result_set.columns.len() {
+        1 => Ok(()),
+        n => Err(RUSQLError::General(format!(
+            "sub-select returns {} columns - expected 1",
+            n
+        ))),
+    }
+}
+
+/// Whether `expr` contains a subquery, which may refer to any column of the row.
+pub fn contains_subquery(expr: &Expr) -> bool {
+    let mut found = false;
+    walk_expr(expr, &mut |expr| {
+        found |= matches!(
+            expr,
+            Expr::Subquery(_) | Expr::Exists { .. } | Expr::InSubquery { .. }
+        );
+        !found
+    });
+    found
+}
+
+/// Calls `visit` on `expr` and then on each of its subexpressions, depth first. Returning
+/// false from `visit` skips the subexpressions of that node. Subqueries are not entered.
+pub fn walk_expr<'a>(expr: &'a Expr, visit: &mut dyn FnMut(&'a Expr) -> bool) {
+    if !visit(expr) {
+        return;
+    }
+    match expr {
+        Expr::Nested(inner)
+        | Expr::UnaryOp { expr: inner, .. }
+        | Expr::IsNull(inner)
+        | Expr::IsNotNull(inner)
+        | Expr::IsTrue(inner)
+        | Expr::IsNotTrue(inner)
+        | Expr::IsFalse(inner)
+        | Expr::IsNotFalse(inner)
+        | Expr::Cast { expr: inner, .. } => walk_expr(inner, visit),
+        Expr::BinaryOp { left, right, .. }
+        | Expr::JsonAccess { left, right, .. }
+        | Expr::IsDistinctFrom(left, right)
+        | Expr::IsNotDistinctFrom(left, right) => {
+            walk_expr(left, visit);
+            walk_expr(right, visit);
+        }
+        Expr::InList { expr, list, .. } => {
+            walk_expr(expr, visit);
+            list.iter().for_each(|item| walk_expr(item, visit));
+        }
+        Expr::InSubquery { expr, .. } => walk_expr(expr, visit),
+        Expr::Between {
+            expr, low, high, ..
+        } => {
+            walk_expr(expr, visit);
+            walk_expr(low, visit);
+            walk_expr(high, visit);
+        }
+        Expr::Like { expr, pattern, .. } => {
+            walk_expr(expr, visit);
+            walk_expr(pattern, visit);
+        }
+        Expr::Case {
+            operand,
+            conditions,
+            results,
+            else_result,
+        } => {
+            let operand = operand.iter().chain(else_result);
+            for expr in operand.map(Box::as_ref).chain(conditions).chain(results) {
+                walk_expr(expr, visit);
+            }
+        }
+        Expr::Trim {
+            expr, trim_what, ..
+        } => {
+            walk_expr(expr, visit);
+            if let Some(chars) = trim_what {
+                walk_expr(chars, visit);
+            }
+        }
+        Expr::Substring {
+            expr,
+            substring_from,
+            substring_for,
+            ..
+        } => {
+            walk_expr(expr, visit);
+            for arg in [substring_from, substring_for].into_iter().flatten() {
+                walk_expr(arg, visit);
+            }
+        }
+        Expr::Function(function) => {
+            for arg in &function.args {
+                if let FunctionArg::Unnamed(FunctionArgExpr::Expr(arg))
+                | FunctionArg::Named {
+                    arg: FunctionArgExpr::Expr(arg),
+                    ..
+                } = arg
+                {
+                    walk_expr(arg, visit);
+                }
+            }
+            if let Some(filter) = &function.filter {
+                walk_expr(filter, visit);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Whether `expr` is the literal 9223372036854775808. Negated, it is the smallest integer,
+/// as in SQLite, rather than the negation of a real.
+fn is_min_integer_magnitude(expr: &Expr) -> bool {
+    matches!(expr, Expr::Value(SqlValue::Number(n, _)) if n == "9223372036854775808")
+}
+
+fn literal_value(value: &SqlValue) -> Result<Value> {
+    match value {
+        SqlValue::Number(n, _) => parse_number(n)
+            .ok_or_else(|| RUSQLError::General(format!("Invalid numeric literal: {}", n))),
+        SqlValue::SingleQuotedString(s) => Ok(Value::Text(s.to_string())),
+        SqlValue::Boolean(b) => Ok(Value::Bool(*b)),
+        SqlValue::Null => Ok(Value::Null),
+        // `X'0AFF'`: two hex digits per byte.
+        SqlValue::HexStringLiteral(hex) => {
+            let digits = hex
+                .chars()
+                .map(|c| c.to_digit(16).map(|digit| digit as u8))
+                .collect::<Option<Vec<u8>>>()
+                .filter(|digits| digits.len() % 2 == 0)
+                .ok_or_else(|| {
+                    RUSQLError::General(format!("Malformed blob literal: X'{}'", hex))
+                })?;
+            Ok(Value::Blob(
+                digits
+                    .chunks(2)
+                    .map(|pair| pair[0] << 4 | pair[1])
+                    .collect(),
+            ))
+        }
+        _ => Err(RUSQLError::NotImplemented(format!(
+            "Unsupported literal: {}",
+            value
+        ))),
+    }
+}
+
+fn negate_if(value: Value, negated: bool) -> Value {
+    match value {
+        Value::Bool(b) if negated => Value::Bool(!b),
+        value => value,
+    }
+}
+
+fn unary_op(op: &UnaryOperator, value: Value) -> Result<Value> {
+    match op {
+        UnaryOperator::Not => Ok(value.as_bool().map_or(Value::Null, |b| Value::Bool(!b))),
+        UnaryOperator::Plus => Ok(value),
+        UnaryOperator::Minus => Ok(match value.to_numeric() {
+            Value::Integer(i) => i
+                .checked_neg()
+                .map_or(Value::Real(-(i as f64)), Value::Integer),
+            Value::Real(r) => Value::Real(-r),
+            Value::Decimal(d) => Value::Decimal(-d),
+            _ => Value::Null,
+        }),
+        _ => Err(RUSQLError::NotImplemented(format!(
+            "Unsupported unary operator: {}",
+            op
+        ))),
+    }
+}
+
+fn logical_and(left: &Expr, right: &Expr, scope: &Scope) -> Result<Value> {
+    let left = evaluate(left, scope)?.as_bool();
+    if left == Some(false) {
+        return Ok(Value::Bool(false));
+    }
+    let right = evaluate(right, scope)?.as_bool();
+    Ok(and3(bool_value(left), bool_value(right)))
+}
+
+fn logical_or(left: &Expr, right: &Expr, scope: &Scope) -> Result<Value> {
+    let left = evaluate(left, scope)?.as_bool();
+    if left == Some(true) {
+        return Ok(Value::Bool(true));
+    }
+    let right = evaluate(right, scope)?.as_bool();
+    Ok(match (left, right) {
+        (_, Some(true)) => Value::Bool(true),
+        (Some(false), Some(false)) => Value::Bool(false),
+        _ => Value::Null,
+    })
+}
+
+fn bool_value(value: Option<bool>) -> Value {
+    value.map_or(Value::Null, Value::Bool)
+}
+
+/// Three-valued AND: false wins over NULL, NULL wins over true.
+fn and3(left: Value, right: Value) -> Value {
+    match (left.as_bool(), right.as_bool()) {
+        (Some(false), _) | (_, Some(false)) => Value::Bool(false),
+        (Some(true), Some(true)) => Value::Bool(true),
+        _ => Value::Null,
+    }
+}
+
+fn is_not_distinct(left: &Value, right: &Value) -> bool {
+    match (left.is_null(), right.is_null()) {
+        (true, true) => true,
+        (false, false) => left.compare(right) == Some(Ordering::Equal),
+        _ => false,
+    }
+}
+
+/// `value IN (candidates)`: true on a match, otherwise NULL if anything involved was NULL.
+fn in_list(value: &Value, candidates: &[Value]) -> Value {
+    if value.is_null() {
+        return Value::Null;
+    }
+    let mut saw_null = false;
+    for candidate in candidates {
+        match value.compare(candidate) {
+            Some(Ordering::Equal) => return Value::Bool(true),
+            None => saw_null = true,
+            _ => (),
+        }
+    }
+    if saw_null {
+        Value::Null
+    } else {
+        Value::Bool(false)
+    }
+}
+
+fn binary_op(op: &BinaryOperator, left: Value, right: Value) -> Result<Value> {
+    match op {
+        BinaryOperator::Eq
+        | BinaryOperator::NotEq
+        | BinaryOperator::Lt
+        | BinaryOperator::LtEq
+        | BinaryOperator::Gt
+        | BinaryOperator::GtEq => Ok(compare_op(op, &left, &right)),
+        BinaryOperator::Plus
+        | BinaryOperator::Minus
+        | BinaryOperator::Multiply
+        | BinaryOperator::Divide
+        | BinaryOperator::Modulo => arithmetic(op, &left, &right),
+        BinaryOperator::StringConcat => Ok(match (&left, &right) {
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            _ => Value::Text(functions::text(&left) + &functions::text(&right)),
+        }),
+        _ => Err(RUSQLError::NotImplemented(format!(
+            "Unsupported operator: {}",
+            op
+        ))),
+    }
+}
+
+fn compare_op(op: &BinaryOperator, left: &Value, right: &Value) -> Value {
+    let ordering = match left.compare(right) {
+        Some(ordering) => ordering,
+        None => return Value::Null,
+    };
+    Value::Bool(match op {
+        BinaryOperator::Eq => ordering == Ordering::Equal,
+        BinaryOperator::NotEq => ordering != Ordering::Equal,
+        BinaryOperator::Lt => ordering == Ordering::Less,
+        BinaryOperator::LtEq => ordering != Ordering::Greater,
+        BinaryOperator::Gt => ordering == Ordering::Greater,
+        BinaryOperator::GtEq => ordering != Ordering::Less,
+        _ => unreachable!("compare_op called with non-comparison operator"),
+    })
+}
+
+/// Integer arithmetic stays integral and falls back to floating point on overflow;
+/// division or modulo by zero yields NULL as in SQLite. When either side is a decimal
+/// the result is an exact decimal, with reals read as the digits they print as, and a
+/// numeric overflow error when it needs more digits than a decimal holds.
+fn arithmetic(op: &BinaryOperator, left: &Value, right: &Value) -> Result<Value> {
+    Ok(match (left.to_numeric(), right.to_numeric()) {
+        (Value::Null, _) | (_, Value::Null) => Value::Null,
+        (a, b) if matches!(a, Value::Decimal(_)) || matches!(b, Value::Decimal(_)) => {
+            let (Some(a), Some(b)) = (a.as_decimal(), b.as_decimal()) else {
+                return Err(overflow_error());
+            };
+            let exact = match op {
+                BinaryOperator::Plus => a.checked_add(&b),
+                BinaryOperator::Minus => a.checked_sub(&b),
+                BinaryOperator::Multiply => a.checked_mul(&b),
+                BinaryOperator::Divide | BinaryOperator::Modulo if b.is_zero() => {
+                    return Ok(Value::Null)
+                }
+                BinaryOperator::Divide => a.checked_div(&b),
+                BinaryOperator::Modulo => a.checked_rem(&b),
+                _ => unreachable!("arithmetic called with non-arithmetic operator"),
+            };
+            Value::Decimal(exact.ok_or_else(overflow_error)?)
+        }
+        (Value::Integer(a), Value::Integer(b)) => {
+            let checked = match op {
+                BinaryOperator::Plus => a.checked_add(b),
+                BinaryOperator::Minus => a.checked_sub(b),
+                BinaryOperator::Multiply => a.checked_mul(b),
+                BinaryOperator::Divide | BinaryOperator::Modulo if b == 0 => {
+                    return Ok(Value::Null)
+                }
+                BinaryOperator::Divide => a.checked_div(b),
+                BinaryOperator::Modulo => a.checked_rem(b),
+                _ => unreachable!("arithmetic called with non-arithmetic operator"),
+            };
+            checked.map_or_else(|| real_arithmetic(op, a as f64, b as f64), Value::Integer)
+        }
+        (a, b) => real_arithmetic(op, a.as_f64().unwrap(), b.as_f64().unwrap()),
+    })
+}
+
+fn real_arithmetic(op: &BinaryOperator, a: f64, b: f64) -> Value {
+    match op {
+        BinaryOperator::Plus => Value::Real(a + b),
+        BinaryOperator::Minus => Value::Real(a - b),
+        BinaryOperator::Multiply => Value::Real(a * b),
+        BinaryOperator::Divide if b == 0.0 => Value::Null,
+        BinaryOperator::Divide => Value::Real(a / b),
+        BinaryOperator::Modulo => {
+            let (a, b) = (a as i64, b as i64);
+            if b == 0 {
+                Value::Null
+            } else {
+                Value::Real(a.wrapping_rem(b) as f64)
+            }
+        }
+        _ => unreachable!("arithmetic called with non-arithmetic operator"),
+    }
+}
+
+/// SQL LIKE: `%` matches any run of characters, `_` exactly one, and ASCII letters
+/// compare case-insensitively.
+pub fn like_match(pattern: &str, text: &str, escape: Option<char>) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    like_from(&pattern, &text, escape)
+}
+
+fn like_from(pattern: &[char], text: &[char], escape: Option<char>) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((&c, rest)) if Some(c) == escape => match rest.split_first() {
+            Some((&literal, rest)) => {
+                !text.is_empty()
+                    && text[0].eq_ignore_ascii_case(&literal)
+                    && like_from(rest, &text[1..], escape)
+            }
+            None => false,
+        },
+        Some(('%', rest)) => (0..=text.len()).any(|skip| like_from(rest, &text[skip..], escape)),
+        Some(('_', rest)) => !text.is_empty() && like_from(rest, &text[1..], escape),
+        Some((c, rest)) => {
+            !text.is_empty()
+                && text[0].eq_ignore_ascii_case(c)
+                && like_from(rest, &text[1..], escape)
+        }
+    }
+}
+
+/// SQLite GLOB: case-sensitive, `*` matches any run, `?` one character and `[...]`
+/// a character class (with `^` negation and `a-z` ranges).
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_from(&pattern, &text)
+}
+
+fn glob_from(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| glob_from(rest, &text[skip..])),
+        Some(('?', rest)) => !text.is_empty() && glob_from(rest, &text[1..]),
+        Some(('[', rest)) => {
+            let Some(&c) = text.first() else {
+                return false;
+            };
+            match match_char_class(rest, c) {
+                Some((true, rest)) => glob_from(rest, &text[1..]),
+                Some((false, _)) => false,
+                // An unterminated class matches a literal '['.
+                None => c == '[' && glob_from(rest, &text[1..]),
+            }
+        }
+        Some((c, rest)) => !text.is_empty() && text[0] == *c && glob_from(rest, &text[1..]),
+    }
+}
+
+/// Matches `c` against the class body following `[`; returns whether it matched and the
+/// pattern remaining after the closing `]`.
+fn match_char_class(class: &[char], c: char) -> Option<(bool, &[char])> {
+    let (negated, mut i) = match class.first() {
+        Some('^') => (true, 1),
+        _ => (false, 0),
+    };
+    let mut matched = false;
+    let mut first = true;
+
+    while i < class.len() {
+        if class[i] == ']' && !first {
+            return Some((matched != negated, &class[i + 1..]));
+        }
+        if i + 2 < class.len() && class[i + 1] == '-' && class[i + 2] != ']' {
+            matched |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+        } else {
+            matched |= class[i] == c;
+            i += 1;
+        }
+        first = false;
+    }
+    None
+}
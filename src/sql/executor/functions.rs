@@ -0,0 +1,679 @@
+//! Built-in scalar functions.
+//!
+//! Every function is an entry of `FUNCTIONS`: a name, the number of arguments it takes
+//! and the Rust function that computes its result from the already evaluated arguments.
+//! The functions follow SQLite: almost all of them return NULL when an argument is NULL,
+//! text functions count characters rather than bytes, and numbers, booleans and blobs
+//! are read as text where text is expected. UPPER and LOWER, like LIKE, only change the
+//! case of ASCII letters.
+
+use crate::error::{RUSQLError, Result};
+use crate::sql::db::decimal::{Decimal, RoundingMode};
+use crate::sql::db::json::Json;
+use crate::sql::db::table::DataType as ColumnType;
+use crate::sql::db::value::{parse_number, Value};
+use crate::sql::executor::datetime;
+use crate::sql::executor::expr::{glob_match, like_match};
+use crate::sql::parser::create::data_type_as_str;
+use sqlparser::ast::DataType;
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+struct ScalarFunction {
+    name: &'static str,
+    min_args: usize,
+    /// `None` for functions taking any number of arguments from `min_args` on.
+    max_args: Option<usize>,
+    call: fn(&[Value]) -> Result<Value>,
+}
+
+const fn function(
+    name: &'static str,
+    min_args: usize,
+    max_args: Option<usize>,
+    call: fn(&[Value]) -> Result<Value>,
+) -> ScalarFunction {
+    ScalarFunction {
+        name,
+        min_args,
+        max_args,
+        call,
+    }
+}
+
+/// The registry of scalar functions, by lower-case name. MIN and MAX with a single
+/// argument are aggregates and never get here.
+const FUNCTIONS: &[ScalarFunction] = &[
+    function("abs", 1, Some(1), abs),
+    function("coalesce", 2, None, coalesce),
+    function("current_date", 0, Some(0), datetime::current_date),
+    function("current_time", 0, Some(0), datetime::current_time),
+    function("current_timestamp", 0, Some(0), datetime::current_timestamp),
+    function("date", 0, None, datetime::date),
+    function("datetime", 0, None, datetime::datetime),
+    function("format", 1, None, printf),
+    function("glob", 2, Some(2), glob),
+    function("hex", 1, Some(1), hex),
+    function("ifnull", 2, Some(2), coalesce),
+    function("instr", 2, Some(2), instr),
+    function("julianday", 0, None, datetime::julianday),
+    function("length", 1, Some(1), length),
+    function("like", 2, Some(2), like),
+    function("lower", 1, Some(1), lower),
+    function("ltrim", 1, Some(2), ltrim),
+    function("max", 2, None, max),
+    function("min", 2, None, min),
+    function("nullif", 2, Some(2), nullif),
+    function("printf", 1, None, printf),
+    function("random", 0, Some(0), random),
+    function("replace", 3, Some(3), replace),
+    function("round", 1, Some(3), round),
+    function("rtrim", 1, Some(2), rtrim),
+    function("strftime", 1, None, datetime::strftime),
+    function("substr", 2, Some(3), substr),
+    function("substring", 2, Some(3), substr),
+    function("time", 0, None, datetime::time),
+    function("trim", 1, Some(2), trim),
+    function("typeof", 1, Some(1), type_of),
+    function("unixepoch", 0, None, datetime::unixepoch),
+    function("upper", 1, Some(1), upper),
+];
+
+/// Calls the scalar function `name` (in lower case) with `args`.
+pub fn call_function(name: &str, args: &[Value]) -> Result<Value> {
+    let function = FUNCTIONS
+        .iter()
+        .find(|function| function.name == name)
+        .ok_or_else(|| RUSQLError::NotImplemented(format!("Unknown function: {}()", name)))?;
+    if args.len() < function.min_args || function.max_args.is_some_and(|max| args.len() > max) {
+        return Err(RUSQLError::General(format!(
+            "wrong number of arguments to function {}()",
+            name
+        )));
+    }
+    (function.call)(args)
+}
+
+/// Converts `value` to the storage class that `datatype` declares a column with, as
+/// `CAST(value AS datatype)` does. NULL stays NULL. As in SQLite, numbers are read from
+/// the longest numeric prefix of text, so `CAST('12abc' AS INTEGER)` is 12 and
+/// `CAST('abc' AS INTEGER)` is 0. A boolean is true for a non-zero number or the text
+/// `'true'`, and false for zero or `'false'`; other text gives NULL. JSON must be well
+/// formed and comes out minified. A blob holds the bytes of the value's text, and text
+/// read from a blob is its bytes as UTF-8. A decimal reads text digit by digit and is
+/// an error when the value does not fit its precision. A type with no storage class is
+/// an error.
+pub fn cast(value: Value, datatype: &DataType) -> Result<Value> {
+    if value.is_null() {
+        return Ok(Value::Null);
+    }
+    Ok(match data_type_as_str(datatype)?.as_str() {
+        "Integer" => Value::Integer(match value.to_numeric() {
+            Value::Integer(i) => i,
+            // Saturates at the ends of the i64 range, as SQLite does.
+            Value::Real(r) => r as i64,
+            Value::Decimal(d) => d.to_i64(),
+            _ => unreachable!("to_numeric only returns numbers for non-NULL values"),
+        }),
+        "Real" => Value::Real(value.as_f64().unwrap()),
+        "Text" => Value::Text(text(&value)),
+        "Blob" => match value {
+            Value::Blob(bytes) => Value::Blob(bytes),
+            value => Value::Blob(value.to_string().into_bytes()),
+        },
+        "Json" => match Json::parse(&text(&value)) {
+            Some(json) => Value::Text(json.to_string()),
+            None => return Err(RUSQLError::General("malformed JSON".to_string())),
+        },
+        "Bool" => match &value {
+            Value::Text(s) if s.trim().eq_ignore_ascii_case("true") => Value::Bool(true),
+            Value::Text(s) if s.trim().eq_ignore_ascii_case("false") => Value::Bool(false),
+            Value::Text(s) => match parse_number(s) {
+                Some(number) => Value::Bool(number.as_bool().unwrap()),
+                None => Value::Null,
+            },
+            value => Value::Bool(value.as_bool().unwrap()),
+        },
+        name if name.starts_with("Decimal") => {
+            let decimal = match &value {
+                Value::Text(s) => Decimal::parse(s).or_else(|| value.as_decimal()),
+                value => value.as_decimal(),
+            };
+            decimal
+                .and_then(|decimal| {
+                    ColumnType::new(name.to_string()).coerce(&Value::Decimal(decimal))
+                })
+                .ok_or_else(|| {
+                    RUSQLError::General(format!(
+                        "Cannot CAST {} to {}: out of range",
+                        value, datatype
+                    ))
+                })?
+        }
+        _ => {
+            return Err(RUSQLError::General(format!(
+                "Cannot CAST to {}: unknown type",
+                datatype
+            )))
+        }
+    })
+}
+
+/// The value read as text; blobs are read as UTF-8.
+pub fn text(value: &Value) -> String {
+    match value {
+        Value::Blob(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        value => value.to_string(),
+    }
+}
+
+/// Applies `f` to the text of `value`, passing NULL through.
+fn map_text(value: &Value, f: impl FnOnce(&str) -> Value) -> Value {
+    match value {
+        Value::Null => Value::Null,
+        value => f(&text(value)),
+    }
+}
+
+fn abs(args: &[Value]) -> Result<Value> {
+    Ok(match args[0].to_numeric() {
+        Value::Integer(i) => Value::Integer(
+            i.checked_abs()
+                .ok_or_else(|| RUSQLError::General("integer overflow".to_string()))?,
+        ),
+        Value::Real(r) => Value::Real(r.abs()),
+        Value::Decimal(d) => Value::Decimal(d.abs()),
+        _ => Value::Null,
+    })
+}
+
+fn coalesce(args: &[Value]) -> Result<Value> {
+    Ok(args
+        .iter()
+        .find(|arg| !arg.is_null())
+        .cloned()
+        .unwrap_or(Value::Null))
+}
+
+fn nullif(args: &[Value]) -> Result<Value> {
+    Ok(match args[0].compare(&args[1]) {
+        Some(Ordering::Equal) => Value::Null,
+        _ => args[0].clone(),
+    })
+}
+
+fn min(args: &[Value]) -> Result<Value> {
+    Ok(extreme(args, Ordering::Less))
+}
+
+fn max(args: &[Value]) -> Result<Value> {
+    Ok(extreme(args, Ordering::Greater))
+}
+
+/// The first argument that no other argument is `wanted` of, or NULL if any argument
+/// is NULL.
+fn extreme(args: &[Value], wanted: Ordering) -> Value {
+    if args.iter().any(Value::is_null) {
+        return Value::Null;
+    }
+    args.iter()
+        .fold(&args[0], |best, arg| {
+            if arg.sort_cmp(best) == wanted {
+                arg
+            } else {
+                best
+            }
+        })
+        .clone()
+}
+
+fn glob(args: &[Value]) -> Result<Value> {
+    Ok(match args {
+        [Value::Null, _] | [_, Value::Null] => Value::Null,
+        [pattern, value] => Value::Bool(glob_match(&text(pattern), &text(value))),
+        _ => unreachable!(),
+    })
+}
+
+fn like(args: &[Value]) -> Result<Value> {
+    Ok(match args {
+        [Value::Null, _] | [_, Value::Null] => Value::Null,
+        [pattern, value] => Value::Bool(like_match(&text(pattern), &text(value), None)),
+        _ => unreachable!(),
+    })
+}
+
+/// Upper-case hexadecimal of the bytes of the value; the empty string for NULL.
+fn hex(args: &[Value]) -> Result<Value> {
+    let bytes = match &args[0] {
+        Value::Null => vec![],
+        Value::Blob(bytes) => bytes.clone(),
+        value => value.to_string().into_bytes(),
+    };
+    Ok(Value::Text(
+        bytes.iter().map(|byte| format!("{:02X}", byte)).collect(),
+    ))
+}
+
+/// Characters of text, bytes of a blob.
+fn length(args: &[Value]) -> Result<Value> {
+    Ok(match &args[0] {
+        Value::Null => Value::Null,
+        Value::Blob(bytes) => Value::Integer(bytes.len() as i64),
+        value => Value::Integer(value.to_string().chars().count() as i64),
+    })
+}
+
+fn lower(args: &[Value]) -> Result<Value> {
+    Ok(map_text(&args[0], |s| Value::Text(s.to_ascii_lowercase())))
+}
+
+fn upper(args: &[Value]) -> Result<Value> {
+    Ok(map_text(&args[0], |s| Value::Text(s.to_ascii_uppercase())))
+}
+
+/// The 1-based position of the first occurrence of the second argument in the first,
+/// or 0 if there is none.
+fn instr(args: &[Value]) -> Result<Value> {
+    Ok(match (&args[0], &args[1]) {
+        (Value::Null, _) | (_, Value::Null) => Value::Null,
+        (Value::Blob(haystack), Value::Blob(needle)) => Value::Integer(if needle.is_empty() {
+            1
+        } else {
+            haystack
+                .windows(needle.len())
+                .position(|window| window == needle.as_slice())
+                .map_or(0, |idx| idx as i64 + 1)
+        }),
+        (haystack, needle) => {
+            let haystack = text(haystack);
+            Value::Integer(
+                haystack
+                    .find(&text(needle))
+                    .map_or(0, |idx| haystack[..idx].chars().count() as i64 + 1),
+            )
+        }
+    })
+}
+
+fn replace(args: &[Value]) -> Result<Value> {
+    if args.iter().any(Value::is_null) {
+        return Ok(Value::Null);
+    }
+    let (s, from, to) = (text(&args[0]), text(&args[1]), text(&args[2]));
+    Ok(Value::Text(if from.is_empty() {
+        s
+    } else {
+        s.replace(&from, &to)
+    }))
+}
+
+/// `substr(x, start[, length])` with SQLite's rules: the first character is 1, a
+/// negative start counts from the end, a negative length takes the characters before
+/// the start, and blobs are sliced by bytes.
+fn substr(args: &[Value]) -> Result<Value> {
+    if args.iter().any(Value::is_null) {
+        return Ok(Value::Null);
+    }
+    let start = integer_arg(&args[1]);
+    let length = args.get(2).map(integer_arg);
+    Ok(match &args[0] {
+        Value::Blob(bytes) => {
+            let (from, to) = substr_range(bytes.len() as i64, start, length);
+            Value::Blob(bytes[from..to].to_vec())
+        }
+        value => {
+            let chars: Vec<char> = text(value).chars().collect();
+            let (from, to) = substr_range(chars.len() as i64, start, length);
+            Value::Text(chars[from..to].iter().collect())
+        }
+    })
+}
+
+fn substr_range(len: i64, start: i64, length: Option<i64>) -> (usize, usize) {
+    let (mut p1, mut p2) = (start, length.unwrap_or(i64::MAX).saturating_abs());
+    if p1 < 0 {
+        p1 += len;
+        if p1 < 0 {
+            p2 = p2.saturating_add(p1).max(0);
+            p1 = 0;
+        }
+    } else if p1 > 0 {
+        p1 -= 1;
+    } else if p2 > 0 {
+        p2 -= 1;
+    }
+    if length.is_some_and(|length| length < 0) {
+        p1 -= p2;
+        if p1 < 0 {
+            p2 += p1;
+            p1 = 0;
+        }
+    }
+    let p1 = p1.min(len);
+    let p2 = p2.clamp(0, len - p1);
+    (p1 as usize, (p1 + p2) as usize)
+}
+
+fn integer_arg(value: &Value) -> i64 {
+    match value.to_numeric() {
+        Value::Integer(i) => i,
+        Value::Real(r) => r as i64,
+        Value::Decimal(d) => d.to_i64(),
+        _ => 0,
+    }
+}
+
+fn trim(args: &[Value]) -> Result<Value> {
+    trim_with(args, |s, chars| s.trim_matches(|c| chars.contains(&c)))
+}
+
+fn ltrim(args: &[Value]) -> Result<Value> {
+    trim_with(args, |s, chars| {
+        s.trim_start_matches(|c| chars.contains(&c))
+    })
+}
+
+fn rtrim(args: &[Value]) -> Result<Value> {
+    trim_with(args, |s, chars| s.trim_end_matches(|c| chars.contains(&c)))
+}
+
+/// Removes the characters of the second argument, spaces by default, with `strip`.
+fn trim_with(args: &[Value], strip: for<'a> fn(&'a str, &[char]) -> &'a str) -> Result<Value> {
+    if args.iter().any(Value::is_null) {
+        return Ok(Value::Null);
+    }
+    let chars: Vec<char> = match args.get(1) {
+        Some(chars) => text(chars).chars().collect(),
+        None => vec![' '],
+    };
+    Ok(Value::Text(strip(&text(&args[0]), &chars).to_string()))
+}
+
+/// `round(x[, digits[, mode]])` rounds half away from zero, or with the named rounding
+/// mode (`'half_even'`, `'floor'`, ... see `RoundingMode`). A decimal stays a decimal;
+/// anything else comes back as a real, as in SQLite. Reals are rounded on the digits
+/// they print as, so `round(2.675, 2)` is 2.68 even though 2.675 is stored as slightly less.
+fn round(args: &[Value]) -> Result<Value> {
+    if args.iter().any(Value::is_null) {
+        return Ok(Value::Null);
+    }
+    let digits = args.get(1).map_or(0, integer_arg).clamp(0, 30);
+    let mode = match args.get(2) {
+        None => RoundingMode::HalfUp,
+        Some(mode) => RoundingMode::from_name(&text(mode))
+            .ok_or_else(|| RUSQLError::General(format!("unknown rounding mode: {}", text(mode))))?,
+    };
+    let x = match args[0].to_numeric() {
+        Value::Decimal(d) => return Ok(Value::Decimal(d.round(digits as u32, mode))),
+        x => x.as_f64().unwrap(),
+    };
+    if let Some(d) = Decimal::from_f64(x) {
+        return Ok(Value::Real(d.round(digits as u32, mode).to_f64()));
+    }
+    // Reals with too many digits for a decimal are rounded half up in floating point.
+    let scale = 10f64.powi(digits as i32);
+    let rounded = (x * scale).round() / scale;
+    Ok(Value::Real(if rounded.is_finite() { rounded } else { x }))
+}
+
+fn type_of(args: &[Value]) -> Result<Value> {
+    Ok(Value::Text(
+        match args[0] {
+            Value::Null => "null",
+            Value::Integer(_) => "integer",
+            Value::Real(_) => "real",
+            Value::Text(_) => "text",
+            Value::Bool(_) => "boolean",
+            Value::Blob(_) => "blob",
+            Value::Decimal(_) => "decimal",
+        }
+        .to_string(),
+    ))
+}
+
+thread_local! {
+    static RANDOM_STATE: Cell<u64> = Cell::new(random_seed());
+}
+
+fn random_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+    // xorshift never leaves the all-zero state, so make sure not to start there.
+    nanos | 1
+}
+
+/// A pseudo-random 64-bit integer (xorshift64*); not suitable for cryptography.
+fn random(_args: &[Value]) -> Result<Value> {
+    let next = RANDOM_STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    });
+    Ok(Value::Integer(next as i64))
+}
+
+/// `printf(format, ...)`: C-style formatting of the remaining arguments. Supports the
+/// `-`, `+`, space and `0` flags, the `,` flag that groups the digits of `d`, `i` and `u`
+/// in thousands, a width and a precision, and the conversions `d`, `i`,
+/// `u`, `f`, `e`, `E`, `g`, `G`, `x`, `X`, `o`, `c`, `s`, `q` and `%`. Missing arguments
+/// count as NULL, which formats as 0 or the empty string.
+fn printf(args: &[Value]) -> Result<Value> {
+    if args[0].is_null() {
+        return Ok(Value::Null);
+    }
+    let format = text(&args[0]);
+    let mut args = args[1..].iter();
+    let mut out = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+
+        let mut spec = Spec::default();
+        while let Some(&flag) = chars.peek() {
+            match flag {
+                '-' => spec.left = true,
+                '+' => spec.plus = true,
+                ' ' => spec.space = true,
+                '0' => spec.zero = true,
+                ',' => spec.comma = true,
+                _ => break,
+            }
+            chars.next();
+        }
+        spec.width = read_number(&mut chars).unwrap_or(0);
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            spec.precision = Some(read_number(&mut chars).unwrap_or(0));
+        }
+
+        let conversion = match chars.next() {
+            Some(conversion) => conversion,
+            None => break,
+        };
+        if conversion == '%' {
+            out.push('%');
+            continue;
+        }
+        let arg = args.next().unwrap_or(&Value::Null);
+        let body = match conversion {
+            'd' | 'i' | 'u' => {
+                let number = integer_arg(arg).to_string();
+                spec.signed(match spec.comma {
+                    true => group_thousands(&number),
+                    false => number,
+                })
+            }
+            'x' => format!("{:x}", integer_arg(arg)),
+            'X' => format!("{:X}", integer_arg(arg)),
+            'o' => format!("{:o}", integer_arg(arg)),
+            'f' | 'e' | 'E' | 'g' | 'G' => {
+                let x = arg.as_f64().unwrap_or(0.0);
+                spec.signed(format_real(x, conversion, spec.precision.unwrap_or(6)))
+            }
+            'c' => text(arg).chars().take(1).collect(),
+            's' | 'q' => {
+                let s = if arg.is_null() {
+                    String::new()
+                } else {
+                    text(arg)
+                };
+                let s = match spec.precision {
+                    Some(precision) => s.chars().take(precision).collect(),
+                    None => s,
+                };
+                if conversion == 'q' {
+                    s.replace('\'', "''")
+                } else {
+                    s
+                }
+            }
+            _ => {
+                return Err(RUSQLError::General(format!(
+                    "Unsupported printf conversion: %{}",
+                    conversion
+                )))
+            }
+        };
+        out.push_str(&spec.pad(body, conversion));
+    }
+    Ok(Value::Text(out))
+}
+
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    zero: bool,
+    comma: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    /// Adds the sign flags to a formatted number.
+    fn signed(&self, number: String) -> String {
+        if number.starts_with('-') {
+            number
+        } else if self.plus {
+            format!("+{}", number)
+        } else if self.space {
+            format!(" {}", number)
+        } else {
+            number
+        }
+    }
+
+    /// Pads `body` to the width: on the right with `-`, with zeros after the sign for
+    /// numbers with `0`, with spaces on the left otherwise.
+    fn pad(&self, body: String, conversion: char) -> String {
+        let len = body.chars().count();
+        if len >= self.width {
+            return body;
+        }
+        let fill = self.width - len;
+        if self.left {
+            format!("{}{}", body, " ".repeat(fill))
+        } else if self.zero && !matches!(conversion, 's' | 'q' | 'c') {
+            let sign_len = usize::from(body.starts_with(['-', '+', ' ']));
+            format!(
+                "{}{}{}",
+                &body[..sign_len],
+                "0".repeat(fill),
+                &body[sign_len..]
+            )
+        } else {
+            format!("{}{}", " ".repeat(fill), body)
+        }
+    }
+}
+
+/// Inserts a comma between every three digits of an integer, counting from the right.
+fn group_thousands(number: &str) -> String {
+    let (sign, digits) = number.split_at(usize::from(number.starts_with('-')));
+    let mut grouped = String::from(sign);
+    for (idx, digit) in digits.chars().enumerate() {
+        if idx > 0 && (digits.len() - idx) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+fn read_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<usize> {
+    let mut number = None;
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        number = Some(number.unwrap_or(0) * 10 + digit as usize);
+        chars.next();
+    }
+    number
+}
+
+/// Formats a real for `%f`, `%e` and `%g` the way C does.
+fn format_real(x: f64, conversion: char, precision: usize) -> String {
+    match conversion {
+        'f' => format!("{:.*}", precision, x),
+        'e' | 'E' => {
+            let s = exponent_form(x, precision);
+            if conversion == 'E' {
+                s.to_uppercase()
+            } else {
+                s
+            }
+        }
+        _ => {
+            // %g: the shorter of %e and %f for `precision` significant digits, without
+            // trailing zeros.
+            let precision = precision.max(1);
+            let exponent = if x == 0.0 {
+                0
+            } else {
+                x.abs().log10().floor() as i32
+            };
+            let s = if exponent < -4 || exponent >= precision as i32 {
+                let s = exponent_form(x, precision - 1);
+                let (mantissa, exponent) = s.split_once('e').unwrap();
+                format!("{}e{}", strip_zeros(mantissa), exponent)
+            } else {
+                let decimals = (precision as i32 - 1 - exponent).max(0) as usize;
+                strip_zeros(&format!("{:.*}", decimals, x)).to_string()
+            };
+            if conversion == 'G' {
+                s.to_uppercase()
+            } else {
+                s
+            }
+        }
+    }
+}
+
+/// `d.ddde+XX`: Rust writes the exponent without a sign or padding.
+fn exponent_form(x: f64, precision: usize) -> String {
+    let s = format!("{:.*e}", precision, x);
+    let (mantissa, exponent) = s.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    format!(
+        "{}e{}{:02}",
+        mantissa,
+        if exponent < 0 { '-' } else { '+' },
+        exponent.abs()
+    )
+}
+
+fn strip_zeros(number: &str) -> &str {
+    if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        number
+    }
+}
@@ -0,0 +1,461 @@
+//! Joins between the tables of a FROM clause.
+//!
+//! Tables are joined left to right: the rows joined so far form the left input and each
+//! JOIN (or comma) adds one table on the right. Equalities between a column of the new
+//! table and an expression over the left row are join keys, and they pick the algorithm:
+//!
+//! - an index nested-loop join when a key column has an index, looking up the matching
+//!   rowids for every left row;
+//! - a hash join on the keys otherwise, building a hash table over the new table once;
+//! - a nested-loop join comparing every pair of rows when there are no keys.
+//!
+//! A table-valued function such as json_each can take columns of the tables before it
+//! as arguments, so it is called again for every left row and joined with that row only.
+//!
+//! Whichever is used, the output comes in the same order: left rows in order, each
+//! followed by its matches in rowid order. A LEFT JOIN pads a left row without matches
+//! with NULLs.
+
+use crate::error::{RUSQLError, Result};
+use crate::sql::db::table::Index;
+use crate::sql::db::value::{parse_number, real_to_integer, Value};
+use crate::sql::executor::expr::{evaluate, is_true, walk_expr, ColumnBinding, Env, Scope};
+use crate::sql::executor::result_set::ResultSet;
+use crate::sql::executor::select::{resolve_relation, Relation, TableFunction};
+use sqlparser::ast::{
+    BinaryOperator, Expr, Ident, JoinConstraint, JoinOperator, TableFactor, TableWithJoins,
+};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Joins every table of `from` and returns the bindings and rows of the result.
+pub fn join_tables(
+    from: &[TableWithJoins],
+    env: Env,
+) -> Result<(Vec<ColumnBinding>, Vec<Vec<Value>>)> {
+    let cross_join = JoinOperator::CrossJoin;
+    let mut steps: Vec<(&TableFactor, &JoinOperator)> = vec![];
+    for (idx, table_with_joins) in from.iter().enumerate() {
+        // A comma between FROM items is a cross join.
+        if idx > 0 {
+            steps.push((&table_with_joins.relation, &cross_join));
+        }
+        steps.extend(
+            table_with_joins
+                .joins
+                .iter()
+                .map(|join| (&join.relation, &join.join_operator)),
+        );
+    }
+
+    let (relation, mut bindings) = resolve_relation(&from[0].relation, env)?;
+    let mut rows = relation_rows(relation)?;
+
+    for (relation, operator) in steps {
+        // A table-valued function may read the rows joined so far, as in
+        // `FROM t, json_each(t.doc)`, so it runs again for each of them below.
+        let function = TableFunction::new(relation)?;
+        let (right, mut right_bindings) = match &function {
+            Some(function) => (
+                Relation::Derived(ResultSet::default()),
+                function.bindings()?,
+            ),
+            None => resolve_relation(relation, env)?,
+        };
+        let (constraint, outer) = match operator {
+            JoinOperator::Inner(constraint) => (Some(constraint), false),
+            JoinOperator::LeftOuter(constraint) => (Some(constraint), true),
+            JoinOperator::CrossJoin => (None, false),
+            _ => {
+                return Err(RUSQLError::NotImplemented(
+                    "Only INNER, LEFT and CROSS joins are supported.".to_string(),
+                ))
+            }
+        };
+
+        let condition = match constraint {
+            Some(JoinConstraint::On(expr)) => {
+                JoinCondition::from_on(expr, &bindings, &right_bindings)
+            }
+            Some(JoinConstraint::Using(columns)) => {
+                let names: Vec<String> = columns.iter().map(|ident| ident.value.clone()).collect();
+                JoinCondition::from_using(&names, &bindings, &mut right_bindings)?
+            }
+            Some(JoinConstraint::Natural) => {
+                let names = natural_columns(&bindings, &right_bindings);
+                JoinCondition::from_using(&names, &bindings, &mut right_bindings)?
+            }
+            Some(JoinConstraint::None) | None => JoinCondition::default(),
+        };
+
+        let left_width = bindings.len();
+        let mut joined_bindings = bindings;
+        joined_bindings.extend(right_bindings);
+        let join = Join {
+            right: &right,
+            bindings: &joined_bindings,
+            condition: &condition,
+            outer,
+            env,
+        };
+        rows = match function {
+            None => join.execute(rows)?,
+            Some(function) => {
+                let mut output = vec![];
+                for left in rows {
+                    let scope = Scope::new(&joined_bindings[..left_width], &left).with_env(env);
+                    let right = Relation::Derived(function.execute(&scope)?);
+                    output.extend(
+                        Join {
+                            right: &right,
+                            ..join
+                        }
+                        .execute(vec![left])?,
+                    );
+                }
+                output
+            }
+        };
+        bindings = joined_bindings;
+    }
+    Ok((bindings, rows))
+}
+
+/// How the left input expression of a join key is evaluated.
+enum LeftKey<'a> {
+    Column(usize),
+    Expr(&'a Expr),
+}
+
+/// The condition of one join, split into equality keys and everything else.
+#[derive(Default)]
+struct JoinCondition<'a> {
+    /// `left = right` pairs: an expression over the left row and a right table column.
+    keys: Vec<(LeftKey<'a>, usize)>,
+    /// Remaining conditions, evaluated against the joined row.
+    filters: Vec<&'a Expr>,
+}
+
+impl<'a> JoinCondition<'a> {
+    fn from_on(
+        expr: &'a Expr,
+        left: &[ColumnBinding],
+        right: &[ColumnBinding],
+    ) -> JoinCondition<'a> {
+        let mut condition = JoinCondition::default();
+        let mut conjuncts = vec![expr];
+        while let Some(expr) = conjuncts.pop() {
+            match expr {
+                Expr::Nested(inner) => conjuncts.push(inner),
+                Expr::BinaryOp {
+                    left: a,
+                    op: BinaryOperator::And,
+                    right: b,
+                } => {
+                    conjuncts.push(b);
+                    conjuncts.push(a);
+                }
+                Expr::BinaryOp {
+                    left: a,
+                    op: BinaryOperator::Eq,
+                    right: b,
+                } => match join_key(a, b, left, right).or_else(|| join_key(b, a, left, right)) {
+                    Some(key) => condition.keys.push(key),
+                    None => condition.filters.push(expr),
+                },
+                _ => condition.filters.push(expr),
+            }
+        }
+        condition
+    }
+
+    /// Equalities for `USING (names)`. The right table's copies of the columns are only
+    /// reachable through their qualified names afterwards, so `*` and unqualified names
+    /// see each column once.
+    fn from_using(
+        names: &[String],
+        left: &[ColumnBinding],
+        right: &mut [ColumnBinding],
+    ) -> Result<JoinCondition<'a>> {
+        let mut condition = JoinCondition::default();
+        for name in names {
+            let left_idx = Scope::new(left, &[]).resolve(None, name);
+            let right_idx = right
+                .iter()
+                .position(|binding| binding.name.eq_ignore_ascii_case(name));
+            match (left_idx, right_idx) {
+                (Ok(left_idx), Some(right_idx)) => {
+                    right[right_idx].hidden = true;
+                    condition.keys.push((LeftKey::Column(left_idx), right_idx));
+                }
+                _ => {
+                    return Err(RUSQLError::General(format!(
+                        "cannot join using column {} - column not present in both tables",
+                        name
+                    )))
+                }
+            }
+        }
+        Ok(condition)
+    }
+}
+
+/// `left = right` as a join key, when `right` is a column of the right table and `left`
+/// only refers to columns of the left input.
+fn join_key<'a>(
+    left: &'a Expr,
+    right: &'a Expr,
+    left_bindings: &[ColumnBinding],
+    right_bindings: &[ColumnBinding],
+) -> Option<(LeftKey<'a>, usize)> {
+    let right_idx = column_reference(right, right_bindings)?;
+    if column_reference(right, left_bindings).is_some() {
+        // The name is ambiguous; leave it to evaluation to report.
+        return None;
+    }
+
+    let mut left_only = true;
+    walk_expr(left, &mut |expr| {
+        if matches!(expr, Expr::Identifier(_) | Expr::CompoundIdentifier(_)) {
+            left_only &= column_reference(expr, left_bindings).is_some()
+                && column_reference(expr, right_bindings).is_none();
+        }
+        // Subqueries and the like might see the right table.
+        left_only &= !matches!(
+            expr,
+            Expr::Subquery(_) | Expr::Exists { .. } | Expr::InSubquery { .. }
+        );
+        left_only
+    });
+    left_only.then_some((LeftKey::Expr(left), right_idx))
+}
+
+fn column_reference(expr: &Expr, bindings: &[ColumnBinding]) -> Option<usize> {
+    let scope = Scope::new(bindings, &[]);
+    match expr {
+        Expr::Identifier(ident) => scope.resolve(None, &ident.value).ok(),
+        Expr::CompoundIdentifier(idents) => {
+            let (column, qualifier) = idents.split_last().unwrap();
+            let qualifier = qualifier.last().map(|ident: &Ident| ident.value.as_str());
+            scope.resolve(qualifier, &column.value).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Column names shared by the left input and the right table, for NATURAL JOIN.
+fn natural_columns(left: &[ColumnBinding], right: &[ColumnBinding]) -> Vec<String> {
+    right
+        .iter()
+        .filter(|binding| Scope::new(left, &[]).resolve(None, &binding.name).is_ok())
+        .map(|binding| binding.name.clone())
+        .collect()
+}
+
+/// All rows of a table or subquery, in rowid order for a table.
+fn relation_rows(relation: Relation) -> Result<Vec<Vec<Value>>> {
+    match relation {
+        Relation::Table(table) => table
+            .rowids()?
+            .into_iter()
+            .map(|rowid| table.get_row_values(rowid))
+            .collect(),
+        Relation::Derived(result_set) => Ok(result_set.rows),
+    }
+}
+
+/// One join step: the rows joined so far on the left, `right` on the right.
+struct Join<'a> {
+    right: &'a Relation<'a>,
+    /// Bindings of the joined row: the left input's, then the right relation's.
+    bindings: &'a [ColumnBinding],
+    condition: &'a JoinCondition<'a>,
+    outer: bool,
+    env: Env<'a>,
+}
+
+impl Join<'_> {
+    fn execute(&self, left_rows: Vec<Vec<Value>>) -> Result<Vec<Vec<Value>>> {
+        // Only a table has indexes; a subquery's rows are already in memory.
+        let (table, right_width) = match self.right {
+            Relation::Table(table) => (Some(*table), table.columns.len()),
+            Relation::Derived(result_set) => (None, result_set.columns.len()),
+        };
+        let indexed_key = table.and_then(|table| {
+            self.condition
+                .keys
+                .iter()
+                .position(|(_, column)| !matches!(table.columns[*column].index, Index::None))
+        });
+
+        // Rows of the table, read once unless every lookup can go through an index.
+        let mut right_rows: Option<Vec<Vec<Value>>> = None;
+        let all_rows = || -> Result<Vec<Vec<Value>>> {
+            match self.right {
+                Relation::Table(table) => table
+                    .rowids()?
+                    .into_iter()
+                    .map(|rowid| table.get_row_values(rowid))
+                    .collect(),
+                Relation::Derived(result_set) => Ok(result_set.rows.clone()),
+            }
+        };
+        let hash_table = match indexed_key {
+            None if !self.condition.keys.is_empty() => {
+                let rows = all_rows()?;
+                let mut buckets: HashMap<Vec<HashKey>, Vec<usize>> = HashMap::new();
+                for (idx, row) in rows.iter().enumerate() {
+                    let key = self
+                        .condition
+                        .keys
+                        .iter()
+                        .map(|(_, column)| hash_key(&row[*column]))
+                        .collect::<Option<Vec<_>>>();
+                    if let Some(key) = key {
+                        buckets.entry(key).or_default().push(idx);
+                    }
+                }
+                right_rows = Some(rows);
+                Some(buckets)
+            }
+            _ => None,
+        };
+
+        let left_width = self.bindings.len() - right_width;
+        let mut output = vec![];
+        for left in left_rows {
+            let keys = self.left_keys(&left, left_width)?;
+            let mut matched = false;
+            let mut emit = |right: &[Value], output: &mut Vec<Vec<Value>>| -> Result<()> {
+                let mut row = left.clone();
+                row.extend_from_slice(right);
+                if self.matches(&keys, &row, left_width)? {
+                    matched = true;
+                    output.push(row);
+                }
+                Ok(())
+            };
+
+            if let Some(buckets) = &hash_table {
+                let rows = right_rows.as_ref().unwrap();
+                let key = keys.iter().map(hash_key).collect::<Option<Vec<_>>>();
+                if let Some(bucket) = key.and_then(|key| buckets.get(&key)) {
+                    for idx in bucket {
+                        emit(&rows[*idx], &mut output)?;
+                    }
+                }
+            } else {
+                let lookup = table.zip(indexed_key).and_then(|(table, key)| {
+                    let column = &table.columns[self.condition.keys[key].1];
+                    index_lookup(&column.index, &keys[key]).map(|rowids| (table, rowids))
+                });
+                match lookup {
+                    Some((table, rowids)) => {
+                        for rowid in rowids {
+                            emit(&table.get_row_values(rowid)?, &mut output)?;
+                        }
+                    }
+                    // Without keys, or a key the index cannot look up, compare every row.
+                    None => {
+                        if right_rows.is_none() {
+                            right_rows = Some(all_rows()?);
+                        }
+                        for right in right_rows.as_ref().unwrap() {
+                            emit(right, &mut output)?;
+                        }
+                    }
+                }
+            }
+
+            if !matched && self.outer {
+                let mut row = left;
+                row.resize(self.bindings.len(), Value::Null);
+                output.push(row);
+            }
+        }
+        Ok(output)
+    }
+
+    fn left_keys(&self, left: &[Value], left_width: usize) -> Result<Vec<Value>> {
+        let scope = Scope::new(&self.bindings[..left_width], left).with_env(self.env);
+        self.condition
+            .keys
+            .iter()
+            .map(|(key, _)| match key {
+                LeftKey::Column(idx) => Ok(left[*idx].clone()),
+                LeftKey::Expr(expr) => evaluate(expr, &scope),
+            })
+            .collect()
+    }
+
+    /// Whether the joined `row` satisfies the join condition; `keys` holds the values of
+    /// the key expressions over its left part.
+    fn matches(&self, keys: &[Value], row: &[Value], left_width: usize) -> Result<bool> {
+        for (value, (_, column)) in keys.iter().zip(&self.condition.keys) {
+            if value.compare(&row[left_width + column]) != Some(Ordering::Equal) {
+                return Ok(false);
+            }
+        }
+        let scope = Scope::new(self.bindings, row).with_env(self.env);
+        for filter in &self.condition.filters {
+            if !is_true(filter, &scope)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Rowids whose indexed value equals `key`, or `None` when the index cannot tell, as
+/// for a number looked up in a TEXT column where `'5.0'` would equal `5`.
+fn index_lookup(index: &Index, key: &Value) -> Option<Vec<i64>> {
+    let rowids = |value: &Value| {
+        index
+            .rowids_for(value)
+            .map_or(vec![], |rowids| rowids.iter().copied().collect())
+    };
+    match (index, key) {
+        (_, Value::Null) => Some(vec![]),
+        (Index::Integer(_), Value::Text(s)) => match parse_number(s) {
+            Some(number) => index_lookup(index, &number),
+            None => Some(vec![]),
+        },
+        (Index::Integer(_), Value::Bool(_)) => index_lookup(index, &key.to_numeric()),
+        (Index::Integer(_), Value::Real(r)) => match real_to_integer(*r) {
+            Some(i) => index_lookup(index, &Value::Integer(i)),
+            None => Some(vec![]),
+        },
+        (Index::Integer(_), Value::Integer(_)) => Some(rowids(key)),
+        (Index::Integer(_), Value::Blob(_)) => Some(vec![]),
+        (Index::Text(_), Value::Text(s)) if parse_number(s).is_none() => Some(rowids(key)),
+        // A blob only equals a blob.
+        (Index::Blob(_), Value::Blob(_)) => Some(rowids(key)),
+        (Index::Blob(_), _) => Some(vec![]),
+        (Index::Decimal(_), Value::Decimal(_)) => Some(rowids(key)),
+        _ => None,
+    }
+}
+
+/// Hashable stand-in for a value: values that compare equal share a key. Numbers, and
+/// text that reads as one, hash by numeric value. NULL equals nothing and has no key.
+#[derive(PartialEq, Eq, Hash)]
+enum HashKey {
+    Number(u64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+fn hash_key(value: &Value) -> Option<HashKey> {
+    let number = |value: f64| Some(HashKey::Number((value + 0.0).to_bits()));
+    match value {
+        Value::Null => None,
+        Value::Integer(_) | Value::Real(_) | Value::Bool(_) | Value::Decimal(_) => {
+            number(value.as_f64()?)
+        }
+        Value::Text(s) => match parse_number(s) {
+            Some(parsed) => number(parsed.as_f64()?),
+            None => Some(HashKey::Text(s.clone())),
+        },
+        Value::Blob(b) => Some(HashKey::Blob(b.clone())),
+    }
+}
@@ -0,0 +1,619 @@
+//! JSON functions, the `->` and `->>` operators and the json_each and json_tree
+//! table-valued functions.
+//!
+//! JSON is text, as in SQLite. Functions reading JSON parse their argument and report
+//! malformed JSON as an error. Functions building JSON turn SQL values into JSON values,
+//! and text becomes a JSON string, unless it was itself produced by a JSON function or
+//! `->`: then it is embedded as JSON, so `json_array(json_array(1))` nests arrays. Values
+//! of JSON columns are embedded the same way. SQLite tracks this with a subtype on the
+//! value; here it is read off the argument expression, see `returns_json`.
+//!
+//! Paths follow SQLite: `$` is the whole document, `.name` or `."name"` an object member
+//! and `[n]` an array element. `[#-n]` counts from the end of the array and `[#]` is just
+//! past it, so that json_set and json_insert can append.
+
+use crate::error::{RUSQLError, Result};
+use crate::sql::db::json::Json;
+use crate::sql::db::value::Value;
+use crate::sql::executor::expr::{ColumnBinding, Scope};
+use crate::sql::executor::result_set::ResultSet;
+use sqlparser::ast::{Expr, JsonOperator};
+
+/// An evaluated argument of a JSON function.
+pub struct Arg {
+    pub value: Value,
+    /// Whether the value is JSON produced by another JSON function.
+    pub is_json: bool,
+}
+
+struct JsonFunction {
+    name: &'static str,
+    min_args: usize,
+    /// `None` for functions taking any number of arguments from `min_args` on.
+    max_args: Option<usize>,
+    call: fn(&[Arg]) -> Result<Value>,
+}
+
+const fn function(
+    name: &'static str,
+    min_args: usize,
+    max_args: Option<usize>,
+    call: fn(&[Arg]) -> Result<Value>,
+) -> JsonFunction {
+    JsonFunction {
+        name,
+        min_args,
+        max_args,
+        call,
+    }
+}
+
+const FUNCTIONS: &[JsonFunction] = &[
+    function("json", 1, Some(1), json),
+    function("json_array", 0, None, json_array),
+    function("json_array_length", 1, Some(2), json_array_length),
+    function("json_extract", 2, None, json_extract),
+    function("json_insert", 1, None, json_insert),
+    function("json_object", 0, None, json_object),
+    function("json_quote", 1, Some(1), json_quote),
+    function("json_remove", 1, None, json_remove),
+    function("json_replace", 1, None, json_replace),
+    function("json_set", 1, None, json_set),
+    function("json_type", 1, Some(2), json_type),
+    function("json_valid", 1, Some(1), json_valid),
+];
+
+/// Whether `name` (in lower case) is a JSON function, called with `call_json_function`.
+pub fn is_json_function(name: &str) -> bool {
+    FUNCTIONS.iter().any(|function| function.name == name)
+}
+
+/// Calls the JSON function `name` (in lower case) with `args`.
+pub fn call_json_function(name: &str, args: &[Arg]) -> Result<Value> {
+    let function = FUNCTIONS
+        .iter()
+        .find(|function| function.name == name)
+        .ok_or_else(|| RUSQLError::NotImplemented(format!("Unknown function: {}()", name)))?;
+    if args.len() < function.min_args || function.max_args.is_some_and(|max| args.len() > max) {
+        return Err(RUSQLError::General(format!(
+            "wrong number of arguments to function {}()",
+            name
+        )));
+    }
+    (function.call)(args)
+}
+
+/// Whether `expr` evaluates to JSON that a function building JSON embeds as is: a call
+/// of a JSON function returning JSON text, the `->` operator, or a column of `bindings`
+/// of type JSON. json_extract() and `->>` return SQL values and are not among them.
+pub fn returns_json(expr: &Expr, bindings: &[ColumnBinding]) -> bool {
+    let is_json_column = |qualifier: Option<&str>, name: &str| {
+        Scope::new(bindings, &[])
+            .resolve(qualifier, name)
+            .is_ok_and(|idx| bindings[idx].json)
+    };
+    match expr {
+        Expr::Nested(inner) => returns_json(inner, bindings),
+        Expr::Identifier(ident) => is_json_column(None, &ident.value),
+        Expr::CompoundIdentifier(idents) => {
+            let (column, qualifier) = idents.split_last().unwrap();
+            is_json_column(
+                qualifier.last().map(|ident| ident.value.as_str()),
+                &column.value,
+            )
+        }
+        Expr::JsonAccess {
+            operator: JsonOperator::Arrow,
+            ..
+        } => true,
+        Expr::Function(function) => matches!(
+            function.name.to_string().to_lowercase().as_str(),
+            "json"
+                | "json_array"
+                | "json_group_array"
+                | "json_group_object"
+                | "json_insert"
+                | "json_object"
+                | "json_remove"
+                | "json_replace"
+                | "json_set"
+        ),
+        _ => false,
+    }
+}
+
+fn malformed() -> RUSQLError {
+    RUSQLError::General("malformed JSON".to_string())
+}
+
+/// Reads the JSON document an argument holds: text is parsed, numbers and booleans are
+/// the JSON values they would print as. `None` for NULL.
+fn parse_arg(value: &Value) -> Result<Option<Json>> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Blob(_) => Err(malformed()),
+        value => Json::parse(&value.to_string())
+            .map(Some)
+            .ok_or_else(malformed),
+    }
+}
+
+/// Converts an SQL value to the JSON value a function building JSON embeds for it.
+pub fn to_json(value: &Value, is_json: bool) -> Result<Json> {
+    Ok(match value {
+        Value::Null => Json::Null,
+        Value::Integer(i) => Json::Integer(*i),
+        Value::Real(r) => Json::Real(*r),
+        Value::Decimal(d) => Json::Real(d.to_f64()),
+        Value::Bool(b) => Json::Bool(*b),
+        Value::Text(s) if is_json => Json::parse(s).ok_or_else(malformed)?,
+        Value::Text(s) => Json::Text(s.clone()),
+        Value::Blob(_) => {
+            return Err(RUSQLError::General(
+                "JSON cannot hold BLOB values".to_string(),
+            ))
+        }
+    })
+}
+
+fn arg_json(arg: &Arg) -> Result<Json> {
+    to_json(&arg.value, arg.is_json)
+}
+
+/// One step of a path.
+#[derive(Clone, Debug, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    /// `[#-n]`, and `[#]` for `n = 0`.
+    FromEnd(usize),
+}
+
+impl Step {
+    /// The position in an array of `len` elements the step refers to; `len` itself is
+    /// just past the end.
+    fn position(&self, len: usize) -> Option<usize> {
+        match self {
+            Step::Key(_) => None,
+            Step::Index(idx) => Some(*idx),
+            Step::FromEnd(back) => len.checked_sub(*back),
+        }
+    }
+}
+
+/// Parses a path argument. `None` for NULL, which makes most functions return NULL.
+fn parse_path(value: &Value) -> Result<Option<Vec<Step>>> {
+    let path = match value {
+        Value::Null => return Ok(None),
+        value => value.to_string(),
+    };
+    let bad_path = || RUSQLError::General(format!("bad JSON path: '{}'", path));
+    let mut rest = path.strip_prefix('$').ok_or_else(bad_path)?;
+    let mut steps = vec![];
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix(".\"") {
+            let end = after.find('"').ok_or_else(bad_path)?;
+            steps.push(Step::Key(after[..end].to_string()));
+            rest = &after[end + 1..];
+        } else if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            if end == 0 {
+                return Err(bad_path());
+            }
+            steps.push(Step::Key(after[..end].to_string()));
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or_else(bad_path)?;
+            let index = &after[..end];
+            steps.push(match index.strip_prefix('#') {
+                Some("") => Step::FromEnd(0),
+                Some(back) => Step::FromEnd(
+                    back.strip_prefix('-')
+                        .and_then(|back| back.parse().ok())
+                        .ok_or_else(bad_path)?,
+                ),
+                None => Step::Index(index.parse().map_err(|_| bad_path())?),
+            });
+            rest = &after[end + 1..];
+        } else {
+            return Err(bad_path());
+        }
+    }
+    Ok(Some(steps))
+}
+
+/// The element of `json` that `steps` lead to.
+fn lookup<'a>(json: &'a Json, steps: &[Step]) -> Option<&'a Json> {
+    steps
+        .iter()
+        .try_fold(json, |json, step| match (json, step) {
+            (Json::Object(_), Step::Key(key)) => json.get(key),
+            (Json::Array(elements), step) => elements.get(step.position(elements.len())?),
+            _ => None,
+        })
+}
+
+/// How json_set, json_insert and json_replace treat existing and missing elements.
+#[derive(Clone, Copy, PartialEq)]
+enum Edit {
+    /// Overwrite existing elements and create missing ones.
+    Set,
+    /// Only create missing elements.
+    Insert,
+    /// Only overwrite existing elements.
+    Replace,
+}
+
+/// Stores `value` at `steps` in `json` as `edit` allows. Missing objects and arrays
+/// along the way are created by Set and Insert, as in `json_set('{}', '$.a.b', 1)`.
+fn edit(json: &mut Json, steps: &[Step], value: Json, edit_mode: Edit) {
+    let Some((step, rest)) = steps.split_first() else {
+        if edit_mode != Edit::Insert {
+            *json = value;
+        }
+        return;
+    };
+    match (json, step) {
+        (Json::Object(members), Step::Key(key)) => {
+            match members.iter_mut().find(|(name, _)| name == key) {
+                Some((_, member)) => edit(member, rest, value, edit_mode),
+                None if edit_mode != Edit::Replace => {
+                    if let Some(member) = build(rest, value) {
+                        members.push((key.clone(), member));
+                    }
+                }
+                None => (),
+            }
+        }
+        (Json::Array(elements), step) => match step.position(elements.len()) {
+            Some(idx) if idx < elements.len() => edit(&mut elements[idx], rest, value, edit_mode),
+            Some(idx) if idx == elements.len() && edit_mode != Edit::Replace => {
+                if let Some(element) = build(rest, value) {
+                    elements.push(element);
+                }
+            }
+            _ => (),
+        },
+        _ => (),
+    }
+}
+
+/// The value that holds `value` at `steps` once created, if the steps can be created:
+/// object members and appended array elements.
+fn build(steps: &[Step], value: Json) -> Option<Json> {
+    let Some((step, rest)) = steps.split_first() else {
+        return Some(value);
+    };
+    match step {
+        Step::Key(key) => Some(Json::Object(vec![(key.clone(), build(rest, value)?)])),
+        Step::FromEnd(0) | Step::Index(0) => Some(Json::Array(vec![build(rest, value)?])),
+        _ => None,
+    }
+}
+
+/// Removes the element at `steps`, which must not be empty.
+fn remove(json: &mut Json, steps: &[Step]) {
+    let (step, rest) = steps.split_first().expect("the root cannot be removed");
+    match (json, step) {
+        (Json::Object(members), Step::Key(key)) => {
+            if let Some(idx) = members.iter().position(|(name, _)| name == key) {
+                if rest.is_empty() {
+                    members.remove(idx);
+                } else {
+                    remove(&mut members[idx].1, rest);
+                }
+            }
+        }
+        (Json::Array(elements), step) => {
+            if let Some(idx) = step.position(elements.len()) {
+                if idx < elements.len() {
+                    if rest.is_empty() {
+                        elements.remove(idx);
+                    } else {
+                        remove(&mut elements[idx], rest);
+                    }
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+fn json(args: &[Arg]) -> Result<Value> {
+    Ok(parse_arg(&args[0].value)?.map_or(Value::Null, |json| Value::Text(json.to_string())))
+}
+
+fn json_valid(args: &[Arg]) -> Result<Value> {
+    Ok(match &args[0].value {
+        Value::Null => Value::Null,
+        Value::Blob(_) => Value::Bool(false),
+        value => Value::Bool(Json::parse(&value.to_string()).is_some()),
+    })
+}
+
+/// The document of the first argument and the element the optional path argument
+/// leads to, for functions inspecting one element.
+fn element<T>(args: &[Arg], inspect: impl FnOnce(&Json) -> T) -> Result<Option<T>> {
+    let Some(json) = parse_arg(&args[0].value)? else {
+        return Ok(None);
+    };
+    let steps = match args.get(1) {
+        Some(path) => match parse_path(&path.value)? {
+            Some(steps) => steps,
+            None => return Ok(None),
+        },
+        None => vec![],
+    };
+    Ok(lookup(&json, &steps).map(inspect))
+}
+
+fn json_type(args: &[Arg]) -> Result<Value> {
+    Ok(element(args, |json| Value::Text(json.type_name().to_string()))?.unwrap_or(Value::Null))
+}
+
+fn json_array_length(args: &[Arg]) -> Result<Value> {
+    Ok(element(args, |json| match json {
+        Json::Array(elements) => Value::Integer(elements.len() as i64),
+        _ => Value::Integer(0),
+    })?
+    .unwrap_or(Value::Null))
+}
+
+/// `json_extract(json, path, ...)`: the SQL value at a single path, or a JSON array of
+/// the values at several paths.
+fn json_extract(args: &[Arg]) -> Result<Value> {
+    let Some(json) = parse_arg(&args[0].value)? else {
+        return Ok(Value::Null);
+    };
+    let mut found = vec![];
+    for path in &args[1..] {
+        let Some(steps) = parse_path(&path.value)? else {
+            return Ok(Value::Null);
+        };
+        found.push(lookup(&json, &steps));
+    }
+    Ok(match found.as_slice() {
+        [element] => element.map_or(Value::Null, Json::to_value),
+        elements => Value::Text(
+            Json::Array(
+                elements
+                    .iter()
+                    .map(|element| element.cloned().unwrap_or(Json::Null))
+                    .collect(),
+            )
+            .to_string(),
+        ),
+    })
+}
+
+fn json_quote(args: &[Arg]) -> Result<Value> {
+    Ok(Value::Text(arg_json(&args[0])?.to_string()))
+}
+
+fn json_array(args: &[Arg]) -> Result<Value> {
+    let elements = args.iter().map(arg_json).collect::<Result<Vec<Json>>>()?;
+    Ok(Value::Text(Json::Array(elements).to_string()))
+}
+
+fn json_object(args: &[Arg]) -> Result<Value> {
+    if !args.len().is_multiple_of(2) {
+        return Err(RUSQLError::General(
+            "json_object() requires an even number of arguments".to_string(),
+        ));
+    }
+    let members = args
+        .chunks(2)
+        .map(|pair| match &pair[0].value {
+            Value::Text(key) => Ok((key.clone(), arg_json(&pair[1])?)),
+            _ => Err(RUSQLError::General(
+                "json_object() labels must be TEXT".to_string(),
+            )),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Value::Text(Json::Object(members).to_string()))
+}
+
+fn json_set(args: &[Arg]) -> Result<Value> {
+    edit_paths("json_set", args, Edit::Set)
+}
+
+fn json_insert(args: &[Arg]) -> Result<Value> {
+    edit_paths("json_insert", args, Edit::Insert)
+}
+
+fn json_replace(args: &[Arg]) -> Result<Value> {
+    edit_paths("json_replace", args, Edit::Replace)
+}
+
+/// `name(json, path, value, ...)`: applies each path and value pair in turn.
+fn edit_paths(name: &str, args: &[Arg], edit_mode: Edit) -> Result<Value> {
+    if args.len().is_multiple_of(2) {
+        return Err(RUSQLError::General(format!(
+            "{}() needs an odd number of arguments",
+            name
+        )));
+    }
+    let Some(mut json) = parse_arg(&args[0].value)? else {
+        return Ok(Value::Null);
+    };
+    for pair in args[1..].chunks(2) {
+        let Some(steps) = parse_path(&pair[0].value)? else {
+            return Ok(Value::Null);
+        };
+        edit(&mut json, &steps, arg_json(&pair[1])?, edit_mode);
+    }
+    Ok(Value::Text(json.to_string()))
+}
+
+fn json_remove(args: &[Arg]) -> Result<Value> {
+    let Some(mut json) = parse_arg(&args[0].value)? else {
+        return Ok(Value::Null);
+    };
+    for path in &args[1..] {
+        match parse_path(&path.value)? {
+            None => return Ok(Value::Null),
+            Some(steps) if steps.is_empty() => return Ok(Value::Null),
+            Some(steps) => remove(&mut json, &steps),
+        }
+    }
+    Ok(Value::Text(json.to_string()))
+}
+
+/// `left -> right` and, when `sql_value` is set, `left ->> right`. The right side is a
+/// path, an object key or an array index, negative indexes counting from the end.
+/// `->` returns the element as JSON and `->>` as an SQL value; both return NULL when
+/// the element does not exist.
+pub fn json_access(left: &Value, right: &Value, sql_value: bool) -> Result<Value> {
+    let Some(json) = parse_arg(left)? else {
+        return Ok(Value::Null);
+    };
+    let steps = match right {
+        Value::Null => return Ok(Value::Null),
+        Value::Text(path) if path.starts_with('$') => parse_path(right)?.unwrap_or_default(),
+        Value::Integer(idx) if *idx < 0 => vec![Step::FromEnd(idx.unsigned_abs() as usize)],
+        Value::Integer(idx) => vec![Step::Index(*idx as usize)],
+        key => vec![Step::Key(key.to_string())],
+    };
+    Ok(match lookup(&json, &steps) {
+        None => Value::Null,
+        Some(element) if sql_value => element.to_value(),
+        Some(element) => Value::Text(element.to_string()),
+    })
+}
+
+/// The columns of json_each and json_tree.
+pub const EACH_COLUMNS: [&str; 8] = [
+    "key", "value", "type", "atom", "id", "parent", "fullkey", "path",
+];
+
+/// `json_each(json [, path])` and, when `recursive` is set, `json_tree(json [, path])`.
+///
+/// json_each has a row for each member or element of the document, or of the element
+/// at `path`; a scalar there is a single row. json_tree also has a row for that element
+/// itself and descends into every nested array and object. Elements are numbered by
+/// `id` in document order, and `parent` is the id of the containing element in
+/// json_tree.
+pub fn each(args: &[Value], recursive: bool) -> Result<ResultSet> {
+    let name = if recursive { "json_tree" } else { "json_each" };
+    if args.is_empty() || args.len() > 2 {
+        return Err(RUSQLError::General(format!(
+            "wrong number of arguments to function {}()",
+            name
+        )));
+    }
+    let mut result_set = ResultSet::new(EACH_COLUMNS.iter().map(|c| c.to_string()).collect());
+    let Some(json) = parse_arg(&args[0])? else {
+        return Ok(result_set);
+    };
+    let (steps, path) = match args.get(1) {
+        Some(path) => match parse_path(path)? {
+            Some(steps) => (steps, path.to_string()),
+            None => return Ok(result_set),
+        },
+        None => (vec![], "$".to_string()),
+    };
+    let Some(root) = lookup(&json, &steps) else {
+        return Ok(result_set);
+    };
+
+    let mut walk = Walk {
+        rows: &mut result_set.rows,
+        next_id: 0,
+        recursive,
+    };
+    let root_key = match steps.last() {
+        Some(Step::Key(key)) => Value::Text(key.clone()),
+        Some(Step::Index(idx)) => Value::Integer(*idx as i64),
+        Some(Step::FromEnd(_)) | None => Value::Null,
+    };
+    let parent_path = match path.rfind(['.', '[']) {
+        Some(end) if !steps.is_empty() => path[..end].to_string(),
+        _ => "$".to_string(),
+    };
+    if recursive || !root.is_container() {
+        walk.visit(root, root_key, None, &path, &parent_path);
+    } else {
+        let id = walk.next_id();
+        walk.children(root, id, &path);
+    }
+    Ok(result_set)
+}
+
+/// The traversal of json_each and json_tree.
+struct Walk<'a> {
+    rows: &'a mut Vec<Vec<Value>>,
+    next_id: i64,
+    recursive: bool,
+}
+
+impl Walk<'_> {
+    fn next_id(&mut self) -> i64 {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    /// Adds the row of `json`, found at `fullkey` inside the element at `path`, and the
+    /// rows of its children when recursive.
+    fn visit(&mut self, json: &Json, key: Value, parent: Option<i64>, fullkey: &str, path: &str) {
+        let id = self.next_id();
+        let atom = match json.is_container() {
+            true => Value::Null,
+            false => json.to_value(),
+        };
+        self.rows.push(vec![
+            key,
+            json.to_value(),
+            Value::Text(json.type_name().to_string()),
+            atom,
+            Value::Integer(id),
+            parent.map_or(Value::Null, Value::Integer),
+            Value::Text(fullkey.to_string()),
+            Value::Text(path.to_string()),
+        ]);
+        if self.recursive {
+            self.children(json, id, fullkey);
+        }
+    }
+
+    /// Adds the rows of the members or elements of `json`, whose id is `id`.
+    fn children(&mut self, json: &Json, id: i64, fullkey: &str) {
+        // json_each leaves `parent` NULL, as SQLite does.
+        let parent = self.recursive.then_some(id);
+        match json {
+            Json::Array(elements) => {
+                for (idx, element) in elements.iter().enumerate() {
+                    let child_key = format!("{}[{}]", fullkey, idx);
+                    self.visit(
+                        element,
+                        Value::Integer(idx as i64),
+                        parent,
+                        &child_key,
+                        fullkey,
+                    );
+                }
+            }
+            Json::Object(members) => {
+                for (key, member) in members {
+                    let child_key = format!("{}.{}", fullkey, quote_key(key));
+                    self.visit(
+                        member,
+                        Value::Text(key.clone()),
+                        parent,
+                        &child_key,
+                        fullkey,
+                    );
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// An object key as written in a path: quoted unless it is a plain name.
+fn quote_key(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        key.to_string()
+    } else {
+        format!("\"{}\"", key)
+    }
+}
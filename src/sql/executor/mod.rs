@@ -0,0 +1,15 @@
+//! Query execution layer.
+//! Executors read the columnar storage of a `Table` and produce a `ResultSet`.
+
+pub mod aggregate;
+pub mod compound;
+pub mod cte;
+pub mod datetime;
+pub mod expr;
+pub mod functions;
+pub mod join;
+pub mod json;
+pub mod order;
+pub mod result_set;
+pub mod select;
+pub mod window;
@@ -0,0 +1,255 @@
+//! ORDER BY, LIMIT and OFFSET.
+//!
+//! Sort keys are evaluated for each row next to the select list. Without a LIMIT every
+//! row is collected and sorted; with one, a bounded heap keeps only the first
+//! `OFFSET + LIMIT` rows in sort order, so memory stays proportional to the rows returned.
+//! Ties keep the order the rows were produced in.
+
+use crate::error::{RUSQLError, Result};
+use crate::sql::db::value::Value;
+use crate::sql::executor::expr::{evaluate, Scope};
+use crate::sql::executor::result_set::ResultSet;
+use sqlparser::ast::Value as SqlValue;
+use sqlparser::ast::{Expr, Offset, OrderByExpr};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::rc::Rc;
+
+/// What an ORDER BY term sorts on.
+pub enum SortTerm<'a> {
+    /// An output column, named by its label or its 1-based position.
+    Output(usize),
+    /// An expression over the input row.
+    Expr(&'a Expr),
+}
+
+pub struct SortKey<'a> {
+    pub term: SortTerm<'a>,
+    pub order: KeyOrder,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct KeyOrder {
+    pub descending: bool,
+    /// Defaults to true for ascending keys, as NULL sorts before every other value.
+    pub nulls_first: bool,
+}
+
+/// Resolves ORDER BY terms against the output columns `labels`. A term that is a
+/// position or matches a label sorts on that output column, any other term is an
+/// expression over the input row.
+pub fn resolve_order_by<'a>(
+    order_by: &'a [OrderByExpr],
+    labels: &[String],
+) -> Result<Vec<SortKey<'a>>> {
+    order_by
+        .iter()
+        .map(|item| {
+            let descending = item.asc == Some(false);
+            let order = KeyOrder {
+                descending,
+                nulls_first: item.nulls_first.unwrap_or(!descending),
+            };
+            let term = match &item.expr {
+                Expr::Value(SqlValue::Number(position, _)) => {
+                    SortTerm::Output(output_position(position, labels.len())?)
+                }
+                Expr::Identifier(ident) => match labels
+                    .iter()
+                    .position(|label| label.eq_ignore_ascii_case(&ident.value))
+                {
+                    Some(idx) => SortTerm::Output(idx),
+                    None => SortTerm::Expr(&item.expr),
+                },
+                expr => SortTerm::Expr(expr),
+            };
+            Ok(SortKey { term, order })
+        })
+        .collect()
+}
+
+fn output_position(position: &str, columns: usize) -> Result<usize> {
+    match position.parse::<usize>() {
+        Ok(position) if (1..=columns).contains(&position) => Ok(position - 1),
+        _ => Err(RUSQLError::General(format!(
+            "ORDER BY term out of range - should be between 1 and {}",
+            columns
+        ))),
+    }
+}
+
+/// Evaluates the sort keys of one row: `scope` is the input row, `row` the output row.
+pub fn sort_values(keys: &[SortKey], scope: &Scope, row: &[Value]) -> Result<Vec<Value>> {
+    keys.iter()
+        .map(|key| match key.term {
+            SortTerm::Output(idx) => Ok(row[idx].clone()),
+            SortTerm::Expr(expr) => evaluate(expr, scope),
+        })
+        .collect()
+}
+
+/// Compares two rows' sort keys.
+pub fn compare_keys(a: &[Value], b: &[Value], orders: &[KeyOrder]) -> Ordering {
+    for ((a, b), order) in a.iter().zip(b).zip(orders) {
+        let ordering = match (a.is_null(), b.is_null()) {
+            (true, true) => Ordering::Equal,
+            (true, false) if order.nulls_first => Ordering::Less,
+            (true, false) => Ordering::Greater,
+            (false, true) if order.nulls_first => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) if order.descending => b.sort_cmp(a),
+            (false, false) => a.sort_cmp(b),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// A row waiting to be sorted. `seq` is its arrival number and breaks ties.
+struct SortRow {
+    keys: Vec<Value>,
+    seq: usize,
+    row: Vec<Value>,
+    orders: Rc<[KeyOrder]>,
+}
+
+impl Ord for SortRow {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_keys(&self.keys, &other.keys, &self.orders).then(self.seq.cmp(&other.seq))
+    }
+}
+
+impl PartialOrd for SortRow {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SortRow {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortRow {}
+
+/// Collects rows with their sort keys and hands them back in order.
+pub struct Sorter {
+    orders: Rc<[KeyOrder]>,
+    /// Keep only the first this many rows; `None` keeps every row.
+    capacity: Option<usize>,
+    /// Max-heap: with a capacity, the top is the row that would be dropped next.
+    rows: BinaryHeap<SortRow>,
+    seq: usize,
+}
+
+impl Sorter {
+    pub fn new(keys: &[SortKey], capacity: Option<usize>) -> Self {
+        Sorter {
+            orders: keys.iter().map(|key| key.order).collect(),
+            capacity,
+            rows: BinaryHeap::new(),
+            seq: 0,
+        }
+    }
+
+    pub fn push(&mut self, keys: Vec<Value>, row: Vec<Value>) {
+        self.seq += 1;
+        let row = SortRow {
+            keys,
+            seq: self.seq,
+            row,
+            orders: Rc::clone(&self.orders),
+        };
+        match self.capacity {
+            Some(0) => {}
+            Some(capacity) if self.rows.len() == capacity => {
+                // Replace the worst kept row if the new one sorts before it.
+                if let Some(mut worst) = self.rows.peek_mut() {
+                    if row < *worst {
+                        *worst = row;
+                    }
+                }
+            }
+            _ => self.rows.push(row),
+        }
+    }
+
+    pub fn finish(self) -> Vec<Vec<Value>> {
+        self.rows
+            .into_sorted_vec()
+            .into_iter()
+            .map(|row| row.row)
+            .collect()
+    }
+}
+
+/// The LIMIT and OFFSET of a query.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limit {
+    /// `None` returns every row; a negative LIMIT means the same.
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+impl Limit {
+    pub fn new(limit: Option<&Expr>, offset: Option<&Offset>) -> Result<Self> {
+        let limit = match limit {
+            Some(expr) => usize::try_from(evaluate_count(expr, "LIMIT")?).ok(),
+            None => None,
+        };
+        let offset = match offset {
+            Some(offset) => usize::try_from(evaluate_count(&offset.value, "OFFSET")?).unwrap_or(0),
+            None => 0,
+        };
+        Ok(Limit { limit, offset })
+    }
+
+    /// Number of rows needed before LIMIT starts dropping them.
+    pub fn end(&self) -> Option<usize> {
+        self.limit.map(|limit| limit.saturating_add(self.offset))
+    }
+
+    pub fn apply(&self, rows: Vec<Vec<Value>>) -> Vec<Vec<Value>> {
+        rows.into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+fn evaluate_count(expr: &Expr, clause: &str) -> Result<i64> {
+    match evaluate(expr, &Scope::new(&[], &[]))? {
+        Value::Integer(count) => Ok(count),
+        value => Err(RUSQLError::General(format!(
+            "{} must be an integer, found {}.",
+            clause, value
+        ))),
+    }
+}
+
+/// Sorts a finished result set on its output columns and applies LIMIT and OFFSET.
+pub fn sort_result_set(
+    mut result_set: ResultSet,
+    order_by: &[OrderByExpr],
+    limit: &Limit,
+) -> Result<ResultSet> {
+    let keys = resolve_order_by(order_by, &result_set.columns)?;
+    if keys.iter().any(|key| matches!(key.term, SortTerm::Expr(_))) {
+        return Err(RUSQLError::General(
+            "ORDER BY term does not match any column in the result set.".to_string(),
+        ));
+    }
+
+    if !keys.is_empty() {
+        let mut sorter = Sorter::new(&keys, limit.end());
+        for row in std::mem::take(&mut result_set.rows) {
+            sorter.push(sort_values(&keys, &Scope::new(&[], &[]), &row)?, row);
+        }
+        result_set.rows = sorter.finish();
+    }
+    result_set.rows = limit.apply(result_set.rows);
+    Ok(result_set)
+}
@@ -0,0 +1,48 @@
+//! Materialised output of a query: column labels plus rows of typed values.
+
+use crate::sql::db::value::Value;
+use colored::*;
+use prettytable::{Cell as PrintCell, Row as PrintRow, Table as PrintTable};
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl ResultSet {
+    pub fn new(columns: Vec<String>) -> Self {
+        ResultSet {
+            columns,
+            rows: vec![],
+        }
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Print the result set to standard output in the same layout as `Table::print_table_data`.
+    pub fn print_result_set(&self) {
+        let mut print_table = PrintTable::new();
+
+        print_table.add_row(PrintRow::new(
+            self.columns
+                .iter()
+                .map(|col| PrintCell::new(col))
+                .collect::<Vec<PrintCell>>(),
+        ));
+
+        for row in &self.rows {
+            print_table.add_row(PrintRow::new(
+                row.iter()
+                    .map(|value| PrintCell::new(&value.to_string()))
+                    .collect::<Vec<PrintCell>>(),
+            ));
+        }
+
+        let table_string = format!("{}", print_table).blue();
+
+        println!("{}", table_string);
+    }
+}
@@ -0,0 +1,603 @@
+//! SELECT execution.
+//! Resolves the FROM table, scans its rowids, filters rows with the WHERE clause and
+//! evaluates the select list against each remaining row, then applies ORDER BY, LIMIT
+//! and OFFSET. When the only sort key is an indexed column, the scan walks the column
+//! index in order instead, so no sort is needed and a LIMIT stops the scan early.
+//! Queries with GROUP BY, HAVING or aggregate functions are handed to the aggregate
+//! executor, and joins and subqueries in FROM are evaluated into rows first.
+
+use crate::error::{RUSQLError, Result};
+use crate::sql::db::database::Database;
+use crate::sql::db::table::{Index, Table};
+use crate::sql::db::value::Value;
+use crate::sql::executor::aggregate::{
+    contains_aggregate, execute_aggregate, is_aggregate_query, projection_exprs,
+};
+use crate::sql::executor::compound::execute_compound;
+use crate::sql::executor::cte::{plain_scan, CommonTables};
+use crate::sql::executor::expr::{evaluate, is_true, ColumnBinding, Env, Scope, SubqueryCache};
+use crate::sql::executor::join::join_tables;
+use crate::sql::executor::json::{self, EACH_COLUMNS};
+use crate::sql::executor::order::{
+    resolve_order_by, sort_result_set, sort_values, Limit, SortKey, SortTerm, Sorter,
+};
+use crate::sql::executor::result_set::ResultSet;
+use crate::sql::executor::window::{compute_windows, window_calls, window_operands};
+use sqlparser::ast::{
+    Expr, FunctionArg, FunctionArgExpr, ObjectName, OrderByExpr, Query, Select, SelectItem,
+    SetExpr, TableAlias, TableFactor, TableWithJoins, Values,
+};
+use std::collections::BTreeSet;
+
+/// Where the rows of a SELECT come from.
+pub enum RowSource<'a> {
+    /// No FROM clause: a single empty row.
+    Empty,
+    Table(&'a Table),
+    /// Rows produced by joining the FROM tables.
+    Rows(Vec<Vec<Value>>),
+}
+
+/// One output column of the select list.
+pub enum Projection<'a> {
+    /// A column copied straight from the scanned row, as produced by `*` and `t.*`.
+    Column(usize),
+    Expr(&'a Expr),
+}
+
+pub fn execute_query(query: &Query, db: &Database) -> Result<ResultSet> {
+    let cache = SubqueryCache::default();
+    execute_query_in(query, Env::new(db, &cache))
+}
+
+/// Executes a query within `env`, which for a subquery carries the enclosing row.
+pub fn execute_query_in(query: &Query, env: Env) -> Result<ResultSet> {
+    let limit = Limit::new(query.limit.as_ref(), query.offset.as_ref())?;
+    let (tables, cache);
+    let env = match &query.with {
+        Some(with) => {
+            let scan = plain_scan(query).zip(limit.end());
+            tables = CommonTables::define(with, scan, env)?;
+            cache = SubqueryCache::default();
+            Env {
+                cache: &cache,
+                tables: Some(&tables),
+                ..env
+            }
+        }
+        None => env,
+    };
+    execute_set_expr(&query.body, env, &query.order_by, &limit)
+}
+
+/// Executes the body of a query, followed by its ORDER BY and LIMIT.
+pub fn execute_set_expr(
+    body: &SetExpr,
+    env: Env,
+    order_by: &[OrderByExpr],
+    limit: &Limit,
+) -> Result<ResultSet> {
+    match body {
+        SetExpr::Select(select) => execute_select(select, env, order_by, limit),
+        SetExpr::Query(inner) => sort_result_set(execute_query_in(inner, env)?, order_by, limit),
+        SetExpr::SetOperation {
+            op,
+            set_quantifier,
+            left,
+            right,
+        } => sort_result_set(
+            execute_compound(op, set_quantifier, left, right, env)?,
+            order_by,
+            limit,
+        ),
+        SetExpr::Values(values) => sort_result_set(execute_values(values, env)?, order_by, limit),
+        _ => Err(RUSQLError::NotImplemented(
+            "Only SELECT, VALUES and compound queries are supported.".to_string(),
+        )),
+    }
+}
+
+/// `VALUES (...), (...)` as a query, with columns named `column1`, `column2`, ...
+fn execute_values(values: &Values, env: Env) -> Result<ResultSet> {
+    let width = values.rows.first().map_or(0, Vec::len);
+    let mut result_set = ResultSet::new((1..=width).map(|n| format!("column{}", n)).collect());
+    let scope = Scope::new(&[], &[]).with_env(env);
+    for row in &values.rows {
+        if row.len() != width {
+            return Err(RUSQLError::General(
+                "all VALUES must have the same number of terms".to_string(),
+            ));
+        }
+        result_set.rows.push(
+            row.iter()
+                .map(|expr| evaluate(expr, &scope))
+                .collect::<Result<_>>()?,
+        );
+    }
+    Ok(result_set)
+}
+
+fn execute_select(
+    select: &Select,
+    env: Env,
+    order_by: &[OrderByExpr],
+    limit: &Limit,
+) -> Result<ResultSet> {
+    if select.distinct.is_some() {
+        return Err(RUSQLError::NotImplemented(
+            "SELECT DISTINCT is not supported yet.".to_string(),
+        ));
+    }
+    let (source, bindings) = resolve_from(&select.from, env)?;
+
+    let (labels, projections) = resolve_projection(&select.projection, &bindings)?;
+    let keys = resolve_order_by(order_by, &labels)?;
+    let sort_exprs = keys.iter().filter_map(|key| match key.term {
+        SortTerm::Expr(expr) => Some(expr),
+        SortTerm::Output(_) => None,
+    });
+    let windows = window_calls(projection_exprs(&projections).chain(sort_exprs));
+    let window_operands = window_operands(&windows, select)?;
+    if is_aggregate_query(select, &projections, &keys)
+        || window_operands.iter().any(|expr| contains_aggregate(expr))
+    {
+        return execute_aggregate(
+            select,
+            source,
+            &bindings,
+            labels,
+            &projections,
+            &keys,
+            &windows,
+            limit,
+            env,
+        );
+    }
+    let mut result_set = ResultSet::new(labels);
+
+    let mut presorted = false;
+    let rows: Box<dyn Iterator<Item = Result<Vec<Value>>>> = match source {
+        RowSource::Table(table) => {
+            let ordered = match windows.is_empty() {
+                true => index_order(table, &bindings, &keys, &projections),
+                false => None,
+            };
+            let rowids: Box<dyn Iterator<Item = i64>> = match ordered {
+                Some(rowids) => {
+                    presorted = true;
+                    rowids
+                }
+                None => Box::new(table.rowids()?.into_iter()),
+            };
+            Box::new(rowids.map(|rowid| table.get_row_values(rowid)))
+        }
+        RowSource::Rows(rows) => Box::new(rows.into_iter().map(Ok)),
+        RowSource::Empty => Box::new(std::iter::once(Ok(vec![]))),
+    };
+    let mut sorter = (!keys.is_empty() && !presorted).then(|| Sorter::new(&keys, limit.end()));
+
+    // Adds the output row for `scope`; true once the LIMIT is reached.
+    let mut emit = |scope: &Scope| -> Result<bool> {
+        let row = project(&projections, scope)?;
+        match &mut sorter {
+            Some(sorter) => {
+                sorter.push(sort_values(&keys, scope, &row)?, row);
+                Ok(false)
+            }
+            None => {
+                result_set.rows.push(row);
+                // Rows already come out in order, so the scan can stop at the LIMIT.
+                Ok(limit.end().is_some_and(|end| result_set.rows.len() >= end))
+            }
+        }
+    };
+
+    // Window functions need every row before any can be output.
+    let mut window_rows = vec![];
+    for values in rows {
+        let values = values?;
+        let scope = Scope::new(&bindings, &values).with_env(env);
+        if let Some(selection) = &select.selection {
+            if !is_true(selection, &scope)? {
+                continue;
+            }
+        }
+        if !windows.is_empty() {
+            window_rows.push(values);
+        } else if emit(&scope)? {
+            break;
+        }
+    }
+
+    if !windows.is_empty() {
+        let scope = |idx: usize| Scope::new(&bindings, &window_rows[idx]).with_env(env);
+        let results = compute_windows(&windows, select, &bindings, window_rows.len(), &scope)?;
+        for (idx, values) in window_rows.iter().enumerate() {
+            let computed: Vec<(&Expr, Value)> = windows
+                .iter()
+                .zip(&results)
+                .map(|(call, values)| (*call, values[idx].clone()))
+                .collect();
+            let scope = Scope::new(&bindings, values)
+                .with_computed(&computed)
+                .with_env(env);
+            if emit(&scope)? {
+                break;
+            }
+        }
+    }
+
+    if let Some(sorter) = sorter {
+        result_set.rows = sorter.finish();
+    }
+    result_set.rows = limit.apply(result_set.rows);
+    Ok(result_set)
+}
+
+/// Evaluates the select list against one row.
+pub fn project(projections: &[Projection], scope: &Scope) -> Result<Vec<Value>> {
+    projections
+        .iter()
+        .map(|projection| match projection {
+            Projection::Column(idx) => Ok(scope.values[*idx].clone()),
+            Projection::Expr(expr) => evaluate(expr, scope),
+        })
+        .collect()
+}
+
+/// Rowids of `table` in ORDER BY order, read from a column index, when the query sorts
+/// on a single indexed column. Rows whose value is NULL are not in the index and go
+/// first or last as the sort key asks. The rowids come out lazily, so a LIMIT stops the
+/// walk as soon as enough rows matched.
+fn index_order<'a>(
+    table: &'a Table,
+    bindings: &[ColumnBinding],
+    keys: &[SortKey],
+    projections: &[Projection],
+) -> Option<Box<dyn Iterator<Item = i64> + 'a>> {
+    let key = match keys {
+        [key] => key,
+        _ => return None,
+    };
+    let column_idx = match key.term {
+        SortTerm::Output(idx) => match projections[idx] {
+            Projection::Column(column_idx) => Some(column_idx),
+            Projection::Expr(expr) => column_reference(expr, bindings),
+        },
+        SortTerm::Expr(expr) => column_reference(expr, bindings),
+    };
+    let column = match column_idx {
+        Some(column_idx) => &table.columns[column_idx],
+        None => return None,
+    };
+
+    let descending = key.order.descending;
+    let ordered = match &column.index {
+        Index::Integer(index) => index_rowids(index.values(), descending),
+        Index::Text(index) => index_rowids(index.values(), descending),
+        Index::Blob(index) => index_rowids(index.values(), descending),
+        Index::Decimal(index) => index_rowids(index.values(), descending),
+        Index::None => return None,
+    };
+    let nulls = column.nulls.iter().copied();
+    Some(match key.order.nulls_first {
+        true => Box::new(nulls.chain(ordered)),
+        false => Box::new(ordered.chain(nulls)),
+    })
+}
+
+/// Flattens the rowid sets of an index in key order. Rows sharing a value stay in
+/// rowid order either way, as a stable sort would leave them.
+fn index_rowids<'a>(
+    sets: impl DoubleEndedIterator<Item = &'a BTreeSet<i64>> + 'a,
+    descending: bool,
+) -> Box<dyn Iterator<Item = i64> + 'a> {
+    if descending {
+        Box::new(sets.rev().flatten().copied())
+    } else {
+        Box::new(sets.flatten().copied())
+    }
+}
+
+/// Position of the column `expr` refers to, if it is a plain column reference.
+fn column_reference(expr: &Expr, bindings: &[ColumnBinding]) -> Option<usize> {
+    let scope = Scope::new(bindings, &[]);
+    match expr {
+        Expr::Identifier(ident) => scope.resolve(None, &ident.value).ok(),
+        Expr::CompoundIdentifier(idents) if idents.len() == 2 => {
+            scope.resolve(Some(&idents[0].value), &idents[1].value).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Rowids of the rows in `table` for which `selection` is true, or every row when there
+/// is no WHERE clause. `qualifier` is the name the table's columns can be qualified with.
+pub fn matching_rowids(
+    table: &Table,
+    qualifier: &str,
+    selection: Option<&Expr>,
+    env: Env,
+) -> Result<Vec<i64>> {
+    let rowids = table.rowids()?;
+    let selection = match selection {
+        Some(selection) => selection,
+        None => return Ok(rowids),
+    };
+
+    let bindings = ColumnBinding::for_table(table, qualifier);
+    let mut matching = vec![];
+    for rowid in rowids {
+        let values = table.get_row_values(rowid)?;
+        if is_true(selection, &Scope::new(&bindings, &values).with_env(env))? {
+            matching.push(rowid);
+        }
+    }
+    Ok(matching)
+}
+
+/// Resolves the FROM clause. A single table is scanned by the executors themselves;
+/// joins and subqueries are evaluated up front into rows.
+fn resolve_from<'a>(
+    from: &[TableWithJoins],
+    env: Env<'a>,
+) -> Result<(RowSource<'a>, Vec<ColumnBinding>)> {
+    match from {
+        [] => Ok((RowSource::Empty, vec![])),
+        [table_with_joins] if table_with_joins.joins.is_empty() => {
+            match resolve_relation(&table_with_joins.relation, env)? {
+                (Relation::Table(table), bindings) => Ok((RowSource::Table(table), bindings)),
+                (Relation::Derived(result_set), bindings) => {
+                    Ok((RowSource::Rows(result_set.rows), bindings))
+                }
+            }
+        }
+        _ => {
+            let (bindings, rows) = join_tables(from, env)?;
+            Ok((RowSource::Rows(rows), bindings))
+        }
+    }
+}
+
+/// A table, a subquery or a table-valued function in FROM.
+pub enum Relation<'a> {
+    Table(&'a Table),
+    /// The rows of `FROM (SELECT ...)` or of a table-valued function.
+    Derived(ResultSet),
+}
+
+/// Looks up a table named in the FROM clause, or runs a subquery there, and returns it
+/// along with the bindings of its columns. Columns are qualified by the alias if one was
+/// given, otherwise by the table name; a subquery's columns can be renamed with
+/// `AS t(a, b)`.
+pub fn resolve_relation<'a>(
+    relation: &TableFactor,
+    env: Env<'a>,
+) -> Result<(Relation<'a>, Vec<ColumnBinding>)> {
+    match relation {
+        TableFactor::Table { args: Some(_), .. } => {
+            let function = TableFunction::new(relation)?.unwrap();
+            let result_set = function.execute(&Scope::new(&[], &[]).with_env(env))?;
+            Ok((Relation::Derived(result_set), function.bindings()?))
+        }
+        TableFactor::Table { name, alias, .. } => {
+            let table_name = name.to_string();
+            // A table of an enclosing WITH clause hides a stored table of the same name.
+            let common_table = match (name.0.as_slice(), env.tables) {
+                ([ident], Some(tables)) => tables.get(&ident.value).map(|rows| (ident, rows)),
+                _ => None,
+            };
+            if let Some((ident, result_set)) = common_table {
+                let qualifier = alias
+                    .as_ref()
+                    .map_or(&ident.value, |alias| &alias.name.value);
+                let bindings = result_set
+                    .columns
+                    .iter()
+                    .map(|column| ColumnBinding {
+                        table: Some(qualifier.to_string()),
+                        name: column.clone(),
+                        hidden: false,
+                        json: false,
+                    })
+                    .collect();
+                return Ok((Relation::Derived(result_set.clone()), bindings));
+            }
+            let table = env
+                .db
+                .get_table(table_name.clone())
+                .map_err(|_| RUSQLError::General(format!("No such table: {}", table_name)))?;
+            let qualifier = alias
+                .as_ref()
+                .map_or(table_name, |alias| alias.name.value.clone());
+            let bindings = ColumnBinding::for_table(table, &qualifier);
+            Ok((Relation::Table(table), bindings))
+        }
+        TableFactor::Derived {
+            lateral: false,
+            subquery,
+            alias,
+        } => {
+            let result_set = execute_query_in(subquery, env)?;
+            let names = match alias {
+                Some(alias) => column_names(result_set.columns.clone(), alias)?,
+                None => result_set.columns.clone(),
+            };
+            let bindings = names
+                .into_iter()
+                .map(|name| ColumnBinding {
+                    table: alias.as_ref().map(|alias| alias.name.value.clone()),
+                    name,
+                    hidden: false,
+                    json: false,
+                })
+                .collect();
+            Ok((Relation::Derived(result_set), bindings))
+        }
+        _ => Err(RUSQLError::NotImplemented(
+            "Only table names and subqueries are supported in FROM.".to_string(),
+        )),
+    }
+}
+
+/// A table-valued function in FROM: `json_each(doc)` or `json_tree(doc)`.
+pub struct TableFunction<'a> {
+    name: String,
+    args: Vec<&'a Expr>,
+    alias: Option<&'a TableAlias>,
+}
+
+impl<'a> TableFunction<'a> {
+    /// The table-valued function `relation` calls, if it calls one.
+    pub fn new(relation: &'a TableFactor) -> Result<Option<Self>> {
+        let TableFactor::Table {
+            name,
+            alias,
+            args: Some(args),
+            ..
+        } = relation
+        else {
+            return Ok(None);
+        };
+        let name = name.to_string().to_lowercase();
+        if !matches!(name.as_str(), "json_each" | "json_tree") {
+            return Err(RUSQLError::General(format!(
+                "No such table-valued function: {}",
+                name
+            )));
+        }
+        let args = args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Ok(expr),
+                _ => Err(RUSQLError::NotImplemented(format!(
+                    "Unsupported argument to {}(): {}",
+                    name, arg
+                ))),
+            })
+            .collect::<Result<Vec<&Expr>>>()?;
+        Ok(Some(TableFunction {
+            name,
+            args,
+            alias: alias.as_ref(),
+        }))
+    }
+
+    /// Bindings of the function's columns, qualified by the alias or the function name.
+    pub fn bindings(&self) -> Result<Vec<ColumnBinding>> {
+        let columns = EACH_COLUMNS
+            .iter()
+            .map(|column| column.to_string())
+            .collect();
+        let names = match self.alias {
+            Some(alias) => column_names(columns, alias)?,
+            None => columns,
+        };
+        let qualifier = self.alias.map_or(&self.name, |alias| &alias.name.value);
+        Ok(names
+            .into_iter()
+            .map(|name| ColumnBinding {
+                table: Some(qualifier.clone()),
+                name,
+                hidden: false,
+                json: false,
+            })
+            .collect())
+    }
+
+    /// Runs the function with its arguments evaluated in `scope`, which holds the row of
+    /// the tables before it in FROM.
+    pub fn execute(&self, scope: &Scope) -> Result<ResultSet> {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| evaluate(arg, scope))
+            .collect::<Result<Vec<Value>>>()?;
+        json::each(&args, self.name == "json_tree")
+    }
+}
+
+/// The column names of a subquery or common table, renamed by `AS t(a, b)` if given.
+pub fn column_names(columns: Vec<String>, alias: &TableAlias) -> Result<Vec<String>> {
+    if alias.columns.is_empty() {
+        return Ok(columns);
+    }
+    if alias.columns.len() != columns.len() {
+        return Err(RUSQLError::General(format!(
+            "Table {} has {} columns but {} column names were given.",
+            alias.name,
+            columns.len(),
+            alias.columns.len()
+        )));
+    }
+    Ok(alias
+        .columns
+        .iter()
+        .map(|ident| ident.value.clone())
+        .collect())
+}
+
+fn resolve_projection<'a>(
+    items: &'a [SelectItem],
+    bindings: &[ColumnBinding],
+) -> Result<(Vec<String>, Vec<Projection<'a>>)> {
+    let mut labels = vec![];
+    let mut projections = vec![];
+
+    for item in items {
+        match item {
+            SelectItem::Wildcard(_) => {
+                if bindings.is_empty() {
+                    return Err(RUSQLError::General("No tables specified.".to_string()));
+                }
+                for (idx, binding) in bindings.iter().enumerate() {
+                    if binding.hidden {
+                        continue;
+                    }
+                    labels.push(binding.name.clone());
+                    projections.push(Projection::Column(idx));
+                }
+            }
+            SelectItem::QualifiedWildcard(ObjectName(idents), _) => {
+                let qualifier = &idents.last().unwrap().value;
+                let matching = bindings
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, binding)| {
+                        binding
+                            .table
+                            .as_ref()
+                            .is_some_and(|table| table.eq_ignore_ascii_case(qualifier))
+                    })
+                    .collect::<Vec<_>>();
+                if matching.is_empty() {
+                    return Err(RUSQLError::General(format!("No such table: {}", qualifier)));
+                }
+                for (idx, binding) in matching {
+                    labels.push(binding.name.clone());
+                    projections.push(Projection::Column(idx));
+                }
+            }
+            SelectItem::UnnamedExpr(expr) => {
+                labels.push(expression_label(expr));
+                projections.push(Projection::Expr(expr));
+            }
+            SelectItem::ExprWithAlias { expr, alias } => {
+                labels.push(alias.value.clone());
+                projections.push(Projection::Expr(expr));
+            }
+        }
+    }
+
+    Ok((labels, projections))
+}
+
+/// Column label for an un-aliased select item: the column name for plain column
+/// references, otherwise the expression text.
+fn expression_label(expr: &Expr) -> String {
+    match expr {
+        Expr::Identifier(ident) => ident.value.clone(),
+        Expr::CompoundIdentifier(idents) => idents.last().unwrap().value.clone(),
+        _ => expr.to_string(),
+    }
+}
@@ -0,0 +1,585 @@
+//! Window functions: `f(...) OVER (PARTITION BY ... ORDER BY ... frame)`.
+//!
+//! A query with window functions first collects the rows that pass its WHERE clause.
+//! Each window call then splits them into partitions, sorts every partition on the
+//! window's ORDER BY (ties keep scan order) and computes one value per row. The select
+//! list and ORDER BY of the query are evaluated afterwards, with each call replaced by
+//! its value for the row, exactly as aggregate calls are in a grouped query.
+//!
+//! Rows with equal window ORDER BY values are peers. Ranking functions number peer
+//! groups; aggregates and FIRST_VALUE, LAST_VALUE and NTH_VALUE read the frame, which by
+//! default runs from the start of the partition to the last peer of the current row.
+//! Frames are `ROWS`, `GROUPS` (counted in peer groups) or `RANGE` without offsets.
+//! Aggregates over frames that start at the partition start are computed as running
+//! totals in one pass; other frames are summed afresh for every row.
+
+use crate::error::{RUSQLError, Result};
+use crate::sql::db::value::Value;
+use crate::sql::executor::aggregate::{Aggregate, GroupKey, Inputs};
+use crate::sql::executor::expr::{evaluate, walk_expr, ColumnBinding, Scope};
+use crate::sql::executor::order::{compare_keys, KeyOrder};
+use sqlparser::ast::{
+    Expr, Function, FunctionArg, FunctionArgExpr, Select, WindowFrameBound, WindowFrameUnits,
+    WindowSpec, WindowType,
+};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// Window function calls in `exprs`, each once.
+pub fn window_calls<'a>(exprs: impl Iterator<Item = &'a Expr>) -> Vec<&'a Expr> {
+    let mut calls = vec![];
+    for expr in exprs {
+        walk_expr(expr, &mut |expr| match expr {
+            Expr::Function(Function { over: Some(_), .. }) => {
+                if !calls.contains(&expr) {
+                    calls.push(expr);
+                }
+                false
+            }
+            _ => true,
+        });
+    }
+    calls
+}
+
+/// The expressions window calls evaluate per row: their arguments, FILTER, PARTITION BY
+/// and ORDER BY. In an aggregate query these may hold aggregate calls.
+pub fn window_operands<'a>(calls: &[&'a Expr], select: &'a Select) -> Result<Vec<&'a Expr>> {
+    let mut operands = vec![];
+    for call in calls {
+        let function = match call {
+            Expr::Function(function) => function,
+            _ => unreachable!("window calls are function calls"),
+        };
+        for arg in &function.args {
+            if let FunctionArg::Unnamed(FunctionArgExpr::Expr(arg))
+            | FunctionArg::Named {
+                arg: FunctionArgExpr::Expr(arg),
+                ..
+            } = arg
+            {
+                operands.push(arg);
+            }
+        }
+        operands.extend(function.filter.as_deref());
+        let spec = window_spec(function, select)?;
+        operands.extend(&spec.partition_by);
+        operands.extend(spec.order_by.iter().map(|item| &item.expr));
+    }
+    Ok(operands)
+}
+
+/// Computes every call of `calls` for each of `len` rows with the columns of `bindings`,
+/// where `scope` gives the scope of the row at a position; the result holds one value
+/// per row for each call, in the order of `calls`.
+pub fn compute_windows<'s>(
+    calls: &[&Expr],
+    select: &Select,
+    bindings: &[ColumnBinding],
+    len: usize,
+    scope: &dyn Fn(usize) -> Scope<'s>,
+) -> Result<Vec<Vec<Value>>> {
+    calls
+        .iter()
+        .map(|call| Window::new(call, select, bindings)?.compute(len, scope))
+        .collect()
+}
+
+/// The window definition of `function`, looking up named windows in `select`.
+fn window_spec<'a>(function: &'a Function, select: &'a Select) -> Result<&'a WindowSpec> {
+    match &function.over {
+        Some(WindowType::WindowSpec(spec)) => Ok(spec),
+        Some(WindowType::NamedWindow(name)) => select
+            .named_window
+            .iter()
+            .find(|definition| definition.0.value.eq_ignore_ascii_case(&name.value))
+            .map(|definition| &definition.1)
+            .ok_or_else(|| RUSQLError::General(format!("no such window: {}", name))),
+        None => unreachable!("window calls have an OVER clause"),
+    }
+}
+
+/// What a window call computes.
+enum WindowFunction<'a> {
+    RowNumber,
+    Rank,
+    DenseRank,
+    PercentRank,
+    CumeDist,
+    Ntile(&'a Expr),
+    /// LAG (backwards) or LEAD, with the value, the offset and the default.
+    Shift {
+        backwards: bool,
+        value: &'a Expr,
+        offset: Option<&'a Expr>,
+        default: Option<&'a Expr>,
+    },
+    FirstValue(&'a Expr),
+    LastValue(&'a Expr),
+    NthValue(&'a Expr, &'a Expr),
+    Aggregate(Aggregate<'a>),
+}
+
+/// One end of a frame.
+#[derive(Clone, Copy)]
+enum Bound {
+    UnboundedPreceding,
+    Preceding(usize),
+    CurrentRow,
+    Following(usize),
+    UnboundedFollowing,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Units {
+    Rows,
+    Range,
+    Groups,
+}
+
+struct Frame {
+    units: Units,
+    start: Bound,
+    end: Bound,
+}
+
+struct Window<'a> {
+    function: WindowFunction<'a>,
+    partition_by: &'a [Expr],
+    order_by: Vec<(&'a Expr, KeyOrder)>,
+    frame: Frame,
+}
+
+impl<'a> Window<'a> {
+    fn new(call: &'a Expr, select: &'a Select, bindings: &[ColumnBinding]) -> Result<Self> {
+        let function = match call {
+            Expr::Function(function) => function,
+            _ => unreachable!("window calls are function calls"),
+        };
+        let spec = window_spec(function, select)?;
+
+        let order_by = spec
+            .order_by
+            .iter()
+            .map(|item| {
+                let descending = item.asc == Some(false);
+                let order = KeyOrder {
+                    descending,
+                    nulls_first: item.nulls_first.unwrap_or(!descending),
+                };
+                (&item.expr, order)
+            })
+            .collect();
+        Ok(Window {
+            function: window_function(call, function, bindings)?,
+            partition_by: &spec.partition_by,
+            order_by,
+            frame: frame(spec)?,
+        })
+    }
+
+    fn compute<'s>(&self, len: usize, scope: &dyn Fn(usize) -> Scope<'s>) -> Result<Vec<Value>> {
+        let mut partitions: BTreeMap<GroupKey, Vec<usize>> = BTreeMap::new();
+        let mut sort_values = Vec::with_capacity(len);
+        for idx in 0..len {
+            let scope = scope(idx);
+            let key = self
+                .partition_by
+                .iter()
+                .map(|expr| evaluate(expr, &scope))
+                .collect::<Result<Vec<_>>>()?;
+            partitions.entry(GroupKey(key)).or_default().push(idx);
+            sort_values.push(
+                self.order_by
+                    .iter()
+                    .map(|(expr, _)| evaluate(expr, &scope))
+                    .collect::<Result<Vec<_>>>()?,
+            );
+        }
+
+        let orders: Vec<KeyOrder> = self.order_by.iter().map(|(_, order)| *order).collect();
+        let mut results = vec![Value::Null; len];
+        for mut positions in partitions.into_values() {
+            positions.sort_by(|a, b| compare_keys(&sort_values[*a], &sort_values[*b], &orders));
+            let partition = Partition::new(positions, |a, b| {
+                compare_keys(&sort_values[a], &sort_values[b], &orders) == Ordering::Equal
+            });
+            let values = self.compute_partition(&partition, scope)?;
+            for (idx, value) in partition.rows.iter().zip(values) {
+                results[*idx] = value;
+            }
+        }
+        Ok(results)
+    }
+
+    /// The values of the call for the rows of one partition, in partition order.
+    fn compute_partition<'s>(
+        &self,
+        partition: &Partition,
+        scope: &dyn Fn(usize) -> Scope<'s>,
+    ) -> Result<Vec<Value>> {
+        let rows = &partition.rows;
+        let len = rows.len();
+        let value_at = |expr: &Expr, position: usize| evaluate(expr, &scope(rows[position]));
+
+        let values = match &self.function {
+            WindowFunction::RowNumber => (1..=len).map(|n| Value::Integer(n as i64)).collect(),
+            WindowFunction::Rank => (0..len)
+                .map(|position| Value::Integer(partition.group_start(position) as i64 + 1))
+                .collect(),
+            WindowFunction::DenseRank => (0..len)
+                .map(|position| Value::Integer(partition.groups[position] as i64 + 1))
+                .collect(),
+            WindowFunction::PercentRank => (0..len)
+                .map(|position| match len {
+                    1 => Value::Real(0.0),
+                    _ => Value::Real(partition.group_start(position) as f64 / (len - 1) as f64),
+                })
+                .collect(),
+            WindowFunction::CumeDist => (0..len)
+                .map(|position| Value::Real(partition.group_end(position) as f64 / len as f64))
+                .collect(),
+            WindowFunction::Ntile(buckets) => {
+                let buckets = match value_at(buckets, 0)?.to_numeric() {
+                    Value::Integer(buckets) if buckets > 0 => buckets as usize,
+                    _ => {
+                        return Err(RUSQLError::General(
+                            "argument of ntile must be a positive integer".to_string(),
+                        ))
+                    }
+                };
+                // The first `len % buckets` buckets take one row more than the others.
+                let size = len / buckets;
+                let larger = len % buckets;
+                (0..len)
+                    .map(|position| {
+                        let bucket = if position < larger * (size + 1) {
+                            position / (size + 1)
+                        } else {
+                            larger + (position - larger * (size + 1)) / size
+                        };
+                        Value::Integer(bucket as i64 + 1)
+                    })
+                    .collect()
+            }
+            WindowFunction::Shift {
+                backwards,
+                value,
+                offset,
+                default,
+            } => (0..len)
+                .map(|position| {
+                    let offset = match offset {
+                        Some(offset) => match value_at(offset, position)?.to_numeric() {
+                            Value::Integer(offset) => offset,
+                            Value::Null => return Ok(Value::Null),
+                            _ => {
+                                return Err(RUSQLError::General(
+                                    "the offset of lag and lead must be an integer".to_string(),
+                                ))
+                            }
+                        },
+                        None => 1,
+                    };
+                    let offset = if *backwards { -offset } else { offset };
+                    let target = (position as i64).checked_add(offset);
+                    match target.filter(|target| (0..len as i64).contains(target)) {
+                        Some(target) => value_at(value, target as usize),
+                        None => match default {
+                            Some(default) => value_at(default, position),
+                            None => Ok(Value::Null),
+                        },
+                    }
+                })
+                .collect::<Result<_>>()?,
+            WindowFunction::FirstValue(value) => (0..len)
+                .map(|position| match self.frame_bounds(partition, position) {
+                    Some((start, _)) => value_at(value, start),
+                    None => Ok(Value::Null),
+                })
+                .collect::<Result<_>>()?,
+            WindowFunction::LastValue(value) => (0..len)
+                .map(|position| match self.frame_bounds(partition, position) {
+                    Some((_, end)) => value_at(value, end - 1),
+                    None => Ok(Value::Null),
+                })
+                .collect::<Result<_>>()?,
+            WindowFunction::NthValue(value, n) => (0..len)
+                .map(|position| {
+                    let n = match value_at(n, position)?.to_numeric() {
+                        Value::Integer(n) if n > 0 => n as usize,
+                        _ => {
+                            return Err(RUSQLError::General(
+                                "second argument to nth_value must be a positive integer"
+                                    .to_string(),
+                            ))
+                        }
+                    };
+                    match self.frame_bounds(partition, position) {
+                        Some((start, end)) if n <= end - start => value_at(value, start + n - 1),
+                        _ => Ok(Value::Null),
+                    }
+                })
+                .collect::<Result<_>>()?,
+            WindowFunction::Aggregate(aggregate) => {
+                let inputs = (0..len)
+                    .map(|position| aggregate.inputs(&scope(rows[position])))
+                    .collect::<Result<Vec<_>>>()?;
+                self.compute_aggregate(aggregate, partition, inputs)?
+            }
+        };
+        Ok(values)
+    }
+
+    fn compute_aggregate(
+        &self,
+        aggregate: &Aggregate,
+        partition: &Partition,
+        inputs: Vec<Option<Inputs>>,
+    ) -> Result<Vec<Value>> {
+        let len = partition.rows.len();
+        let mut values = Vec::with_capacity(len);
+        if matches!(self.frame.start, Bound::UnboundedPreceding) {
+            // The frame only ever grows at its end: keep a running total.
+            let mut accumulator = aggregate.accumulator();
+            let mut added = 0;
+            for position in 0..len {
+                let end = self
+                    .frame_bounds(partition, position)
+                    .map_or(0, |(_, end)| end);
+                while added < end {
+                    if let Some(inputs) = &inputs[added] {
+                        aggregate.step(&mut accumulator, inputs.clone())?;
+                    }
+                    added += 1;
+                }
+                values.push(aggregate.finish(&accumulator));
+            }
+        } else {
+            for position in 0..len {
+                let mut accumulator = aggregate.accumulator();
+                if let Some((start, end)) = self.frame_bounds(partition, position) {
+                    for inputs in inputs[start..end].iter().flatten() {
+                        aggregate.step(&mut accumulator, inputs.clone())?;
+                    }
+                }
+                values.push(aggregate.finish(&accumulator));
+            }
+        }
+        Ok(values)
+    }
+
+    /// The frame of the row at `position` as a range of partition positions, `None`
+    /// when it is empty.
+    fn frame_bounds(&self, partition: &Partition, position: usize) -> Option<(usize, usize)> {
+        let len = partition.rows.len();
+        let group = partition.groups[position];
+        let last_group = partition.groups[len - 1];
+        let group_start = |group: usize| partition.first_of_group(group);
+
+        let start = match (self.frame.start, self.frame.units) {
+            (Bound::UnboundedPreceding, _) => 0,
+            (Bound::CurrentRow, Units::Rows) => position,
+            (Bound::CurrentRow, _) => partition.group_start(position),
+            (Bound::Preceding(n), Units::Rows) => position.saturating_sub(n),
+            (Bound::Preceding(n), _) => group_start(group.saturating_sub(n)),
+            (Bound::Following(n), Units::Rows) => position.saturating_add(n),
+            (Bound::Following(n), _) => group_start(group.saturating_add(n)),
+            (Bound::UnboundedFollowing, _) => len,
+        };
+        let end = match (self.frame.end, self.frame.units) {
+            (Bound::UnboundedPreceding, _) => 0,
+            (Bound::CurrentRow, Units::Rows) => position + 1,
+            (Bound::CurrentRow, _) => partition.group_end(position),
+            (Bound::Preceding(n), Units::Rows) => (position + 1).saturating_sub(n),
+            (Bound::Preceding(n), _) => match group.checked_sub(n) {
+                Some(group) => group_start(group + 1),
+                None => 0,
+            },
+            (Bound::Following(n), Units::Rows) => position.saturating_add(n).saturating_add(1),
+            (Bound::Following(n), _) => group_start(group.saturating_add(n).min(last_group) + 1),
+            (Bound::UnboundedFollowing, _) => len,
+        };
+        let end = end.min(len);
+        (start < end).then_some((start, end))
+    }
+}
+
+/// The rows of one partition in window order, with their peer groups.
+struct Partition {
+    rows: Vec<usize>,
+    /// Peer group number of each position, counting from 0.
+    groups: Vec<usize>,
+}
+
+impl Partition {
+    fn new(rows: Vec<usize>, peers: impl Fn(usize, usize) -> bool) -> Self {
+        let mut groups = Vec::with_capacity(rows.len());
+        for position in 0..rows.len() {
+            let group = match position {
+                0 => 0,
+                _ if peers(rows[position - 1], rows[position]) => groups[position - 1],
+                _ => groups[position - 1] + 1,
+            };
+            groups.push(group);
+        }
+        Partition { rows, groups }
+    }
+
+    /// Position of the first row of peer group `group`, or the partition length past
+    /// the last group.
+    fn first_of_group(&self, group: usize) -> usize {
+        self.groups.partition_point(|other| *other < group)
+    }
+
+    /// Position of the first peer of the row at `position`.
+    fn group_start(&self, position: usize) -> usize {
+        self.first_of_group(self.groups[position])
+    }
+
+    /// Position just past the last peer of the row at `position`.
+    fn group_end(&self, position: usize) -> usize {
+        self.first_of_group(self.groups[position] + 1)
+    }
+}
+
+fn window_function<'a>(
+    call: &'a Expr,
+    function: &'a Function,
+    bindings: &[ColumnBinding],
+) -> Result<WindowFunction<'a>> {
+    let name = function.name.to_string().to_lowercase();
+    if matches!(
+        name.as_str(),
+        "count"
+            | "sum"
+            | "avg"
+            | "total"
+            | "min"
+            | "max"
+            | "group_concat"
+            | "json_group_array"
+            | "json_group_object"
+    ) {
+        if function.distinct {
+            return Err(RUSQLError::General(
+                "DISTINCT is not supported for window functions".to_string(),
+            ));
+        }
+        return Ok(WindowFunction::Aggregate(Aggregate::new(call, bindings)?));
+    }
+    if function.filter.is_some() {
+        return Err(RUSQLError::General(
+            "FILTER clause may only be used with aggregate window functions".to_string(),
+        ));
+    }
+
+    let args = function
+        .args
+        .iter()
+        .map(|arg| match arg {
+            FunctionArg::Unnamed(FunctionArgExpr::Expr(expr))
+            | FunctionArg::Named {
+                arg: FunctionArgExpr::Expr(expr),
+                ..
+            } => Ok(expr),
+            _ => Err(RUSQLError::NotImplemented(format!(
+                "Unsupported argument to {}(): {}",
+                name, arg
+            ))),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let function = match (name.as_str(), args.as_slice()) {
+        ("row_number", []) => WindowFunction::RowNumber,
+        ("rank", []) => WindowFunction::Rank,
+        ("dense_rank", []) => WindowFunction::DenseRank,
+        ("percent_rank", []) => WindowFunction::PercentRank,
+        ("cume_dist", []) => WindowFunction::CumeDist,
+        ("ntile", [buckets]) => WindowFunction::Ntile(buckets),
+        ("lag" | "lead", [value, rest @ ..]) if rest.len() <= 2 => WindowFunction::Shift {
+            backwards: name == "lag",
+            value,
+            offset: rest.first().copied(),
+            default: rest.get(1).copied(),
+        },
+        ("first_value", [value]) => WindowFunction::FirstValue(value),
+        ("last_value", [value]) => WindowFunction::LastValue(value),
+        ("nth_value", [value, n]) => WindowFunction::NthValue(value, n),
+        (
+            "row_number" | "rank" | "dense_rank" | "percent_rank" | "cume_dist" | "ntile" | "lag"
+            | "lead" | "first_value" | "last_value" | "nth_value",
+            _,
+        ) => {
+            return Err(RUSQLError::General(format!(
+                "wrong number of arguments to function {}()",
+                name
+            )))
+        }
+        _ => {
+            return Err(RUSQLError::General(format!(
+                "{}() may not be used as a window function",
+                name
+            )))
+        }
+    };
+    Ok(function)
+}
+
+/// The frame of `spec`: by default from the start of the partition to the last peer
+/// of the current row.
+fn frame(spec: &WindowSpec) -> Result<Frame> {
+    let frame = match &spec.window_frame {
+        Some(frame) => frame,
+        None => {
+            return Ok(Frame {
+                units: Units::Range,
+                start: Bound::UnboundedPreceding,
+                end: Bound::CurrentRow,
+            })
+        }
+    };
+    let units = match frame.units {
+        WindowFrameUnits::Rows => Units::Rows,
+        WindowFrameUnits::Range => Units::Range,
+        WindowFrameUnits::Groups => Units::Groups,
+    };
+    let start = bound(&frame.start_bound, units)?;
+    let end = match &frame.end_bound {
+        Some(end) => bound(end, units)?,
+        None => Bound::CurrentRow,
+    };
+    if matches!(start, Bound::UnboundedFollowing) {
+        return Err(RUSQLError::General(
+            "frame start cannot be UNBOUNDED FOLLOWING".to_string(),
+        ));
+    }
+    if matches!(end, Bound::UnboundedPreceding) {
+        return Err(RUSQLError::General(
+            "frame end cannot be UNBOUNDED PRECEDING".to_string(),
+        ));
+    }
+    Ok(Frame { units, start, end })
+}
+
+fn bound(bound: &WindowFrameBound, units: Units) -> Result<Bound> {
+    let offset = |expr: &Expr| -> Result<usize> {
+        if units == Units::Range {
+            return Err(RUSQLError::NotImplemented(
+                "RANGE frames with an offset are not supported; use ROWS or GROUPS.".to_string(),
+            ));
+        }
+        match evaluate(expr, &Scope::new(&[], &[]))?.to_numeric() {
+            Value::Integer(n) if n >= 0 => Ok(n as usize),
+            _ => Err(RUSQLError::General(
+                "frame offset must be a non-negative integer".to_string(),
+            )),
+        }
+    };
+    Ok(match bound {
+        WindowFrameBound::CurrentRow => Bound::CurrentRow,
+        WindowFrameBound::Preceding(None) => Bound::UnboundedPreceding,
+        WindowFrameBound::Preceding(Some(expr)) => Bound::Preceding(offset(expr)?),
+        WindowFrameBound::Following(None) => Bound::UnboundedFollowing,
+        WindowFrameBound::Following(Some(expr)) => Bound::Following(offset(expr)?),
+    })
+}
@@ -1,14 +1,22 @@
 pub mod db;
+pub mod executor;
 pub mod parser;
 
 use colored::*;
 use parser::create::CreateQuery;
+use parser::delete::DeleteQuery;
+use parser::dialect::RusqlDialect;
 use parser::drop::drop_table;
 use parser::insert::InsertQuery;
 use parser::list_tables::list_tables;
+use parser::transaction::transaction_control;
+use parser::update::UpdateQuery;
 
-use sqlparser::ast::Statement;
-use sqlparser::dialect::SQLiteDialect;
+use executor::expr::{evaluate, ColumnBinding, Env, Scope, SubqueryCache};
+use executor::result_set::ResultSet;
+use executor::select::{execute_query, matching_rowids};
+
+use sqlparser::ast::{Expr, Query, Statement};
 use sqlparser::parser::{Parser, ParserError};
 
 use crate::error::{RUSQLError, Result};
@@ -43,14 +51,11 @@ impl SQLCommand {
     }
 }
 
-pub fn process_command(query: &str, db: &mut Database) -> Result<String> {
-    if query.trim().to_uppercase() == "LIST TABLES;" {
-        return list_tables(db);
-    }
-    let dialect = SQLiteDialect {};
-    let mut ast = Parser::parse_sql(&dialect, &query).map_err(RUSQLError::from)?;
+fn parse_statement(query: &str) -> Result<Statement> {
+    let dialect = RusqlDialect::new();
+    let mut ast = Parser::parse_sql(&dialect, query).map_err(RUSQLError::from)?;
 
-    if ast.len() > 1 {
+    if ast.len() != 1 {
         return Err(RUSQLError::SqlError(ParserError::ParserError(
             format!("Expected one statement, found {}", ast.len())
                 .red()
@@ -58,8 +63,42 @@ pub fn process_command(query: &str, db: &mut Database) -> Result<String> {
         )));
     }
 
-    let query = ast.pop().unwrap();
+    Ok(ast.pop().unwrap())
+}
+
+/// Parses a single expression, such as the stored text of a column's DEFAULT.
+fn parse_expr(expr: &str) -> Result<Expr> {
+    let dialect = RusqlDialect::new();
+    Parser::new(&dialect)
+        .try_with_sql(expr)
+        .and_then(|mut parser| parser.parse_expr())
+        .map_err(RUSQLError::from)
+}
+
+pub fn process_command(query: &str, db: &mut Database) -> Result<String> {
+    if query.trim().to_uppercase() == "LIST TABLES;" {
+        return list_tables(db);
+    }
+
+    let query = parse_statement(query)?;
 
+    if let Statement::StartTransaction { .. }
+    | Statement::Commit { .. }
+    | Statement::Rollback { .. }
+    | Statement::Savepoint { .. }
+    | Statement::ReleaseSavepoint { .. } = query
+    {
+        return transaction_control(&query, db);
+    }
+
+    // A statement either applies completely or not at all.
+    db.begin_statement();
+    let result = execute_statement(query, db);
+    db.end_statement(result.is_ok())?;
+    result
+}
+
+fn execute_statement(query: Statement, db: &mut Database) -> Result<String> {
     match query {
         Statement::CreateTable { .. } => create_table(&query, db),
         Statement::Insert { .. } => insert_into_table(&query, db),
@@ -72,14 +111,37 @@ pub fn process_command(query: &str, db: &mut Database) -> Result<String> {
                 ))
             }
         }
-        Statement::Query(_query) => Ok(String::from("Not Implemented yet.").yellow().to_string()),
-        Statement::Delete { .. } => Ok(String::from("Not Implemented yet.").yellow().to_string()),
+        Statement::Query(query) => select_from_table(&query, db),
+        Statement::Update { .. } => update_table(&query, db),
+        Statement::Delete { .. } => delete_from_table(&query, db),
         _ => Err(RUSQLError::NotImplemented(
             "SQL command not supported yet.".red().to_string(),
         )),
     }
 }
 
+/// Runs a single SELECT statement and returns its rows instead of printing them.
+pub fn run_query(query: &str, db: &Database) -> Result<ResultSet> {
+    match parse_statement(query)? {
+        Statement::Query(query) => execute_query(&query, db),
+        _ => Err(RUSQLError::Internal(
+            "Expected a SELECT statement.".red().to_string(),
+        )),
+    }
+}
+
+fn select_from_table(query: &Query, db: &Database) -> Result<String> {
+    let result_set = execute_query(query, db)?;
+    result_set.print_result_set();
+
+    Ok(format!(
+        "SELECT Statement executed. {} row(s) returned.",
+        result_set.row_count()
+    )
+    .green()
+    .to_string())
+}
+
 fn create_table(query: &Statement, db: &mut Database) -> Result<String> {
     let create_query = CreateQuery::new(query)?;
     let table_name = create_query.table_name.clone();
@@ -94,7 +156,7 @@ fn create_table(query: &Statement, db: &mut Database) -> Result<String> {
 
     let table = Table::new(create_query);
     table.print_table_schema()?;
-    db.tables.insert(table_name.to_string(), table);
+    db.add_table(table)?;
 
     Ok(String::from("CREATE TABLE Statement executed.")
         .green()
@@ -104,13 +166,21 @@ fn create_table(query: &Statement, db: &mut Database) -> Result<String> {
 fn insert_into_table(query: &Statement, db: &mut Database) -> Result<String> {
     let insert_query = InsertQuery::new(query)?;
     let table_name = insert_query.table_name;
-    let columns = insert_query.columns;
     let values = insert_query.rows;
-    let db_table = db.get_table_mut(table_name.to_string()).or_else(|_| {
-        Err(RUSQLError::Internal(
-            "Table doesn't exist.".red().to_string(),
-        ))
-    })?;
+    let db_table = db
+        .get_table(table_name.to_string())
+        .map_err(|_| RUSQLError::Internal("Table doesn't exist.".red().to_string()))?;
+
+    // Without a column list the values fill every column in schema order.
+    let columns = if insert_query.columns.is_empty() {
+        db_table
+            .columns
+            .iter()
+            .map(|col| col.column_name.to_string())
+            .collect()
+    } else {
+        insert_query.columns
+    };
 
     if !columns
         .iter()
@@ -121,7 +191,21 @@ fn insert_into_table(query: &Statement, db: &mut Database) -> Result<String> {
         ));
     }
 
-    for value in &values {
+    // Columns left out take their DEFAULT, evaluated afresh for every row.
+    let defaults = db_table
+        .columns
+        .iter()
+        .filter(|col| !columns.contains(&col.column_name))
+        .filter_map(|col| {
+            col.default
+                .as_ref()
+                .map(|default| Ok((col.column_name.to_string(), parse_expr(default)?)))
+        })
+        .collect::<Result<Vec<(String, Expr)>>>()?;
+    let mut all_columns = columns.clone();
+    all_columns.extend(defaults.iter().map(|(column, _)| column.to_string()));
+
+    for mut value in values {
         if columns.len() != value.len() {
             return Err(RUSQLError::Internal(
                 format!(
@@ -133,22 +217,98 @@ fn insert_into_table(query: &Statement, db: &mut Database) -> Result<String> {
                 .to_string(),
             ));
         }
+        for (_, default) in &defaults {
+            value.push(evaluate(default, &Scope::new(&[], &[]))?);
+        }
 
-        db_table
-            .validate_unique_constraint(&columns, value)
+        db.insert_row(&table_name, &all_columns, &value)
             .map_err(|err| {
-                RUSQLError::Internal(
-                    format!("Unique key constraint violation: {}", err)
-                        .red()
-                        .to_string(),
-                )
+                let message = match err {
+                    RUSQLError::KeyViolation(message) => {
+                        format!("Unique key constraint violation: {}", message)
+                    }
+                    err => err.to_string(),
+                };
+                RUSQLError::Internal(message.red().to_string())
             })?;
-
-        db_table.insert_row(&columns, &value);
     }
 
-    db_table.print_table_data();
+    db.get_table(table_name.to_string())?.print_table_data()?;
     Ok(String::from("INSERT Statement executed.")
         .green()
         .to_string())
 }
+
+fn delete_from_table(query: &Statement, db: &mut Database) -> Result<String> {
+    let delete_query = DeleteQuery::new(query)?;
+    let table_name = delete_query.table_name;
+    let db_table = db
+        .get_table(table_name.to_string())
+        .map_err(|_| RUSQLError::Internal("Table doesn't exist.".red().to_string()))?;
+
+    let qualifier = delete_query.alias.unwrap_or(table_name.to_string());
+    let cache = SubqueryCache::default();
+    let env = Env::new(db, &cache);
+    let rowids = matching_rowids(db_table, &qualifier, delete_query.selection.as_ref(), env)?;
+
+    for rowid in &rowids {
+        db.delete_row(&table_name, *rowid)?;
+    }
+
+    Ok(format!(
+        "DELETE Statement executed. {} row(s) affected.",
+        rowids.len()
+    )
+    .green()
+    .to_string())
+}
+
+fn update_table(query: &Statement, db: &mut Database) -> Result<String> {
+    let update_query = UpdateQuery::new(query)?;
+    let table_name = update_query.table_name;
+    let db_table = db
+        .get_table(table_name.to_string())
+        .map_err(|_| RUSQLError::Internal("Table doesn't exist.".red().to_string()))?;
+
+    for (column, _) in &update_query.assignments {
+        if !db_table.contains_column(column.to_string()) {
+            return Err(RUSQLError::Internal(
+                format!("Cannot Update, column {} doesn't exist.", column)
+                    .red()
+                    .to_string(),
+            ));
+        }
+    }
+
+    let qualifier = update_query.alias.unwrap_or(table_name.to_string());
+    let cache = SubqueryCache::default();
+    let env = Env::new(db, &cache);
+    let rowids = matching_rowids(db_table, &qualifier, update_query.selection.as_ref(), env)?;
+    let bindings = ColumnBinding::for_table(db_table, &qualifier);
+
+    // Every new value is computed before any row changes, so subqueries in SET see the
+    // table as it was before the statement.
+    let mut updates = Vec::with_capacity(rowids.len());
+    for rowid in &rowids {
+        let old_values = db_table.get_row_values(*rowid)?;
+        let scope = Scope::new(&bindings, &old_values).with_env(env);
+        let new_values = update_query
+            .assignments
+            .iter()
+            .map(|(column, expr)| Ok((column.to_string(), evaluate(expr, &scope)?)))
+            .collect::<Result<Vec<_>>>()?;
+        updates.push((*rowid, new_values));
+    }
+
+    for (rowid, new_values) in &updates {
+        db.update_row(&table_name, *rowid, new_values)
+            .map_err(|err| RUSQLError::Internal(err.to_string().red().to_string()))?;
+    }
+
+    Ok(format!(
+        "UPDATE Statement executed. {} row(s) affected.",
+        rowids.len()
+    )
+    .green()
+    .to_string())
+}
@@ -1,5 +1,7 @@
 use crate::error::{RUSQLError, Result};
-use sqlparser::ast::{ColumnOption, DataType, Statement};
+use crate::sql::db::decimal::MAX_PRECISION;
+use crate::sql::executor::expr::{evaluate, walk_expr, Scope};
+use sqlparser::ast::{ColumnOption, DataType, ExactNumberInfo, Expr, Statement, TableConstraint};
 use std::collections::HashSet;
 
 #[derive(PartialEq, Debug)]
@@ -9,12 +11,26 @@ pub struct ParsedColumn {
     pub is_pk: bool,
     pub not_null: bool,
     pub is_unique: bool,
+    /// The DEFAULT expression as SQL text, evaluated for every row that leaves the
+    /// column out.
+    pub default: Option<String>,
+}
+
+/// A `UNIQUE` or `PRIMARY KEY` constraint enforced on the table rather than by a column
+/// index: one over several columns, or over a single REAL or BOOL column, which has no
+/// index. Other single-column constraints are folded into that column's `ParsedColumn`.
+#[derive(PartialEq, Debug)]
+pub struct ParsedConstraint {
+    pub name: Option<String>,
+    pub columns: Vec<String>,
+    pub is_primary: bool,
 }
 
 #[derive(Debug)]
 pub struct CreateQuery {
     pub table_name: String,
     pub columns: Vec<ParsedColumn>,
+    pub constraints: Vec<ParsedConstraint>,
 }
 
 impl CreateQuery {
@@ -32,6 +48,7 @@ impl CreateQuery {
             } => {
                 let table_name = name.to_string();
                 let mut parsed_columns: Vec<ParsedColumn> = Vec::new();
+                let mut parsed_constraints: Vec<ParsedConstraint> = Vec::new();
                 let mut column_names = HashSet::new();
 
                 for col in columns {
@@ -44,50 +61,125 @@ impl CreateQuery {
                         )));
                     }
 
-                    let datatype = data_type_as_str(&col.data_type);
+                    let datatype = data_type_as_str(&col.data_type)?;
 
                     let mut is_pk = false;
                     let mut is_unique = false;
                     let mut not_null = false;
+                    let mut default = None;
 
                     for column_option in &col.options {
-                        match column_option.option {
+                        match &column_option.option {
                             ColumnOption::Unique { is_primary, .. } => {
                                 let (new_is_pk, new_is_unique, new_not_null) =
                                     handle_unique_option(
-                                        is_primary,
+                                        *is_primary,
                                         &datatype,
                                         &parsed_columns,
+                                        &parsed_constraints,
                                         &table_name,
                                     )?;
 
                                 is_pk = new_is_pk;
                                 is_unique = new_is_unique;
-                                not_null = new_not_null;
+                                not_null = not_null || new_not_null;
+                                if !has_index(&datatype) {
+                                    parsed_constraints.push(ParsedConstraint {
+                                        name: column_option.name.as_ref().map(|n| n.to_string()),
+                                        columns: vec![name.clone()],
+                                        is_primary: *is_primary,
+                                    });
+                                }
                             }
                             ColumnOption::NotNull => {
                                 not_null = true;
                             }
+                            ColumnOption::Default(expr) => {
+                                check_default(&name, expr)?;
+                                default = Some(expr.to_string());
+                            }
                             _ => (),
                         };
                     }
 
                     parsed_columns.push(ParsedColumn {
                         name,
-                        datatype: datatype.to_string(),
+                        datatype,
                         is_pk,
                         not_null,
                         is_unique,
+                        default,
                     });
                 }
 
+                // Only UNIQUE and PRIMARY KEY constraints are enforced; FOREIGN KEY and
+                // CHECK constraints are accepted and ignored.
                 for constraint in constraints {
-                    println!("{:?}", constraint);
+                    if let TableConstraint::Unique {
+                        name,
+                        columns,
+                        is_primary,
+                        ..
+                    } = constraint
+                    {
+                        let columns: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+                        for (idx, column) in columns.iter().enumerate() {
+                            if !column_names.contains(column) {
+                                return Err(RUSQLError::Internal(format!(
+                                    "Constraint on table {} refers to unknown column {}",
+                                    table_name, column
+                                )));
+                            }
+                            if columns[..idx].contains(column) {
+                                return Err(RUSQLError::Internal(format!(
+                                    "Column {} appears more than once in a constraint",
+                                    column
+                                )));
+                            }
+                        }
+                        if let [column] = columns.as_slice() {
+                            let idx = parsed_columns
+                                .iter()
+                                .position(|col| &col.name == column)
+                                .unwrap();
+                            let (is_pk, is_unique, not_null) = handle_unique_option(
+                                *is_primary,
+                                &parsed_columns[idx].datatype,
+                                &parsed_columns,
+                                &parsed_constraints,
+                                &table_name,
+                            )?;
+                            let parsed_column = &mut parsed_columns[idx];
+                            parsed_column.is_pk = parsed_column.is_pk || is_pk;
+                            parsed_column.is_unique = parsed_column.is_unique || is_unique;
+                            parsed_column.not_null = parsed_column.not_null || not_null;
+                            if has_index(&parsed_column.datatype) {
+                                continue;
+                            }
+                        } else if *is_primary {
+                            check_single_primary_key(
+                                &parsed_columns,
+                                &parsed_constraints,
+                                &table_name,
+                            )?;
+                            for col in parsed_columns.iter_mut() {
+                                if columns.contains(&col.name) {
+                                    col.not_null = true;
+                                }
+                            }
+                        }
+                        parsed_constraints.push(ParsedConstraint {
+                            name: name.as_ref().map(|name| name.to_string()),
+                            columns,
+                            is_primary: *is_primary,
+                        });
+                    }
                 }
 
                 Ok(CreateQuery {
                     table_name,
                     columns: parsed_columns,
+                    constraints: parsed_constraints,
                 })
             }
             _ => Err(RUSQLError::Internal("Error Parsing Query".to_string())),
@@ -95,45 +187,150 @@ impl CreateQuery {
     }
 }
 
-fn data_type_as_str(datatype: &DataType) -> &'static str {
-    match datatype {
+/// The storage class of a declared type, used both for column declarations and for
+/// `CAST(x AS type)`. Type names sqlparser has no variant for are classified by SQLite's
+/// affinity rules: see `affinity_from_name`. Decimal types keep their precision and
+/// scale, as in `Decimal(10,2)`, and are an error when those are out of range.
+pub fn data_type_as_str(datatype: &DataType) -> Result<String> {
+    let name = match datatype {
         DataType::SmallInt(_) => "Integer",
         DataType::Int(_) => "Integer",
         DataType::BigInt(_) => "Integer",
         DataType::Integer(_) => "Integer",
+        DataType::Bool => "Bool",
         DataType::Boolean => "Bool",
         DataType::Text => "Text",
         DataType::Varchar(_) => "Text",
         DataType::Real => "Real",
         DataType::Float(_) => "Real",
         DataType::Double => "Real",
-        DataType::Decimal(_) => "Real",
-        _ => {
-            eprintln!("Not matched on datatype: {:?}", datatype);
-            "Invalid"
+        DataType::Decimal(info) | DataType::Numeric(info) | DataType::Dec(info) => {
+            return decimal_type(info)
         }
+        // Dates and times are stored as ISO-8601 text, as in SQLite.
+        DataType::Date => "Text",
+        DataType::Time(..) => "Text",
+        DataType::Datetime(_) => "Text",
+        DataType::Timestamp(..) => "Text",
+        DataType::JSON => "Json",
+        DataType::JSONB => "Json",
+        DataType::Blob(_) => "Blob",
+        DataType::Bytea => "Blob",
+        DataType::Binary(_) => "Blob",
+        DataType::Varbinary(_) => "Blob",
+        DataType::Bytes(_) => "Blob",
+        _ => affinity_from_name(&datatype.to_string()),
+    };
+    Ok(name.to_string())
+}
+
+/// `DECIMAL` alone keeps each value's own scale; `DECIMAL(p)` holds whole numbers.
+fn decimal_type(info: &ExactNumberInfo) -> Result<String> {
+    let (precision, scale) = match *info {
+        ExactNumberInfo::None => return Ok("Decimal".to_string()),
+        ExactNumberInfo::Precision(precision) => (precision, 0),
+        ExactNumberInfo::PrecisionAndScale(precision, scale) => (precision, scale),
+    };
+    if precision == 0 || precision > MAX_PRECISION as u64 || scale > precision {
+        return Err(RUSQLError::General(format!(
+            "DECIMAL({}, {}) is out of range: the precision must be between 1 and {} \
+             and the scale at most the precision",
+            precision, scale, MAX_PRECISION
+        )));
+    }
+    Ok(format!("Decimal({},{})", precision, scale))
+}
+
+/// SQLite's affinity rules, applied in order to the type name: `INT` anywhere makes it
+/// an integer type, then `CHAR`, `CLOB` or `TEXT` a text type, `BLOB` a blob type and
+/// `REAL`, `FLOA` or `DOUB` a real type. `BOOL` makes it boolean, which SQLite does not have.
+fn affinity_from_name(name: &str) -> &'static str {
+    let name = name.to_uppercase();
+    let has = |parts: &[&str]| parts.iter().any(|part| name.contains(part));
+    if has(&["INT"]) {
+        "Integer"
+    } else if has(&["CHAR", "CLOB", "TEXT"]) {
+        "Text"
+    } else if has(&["BLOB"]) {
+        "Blob"
+    } else if has(&["REAL", "FLOA", "DOUB"]) {
+        "Real"
+    } else if has(&["BOOL"]) {
+        "Bool"
+    } else {
+        eprintln!("Not matched on datatype: {}", name);
+        "Invalid"
     }
 }
 
+/// A default must not depend on the row: it may not name a column or run a subquery.
+/// It is evaluated once here so that errors such as an unknown function show up when
+/// the table is created rather than on the first INSERT.
+fn check_default(column: &str, expr: &Expr) -> Result<()> {
+    let mut constant = true;
+    walk_expr(expr, &mut |expr| {
+        if matches!(
+            expr,
+            Expr::Identifier(_)
+                | Expr::CompoundIdentifier(_)
+                | Expr::Subquery(_)
+                | Expr::Exists { .. }
+                | Expr::InSubquery { .. }
+        ) {
+            constant = false;
+        }
+        constant
+    });
+    if !constant {
+        return Err(RUSQLError::Internal(format!(
+            "default value of column [{}] is not constant",
+            column
+        )));
+    }
+    evaluate(expr, &Scope::new(&[], &[]))?;
+    Ok(())
+}
+
+/// Whether columns of `datatype` have an index to enforce UNIQUE with, see
+/// `Index::for_datatype`.
+fn has_index(datatype: &str) -> bool {
+    datatype != "Real" && datatype != "Bool"
+}
+
+fn check_single_primary_key(
+    parsed_columns: &[ParsedColumn],
+    parsed_constraints: &[ParsedConstraint],
+    table_name: &str,
+) -> Result<()> {
+    if parsed_columns.iter().any(|col| col.is_pk)
+        || parsed_constraints
+            .iter()
+            .any(|constraint| constraint.is_primary)
+    {
+        return Err(RUSQLError::Internal(format!(
+            "Table {} already has more than one primary key",
+            table_name
+        )));
+    }
+    Ok(())
+}
+
+/// The PRIMARY KEY, UNIQUE and NOT NULL flags of a column given a UNIQUE or PRIMARY KEY
+/// constraint. Columns without an index leave PRIMARY KEY and UNIQUE unset; their
+/// constraint is kept as a `ParsedConstraint` instead.
 fn handle_unique_option(
     is_primary: bool,
     datatype: &str,
     parsed_columns: &[ParsedColumn],
+    parsed_constraints: &[ParsedConstraint],
     table_name: &str,
 ) -> Result<(bool, bool, bool)> {
-    if datatype != "Real" && datatype != "Bool" {
-        let is_pk = is_primary;
-        if is_primary {
-            if parsed_columns.iter().any(|col| col.is_pk) {
-                return Err(RUSQLError::Internal(format!(
-                    "Table {} already has more than one primary key",
-                    table_name
-                )));
-            }
-            let not_null = true;
-            return Ok((is_pk, true, not_null));
-        }
-        return Ok((is_pk, true, false));
+    if is_primary {
+        check_single_primary_key(parsed_columns, parsed_constraints, table_name)?;
+    }
+    if has_index(datatype) {
+        Ok((is_primary, true, is_primary))
+    } else {
+        Ok((false, false, is_primary))
     }
-    Ok((false, false, false))
 }
@@ -0,0 +1,45 @@
+use crate::error::{RUSQLError, Result};
+use sqlparser::ast::{Expr, Statement, TableFactor};
+
+#[derive(Debug)]
+pub struct DeleteQuery {
+    pub table_name: String,
+    pub alias: Option<String>,
+    pub selection: Option<Expr>,
+}
+
+impl DeleteQuery {
+    pub fn new(statement: &Statement) -> Result<DeleteQuery> {
+        match statement {
+            Statement::Delete {
+                tables,
+                from,
+                using: None,
+                selection,
+                returning: None,
+                order_by,
+                limit: None,
+            } if tables.is_empty() && order_by.is_empty() => match from.as_slice() {
+                [table] if table.joins.is_empty() => match &table.relation {
+                    TableFactor::Table { name, alias, .. } => Ok(DeleteQuery {
+                        table_name: name.to_string(),
+                        alias: alias.as_ref().map(|alias| alias.name.value.clone()),
+                        selection: selection.clone(),
+                    }),
+                    _ => Err(RUSQLError::Internal(
+                        "Can only DELETE FROM a table.".to_string(),
+                    )),
+                },
+                _ => Err(RUSQLError::NotImplemented(
+                    "DELETE from multiple tables is not supported.".to_string(),
+                )),
+            },
+            Statement::Delete { .. } => Err(RUSQLError::NotImplemented(
+                "DELETE only supports a single table with an optional WHERE clause.".to_string(),
+            )),
+            _ => Err(RUSQLError::Internal(
+                "Error Parsing Delete Query.".to_string(),
+            )),
+        }
+    }
+}
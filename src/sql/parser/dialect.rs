@@ -0,0 +1,210 @@
+//! SQL dialect used by RUSQL.
+//! Behaves exactly like sqlparser's `SQLiteDialect` and adds the SQLite operators the
+//! upstream dialect does not parse, such as `GLOB` and SQLite's `trim(x, chars)`, and
+//! parses the JSON operators `->` and `->>` with SQLite's precedence.
+
+use sqlparser::ast::{
+    Expr, Function, FunctionArg, FunctionArgExpr, Ident, JsonOperator, ObjectName, UnaryOperator,
+};
+use sqlparser::dialect::{Dialect, SQLiteDialect};
+use sqlparser::keywords::Keyword;
+use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::Token;
+use std::any::TypeId;
+
+/// Binding power of `GLOB`, the same as sqlparser uses for `LIKE`.
+const GLOB_PREC: u8 = 19;
+
+/// Binding power of `->` and `->>`, the one sqlparser gives them.
+const JSON_ARROW_PREC: u8 = 50;
+
+#[derive(Debug)]
+pub struct RusqlDialect {
+    sqlite: SQLiteDialect,
+}
+
+impl RusqlDialect {
+    pub fn new() -> Self {
+        RusqlDialect {
+            sqlite: SQLiteDialect {},
+        }
+    }
+}
+
+impl Default for RusqlDialect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_word(token: &Token, word: &str) -> bool {
+    matches!(token, Token::Word(w) if w.value.eq_ignore_ascii_case(word))
+}
+
+/// Builds a call to a built-in function, the form infix operators are rewritten into.
+pub fn function_call(name: &str, args: Vec<Expr>) -> Expr {
+    Expr::Function(Function {
+        name: ObjectName(vec![Ident::new(name)]),
+        args: args
+            .into_iter()
+            .map(|arg| FunctionArg::Unnamed(FunctionArgExpr::Expr(arg)))
+            .collect(),
+        filter: None,
+        null_treatment: None,
+        over: None,
+        distinct: false,
+        special: false,
+        order_by: vec![],
+    })
+}
+
+impl Dialect for RusqlDialect {
+    // Report ourselves as SQLite so every `dialect_of!(.. is SQLiteDialect)` check in the
+    // parser keeps applying.
+    fn dialect(&self) -> TypeId {
+        TypeId::of::<SQLiteDialect>()
+    }
+
+    fn is_delimited_identifier_start(&self, ch: char) -> bool {
+        self.sqlite.is_delimited_identifier_start(ch)
+    }
+
+    fn is_identifier_start(&self, ch: char) -> bool {
+        self.sqlite.is_identifier_start(ch)
+    }
+
+    fn is_identifier_part(&self, ch: char) -> bool {
+        self.sqlite.is_identifier_part(ch)
+    }
+
+    fn supports_filter_during_aggregation(&self) -> bool {
+        self.sqlite.supports_filter_during_aggregation()
+    }
+
+    fn supports_start_transaction_modifier(&self) -> bool {
+        self.sqlite.supports_start_transaction_modifier()
+    }
+
+    fn supports_in_empty_list(&self) -> bool {
+        self.sqlite.supports_in_empty_list()
+    }
+
+    /// `TRIM(x, chars)` is parsed as `trim(x, chars)`; every other form of TRIM is left
+    /// to sqlparser.
+    fn parse_prefix(&self, parser: &mut Parser) -> Option<std::result::Result<Expr, ParserError>> {
+        if !is_word(&parser.peek_token().token, "TRIM")
+            || parser.peek_nth_token(1).token != Token::LParen
+            || ["BOTH", "LEADING", "TRAILING"]
+                .iter()
+                .any(|word| is_word(&parser.peek_nth_token(2).token, word))
+        {
+            return None;
+        }
+
+        parser.next_token();
+        Some(parse_trim(parser))
+    }
+
+    fn parse_statement(
+        &self,
+        parser: &mut Parser,
+    ) -> Option<std::result::Result<sqlparser::ast::Statement, ParserError>> {
+        self.sqlite.parse_statement(parser)
+    }
+
+    fn get_next_precedence(&self, parser: &Parser) -> Option<std::result::Result<u8, ParserError>> {
+        let token = parser.peek_token().token;
+        if is_word(&token, "GLOB")
+            || (matches!(&token, Token::Word(w) if w.value.eq_ignore_ascii_case("NOT"))
+                && is_word(&parser.peek_nth_token(1).token, "GLOB"))
+        {
+            return Some(Ok(GLOB_PREC));
+        }
+        None
+    }
+
+    /// `x GLOB pattern` is parsed as `glob(pattern, x)`, which is how SQLite defines it.
+    /// sqlparser parses the whole rest of the expression as the right side of `->` and
+    /// `->>`; here they bind tightly and group to the left, so `a -> 'b' ->> 'c' = 1`
+    /// is `((a -> 'b') ->> 'c') = 1`.
+    fn parse_infix(
+        &self,
+        parser: &mut Parser,
+        expr: &Expr,
+        _precedence: u8,
+    ) -> Option<std::result::Result<Expr, ParserError>> {
+        let operator = match parser.peek_token().token {
+            Token::Arrow => Some(JsonOperator::Arrow),
+            Token::LongArrow => Some(JsonOperator::LongArrow),
+            _ => None,
+        };
+        if let Some(operator) = operator {
+            parser.next_token();
+            // A negative index, as in `a -> -1`, would otherwise take the rest of the
+            // chain as its operand.
+            let right = if parser.peek_token().token == Token::Minus
+                && matches!(parser.peek_nth_token(1).token, Token::Number(..))
+            {
+                parser.next_token();
+                parser.parse_prefix().map(|number| Expr::UnaryOp {
+                    op: UnaryOperator::Minus,
+                    expr: Box::new(number),
+                })
+            } else {
+                parser.parse_subexpr(JSON_ARROW_PREC)
+            };
+            return Some(right.map(|right| Expr::JsonAccess {
+                left: Box::new(expr.clone()),
+                operator,
+                right: Box::new(right),
+            }));
+        }
+
+        let negated = is_word(&parser.peek_token().token, "NOT");
+        let glob_token = if negated { 1 } else { 0 };
+        if !is_word(&parser.peek_nth_token(glob_token).token, "GLOB") {
+            return None;
+        }
+
+        for _ in 0..=glob_token {
+            parser.next_token();
+        }
+        let pattern = match parser.parse_subexpr(GLOB_PREC) {
+            Ok(pattern) => pattern,
+            Err(err) => return Some(Err(err)),
+        };
+
+        let call = function_call("glob", vec![pattern, expr.clone()]);
+        Some(Ok(if negated {
+            Expr::UnaryOp {
+                op: UnaryOperator::Not,
+                expr: Box::new(call),
+            }
+        } else {
+            call
+        }))
+    }
+}
+
+/// The rest of a TRIM call after the TRIM keyword, when it has no BOTH, LEADING or
+/// TRAILING.
+fn parse_trim(parser: &mut Parser) -> std::result::Result<Expr, ParserError> {
+    parser.expect_token(&Token::LParen)?;
+    let expr = parser.parse_expr()?;
+    let (expr, trim_what) = if parser.parse_keyword(Keyword::FROM) {
+        (parser.parse_expr()?, Some(Box::new(expr)))
+    } else if parser.consume_token(&Token::Comma) {
+        let chars = parser.parse_expr()?;
+        parser.expect_token(&Token::RParen)?;
+        return Ok(function_call("trim", vec![expr, chars]));
+    } else {
+        (expr, None)
+    };
+    parser.expect_token(&Token::RParen)?;
+    Ok(Expr::Trim {
+        expr: Box::new(expr),
+        trim_where: None,
+        trim_what,
+        trim_characters: None,
+    })
+}
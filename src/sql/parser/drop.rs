@@ -9,7 +9,7 @@ pub fn drop_table(query: &Statement, db: &mut Database) -> Result<String, RUSQLE
     } = query
     {
         if let sqlparser::ast::ObjectType::Table = object_type {
-            if let Some(table_name) = names.get(0) {
+            if let Some(table_name) = names.first() {
                 db.drop_table(table_name.to_string())?;
                 Ok(String::from("DROP TABLE Statement executed.")
                     .green()
@@ -1,11 +1,13 @@
 use crate::error::{RUSQLError, Result};
-use sqlparser::ast::{Expr, SetExpr, Statement, Value, Values};
+use crate::sql::db::value::Value;
+use crate::sql::executor::expr::{evaluate, Scope};
+use sqlparser::ast::{Expr, SetExpr, Statement, Values};
 
 #[derive(Debug)]
 pub struct InsertQuery {
     pub table_name: String,
     pub columns: Vec<String>,
-    pub rows: Vec<Vec<String>>,
+    pub rows: Vec<Vec<Value>>,
 }
 
 impl InsertQuery {
@@ -18,7 +20,7 @@ impl InsertQuery {
                 ..
             } => {
                 let table_name = table_name.to_string();
-                let columns = columns.iter().map(|col| col.to_string()).collect();
+                let columns = columns.iter().map(|col| col.value.to_string()).collect();
                 let rowvec = extract_values(&query.body)?;
 
                 Ok(InsertQuery {
@@ -34,37 +36,30 @@ impl InsertQuery {
     }
 }
 
-fn extract_values(body: &SetExpr) -> Result<Vec<Vec<String>>> {
+fn extract_values(body: &SetExpr) -> Result<Vec<Vec<Value>>> {
     if let SetExpr::Values(Values {
         explicit_row: _,
         rows,
     }) = body
     {
-        Ok(rows.iter().map(|row| extract_row_values(row)).collect())
+        rows.iter().map(|row| extract_row_values(row)).collect()
     } else {
         Err(RUSQLError::Internal("Error extracting values".to_string()))
     }
 }
 
-fn extract_row_values(row: &[Expr]) -> Vec<String> {
+/// Evaluates each expression of a VALUES row. There is no row in scope, so the row may
+/// hold any constant expression (`-1`, `2 * 3`, `'a' || 'b'`, `NULL`) but no column
+/// references.
+fn extract_row_values(row: &[Expr]) -> Result<Vec<Value>> {
+    let scope = Scope::new(&[], &[]);
     row.iter()
-        .filter_map(|expr| match expr {
-            Expr::Value(v) => Some(match_value(v)),
-            Expr::Identifier(i) => Some(i.to_string()),
-            _ => None,
+        .map(|expr| match expr {
+            // Like SQLite, a double-quoted identifier that names no column is a string.
+            Expr::Identifier(ident) if ident.quote_style == Some('"') => {
+                Ok(Value::Text(ident.value.to_string()))
+            }
+            _ => evaluate(expr, &scope),
         })
         .collect()
 }
-
-fn match_value(value: &Value) -> String {
-    match value {
-        Value::Number(n, _) => n.to_string(),
-        Value::Boolean(b) => b.to_string(),
-        Value::SingleQuotedString(s) => s.to_string(),
-        Value::Null => "Null".to_string(),
-        _ => {
-            eprintln!("Unhandled Value variant: {:?}", value);
-            String::new()
-        }
-    }
-}
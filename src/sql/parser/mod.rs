@@ -1,6 +1,10 @@
 //! SQL parser modules for different statement types.
 
 pub mod create;
+pub mod delete;
+pub mod dialect;
 pub mod drop;
 pub mod insert;
 pub mod list_tables;
+pub mod transaction;
+pub mod update;
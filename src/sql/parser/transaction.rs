@@ -0,0 +1,37 @@
+use crate::sql::RUSQLError;
+use crate::Database;
+use colored::*;
+use sqlparser::ast::Statement;
+
+/// Runs BEGIN, COMMIT, ROLLBACK, SAVEPOINT, RELEASE and ROLLBACK TO.
+pub fn transaction_control(query: &Statement, db: &mut Database) -> Result<String, RUSQLError> {
+    let executed = match query {
+        Statement::StartTransaction { .. } => db.begin().map(|_| "BEGIN"),
+        Statement::Commit { .. } => db.commit().map(|_| "COMMIT"),
+        Statement::Rollback {
+            savepoint: None, ..
+        } => db.rollback().map(|_| "ROLLBACK"),
+        Statement::Rollback {
+            savepoint: Some(name),
+            ..
+        } => db.rollback_to(&name.value).map(|_| "ROLLBACK TO"),
+        Statement::Savepoint { name } => {
+            db.savepoint(&name.value);
+            Ok("SAVEPOINT")
+        }
+        Statement::ReleaseSavepoint { name } => db.release(&name.value).map(|_| "RELEASE"),
+        _ => {
+            return Err(RUSQLError::Internal(
+                "Invalid Transaction Statement".red().to_string(),
+            ))
+        }
+    };
+
+    executed
+        .map(|statement| {
+            format!("{} Statement executed.", statement)
+                .green()
+                .to_string()
+        })
+        .map_err(|err| RUSQLError::Internal(err.to_string().red().to_string()))
+}
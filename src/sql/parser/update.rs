@@ -0,0 +1,51 @@
+use crate::error::{RUSQLError, Result};
+use sqlparser::ast::{Expr, Statement, TableFactor};
+
+#[derive(Debug)]
+pub struct UpdateQuery {
+    pub table_name: String,
+    pub alias: Option<String>,
+    pub assignments: Vec<(String, Expr)>,
+    pub selection: Option<Expr>,
+}
+
+impl UpdateQuery {
+    pub fn new(statement: &Statement) -> Result<UpdateQuery> {
+        match statement {
+            Statement::Update {
+                table,
+                assignments,
+                from: None,
+                selection,
+                returning: None,
+            } if table.joins.is_empty() => match &table.relation {
+                TableFactor::Table { name, alias, .. } => {
+                    let assignments = assignments
+                        .iter()
+                        .map(|assignment| match assignment.id.last() {
+                            Some(column) => Ok((column.value.clone(), assignment.value.clone())),
+                            None => Err(RUSQLError::Internal(
+                                "Missing column in SET clause.".to_string(),
+                            )),
+                        })
+                        .collect::<Result<Vec<(String, Expr)>>>()?;
+
+                    Ok(UpdateQuery {
+                        table_name: name.to_string(),
+                        alias: alias.as_ref().map(|alias| alias.name.value.clone()),
+                        assignments,
+                        selection: selection.clone(),
+                    })
+                }
+                _ => Err(RUSQLError::Internal("Can only UPDATE a table.".to_string())),
+            },
+            Statement::Update { .. } => Err(RUSQLError::NotImplemented(
+                "UPDATE only supports a single table with SET and an optional WHERE clause."
+                    .to_string(),
+            )),
+            _ => Err(RUSQLError::Internal(
+                "Error Parsing Update Query.".to_string(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,310 @@
+mod common;
+
+use common::{select, text};
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+use tempfile::tempdir;
+
+#[cfg(test)]
+mod aggregate_tests {
+    use super::*;
+
+    fn setup_sales(db: &mut Database) {
+        process_command(
+            "CREATE TABLE sales (id INTEGER PRIMARY KEY, region TEXT, rep TEXT, amount INTEGER, rate REAL);",
+            db,
+        )
+        .unwrap();
+        process_command(
+            "INSERT INTO sales (region, rep, amount, rate) VALUES \
+             ('east', 'ann', 100, 0.5), ('west', 'bob', 250, 1.5), ('east', 'cid', NULL, 2.0), \
+             ('east', 'ann', 40, NULL), ('west', 'dee', 250, 0.25), (NULL, 'eve', 10, 1.0);",
+            db,
+        )
+        .unwrap();
+    }
+
+    fn sales() -> Database {
+        let mut db = Database::new("test_db".to_string());
+        setup_sales(&mut db);
+        db
+    }
+
+    #[test]
+    fn test_aggregates_over_whole_table() {
+        let db = sales();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT COUNT(*), COUNT(amount), SUM(amount), AVG(amount), MIN(amount), MAX(amount), TOTAL(amount) FROM sales;"
+            ),
+            vec![vec![
+                Value::Integer(6),
+                Value::Integer(5),
+                Value::Integer(650),
+                Value::Real(130.0),
+                Value::Integer(10),
+                Value::Integer(250),
+                Value::Real(650.0),
+            ]]
+        );
+        assert_eq!(
+            select(&db, "SELECT SUM(rate), MIN(rep), MAX(region) FROM sales;"),
+            vec![vec![Value::Real(5.25), text("ann"), text("west")]]
+        );
+    }
+
+    #[test]
+    fn test_aggregates_over_no_rows() {
+        let db = sales();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT COUNT(*), COUNT(amount), SUM(amount), AVG(amount), MAX(amount), TOTAL(amount), GROUP_CONCAT(rep) FROM sales WHERE amount > 1000;"
+            ),
+            vec![vec![
+                Value::Integer(0),
+                Value::Integer(0),
+                Value::Null,
+                Value::Null,
+                Value::Null,
+                Value::Real(0.0),
+                Value::Null,
+            ]]
+        );
+        // With GROUP BY, no rows means no groups.
+        assert!(select(
+            &db,
+            "SELECT region, COUNT(*) FROM sales WHERE amount > 1000 GROUP BY region;"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_count_distinct_and_group_concat() {
+        let db = sales();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT COUNT(DISTINCT amount), COUNT(DISTINCT rep), SUM(DISTINCT amount) FROM sales;"
+            ),
+            vec![vec![
+                Value::Integer(4),
+                Value::Integer(5),
+                Value::Integer(400)
+            ]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT GROUP_CONCAT(rep), GROUP_CONCAT(DISTINCT rep), GROUP_CONCAT(amount, ' + ') FROM sales WHERE region = 'east';"
+            ),
+            vec![vec![
+                text("ann,cid,ann"),
+                text("ann,cid"),
+                text("100 + 40")
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_group_by() {
+        let db = sales();
+        // Groups come out in key order, the NULL group first.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT region, COUNT(*), SUM(amount) FROM sales GROUP BY region;"
+            ),
+            vec![
+                vec![Value::Null, Value::Integer(1), Value::Integer(10)],
+                vec![text("east"), Value::Integer(3), Value::Integer(140)],
+                vec![text("west"), Value::Integer(2), Value::Integer(500)],
+            ]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT region, rep, COUNT(*) AS n FROM sales WHERE region IS NOT NULL GROUP BY region, rep ORDER BY n DESC, rep;"
+            ),
+            vec![
+                vec![text("east"), text("ann"), Value::Integer(2)],
+                vec![text("west"), text("bob"), Value::Integer(1)],
+                vec![text("east"), text("cid"), Value::Integer(1)],
+                vec![text("west"), text("dee"), Value::Integer(1)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_by_expression_alias_and_position() {
+        let db = sales();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT amount >= 100, COUNT(*) FROM sales WHERE amount IS NOT NULL GROUP BY amount >= 100 ORDER BY 1 DESC;"
+            ),
+            vec![
+                vec![Value::Bool(true), Value::Integer(3)],
+                vec![Value::Bool(false), Value::Integer(2)],
+            ]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT amount > 99 AS big, COUNT(*) FROM sales WHERE amount IS NOT NULL GROUP BY big;"
+            ),
+            vec![
+                vec![Value::Bool(false), Value::Integer(2)],
+                vec![Value::Bool(true), Value::Integer(3)],
+            ]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT amount < 100 AS small, COUNT(*) FROM sales WHERE amount IS NOT NULL GROUP BY 1;"
+            ),
+            vec![
+                vec![Value::Bool(false), Value::Integer(3)],
+                vec![Value::Bool(true), Value::Integer(2)],
+            ]
+        );
+        assert!(run_query("SELECT region FROM sales GROUP BY 2;", &db).is_err());
+    }
+
+    #[test]
+    fn test_having() {
+        let db = sales();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT region, SUM(amount) AS total FROM sales GROUP BY region HAVING COUNT(*) > 1 AND total > 200;"
+            ),
+            vec![vec![text("west"), Value::Integer(500)]]
+        );
+        // HAVING may use aggregates that are not selected, and ORDER BY too.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT rep FROM sales GROUP BY rep HAVING MAX(rate) >= 1 ORDER BY MIN(amount) DESC;"
+            ),
+            vec![vec![text("bob")], vec![text("eve")], vec![text("cid")]]
+        );
+    }
+
+    #[test]
+    fn test_bare_column_follows_min_and_max() {
+        let db = sales();
+        assert_eq!(
+            select(&db, "SELECT rep, MAX(rate) FROM sales;"),
+            vec![vec![text("cid"), Value::Real(2.0)]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT region, rep, MIN(amount) FROM sales WHERE region IS NOT NULL GROUP BY region;"
+            ),
+            vec![
+                vec![text("east"), text("ann"), Value::Integer(40)],
+                vec![text("west"), text("bob"), Value::Integer(250)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_aggregate_expressions_and_filter() {
+        let db = sales();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT SUM(amount) / COUNT(amount), MAX(amount) - MIN(amount), COUNT(*) FILTER (WHERE rate > 1) FROM sales;"
+            ),
+            vec![vec![
+                Value::Integer(130),
+                Value::Integer(240),
+                Value::Integer(2)
+            ]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT COUNT(*) FROM sales WHERE region = 'east' LIMIT 1;"
+            ),
+            vec![vec![Value::Integer(3)]]
+        );
+        assert_eq!(
+            select(&db, "SELECT COUNT(*) + 1;"),
+            vec![vec![Value::Integer(2)]]
+        );
+    }
+
+    #[test]
+    fn test_sum_overflow_and_real_inputs() {
+        let mut db = Database::new("test_db".to_string());
+        process_command("CREATE TABLE n (v INTEGER, r REAL);", &mut db).unwrap();
+        process_command(
+            "INSERT INTO n (v, r) VALUES (2000000000, 0.5), (2000000000, 0.25);",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            select(&db, "SELECT SUM(v), AVG(v), SUM(v + r), TOTAL(r) FROM n;"),
+            vec![vec![
+                Value::Integer(4000000000),
+                Value::Real(2000000000.0),
+                Value::Real(4000000000.75),
+                Value::Real(0.75),
+            ]]
+        );
+        assert!(run_query("SELECT SUM(v * 4611686018) FROM n;", &db).is_err());
+        assert_eq!(
+            select(&db, "SELECT TOTAL(v * 4611686018) > 0 FROM n;"),
+            vec![vec![Value::Bool(true)]]
+        );
+    }
+
+    #[test]
+    fn test_aggregate_misuse_errors() {
+        let db = sales();
+        for sql in [
+            "SELECT * FROM sales WHERE COUNT(*) > 1;",
+            "SELECT region FROM sales GROUP BY COUNT(*);",
+            "SELECT COUNT(MAX(amount)) FROM sales;",
+            "SELECT SUM(amount, rate) FROM sales;",
+            "SELECT AVG(*) FROM sales;",
+            "SELECT COUNT(DISTINCT *) FROM sales;",
+            "SELECT SUM(missing) FROM sales;",
+        ] {
+            assert!(run_query(sql, &db).is_err(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_aggregates_on_file_backed_table() {
+        let dir = tempdir().unwrap();
+        let mut db = Database::open(dir.path().join("sales.db")).unwrap();
+        setup_sales(&mut db);
+        assert_eq!(
+            select(
+                &db,
+                "SELECT region, COUNT(*), MAX(rate), GROUP_CONCAT(rep, '|') FROM sales GROUP BY region;"
+            ),
+            vec![
+                vec![Value::Null, Value::Integer(1), Value::Real(1.0), text("eve")],
+                vec![
+                    text("east"),
+                    Value::Integer(3),
+                    Value::Real(2.0),
+                    text("ann|cid|ann")
+                ],
+                vec![
+                    text("west"),
+                    Value::Integer(2),
+                    Value::Real(1.5),
+                    text("bob|dee")
+                ],
+            ]
+        );
+    }
+}
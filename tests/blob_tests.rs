@@ -0,0 +1,147 @@
+mod common;
+
+use common::{database, select};
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+use tempfile::tempdir;
+
+#[cfg(test)]
+mod blob_tests {
+    use super::*;
+
+    fn setup() -> Database {
+        database(&[
+            "CREATE TABLE files (id INTEGER PRIMARY KEY, name TEXT, digest BLOB UNIQUE, body BYTEA);",
+            "INSERT INTO files (name, digest, body) VALUES \
+             ('a.bin', X'DEADBEEF', X'000102'), ('b.txt', x'cafe', 'hi'), ('empty', X'', NULL);",
+        ])
+    }
+
+    fn blob(bytes: &[u8]) -> Value {
+        Value::Blob(bytes.to_vec())
+    }
+
+    #[test]
+    fn test_hex_literals_and_storage() {
+        let db = setup();
+        assert_eq!(
+            select(&db, "SELECT digest, body FROM files ORDER BY id;"),
+            vec![
+                vec![blob(&[0xDE, 0xAD, 0xBE, 0xEF]), blob(&[0, 1, 2])],
+                // Text stored in a BLOB column keeps its UTF-8 bytes.
+                vec![blob(&[0xCA, 0xFE]), blob(b"hi")],
+                vec![blob(&[]), Value::Null],
+            ]
+        );
+        // Blobs print as hex.
+        assert_eq!(blob(&[0x0A, 0xFF]).to_string(), "0AFF");
+        assert_eq!(
+            select(
+                &db,
+                "SELECT TYPEOF(digest), LENGTH(digest), HEX(body) FROM files WHERE id = 1;"
+            ),
+            vec![vec![
+                Value::Text("blob".to_string()),
+                Value::Integer(4),
+                Value::Text("000102".to_string())
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_blob_comparisons_and_index() {
+        let mut db = setup();
+        assert_eq!(
+            select(&db, "SELECT name FROM files WHERE digest = X'CAFE';"),
+            vec![vec![Value::Text("b.txt".to_string())]]
+        );
+        assert_eq!(
+            select(&db, "SELECT name FROM files ORDER BY digest;"),
+            vec![
+                vec![Value::Text("empty".to_string())],
+                vec![Value::Text("b.txt".to_string())],
+                vec![Value::Text("a.bin".to_string())],
+            ]
+        );
+        // A blob never equals text, even text spelling out the same hex.
+        assert!(select(&db, "SELECT id FROM files WHERE digest = 'CAFE';").is_empty());
+        assert!(process_command(
+            "INSERT INTO files (name, digest) VALUES ('dup', X'deadbeef');",
+            &mut db
+        )
+        .is_err());
+        process_command("CREATE TABLE refs (digest BLOB, note TEXT);", &mut db).unwrap();
+        process_command("INSERT INTO refs VALUES (X'CAFE', 'second');", &mut db).unwrap();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT f.name, r.note FROM refs r JOIN files f ON f.digest = r.digest;"
+            ),
+            vec![vec![
+                Value::Text("b.txt".to_string()),
+                Value::Text("second".to_string())
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_blob_casts_and_errors() {
+        let mut db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT CAST('AB' AS BLOB), CAST(X'4142' AS TEXT), X'41' || 'B';"
+            ),
+            vec![vec![
+                blob(b"AB"),
+                Value::Text("AB".to_string()),
+                Value::Text("AB".to_string())
+            ]]
+        );
+        for sql in ["SELECT X'ABC';", "SELECT X'ZZ';"] {
+            assert!(run_query(sql, &db).is_err(), "{}", sql);
+        }
+        for sql in [
+            "INSERT INTO files (digest) VALUES (42);",
+            "CREATE TABLE t (n INTEGER); INSERT INTO t VALUES (X'01');",
+        ] {
+            let result = sql
+                .split_inclusive(';')
+                .try_for_each(|sql| process_command(sql, &mut db).map(|_| ()));
+            assert!(result.is_err(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_blobs_survive_reopen() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("files.db");
+        let db = setup();
+        db.save(&path).unwrap();
+
+        // A value larger than a page goes to overflow pages.
+        let mut reopened = Database::open(&path).unwrap();
+        let large = "AB".repeat(10_000);
+        process_command(
+            &format!(
+                "INSERT INTO files (name, body) VALUES ('big', X'{}');",
+                large
+            ),
+            &mut reopened,
+        )
+        .unwrap();
+        let reopened = Database::open(&path).unwrap();
+        assert_eq!(
+            select(&reopened, "SELECT digest FROM files WHERE id = 1;"),
+            vec![vec![blob(&[0xDE, 0xAD, 0xBE, 0xEF])]]
+        );
+        assert_eq!(
+            select(
+                &reopened,
+                "SELECT LENGTH(body) FROM files WHERE name = 'big';"
+            ),
+            vec![vec![Value::Integer(10_000)]]
+        );
+    }
+}
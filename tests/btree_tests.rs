@@ -0,0 +1,139 @@
+use rusql::sql::db::btree::{BTree, MAX_INLINE};
+use rusql::sql::db::pager::Pager;
+use tempfile::tempdir;
+
+#[cfg(test)]
+mod btree_tests {
+    use super::*;
+
+    fn value_for(key: i64) -> Vec<u8> {
+        format!("value-{}", key).into_bytes()
+    }
+
+    #[test]
+    fn test_insert_get_and_overwrite() {
+        let dir = tempdir().unwrap();
+        let mut pager = Pager::create(&dir.path().join("t.db"), 16).unwrap();
+        let tree = BTree::create(&mut pager).unwrap();
+
+        tree.insert(&mut pager, 7, b"seven").unwrap();
+        tree.insert(&mut pager, -3, b"minus three").unwrap();
+        assert_eq!(tree.get(&mut pager, 7).unwrap(), Some(b"seven".to_vec()));
+        assert_eq!(tree.get(&mut pager, 8).unwrap(), None);
+
+        tree.insert(&mut pager, 7, b"SEVEN").unwrap();
+        assert_eq!(tree.get(&mut pager, 7).unwrap(), Some(b"SEVEN".to_vec()));
+        assert_eq!(tree.keys(&mut pager).unwrap(), vec![-3, 7]);
+    }
+
+    #[test]
+    fn test_splits_keep_keys_ordered_with_tiny_cache() {
+        let dir = tempdir().unwrap();
+        let mut pager = Pager::create(&dir.path().join("t.db"), 3).unwrap();
+        let tree = BTree::create(&mut pager).unwrap();
+
+        // Insert in a scrambled order so splits happen all over the tree.
+        let keys: Vec<i64> = (0..5000).map(|i| (i * 7919) % 5000).collect();
+        for key in &keys {
+            tree.insert(&mut pager, *key, &value_for(*key)).unwrap();
+        }
+        assert!(pager.cached_pages() <= 3);
+        assert!(pager.page_count() > 10);
+
+        assert_eq!(
+            tree.keys(&mut pager).unwrap(),
+            (0..5000).collect::<Vec<_>>()
+        );
+        for key in [0, 1, 2499, 4999] {
+            assert_eq!(tree.get(&mut pager, key).unwrap(), Some(value_for(key)));
+        }
+    }
+
+    #[test]
+    fn test_delete_removes_keys() {
+        let dir = tempdir().unwrap();
+        let mut pager = Pager::create(&dir.path().join("t.db"), 8).unwrap();
+        let tree = BTree::create(&mut pager).unwrap();
+        for key in 0..1000 {
+            tree.insert(&mut pager, key, &value_for(key)).unwrap();
+        }
+
+        for key in (0..1000).filter(|key| key % 3 != 0) {
+            assert!(tree.delete(&mut pager, key).unwrap());
+        }
+        assert!(!tree.delete(&mut pager, 1).unwrap());
+        assert!(!tree.contains(&mut pager, 500).unwrap());
+        assert!(tree.contains(&mut pager, 501).unwrap());
+        assert_eq!(
+            tree.keys(&mut pager).unwrap(),
+            (0..1000).filter(|key| key % 3 == 0).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_overflow_values_round_trip_and_free_pages() {
+        let dir = tempdir().unwrap();
+        let mut pager = Pager::create(&dir.path().join("t.db"), 4).unwrap();
+        let tree = BTree::create(&mut pager).unwrap();
+
+        let big: Vec<u8> = (0..20_000).map(|i| (i % 251) as u8).collect();
+        assert!(big.len() > MAX_INLINE);
+        tree.insert(&mut pager, 1, &big).unwrap();
+        assert_eq!(tree.get(&mut pager, 1).unwrap(), Some(big.clone()));
+
+        // Replacing the value frees its overflow chain, which the next one reuses.
+        let page_count = pager.page_count();
+        tree.insert(&mut pager, 1, b"small").unwrap();
+        tree.insert(&mut pager, 2, &big).unwrap();
+        assert_eq!(pager.page_count(), page_count);
+        assert_eq!(tree.get(&mut pager, 2).unwrap(), Some(big));
+    }
+
+    #[test]
+    fn test_tree_survives_reopen() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("t.db");
+        let root = {
+            let mut pager = Pager::create(&path, 4).unwrap();
+            let tree = BTree::create(&mut pager).unwrap();
+            for key in 0..2000 {
+                tree.insert(&mut pager, key, &value_for(key)).unwrap();
+            }
+            pager.set_catalog_root(tree.root());
+            pager.commit().unwrap();
+            tree.root()
+        };
+
+        let mut pager = Pager::open(&path, 4).unwrap();
+        assert_eq!(pager.catalog_root(), root);
+        let tree = BTree::open(root);
+        assert_eq!(tree.keys(&mut pager).unwrap().len(), 2000);
+        assert_eq!(tree.get(&mut pager, 1234).unwrap(), Some(value_for(1234)));
+    }
+
+    #[test]
+    fn test_destroy_returns_pages_to_freelist() {
+        let dir = tempdir().unwrap();
+        let mut pager = Pager::create(&dir.path().join("t.db"), 8).unwrap();
+        let tree = BTree::create(&mut pager).unwrap();
+        for key in 0..2000 {
+            tree.insert(&mut pager, key, &value_for(key)).unwrap();
+        }
+        let page_count = pager.page_count();
+        tree.destroy(&mut pager).unwrap();
+
+        let tree = BTree::create(&mut pager).unwrap();
+        for key in 0..2000 {
+            tree.insert(&mut pager, key, &value_for(key)).unwrap();
+        }
+        assert_eq!(pager.page_count(), page_count);
+    }
+
+    #[test]
+    fn test_pager_rejects_out_of_range_page() {
+        let dir = tempdir().unwrap();
+        let mut pager = Pager::create(&dir.path().join("t.db"), 8).unwrap();
+        assert!(pager.read(0).is_err());
+        assert!(pager.read(42).is_err());
+    }
+}
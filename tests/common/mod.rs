@@ -0,0 +1,70 @@
+//! Helpers shared by the SQL test suites. Every test file is its own crate and uses only
+//! some of them.
+#![allow(dead_code)]
+
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+
+/// A new in-memory database with `statements` executed in order.
+pub fn database(statements: &[&str]) -> Database {
+    let mut db = Database::new("test_db".to_string());
+    for sql in statements {
+        run(&mut db, sql);
+    }
+    db
+}
+
+/// Executes a statement that is expected to succeed.
+pub fn run(db: &mut Database, sql: &str) {
+    process_command(sql, db).unwrap();
+}
+
+/// Every row of a query.
+pub fn select(db: &Database, sql: &str) -> Vec<Vec<Value>> {
+    run_query(sql, db).unwrap().rows
+}
+
+/// The first column of every row of a query.
+pub fn column(db: &Database, sql: &str) -> Vec<Value> {
+    select(db, sql)
+        .into_iter()
+        .map(|mut row| row.remove(0))
+        .collect()
+}
+
+/// The first column of every row of a query, which holds integers.
+pub fn integers(db: &Database, sql: &str) -> Vec<i64> {
+    column(db, sql)
+        .into_iter()
+        .map(|value| match value {
+            Value::Integer(n) => n,
+            other => panic!("expected an integer, got {}", other),
+        })
+        .collect()
+}
+
+/// The single value of a query returning one row and one column.
+pub fn value(db: &Database, sql: &str) -> Value {
+    let rows = select(db, sql);
+    assert_eq!(rows.len(), 1, "{}", sql);
+    rows[0][0].clone()
+}
+
+/// The value of `expr` selected without a table.
+pub fn eval(expr: &str) -> Value {
+    let db = Database::new("test_db".to_string());
+    value(&db, &format!("SELECT {};", expr))
+}
+
+pub fn text(s: &str) -> Value {
+    Value::Text(s.to_string())
+}
+
+/// Integers, with `None` for NULL.
+pub fn ints(values: &[Option<i64>]) -> Vec<Value> {
+    values
+        .iter()
+        .map(|value| value.map_or(Value::Null, Value::Integer))
+        .collect()
+}
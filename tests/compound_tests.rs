@@ -0,0 +1,177 @@
+mod common;
+
+use common::{column, database, ints, select};
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::run_query;
+
+#[cfg(test)]
+mod compound_tests {
+    use super::*;
+
+    fn setup() -> Database {
+        database(&[
+            "CREATE TABLE a (n INTEGER, s TEXT);",
+            "CREATE TABLE b (m INTEGER, r REAL);",
+            "INSERT INTO a (n, s) VALUES (1, 'x'), (2, 'y'), (2, 'y'), (3, 'z'), (NULL, 'w');",
+            "INSERT INTO b (m, r) VALUES (2, 2.0), (2, 2.5), (4, 4.0), (NULL, 0.5), (1, 1.0);",
+        ])
+    }
+
+    #[test]
+    fn test_union_and_union_all() {
+        let db = setup();
+        let result = run_query("SELECT n AS num FROM a UNION ALL SELECT m FROM b;", &db).unwrap();
+        assert_eq!(result.columns, vec!["num"]);
+        assert_eq!(
+            result
+                .rows
+                .into_iter()
+                .map(|row| row[0].clone())
+                .collect::<Vec<_>>(),
+            ints(&[
+                Some(1),
+                Some(2),
+                Some(2),
+                Some(3),
+                None,
+                Some(2),
+                Some(2),
+                Some(4),
+                None,
+                Some(1)
+            ])
+        );
+        // Duplicates, NULLs included, appear once, where they first appear.
+        assert_eq!(
+            column(&db, "SELECT n FROM a UNION SELECT m FROM b;"),
+            ints(&[Some(1), Some(2), Some(3), None, Some(4)])
+        );
+    }
+
+    #[test]
+    fn test_intersect_and_except() {
+        let db = setup();
+        assert_eq!(
+            column(&db, "SELECT n FROM a INTERSECT SELECT m FROM b;"),
+            ints(&[Some(1), Some(2), None])
+        );
+        assert_eq!(
+            column(&db, "SELECT n FROM a EXCEPT SELECT m FROM b;"),
+            ints(&[Some(3)])
+        );
+        assert_eq!(
+            column(&db, "SELECT m FROM b EXCEPT SELECT n FROM a;"),
+            ints(&[Some(4)])
+        );
+        // Numbers compare by value across types.
+        assert_eq!(
+            column(&db, "SELECT r FROM b INTERSECT SELECT n FROM a;"),
+            vec![Value::Real(2.0), Value::Real(1.0)]
+        );
+    }
+
+    #[test]
+    fn test_all_forms_keep_counts() {
+        let db = setup();
+        assert_eq!(
+            column(&db, "SELECT n FROM a INTERSECT ALL SELECT m FROM b;"),
+            ints(&[Some(1), Some(2), Some(2), None])
+        );
+        assert_eq!(
+            column(
+                &db,
+                "SELECT m FROM b EXCEPT ALL SELECT n FROM a WHERE s <> 'y';"
+            ),
+            ints(&[Some(2), Some(2), Some(4)])
+        );
+    }
+
+    #[test]
+    fn test_order_by_and_limit_apply_to_compound() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT n, s FROM a UNION SELECT m, 'b' FROM b ORDER BY 1 DESC, 2 LIMIT 3;"
+            ),
+            vec![
+                vec![Value::Integer(4), Value::Text("b".to_string())],
+                vec![Value::Integer(3), Value::Text("z".to_string())],
+                vec![Value::Integer(2), Value::Text("b".to_string())],
+            ]
+        );
+        assert_eq!(
+            column(
+                &db,
+                "SELECT n FROM a WHERE n > 1 UNION ALL SELECT m FROM b ORDER BY n NULLS LAST LIMIT 3 OFFSET 4;"
+            ),
+            ints(&[Some(2), Some(3), Some(4)])
+        );
+        // A parenthesised side keeps its own ORDER BY and LIMIT.
+        assert_eq!(
+            column(
+                &db,
+                "(SELECT n FROM a ORDER BY n DESC LIMIT 1) UNION ALL SELECT m FROM b WHERE m = 4;"
+            ),
+            ints(&[Some(3), Some(4)])
+        );
+        // ORDER BY must name an output column.
+        assert!(run_query("SELECT n FROM a UNION SELECT m FROM b ORDER BY s;", &db).is_err());
+    }
+
+    #[test]
+    fn test_chained_compounds_and_values() {
+        let db = setup();
+        assert_eq!(
+            column(
+                &db,
+                "SELECT n FROM a UNION SELECT m FROM b EXCEPT SELECT 2 ORDER BY 1;"
+            ),
+            ints(&[None, Some(1), Some(3), Some(4)])
+        );
+        let result = run_query(
+            "VALUES (1, 'one'), (2, 'two') UNION ALL SELECT 3, 'three';",
+            &db,
+        )
+        .unwrap();
+        assert_eq!(result.columns, vec!["column1", "column2"]);
+        assert_eq!(result.rows.len(), 3);
+        assert_eq!(
+            column(&db, "SELECT n FROM a INTERSECT VALUES (3), (5);"),
+            ints(&[Some(3)])
+        );
+    }
+
+    #[test]
+    fn test_compound_in_subqueries() {
+        let db = setup();
+        assert_eq!(
+            column(
+                &db,
+                "SELECT s FROM a WHERE n IN (SELECT m FROM b EXCEPT SELECT 1);"
+            ),
+            vec![Value::Text("y".to_string()), Value::Text("y".to_string())]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT COUNT(*) FROM (SELECT n FROM a UNION SELECT m FROM b) t;"
+            ),
+            vec![vec![Value::Integer(5)]]
+        );
+    }
+
+    #[test]
+    fn test_column_count_mismatch() {
+        let db = setup();
+        for sql in [
+            "SELECT n, s FROM a UNION SELECT m FROM b;",
+            "SELECT n FROM a INTERSECT SELECT m, r FROM b;",
+            "SELECT n FROM a EXCEPT ALL SELECT * FROM b;",
+            "VALUES (1, 2), (3);",
+        ] {
+            assert!(run_query(sql, &db).is_err(), "{}", sql);
+        }
+    }
+}
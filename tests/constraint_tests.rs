@@ -0,0 +1,191 @@
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+use tempfile::tempdir;
+
+#[cfg(test)]
+mod constraint_tests {
+    use super::*;
+
+    fn setup(db: &mut Database) {
+        for sql in [
+            "CREATE TABLE members (tenant_id INTEGER, user_id INTEGER, email TEXT, score REAL, \
+             PRIMARY KEY (tenant_id, user_id), UNIQUE (tenant_id, email));",
+            "INSERT INTO members (tenant_id, user_id, email, score) VALUES \
+             (1, 1, 'a@x.io', 1.5), (1, 2, 'b@x.io', 2.5), (2, 1, 'a@x.io', 1.5);",
+        ] {
+            process_command(sql, db).unwrap();
+        }
+    }
+
+    fn count(db: &Database) -> Value {
+        run_query("SELECT COUNT(*) FROM members;", db).unwrap().rows[0][0].clone()
+    }
+
+    #[test]
+    fn test_composite_keys_on_insert() {
+        let mut db = Database::new("test_db".to_string());
+        setup(&mut db);
+        assert_eq!(count(&db), Value::Integer(3));
+
+        let err = process_command(
+            "INSERT INTO members (tenant_id, user_id, email) VALUES (1, 2, 'c@x.io');",
+            &mut db,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("(tenant_id, user_id)"), "{}", err);
+        for sql in [
+            "INSERT INTO members (tenant_id, user_id, email) VALUES (1, 3, 'b@x.io');",
+            "INSERT INTO members (tenant_id, email) VALUES (3, 'c@x.io');",
+            // The second row clashes with the first row of the same statement.
+            "INSERT INTO members (tenant_id, user_id) VALUES (5, 5), (5, 5);",
+        ] {
+            assert!(process_command(sql, &mut db).is_err(), "{}", sql);
+        }
+        assert_eq!(count(&db), Value::Integer(3));
+
+        // Rows with a NULL in a UNIQUE key never conflict.
+        process_command(
+            "INSERT INTO members (tenant_id, user_id) VALUES (1, 3), (1, 4);",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(count(&db), Value::Integer(5));
+    }
+
+    #[test]
+    fn test_composite_keys_on_update_and_delete() {
+        let mut db = Database::new("test_db".to_string());
+        setup(&mut db);
+        for sql in [
+            "UPDATE members SET user_id = 1 WHERE email = 'b@x.io';",
+            "UPDATE members SET tenant_id = 1 WHERE tenant_id = 2;",
+        ] {
+            assert!(process_command(sql, &mut db).is_err(), "{}", sql);
+        }
+
+        // A row may keep its own key, and freed keys can be reused.
+        process_command("UPDATE members SET score = 9 WHERE tenant_id = 1;", &mut db).unwrap();
+        process_command(
+            "UPDATE members SET email = 'c@x.io' WHERE user_id = 2;",
+            &mut db,
+        )
+        .unwrap();
+        process_command("DELETE FROM members WHERE tenant_id = 2;", &mut db).unwrap();
+        process_command(
+            "INSERT INTO members (tenant_id, user_id, email) VALUES (1, 3, 'b@x.io');",
+            &mut db,
+        )
+        .unwrap();
+        process_command(
+            "UPDATE members SET tenant_id = 2 WHERE user_id = 1;",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            run_query(
+                "SELECT tenant_id, user_id, email FROM members ORDER BY user_id;",
+                &db
+            )
+            .unwrap()
+            .rows,
+            vec![
+                vec![
+                    Value::Integer(2),
+                    Value::Integer(1),
+                    Value::Text("a@x.io".to_string())
+                ],
+                vec![
+                    Value::Integer(1),
+                    Value::Integer(2),
+                    Value::Text("c@x.io".to_string())
+                ],
+                vec![
+                    Value::Integer(1),
+                    Value::Integer(3),
+                    Value::Text("b@x.io".to_string())
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_column_keys_of_every_type() {
+        let mut db = Database::new("test_db".to_string());
+        for sql in [
+            "CREATE TABLE readings (r REAL, d DECIMAL(6, 2), flag BOOL UNIQUE, \
+             UNIQUE (r), PRIMARY KEY (d));",
+            "INSERT INTO readings (r, d, flag) VALUES (1.5, 1, true), (2.5, 2, false), (NULL, 3, NULL);",
+        ] {
+            process_command(sql, &mut db).unwrap();
+        }
+        for sql in [
+            "INSERT INTO readings (r, d) VALUES (1.5, 4);",
+            "INSERT INTO readings (r, d) VALUES (4.5, '1.00');",
+            "INSERT INTO readings (r, d) VALUES (4.5, NULL);",
+            "INSERT INTO readings (d, flag) VALUES (4, true);",
+            "UPDATE readings SET r = 2.5 WHERE d = 1;",
+        ] {
+            assert!(process_command(sql, &mut db).is_err(), "{}", sql);
+        }
+        process_command(
+            "INSERT INTO readings (r, d) VALUES (NULL, 4), (4.5, 5);",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            run_query("SELECT COUNT(*) FROM readings;", &db)
+                .unwrap()
+                .rows,
+            vec![vec![Value::Integer(5)]]
+        );
+    }
+
+    #[test]
+    fn test_rolled_back_rows_free_their_keys() {
+        let mut db = Database::new("test_db".to_string());
+        setup(&mut db);
+        for sql in [
+            "BEGIN;",
+            "DELETE FROM members WHERE user_id = 2;",
+            "INSERT INTO members (tenant_id, user_id, email) VALUES (3, 3, 'd@x.io');",
+            "ROLLBACK;",
+        ] {
+            process_command(sql, &mut db).unwrap();
+        }
+        for sql in [
+            "INSERT INTO members (tenant_id, user_id) VALUES (1, 2);",
+            "INSERT INTO members (tenant_id, email) VALUES (1, 'b@x.io');",
+        ] {
+            assert!(process_command(sql, &mut db).is_err(), "{}", sql);
+        }
+        process_command(
+            "INSERT INTO members (tenant_id, user_id, email) VALUES (3, 3, 'd@x.io');",
+            &mut db,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_composite_keys_survive_reopen() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("members.db");
+        let mut db = Database::open(&path).unwrap();
+        setup(&mut db);
+        db.close().unwrap();
+        drop(db);
+
+        let mut reopened = Database::open(&path).unwrap();
+        assert!(process_command(
+            "INSERT INTO members (tenant_id, user_id, score) VALUES (2, 1, 0.5);",
+            &mut reopened
+        )
+        .is_err());
+        process_command(
+            "INSERT INTO members (tenant_id, user_id, score) VALUES (2, 2, 0.5);",
+            &mut reopened,
+        )
+        .unwrap();
+        assert_eq!(count(&reopened), Value::Integer(4));
+    }
+}
@@ -0,0 +1,240 @@
+mod common;
+
+use common::{database, integers, select, text};
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+
+#[cfg(test)]
+mod cte_tests {
+    use super::*;
+
+    fn setup() -> Database {
+        database(&[
+            "CREATE TABLE staff (id INTEGER PRIMARY KEY, name TEXT, manager INTEGER);",
+            "INSERT INTO staff (name, manager) VALUES \
+             ('ceo', NULL), ('cto', 1), ('cfo', 1), ('dev', 2), ('intern', 4), ('clerk', 3);",
+            "CREATE TABLE parts (assembly TEXT, part TEXT, qty INTEGER);",
+            "INSERT INTO parts (assembly, part, qty) VALUES \
+             ('bike', 'wheel', 2), ('bike', 'frame', 1), ('wheel', 'spoke', 32), ('wheel', 'rim', 1);",
+        ])
+    }
+
+    #[test]
+    fn test_with_clause() {
+        let db = setup();
+        let result = run_query(
+            "WITH bosses AS (SELECT id, name FROM staff WHERE manager IS NULL OR manager = 1) \
+             SELECT name FROM bosses WHERE id > 1;",
+            &db,
+        )
+        .unwrap();
+        assert_eq!(result.columns, vec!["name"]);
+        assert_eq!(result.rows, vec![vec![text("cto")], vec![text("cfo")]]);
+        // Later tables see earlier ones, and columns can be renamed.
+        assert_eq!(
+            select(
+                &db,
+                "WITH a(n) AS (SELECT id FROM staff WHERE id < 4), b(n, sq) AS (SELECT n, n * n FROM a) \
+                 SELECT b.sq FROM b JOIN a ON a.n = b.n ORDER BY 1 DESC;"
+            ),
+            vec![
+                vec![Value::Integer(9)],
+                vec![Value::Integer(4)],
+                vec![Value::Integer(1)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_common_table_in_subqueries() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "WITH managers AS (SELECT manager FROM staff) \
+                 SELECT name FROM staff WHERE id IN (SELECT * FROM managers) ORDER BY id;"
+            ),
+            vec![
+                vec![text("ceo")],
+                vec![text("cto")],
+                vec![text("cfo")],
+                vec![text("dev")],
+            ]
+        );
+        // A WITH inside a subquery, and a common table hiding a stored one.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT (WITH t AS (SELECT COUNT(*) FROM staff) SELECT * FROM t), \
+                 (WITH staff AS (SELECT 1) SELECT COUNT(*) FROM staff);"
+            ),
+            vec![vec![Value::Integer(6), Value::Integer(1)]]
+        );
+    }
+
+    #[test]
+    fn test_recursive_counter() {
+        let db = setup();
+        assert_eq!(
+            integers(
+                &db,
+                "WITH RECURSIVE cnt(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM cnt WHERE n < 5) \
+                 SELECT n FROM cnt;"
+            ),
+            vec![1, 2, 3, 4, 5]
+        );
+        // LIMIT on the table ends a recursion that would not stop by itself.
+        assert_eq!(
+            integers(
+                &db,
+                "WITH RECURSIVE pow(n) AS (SELECT 1 UNION ALL SELECT n * 2 FROM pow LIMIT 6) \
+                 SELECT SUM(n) FROM pow;"
+            ),
+            vec![63]
+        );
+        // So does the LIMIT of a query that only scans the table.
+        assert_eq!(
+            integers(
+                &db,
+                "WITH RECURSIVE c(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM c) \
+                 SELECT n FROM c LIMIT 3;"
+            ),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            integers(
+                &db,
+                "WITH RECURSIVE c(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM c LIMIT 10 OFFSET 2) \
+                 SELECT n * 10 AS tens FROM c LIMIT 2 OFFSET 1;"
+            ),
+            vec![40, 50]
+        );
+    }
+
+    #[test]
+    fn test_runaway_recursion_fails() {
+        let db = setup();
+        // The query needs every row to count them, so the recursion is not limited.
+        let err = run_query(
+            "WITH RECURSIVE c(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM c) \
+             SELECT COUNT(*) FROM c LIMIT 3;",
+            &db,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("more than 1000000 rows"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_recursive_org_chart() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "WITH RECURSIVE chain(id, name, depth) AS ( \
+                   SELECT id, name, 0 FROM staff WHERE manager IS NULL \
+                   UNION ALL \
+                   SELECT s.id, s.name, c.depth + 1 FROM staff s JOIN chain c ON s.manager = c.id) \
+                 SELECT name, depth FROM chain ORDER BY depth, name;"
+            ),
+            vec![
+                vec![text("ceo"), Value::Integer(0)],
+                vec![text("cfo"), Value::Integer(1)],
+                vec![text("cto"), Value::Integer(1)],
+                vec![text("clerk"), Value::Integer(2)],
+                vec![text("dev"), Value::Integer(2)],
+                vec![text("intern"), Value::Integer(3)],
+            ]
+        );
+        // Everyone above the intern.
+        assert_eq!(
+            integers(
+                &db,
+                "WITH RECURSIVE up(id) AS (SELECT manager FROM staff WHERE name = 'intern' \
+                   UNION SELECT manager FROM staff JOIN up ON staff.id = up.id WHERE manager IS NOT NULL) \
+                 SELECT id FROM up;"
+            ),
+            vec![4, 2, 1]
+        );
+    }
+
+    #[test]
+    fn test_recursive_bill_of_materials() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "WITH RECURSIVE bom(part, qty) AS ( \
+                   SELECT part, qty FROM parts WHERE assembly = 'bike' \
+                   UNION ALL \
+                   SELECT p.part, p.qty * bom.qty FROM parts p JOIN bom ON p.assembly = bom.part) \
+                 SELECT part, SUM(qty) FROM bom GROUP BY part;"
+            ),
+            vec![
+                vec![text("frame"), Value::Integer(1)],
+                vec![text("rim"), Value::Integer(2)],
+                vec![text("spoke"), Value::Integer(64)],
+                vec![text("wheel"), Value::Integer(2)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_union_ends_cycles() {
+        let mut db = setup();
+        process_command("CREATE TABLE edges (a INTEGER, b INTEGER);", &mut db).unwrap();
+        process_command(
+            "INSERT INTO edges (a, b) VALUES (1, 2), (2, 3), (3, 1), (3, 4);",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            integers(
+                &db,
+                "WITH RECURSIVE reach(n) AS (SELECT 1 UNION SELECT b FROM edges JOIN reach ON a = n) \
+                 SELECT n FROM reach;"
+            ),
+            vec![1, 2, 3, 4]
+        );
+        // A step that does not read the table runs once.
+        assert_eq!(
+            integers(
+                &db,
+                "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT 2) SELECT n FROM t;"
+            ),
+            vec![1, 2]
+        );
+    }
+
+    #[test]
+    fn test_cte_errors() {
+        let db = setup();
+        for sql in [
+            "WITH t(a, b) AS (SELECT 1) SELECT * FROM t;",
+            "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n, n FROM t WHERE n < 3) SELECT * FROM t;",
+            "WITH t AS (SELECT missing FROM staff) SELECT * FROM t;",
+            "WITH t AS (SELECT 1) SELECT * FROM u;",
+        ] {
+            assert!(run_query(sql, &db).is_err(), "{}", sql);
+        }
+
+        for sql in [
+            "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL \
+             SELECT n + 1 FROM staff WHERE n < (SELECT MAX(n) FROM t)) SELECT * FROM t;",
+            "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL \
+             SELECT x.n + 1 FROM (SELECT n FROM t) AS x WHERE x.n < 3) SELECT * FROM t;",
+        ] {
+            let err = run_query(sql, &db).unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains("recursive reference in a subquery: t"),
+                "{}",
+                err
+            );
+        }
+    }
+}
@@ -24,7 +24,9 @@ mod database_tests {
                 is_pk: true,
                 not_null: true,
                 is_unique: true,
+                default: None,
             }],
+            constraints: vec![],
         };
         let table = Table::new(create_query);
         db.tables.insert("users".to_string(), table);
@@ -44,7 +46,9 @@ mod database_tests {
                 is_pk: true,
                 not_null: true,
                 is_unique: true,
+                default: None,
             }],
+            constraints: vec![],
         };
         let table = Table::new(create_query);
         db.tables.insert("users".to_string(), table);
@@ -67,7 +71,9 @@ mod database_tests {
                 is_pk: true,
                 not_null: true,
                 is_unique: true,
+                default: None,
             }],
+            constraints: vec![],
         };
         let table = Table::new(create_query);
         db.tables.insert("users".to_string(), table);
@@ -90,7 +96,9 @@ mod database_tests {
                 is_pk: true,
                 not_null: true,
                 is_unique: true,
+                default: None,
             }],
+            constraints: vec![],
         };
         let table = Table::new(create_query);
         db.tables.insert("users".to_string(), table);
@@ -117,7 +125,9 @@ mod database_tests {
                 is_pk: true,
                 not_null: true,
                 is_unique: true,
+                default: None,
             }],
+            constraints: vec![],
         };
         let table1 = Table::new(create_query1);
         db.tables.insert("users".to_string(), table1);
@@ -130,7 +140,9 @@ mod database_tests {
                 is_pk: true,
                 not_null: true,
                 is_unique: true,
+                default: None,
             }],
+            constraints: vec![],
         };
         let table2 = Table::new(create_query2);
         db.tables.insert("posts".to_string(), table2);
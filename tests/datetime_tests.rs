@@ -0,0 +1,189 @@
+mod common;
+
+use common::{eval, text};
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+
+#[cfg(test)]
+mod datetime_tests {
+    use super::*;
+
+    #[test]
+    fn test_parsing_time_values() {
+        assert_eq!(eval("date('2024-03-15 10:20:30')"), text("2024-03-15"));
+        assert_eq!(eval("time('2024-03-15T10:20')"), text("10:20:00"));
+        assert_eq!(
+            eval("datetime('2024-03-15T10:00:00+02:00')"),
+            text("2024-03-15 08:00:00")
+        );
+        assert_eq!(
+            eval("datetime('2024-03-15 10:00:00Z')"),
+            text("2024-03-15 10:00:00")
+        );
+        assert_eq!(eval("datetime('12:34')"), text("2000-01-01 12:34:00"));
+        assert_eq!(eval("time('12:34:56.789')"), text("12:34:56"));
+        assert_eq!(eval("time('12:34:56.789', 'subsec')"), text("12:34:56.789"));
+        assert_eq!(eval("date(2451545.0)"), text("2000-01-01"));
+        assert_eq!(
+            eval("datetime(1700000000, 'unixepoch')"),
+            text("2023-11-14 22:13:20")
+        );
+        for expr in [
+            "date('garbage')",
+            "date('2024-13-01')",
+            "time('25:00')",
+            "date(NULL)",
+            "date('2024-03-15', NULL)",
+            "date('2024-03-15', 'bogus')",
+            "date('2024-03-15', 'unixepoch')",
+        ] {
+            assert_eq!(eval(expr), Value::Null, "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_modifiers() {
+        for (expr, expected) in [
+            ("date('2024-03-15', '+1 day')", "2024-03-16"),
+            ("date('2024-03-15', '-15 days')", "2024-02-29"),
+            ("datetime('2024-03-15', '-1.5 days')", "2024-03-13 12:00:00"),
+            (
+                "datetime('2024-03-15 23:30', '+45 minutes')",
+                "2024-03-16 00:15:00",
+            ),
+            (
+                "datetime('2024-03-15', '+90 seconds')",
+                "2024-03-15 00:01:30",
+            ),
+            ("time('10:00', '-3 hours')", "07:00:00"),
+            // The day of the month is kept and overflows into the next month.
+            ("date('2024-01-31', '+1 month')", "2024-03-02"),
+            ("date('2024-02-29', '+1 year')", "2025-03-01"),
+            ("date('2024-03-15', '-14 months')", "2023-01-15"),
+            ("date('2024-03-15 10:20', 'start of month')", "2024-03-01"),
+            (
+                "datetime('2024-03-15 10:20', 'start of day')",
+                "2024-03-15 00:00:00",
+            ),
+            (
+                "date('2024-03-15', 'start of year', '+1 year', '-1 day')",
+                "2024-12-31",
+            ),
+            // 2024-03-15 is a Friday.
+            ("date('2024-03-15', 'weekday 0')", "2024-03-17"),
+            ("date('2024-03-15', 'weekday 5')", "2024-03-15"),
+            ("date('2024-03-15', 'weekday 1')", "2024-03-18"),
+        ] {
+            assert_eq!(eval(expr), text(expected), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_numeric_results() {
+        assert_eq!(
+            eval("julianday('2000-01-01 12:00:00')"),
+            Value::Real(2451545.0)
+        );
+        assert_eq!(eval("unixepoch('1970-01-02')"), Value::Integer(86400));
+        assert_eq!(
+            eval("unixepoch('1970-01-01 00:00:01.250', 'subsec')"),
+            Value::Real(1.25)
+        );
+        assert_eq!(
+            eval("julianday('2024-03-15') - julianday('2024-03-01')"),
+            Value::Real(14.0)
+        );
+    }
+
+    #[test]
+    fn test_strftime() {
+        for (expr, expected) in [
+            (
+                "strftime('%Y/%m/%d %H:%M', '2024-03-15 10:20:30')",
+                "2024/03/15 10:20",
+            ),
+            ("strftime('%j %w %u %W', '2024-03-15')", "075 5 5 11"),
+            ("strftime('%s', '2024-03-15')", "1710460800"),
+            ("strftime('%I:%M %p', '2024-03-15 15:05')", "03:05 PM"),
+            (
+                "strftime('%F %T', '2024-03-15 07:08:09')",
+                "2024-03-15 07:08:09",
+            ),
+            ("strftime('%S.%f', '10:20:30.5')", "30.30.500"),
+            ("strftime('%e|%k|100%%', '2024-03-05 07:00')", " 5| 7|100%"),
+        ] {
+            assert_eq!(eval(expr), text(expected), "{}", expr);
+        }
+        assert_eq!(eval("strftime('%Q', '2024-03-15')"), Value::Null);
+    }
+
+    #[test]
+    fn test_now() {
+        let today = eval("date('now')").to_string();
+        assert_eq!(today.len(), 10);
+        assert_eq!(eval("date()"), text(&today));
+        assert_eq!(eval("CURRENT_DATE"), text(&today));
+        assert_eq!(eval("LENGTH(CURRENT_TIMESTAMP)"), Value::Integer(19));
+        assert_eq!(eval("LENGTH(CURRENT_TIME)"), Value::Integer(8));
+        assert_eq!(
+            eval("date('now', 'start of month') <= date('now')"),
+            Value::Bool(true)
+        );
+    }
+
+    #[test]
+    fn test_event_log() {
+        let mut db = Database::new("test_db".to_string());
+        for sql in [
+            "CREATE TABLE events (id INTEGER PRIMARY KEY, name TEXT, at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, day DATE);",
+            "INSERT INTO events (name, at) VALUES ('boot', '2024-02-28 23:59:00'), \
+             ('login', '2024-03-01 08:00:00'), ('logout', '2024-03-01 17:30:00'), ('crash', '2024-04-02 03:00:00');",
+            "INSERT INTO events (name) VALUES ('now');",
+            "UPDATE events SET day = date(at);",
+        ] {
+            process_command(sql, &mut db).unwrap();
+        }
+
+        let names = |sql: &str| -> Vec<Value> {
+            run_query(sql, &db)
+                .unwrap()
+                .rows
+                .into_iter()
+                .map(|row| row[0].clone())
+                .collect()
+        };
+        assert_eq!(
+            names(
+                "SELECT name FROM events WHERE at >= '2024-03-01' \
+                 AND at < date('2024-03-01', '+1 month') ORDER BY at;"
+            ),
+            vec![text("login"), text("logout")]
+        );
+        assert_eq!(
+            run_query(
+                "SELECT strftime('%Y-%m', at) AS month, COUNT(*) FROM events \
+                 WHERE at < '2025-01-01' GROUP BY month ORDER BY month;",
+                &db
+            )
+            .unwrap()
+            .rows,
+            vec![
+                vec![text("2024-02"), Value::Integer(1)],
+                vec![text("2024-03"), Value::Integer(2)],
+                vec![text("2024-04"), Value::Integer(1)],
+            ]
+        );
+        assert_eq!(
+            names("SELECT day FROM events WHERE name = 'now';"),
+            vec![eval("date('now')")]
+        );
+        assert_eq!(
+            names(
+                "SELECT (unixepoch(b.at) - unixepoch(a.at)) / 3600.0 FROM events a, events b \
+                 WHERE a.name = 'login' AND b.name = 'logout';"
+            ),
+            vec![Value::Real(9.5)]
+        );
+    }
+}
@@ -0,0 +1,266 @@
+mod common;
+
+use common::select;
+use rusql::sql::db::database::Database;
+use rusql::sql::db::decimal::Decimal;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+use tempfile::tempdir;
+
+#[cfg(test)]
+mod decimal_tests {
+    use super::*;
+
+    fn setup(db: &mut Database) {
+        for sql in [
+            "CREATE TABLE invoices (id INTEGER PRIMARY KEY, amount DECIMAL(10, 2), rate NUMERIC(5, 4), memo DECIMAL);",
+            "INSERT INTO invoices (amount, rate, memo) VALUES \
+             (0.1, 0.0825, '1.50'), ('0.2', 0.1, 3), (19.999, 1, 2.5e-3);",
+        ] {
+            process_command(sql, db).unwrap();
+        }
+    }
+
+    fn dec(s: &str) -> Value {
+        Value::Decimal(Decimal::parse(s).unwrap())
+    }
+
+    #[test]
+    fn test_decimal_columns() {
+        let mut db = Database::new("test_db".to_string());
+        setup(&mut db);
+        // Values are rounded half up to the column's scale; DECIMAL keeps its own scale.
+        assert_eq!(
+            select(&db, "SELECT amount, rate, memo FROM invoices ORDER BY id;"),
+            vec![
+                vec![dec("0.10"), dec("0.0825"), dec("1.50")],
+                vec![dec("0.20"), dec("0.1000"), dec("3")],
+                vec![dec("20.00"), dec("1.0000"), dec("0.0025")],
+            ]
+        );
+        assert_eq!(dec("0.10").to_string(), "0.10");
+        assert_eq!(
+            select(&db, "SELECT TYPEOF(amount) FROM invoices WHERE id = 1;"),
+            vec![vec![Value::Text("decimal".to_string())]]
+        );
+
+        for sql in [
+            "INSERT INTO invoices (amount) VALUES (123456789.12);",
+            "INSERT INTO invoices (amount) VALUES ('ten');",
+            "CREATE TABLE bad (x DECIMAL(40, 2));",
+            "CREATE TABLE bad (x DECIMAL(4, 5));",
+        ] {
+            assert!(process_command(sql, &mut db).is_err(), "{}", sql);
+        }
+        process_command(
+            "INSERT INTO invoices (amount) VALUES (99999999.994);",
+            &mut db,
+        )
+        .unwrap();
+        assert!(process_command(
+            "INSERT INTO invoices (amount) VALUES (99999999.995);",
+            &mut db
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_exact_arithmetic_and_comparison() {
+        let mut db = Database::new("test_db".to_string());
+        setup(&mut db);
+        assert_eq!(
+            select(
+                &db,
+                "SELECT amount + 0.2, amount * rate, amount / 3, amount % 0.15, -amount \
+                 FROM invoices WHERE id = 1;"
+            ),
+            vec![vec![
+                dec("0.30"),
+                dec("0.008250"),
+                dec("0.033333"),
+                dec("0.10"),
+                dec("-0.10")
+            ]]
+        );
+        // Reals add up with binary error, decimals do not.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT 0.1 + 0.2 = 0.3, CAST('0.1' AS DECIMAL) + CAST('0.2' AS DECIMAL) = 0.3;"
+            ),
+            vec![vec![Value::Bool(false), Value::Bool(true)]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT id FROM invoices WHERE amount = 0.2 OR amount > 19.99 ORDER BY amount DESC;"
+            ),
+            vec![vec![Value::Integer(3)], vec![Value::Integer(2)]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT amount / 0, 1.00 * amount FROM invoices WHERE id = 3;"
+            ),
+            vec![vec![Value::Null, dec("20.00")]]
+        );
+    }
+
+    #[test]
+    fn test_decimal_overflow() {
+        let mut db = Database::new("test_db".to_string());
+        // Products and quotients drop digits after the point to fit.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT CAST('1' AS DECIMAL(38, 37)) * CAST('9.9' AS DECIMAL(38, 37)), \
+                 CAST('1' AS DECIMAL(38, 37)) / CAST('0.5' AS DECIMAL(38, 37)), \
+                 CAST('0.5' AS DECIMAL(38, 37)) * CAST('0.5' AS DECIMAL(38, 37));"
+            ),
+            vec![vec![dec("9.9"), dec("2"), dec("0.25")]]
+        );
+        for sql in [
+            "SELECT CAST('99999999999999999999999999999999999999' AS DECIMAL(38, 0)) + 1;",
+            "SELECT CAST('12345678901234567890' AS DECIMAL) * CAST('12345678901234567890' AS DECIMAL);",
+            "SELECT CAST('1' AS DECIMAL) * 1e300;",
+        ] {
+            let err = run_query(sql, &db).unwrap_err();
+            assert!(err.to_string().contains("numeric overflow"), "{}: {}", sql, err);
+        }
+
+        for sql in [
+            "CREATE TABLE big (d DECIMAL(38, 0));",
+            "INSERT INTO big (d) VALUES ('90000000000000000000000000000000000000'), \
+             ('90000000000000000000000000000000000000');",
+        ] {
+            process_command(sql, &mut db).unwrap();
+        }
+        for sql in ["SELECT SUM(d) FROM big;", "SELECT AVG(d) FROM big;"] {
+            let err = run_query(sql, &db).unwrap_err();
+            assert!(
+                err.to_string().contains("numeric overflow"),
+                "{}: {}",
+                sql,
+                err
+            );
+        }
+        assert_eq!(
+            select(&db, "SELECT TOTAL(d) FROM big;"),
+            vec![vec![Value::Real(1.8e38)]]
+        );
+    }
+
+    #[test]
+    fn test_sum_avg_and_rounding() {
+        let mut db = Database::new("test_db".to_string());
+        setup(&mut db);
+        assert_eq!(
+            select(
+                &db,
+                "SELECT SUM(amount), AVG(amount), TOTAL(amount), SUM(memo) FROM invoices;"
+            ),
+            vec![vec![
+                dec("20.30"),
+                dec("6.766667"),
+                Value::Real(20.3),
+                dec("4.5025")
+            ]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT ROUND(CAST('2.345' AS DECIMAL), 2), \
+                 ROUND(CAST('2.345' AS DECIMAL), 2, 'half_even'), \
+                 ROUND(CAST('-2.341' AS DECIMAL), 2, 'floor'), \
+                 ROUND(CAST('2.341' AS DECIMAL), 2, 'CEILING'), \
+                 ROUND(CAST('2.349' AS DECIMAL), 2, 'down'), \
+                 ROUND(2.675, 2);"
+            ),
+            vec![vec![
+                dec("2.35"),
+                dec("2.34"),
+                dec("-2.35"),
+                dec("2.35"),
+                dec("2.34"),
+                Value::Real(2.68)
+            ]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT CAST(1.005 AS DECIMAL(4, 2)), CAST('12abc' AS DECIMAL), CAST(amount AS INTEGER) \
+                 FROM invoices WHERE id = 3;"
+            ),
+            vec![vec![dec("1.01"), dec("12"), Value::Integer(20)]]
+        );
+        for sql in [
+            "SELECT CAST(1000 AS DECIMAL(3, 1));",
+            "SELECT ROUND(1.5, 0, 'sideways');",
+        ] {
+            assert!(run_query(sql, &db).is_err(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_decimal_keys() {
+        let mut db = Database::new("test_db".to_string());
+        for sql in [
+            "CREATE TABLE prices (sku DECIMAL(10, 2) PRIMARY KEY, code NUMERIC UNIQUE);",
+            "INSERT INTO prices (sku, code) VALUES (1.5, 7), (2.25, '7.10');",
+        ] {
+            process_command(sql, &mut db).unwrap();
+        }
+        for sql in [
+            "INSERT INTO prices (sku) VALUES (1.5);",
+            "INSERT INTO prices (sku) VALUES ('1.50');",
+            "INSERT INTO prices (sku) VALUES (NULL);",
+            // 7.1 equals 7.10 whatever the scale it is written with.
+            "INSERT INTO prices (sku, code) VALUES (3, 7.1);",
+            "UPDATE prices SET sku = 1.5 WHERE code = 7.1;",
+        ] {
+            assert!(process_command(sql, &mut db).is_err(), "{}", sql);
+        }
+        process_command("UPDATE prices SET sku = 3 WHERE code = 7.1;", &mut db).unwrap();
+        assert_eq!(
+            select(&db, "SELECT sku FROM prices ORDER BY sku DESC;"),
+            vec![vec![dec("3.00")], vec![dec("1.50")]]
+        );
+    }
+
+    #[test]
+    fn test_decimals_survive_reopen() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("billing.db");
+        let mut db = Database::open(&path).unwrap();
+        setup(&mut db);
+        process_command(
+            "INSERT INTO invoices (amount, memo) VALUES (-42.5, '12345678901234567890.123456789');",
+            &mut db,
+        )
+        .unwrap();
+        db.close().unwrap();
+        drop(db);
+
+        let reopened = Database::open(&path).unwrap();
+        assert_eq!(
+            select(&reopened, "SELECT amount, memo FROM invoices WHERE id > 2;"),
+            vec![
+                vec![dec("20.00"), dec("0.0025")],
+                vec![dec("-42.50"), dec("12345678901234567890.123456789")],
+            ]
+        );
+
+        // Saving an in-memory database copies its decimal columns into the file.
+        let copy_path = dir.path().join("copy.db");
+        let mut memory = Database::new("billing".to_string());
+        setup(&mut memory);
+        memory.save(&copy_path).unwrap();
+        assert_eq!(
+            select(
+                &Database::open(&copy_path).unwrap(),
+                "SELECT SUM(amount) FROM invoices;"
+            ),
+            vec![vec![dec("20.30")]]
+        );
+    }
+}
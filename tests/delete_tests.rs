@@ -0,0 +1,115 @@
+use rusql::sql::db::database::Database;
+use rusql::sql::db::table::Index;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+
+#[cfg(test)]
+mod delete_tests {
+    use super::*;
+
+    fn setup_users() -> Database {
+        let mut db = Database::new("test_db".to_string());
+        process_command(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE, age INTEGER);",
+            &mut db,
+        )
+        .unwrap();
+        for (email, age) in [("a@x.com", 30), ("b@x.com", 25), ("c@x.com", 41)] {
+            process_command(
+                &format!(
+                    "INSERT INTO users (email, age) VALUES ('{}', {});",
+                    email, age
+                ),
+                &mut db,
+            )
+            .unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn test_delete_with_where() {
+        let mut db = setup_users();
+        let result = process_command("DELETE FROM users WHERE age < 35;", &mut db).unwrap();
+        assert!(result.contains("2 row(s) affected"));
+
+        let remaining = run_query("SELECT id, email FROM users;", &db).unwrap();
+        assert_eq!(
+            remaining.rows,
+            vec![vec![Value::Integer(3), Value::Text("c@x.com".to_string())]]
+        );
+    }
+
+    #[test]
+    fn test_delete_all_rows() {
+        let mut db = setup_users();
+        let result = process_command("DELETE FROM users;", &mut db).unwrap();
+        assert!(result.contains("3 row(s) affected"));
+
+        let table = db.get_table("users".to_string()).unwrap();
+        assert!(table.rowids().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_delete_no_match() {
+        let mut db = setup_users();
+        let result = process_command("DELETE FROM users WHERE id = 99;", &mut db).unwrap();
+        assert!(result.contains("0 row(s) affected"));
+        assert_eq!(
+            run_query("SELECT * FROM users;", &db).unwrap().rows.len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_delete_removes_index_entries() {
+        let mut db = setup_users();
+        process_command("DELETE FROM users WHERE email = 'b@x.com';", &mut db).unwrap();
+
+        let table = db.get_table("users".to_string()).unwrap();
+        match &table.get_column("email".to_string()).unwrap().index {
+            Index::Text(index) => {
+                assert!(!index.contains_key("b@x.com"));
+                assert!(index.contains_key("a@x.com"));
+            }
+            _ => panic!("email column should have a text index"),
+        }
+        match &table.get_column("id".to_string()).unwrap().index {
+            Index::Integer(index) => assert!(!index.contains_key(&2)),
+            _ => panic!("id column should have an integer index"),
+        }
+    }
+
+    #[test]
+    fn test_unique_value_reusable_after_delete() {
+        let mut db = setup_users();
+        process_command("DELETE FROM users WHERE email = 'a@x.com';", &mut db).unwrap();
+
+        let insert = "INSERT INTO users (email, age) VALUES ('a@x.com', 50);";
+        assert!(process_command(insert, &mut db).is_ok());
+        assert!(process_command(insert, &mut db).is_err());
+    }
+
+    #[test]
+    fn test_delete_shared_index_value_keeps_other_rows() {
+        let mut db = setup_users();
+        process_command(
+            "INSERT INTO users (email, age) VALUES ('d@x.com', 30);",
+            &mut db,
+        )
+        .unwrap();
+        process_command("DELETE FROM users WHERE id = 1;", &mut db).unwrap();
+
+        let table = db.get_table("users".to_string()).unwrap();
+        match &table.get_column("age".to_string()).unwrap().index {
+            Index::Integer(index) => assert_eq!(index.get(&30).unwrap().len(), 1),
+            _ => panic!("age column should have an integer index"),
+        }
+    }
+
+    #[test]
+    fn test_delete_from_nonexistent_table() {
+        let mut db = Database::new("test_db".to_string());
+        assert!(process_command("DELETE FROM missing;", &mut db).is_err());
+    }
+}
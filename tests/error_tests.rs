@@ -28,6 +28,12 @@ mod error_tests {
         assert_eq!(error.to_string(), "Unknown command error: INVALID COMMAND");
     }
 
+    #[test]
+    fn test_key_violation_error() {
+        let error = RUSQLError::KeyViolation("Value 1 already exists.".to_string());
+        assert_eq!(error.to_string(), "Key violation: Value 1 already exists.");
+    }
+
     #[test]
     fn test_error_equality() {
         let error1 = RUSQLError::General("Test".to_string());
@@ -45,4 +51,11 @@ mod error_tests {
         assert!(debug_str.contains("NotImplemented"));
         assert!(debug_str.contains("Debug test"));
     }
+
+    #[test]
+    fn test_io_error_conversion() {
+        let io_error = std::io::Error::new(std::io::ErrorKind::NotFound, "missing file");
+        let error = RUSQLError::from(io_error);
+        assert_eq!(error.to_string(), "IO error: missing file");
+    }
 }
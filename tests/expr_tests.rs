@@ -0,0 +1,151 @@
+mod common;
+
+use common::eval;
+use rusql::sql::db::database::Database;
+use rusql::sql::db::decimal::Decimal;
+use rusql::sql::db::value::Value;
+use rusql::sql::run_query;
+
+#[cfg(test)]
+mod expr_tests {
+    use super::*;
+
+    #[test]
+    fn test_null_comparisons_are_unknown() {
+        assert_eq!(eval("NULL = NULL"), Value::Null);
+        assert_eq!(eval("NULL <> 1"), Value::Null);
+        assert_eq!(eval("1 < NULL"), Value::Null);
+        assert_eq!(eval("NOT NULL"), Value::Null);
+    }
+
+    #[test]
+    fn test_three_valued_and_or() {
+        assert_eq!(eval("NULL AND 0"), Value::Bool(false));
+        assert_eq!(eval("NULL AND 1"), Value::Null);
+        assert_eq!(eval("NULL OR 1"), Value::Bool(true));
+        assert_eq!(eval("NULL OR 0"), Value::Null);
+        assert_eq!(eval("1 = 1 AND 2 > 1"), Value::Bool(true));
+    }
+
+    #[test]
+    fn test_is_null() {
+        assert_eq!(eval("NULL IS NULL"), Value::Bool(true));
+        assert_eq!(eval("1 IS NOT NULL"), Value::Bool(true));
+        assert_eq!(eval("NULL IS NOT DISTINCT FROM NULL"), Value::Bool(true));
+    }
+
+    #[test]
+    fn test_in_list() {
+        assert_eq!(eval("2 IN (1, 2, 3)"), Value::Bool(true));
+        assert_eq!(eval("5 IN (1, 2, 3)"), Value::Bool(false));
+        assert_eq!(eval("5 IN (1, NULL)"), Value::Null);
+        assert_eq!(eval("5 NOT IN (1, NULL)"), Value::Null);
+        assert_eq!(eval("1 IN (1, NULL)"), Value::Bool(true));
+        assert_eq!(eval("NULL IN (1)"), Value::Null);
+    }
+
+    #[test]
+    fn test_between() {
+        assert_eq!(eval("5 BETWEEN 1 AND 10"), Value::Bool(true));
+        assert_eq!(eval("5 NOT BETWEEN 1 AND 10"), Value::Bool(false));
+        assert_eq!(eval("50 BETWEEN NULL AND 10"), Value::Bool(false));
+        assert_eq!(eval("5 BETWEEN NULL AND 10"), Value::Null);
+    }
+
+    #[test]
+    fn test_like_and_glob() {
+        assert_eq!(eval("'Alice' LIKE 'a%'"), Value::Bool(true));
+        assert_eq!(eval("'Alice' LIKE '_lic_'"), Value::Bool(true));
+        assert_eq!(eval("'Alice' NOT LIKE 'b%'"), Value::Bool(true));
+        assert_eq!(eval("'50%' LIKE '50!%' ESCAPE '!'"), Value::Bool(true));
+        assert_eq!(eval("'Alice' GLOB 'A*'"), Value::Bool(true));
+        assert_eq!(eval("'Alice' GLOB 'a*'"), Value::Bool(false));
+        assert_eq!(eval("'file7' GLOB 'file[0-9]'"), Value::Bool(true));
+        assert_eq!(eval("'Alice' NOT GLOB '?lice'"), Value::Bool(false));
+        assert_eq!(eval("NULL LIKE 'a%'"), Value::Null);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(eval("1 + 2 * 3"), Value::Integer(7));
+        assert_eq!(eval("7 / 2"), Value::Integer(3));
+        assert_eq!(eval("7.0 / 2"), Value::Real(3.5));
+        assert_eq!(eval("7 % 3"), Value::Integer(1));
+        assert_eq!(eval("1 / 0"), Value::Null);
+        assert_eq!(eval("-(3 - 5)"), Value::Integer(2));
+        assert_eq!(eval("1 + NULL"), Value::Null);
+        assert_eq!(eval("'3' + 4"), Value::Integer(7));
+    }
+
+    #[test]
+    fn test_string_concatenation() {
+        assert_eq!(eval("'foo' || 'bar'"), Value::Text("foobar".to_string()));
+        assert_eq!(eval("'n=' || 5"), Value::Text("n=5".to_string()));
+        assert_eq!(eval("'foo' || NULL"), Value::Null);
+    }
+
+    #[test]
+    fn test_mixed_type_comparisons() {
+        assert_eq!(eval("1 = 1.0"), Value::Bool(true));
+        assert_eq!(eval("'10' = 10"), Value::Bool(true));
+        assert_eq!(eval("10 < 'abc'"), Value::Bool(true));
+        assert_eq!(eval("'abc' < 'abd'"), Value::Bool(true));
+    }
+
+    #[test]
+    fn test_searched_case() {
+        assert_eq!(
+            eval("CASE WHEN 1 > 2 THEN 'a' WHEN 2 > 1 THEN 'b' ELSE 'c' END"),
+            Value::Text("b".to_string())
+        );
+        assert_eq!(eval("CASE WHEN NULL THEN 1 ELSE 2 END"), Value::Integer(2));
+        assert_eq!(eval("CASE WHEN 0 THEN 1 END"), Value::Null);
+        // Only the chosen branch is evaluated.
+        assert_eq!(
+            eval("CASE WHEN 1 THEN 'ok' ELSE ABS(-9223372036854775807 - 1) END"),
+            Value::Text("ok".to_string())
+        );
+    }
+
+    #[test]
+    fn test_simple_case() {
+        assert_eq!(
+            eval("CASE 2 WHEN 1 THEN 'one' WHEN 2 THEN 'two' END"),
+            Value::Text("two".to_string())
+        );
+        assert_eq!(
+            eval("CASE '3' WHEN 3 THEN 'num' ELSE 'text' END"),
+            Value::Text("num".to_string())
+        );
+        assert_eq!(
+            eval("CASE NULL WHEN NULL THEN 1 ELSE 0 END"),
+            Value::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_cast_type_mapping() {
+        assert_eq!(eval("CAST('42' AS BIGINT)"), Value::Integer(42));
+        assert_eq!(eval("CAST(-7.9 AS INT)"), Value::Integer(-7));
+        assert_eq!(eval("CAST('x' AS INTEGER)"), Value::Integer(0));
+        assert_eq!(
+            eval("CAST(12 AS VARCHAR(10))"),
+            Value::Text("12".to_string())
+        );
+        assert_eq!(
+            eval("CAST('1.5' AS DECIMAL(10, 2))"),
+            Value::Decimal(Decimal::parse("1.50").unwrap())
+        );
+        assert_eq!(eval("CAST(3 AS DOUBLE)"), Value::Real(3.0));
+        assert_eq!(eval("CAST(2 AS BOOLEAN)"), Value::Bool(true));
+        assert_eq!(eval("CAST('false' AS BOOL)"), Value::Bool(false));
+        assert_eq!(eval("CAST('0' AS BOOLEAN)"), Value::Bool(false));
+        assert_eq!(eval("CAST('maybe' AS BOOLEAN)"), Value::Null);
+        // Names sqlparser does not know are classified by SQLite's affinity rules.
+        assert_eq!(eval("CAST('5' AS MEDIUMINT)"), Value::Integer(5));
+        assert_eq!(eval("CAST(5 AS NCHAR(2))"), Value::Text("5".to_string()));
+
+        let db = Database::new("test_db".to_string());
+        assert!(run_query("SELECT CAST(1 AS WIDGET);", &db).is_err());
+    }
+}
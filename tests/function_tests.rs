@@ -0,0 +1,234 @@
+mod common;
+
+use common::{column, database, text, value};
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+
+#[cfg(test)]
+mod function_tests {
+    use super::*;
+
+    fn setup() -> Database {
+        database(&[
+            "CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT, city TEXT, score REAL);",
+            "INSERT INTO people (name, city, score) VALUES \
+             ('  Ann ', 'Oslo', 7.25), ('bob', NULL, -3.5), ('Cid', 'Lima', NULL);",
+        ])
+    }
+
+    #[test]
+    fn test_string_functions() {
+        let db = Database::new("test_db".to_string());
+        for (sql, expected) in [
+            ("SELECT LENGTH('héllo');", Value::Integer(5)),
+            ("SELECT LENGTH(12.5);", Value::Integer(4)),
+            ("SELECT UPPER('abc') || LOWER('DeF');", text("ABCdef")),
+            ("SELECT SUBSTR('database', 5);", text("base")),
+            ("SELECT SUBSTR('database', 1, 4);", text("data")),
+            ("SELECT SUBSTR('database', -4, 2);", text("ba")),
+            ("SELECT SUBSTR('database', 5, -2);", text("ta")),
+            ("SELECT SUBSTRING('database' FROM 2 FOR 3);", text("ata")),
+            ("SELECT TRIM('  x  ');", text("x")),
+            ("SELECT TRIM('xxaxx', 'x');", text("a")),
+            (
+                "SELECT LTRIM('..a..', '.') || RTRIM('..a..', '.');",
+                text("a....a"),
+            ),
+            ("SELECT TRIM(LEADING 'x' FROM 'xxaxx');", text("axx")),
+            ("SELECT REPLACE('a-b-c', '-', '+');", text("a+b+c")),
+            ("SELECT INSTR('hello', 'l');", Value::Integer(3)),
+            ("SELECT INSTR('hello', 'z');", Value::Integer(0)),
+            ("SELECT HEX('Az');", text("417A")),
+            ("SELECT UPPER(NULL);", Value::Null),
+            ("SELECT REPLACE('abc', NULL, 'x');", Value::Null),
+        ] {
+            assert_eq!(value(&db, sql), expected, "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_printf() {
+        let db = Database::new("test_db".to_string());
+        for (sql, expected) in [
+            ("SELECT PRINTF('%d items', 3);", "3 items"),
+            (
+                "SELECT PRINTF('%5d|%-5d|%05d', 42, 42, -42);",
+                "   42|42   |-0042",
+            ),
+            ("SELECT PRINTF('%.2f', 2.71828);", "2.72"),
+            ("SELECT PRINTF('%+.1e', 12345.678);", "+1.2e+04"),
+            ("SELECT PRINTF('%g %g', 0.0001, 1000000.0);", "0.0001 1e+06"),
+            ("SELECT PRINTF('%x %X %o', 255, 255, 8);", "ff FF 10"),
+            (
+                "SELECT FORMAT('%s is %.3s', 'name', 'abcdef');",
+                "name is abc",
+            ),
+            ("SELECT PRINTF('%q', 'it''s');", "it''s"),
+            ("SELECT PRINTF('100%% %s', NULL);", "100% "),
+            (
+                "SELECT PRINTF('%,d|%,d|%,d|%,12d|%-,8d|%+,d', 1234567, -1234, 999, 1234567, 1000, 1000000);",
+                "1,234,567|-1,234|999|   1,234,567|1,000   |+1,000,000",
+            ),
+        ] {
+            assert_eq!(value(&db, sql), text(expected), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_math_functions() {
+        let db = Database::new("test_db".to_string());
+        for (sql, expected) in [
+            ("SELECT ABS(-5);", Value::Integer(5)),
+            ("SELECT ABS(-2.5);", Value::Real(2.5)),
+            ("SELECT ABS('-7');", Value::Integer(7)),
+            ("SELECT ROUND(2.5);", Value::Real(3.0)),
+            ("SELECT ROUND(-2.5);", Value::Real(-3.0)),
+            ("SELECT ROUND(2.71828, 2);", Value::Real(2.72)),
+            ("SELECT ROUND(7);", Value::Real(7.0)),
+            ("SELECT MIN(3, 1, 2);", Value::Integer(1)),
+            ("SELECT MAX(3, 'a', 2.5);", text("a")),
+            ("SELECT MAX(1, NULL);", Value::Null),
+        ] {
+            assert_eq!(value(&db, sql), expected, "{}", sql);
+        }
+        assert_eq!(
+            value(&db, "SELECT TYPEOF(-9223372036854775808);"),
+            text("integer")
+        );
+        for sql in [
+            "SELECT ABS(-9223372036854775807 - 1);",
+            "SELECT ABS(-9223372036854775808);",
+        ] {
+            let err = run_query(sql, &db).unwrap_err();
+            assert!(
+                err.to_string().contains("integer overflow"),
+                "{}: {}",
+                sql,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_null_handling_and_typeof() {
+        let db = setup();
+        assert_eq!(
+            column(&db, "SELECT COALESCE(city, score, 'none') FROM people;"),
+            vec![text("Oslo"), Value::Real(-3.5), text("Lima")]
+        );
+        assert_eq!(
+            column(&db, "SELECT IFNULL(score, 0) FROM people;"),
+            vec![Value::Real(7.25), Value::Real(-3.5), Value::Integer(0)]
+        );
+        assert_eq!(
+            column(&db, "SELECT NULLIF(city, 'Oslo') FROM people;"),
+            vec![Value::Null, Value::Null, text("Lima")]
+        );
+        assert_eq!(
+            column(&db, "SELECT TYPEOF(score) FROM people;"),
+            vec![text("real"), text("real"), text("null")]
+        );
+        assert_eq!(
+            value(&db, "SELECT TYPEOF(1) || TYPEOF('a') || TYPEOF(1 = 1);"),
+            text("integertextboolean")
+        );
+    }
+
+    #[test]
+    fn test_cast_and_random() {
+        let db = Database::new("test_db".to_string());
+        for (sql, expected) in [
+            ("SELECT CAST('12abc' AS INTEGER);", Value::Integer(12)),
+            ("SELECT CAST(3.99 AS INT);", Value::Integer(3)),
+            ("SELECT CAST('2.5' AS REAL);", Value::Real(2.5)),
+            ("SELECT CAST(10 AS TEXT);", text("10")),
+            ("SELECT CAST(NULL AS INTEGER);", Value::Null),
+            ("SELECT TYPEOF(CAST(1 AS DOUBLE));", text("real")),
+        ] {
+            assert_eq!(value(&db, sql), expected, "{}", sql);
+        }
+        let (a, b) = (
+            value(&db, "SELECT RANDOM();"),
+            value(&db, "SELECT RANDOM();"),
+        );
+        assert!(matches!(a, Value::Integer(_)));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_functions_in_where_and_update() {
+        let mut db = setup();
+        assert_eq!(
+            column(
+                &db,
+                "SELECT id FROM people WHERE LOWER(TRIM(name)) IN ('ann', 'cid');"
+            ),
+            vec![Value::Integer(1), Value::Integer(3)]
+        );
+        process_command(
+            "UPDATE people SET name = UPPER(TRIM(name)), score = ROUND(ABS(score)) WHERE score IS NOT NULL;",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            run_query("SELECT name, score FROM people WHERE id <= 2;", &db)
+                .unwrap()
+                .rows,
+            vec![
+                vec![text("ANN"), Value::Real(7.0)],
+                vec![text("BOB"), Value::Real(4.0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_expressions() {
+        let mut db = Database::new("test_db".to_string());
+        process_command(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT DEFAULT 'empty', \
+             tag TEXT DEFAULT (UPPER('x') || PRINTF('%03d', 7)), n INTEGER DEFAULT (ABS(-4)));",
+            &mut db,
+        )
+        .unwrap();
+        process_command("INSERT INTO notes (body) VALUES ('hi');", &mut db).unwrap();
+        process_command("INSERT INTO notes (n) VALUES (1);", &mut db).unwrap();
+        process_command(
+            "INSERT INTO notes (body, tag, n) VALUES (NULL, 't', NULL);",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            run_query("SELECT body, tag, n FROM notes;", &db)
+                .unwrap()
+                .rows,
+            vec![
+                vec![text("hi"), text("X007"), Value::Integer(4)],
+                vec![text("empty"), text("X007"), Value::Integer(1)],
+                vec![Value::Null, text("t"), Value::Null],
+            ]
+        );
+
+        for sql in [
+            "CREATE TABLE bad1 (a INTEGER, b INTEGER DEFAULT (a + 1));",
+            "CREATE TABLE bad2 (a INTEGER DEFAULT (SELECT 1));",
+            "CREATE TABLE bad3 (a INTEGER DEFAULT (NO_SUCH_FUNCTION()));",
+        ] {
+            assert!(process_command(sql, &mut db).is_err(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_function_errors() {
+        let db = setup();
+        for sql in [
+            "SELECT LENGTH() FROM people;",
+            "SELECT SUBSTR(name) FROM people;",
+            "SELECT COALESCE(name) FROM people;",
+            "SELECT NO_SUCH_FUNCTION(name) FROM people;",
+            "SELECT PRINTF('%y', 1);",
+        ] {
+            assert!(run_query(sql, &db).is_err(), "{}", sql);
+        }
+    }
+}
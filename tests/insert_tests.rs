@@ -0,0 +1,285 @@
+mod common;
+
+use common::select;
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::process_command;
+
+#[cfg(test)]
+mod insert_tests {
+    use super::*;
+
+    fn setup_items() -> Database {
+        let mut db = Database::new("test_db".to_string());
+        process_command(
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, qty INTEGER, price REAL, active BOOLEAN);",
+            &mut db,
+        )
+        .unwrap();
+        db
+    }
+
+    #[test]
+    fn test_insert_null_into_integer_column() {
+        let mut db = setup_items();
+        let insert = "INSERT INTO items (name, qty, price) VALUES ('pen', NULL, NULL);";
+        assert!(process_command(insert, &mut db).is_ok());
+        assert_eq!(
+            select(&db, "SELECT qty, price FROM items;"),
+            vec![vec![Value::Null, Value::Null]]
+        );
+    }
+
+    #[test]
+    fn test_insert_type_mismatch_is_an_error() {
+        let mut db = setup_items();
+        let insert = "INSERT INTO items (name, price) VALUES ('pen', 'abc');";
+        assert!(process_command(insert, &mut db).is_err());
+        let insert = "INSERT INTO items (name, qty) VALUES ('pen', 1.5);";
+        assert!(process_command(insert, &mut db).is_err());
+        let insert = "INSERT INTO items (name, active) VALUES ('pen', 2);";
+        assert!(process_command(insert, &mut db).is_err());
+        assert!(select(&db, "SELECT * FROM items;").is_empty());
+    }
+
+    #[test]
+    fn test_conversion_errors_are_not_key_violations() {
+        let mut db = setup_items();
+        for insert in [
+            "INSERT INTO items (id, name) VALUES ('one', 'pen');",
+            "INSERT INTO items (qty) VALUES (9223372036854775808);",
+        ] {
+            let err = process_command(insert, &mut db).unwrap_err().to_string();
+            assert!(err.contains("Cannot store"), "{}", err);
+            assert!(!err.contains("Unique key constraint violation"), "{}", err);
+        }
+
+        process_command("INSERT INTO items (id, name) VALUES (1, 'pen');", &mut db).unwrap();
+        let err = process_command("INSERT INTO items (id, name) VALUES (1, 'pen');", &mut db)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Unique key constraint violation"), "{}", err);
+    }
+
+    #[test]
+    fn test_insert_applies_column_affinity() {
+        let mut db = setup_items();
+        process_command(
+            "INSERT INTO items (name, qty, price, active) VALUES (42, '7', 3, 'true');",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            select(&db, "SELECT name, qty, price, active FROM items;"),
+            vec![vec![
+                Value::Text("42".to_string()),
+                Value::Integer(7),
+                Value::Real(3.0),
+                Value::Bool(true)
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_insert_integer_out_of_range() {
+        let mut db = setup_items();
+        for insert in [
+            "INSERT INTO items (qty) VALUES (9223372036854775808);",
+            "INSERT INTO items (qty) VALUES (-1e19);",
+            "INSERT INTO items (qty) VALUES (9223372036854775807 + 1);",
+            "INSERT INTO items (qty) VALUES ('1e19');",
+        ] {
+            assert!(process_command(insert, &mut db).is_err(), "{}", insert);
+        }
+        process_command(
+            "INSERT INTO items (qty) VALUES (-9223372036854775808), (9223372036854775807);",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            select(&db, "SELECT qty FROM items ORDER BY qty;"),
+            vec![
+                vec![Value::Integer(i64::MIN)],
+                vec![Value::Integer(i64::MAX)]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_insert_columns_in_any_order() {
+        let mut db = setup_items();
+        process_command(
+            "INSERT INTO items (price, name, id) VALUES (2.5, 'cup', 7);",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            select(&db, "SELECT id, name, qty, price FROM items;"),
+            vec![vec![
+                Value::Integer(7),
+                Value::Text("cup".to_string()),
+                Value::Null,
+                Value::Real(2.5)
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_insert_without_column_list() {
+        let mut db = setup_items();
+        process_command(
+            "INSERT INTO items VALUES (NULL, 'mug', 3, 1.0, false);",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            select(&db, "SELECT id, name, active FROM items;"),
+            vec![vec![
+                Value::Integer(1),
+                Value::Text("mug".to_string()),
+                Value::Bool(false)
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_insert_constant_expressions() {
+        let mut db = setup_items();
+        process_command(
+            "INSERT INTO items (name, qty, price) VALUES ('a' || 'b', -5, 2 * 1.5);",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            select(&db, "SELECT name, qty, price FROM items;"),
+            vec![vec![
+                Value::Text("ab".to_string()),
+                Value::Integer(-5),
+                Value::Real(3.0)
+            ]]
+        );
+
+        let insert = "INSERT INTO items (qty) VALUES (missing + 1);";
+        assert!(process_command(insert, &mut db).is_err());
+    }
+
+    #[test]
+    fn test_auto_rowid_follows_largest_key() {
+        let mut db = setup_items();
+        process_command("INSERT INTO items (id, name) VALUES (20, 'a');", &mut db).unwrap();
+        process_command("INSERT INTO items (id, name) VALUES (10, 'b');", &mut db).unwrap();
+        process_command("INSERT INTO items (name) VALUES ('c');", &mut db).unwrap();
+        assert_eq!(
+            select(&db, "SELECT id FROM items WHERE name = 'c';"),
+            vec![vec![Value::Integer(21)]]
+        );
+    }
+
+    #[test]
+    fn test_insert_duplicate_column() {
+        let mut db = setup_items();
+        let insert = "INSERT INTO items (name, name) VALUES ('a', 'b');";
+        assert!(process_command(insert, &mut db).is_err());
+    }
+
+    #[test]
+    fn test_insert_enforces_not_null() {
+        let mut db = Database::new("test_db".to_string());
+        process_command(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL UNIQUE, name TEXT);",
+            &mut db,
+        )
+        .unwrap();
+
+        let insert = "INSERT INTO users (name) VALUES ('Alice');";
+        assert!(process_command(insert, &mut db).is_err());
+        let insert = "INSERT INTO users (email, name) VALUES (NULL, 'Alice');";
+        assert!(process_command(insert, &mut db).is_err());
+        assert!(select(&db, "SELECT * FROM users;").is_empty());
+
+        let insert = "INSERT INTO users (email) VALUES ('a@x.com');";
+        assert!(process_command(insert, &mut db).is_ok());
+        assert!(process_command(insert, &mut db).is_err());
+    }
+
+    #[test]
+    fn test_all_null_rows_are_kept() {
+        let mut db = Database::new("test_db".to_string());
+        process_command("CREATE TABLE notes (body TEXT, stars INTEGER);", &mut db).unwrap();
+        process_command("INSERT INTO notes (body) VALUES (NULL);", &mut db).unwrap();
+        process_command("INSERT INTO notes (stars) VALUES (3);", &mut db).unwrap();
+
+        assert_eq!(
+            select(&db, "SELECT body, stars FROM notes;"),
+            vec![
+                vec![Value::Null, Value::Null],
+                vec![Value::Null, Value::Integer(3)]
+            ]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT stars FROM notes WHERE body IS NULL AND stars IS NULL;"
+            ),
+            vec![vec![Value::Null]]
+        );
+    }
+
+    #[test]
+    fn test_nulls_stay_aligned_with_their_rows() {
+        let mut db = setup_items();
+        process_command("INSERT INTO items (name) VALUES ('a');", &mut db).unwrap();
+        process_command("INSERT INTO items (qty) VALUES (5);", &mut db).unwrap();
+        process_command("INSERT INTO items (name, qty) VALUES ('c', 6);", &mut db).unwrap();
+
+        assert_eq!(
+            select(&db, "SELECT id, name, qty FROM items;"),
+            vec![
+                vec![Value::Integer(1), Value::Text("a".to_string()), Value::Null],
+                vec![Value::Integer(2), Value::Null, Value::Integer(5)],
+                vec![
+                    Value::Integer(3),
+                    Value::Text("c".to_string()),
+                    Value::Integer(6)
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_insert_64_bit_values() {
+        let mut db = setup_items();
+        process_command(
+            "INSERT INTO items (id, qty, price) VALUES (3000000000, 3000000000, 1234567.89);",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT id, qty, price FROM items WHERE qty = 3000000000;"
+            ),
+            vec![vec![
+                Value::Integer(3_000_000_000),
+                Value::Integer(3_000_000_000),
+                Value::Real(1234567.89)
+            ]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT qty * 2, price + 0.01 FROM items WHERE id = 3000000000;"
+            ),
+            vec![vec![Value::Integer(6_000_000_000), Value::Real(1234567.9)]]
+        );
+
+        // Once the largest rowid is taken, new rows need an explicit key.
+        process_command(
+            "INSERT INTO items (id) VALUES (9223372036854775807);",
+            &mut db,
+        )
+        .unwrap();
+        assert!(process_command("INSERT INTO items (name) VALUES ('x');", &mut db).is_err());
+        process_command("INSERT INTO items (id, name) VALUES (5, 'x');", &mut db).unwrap();
+    }
+}
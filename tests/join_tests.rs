@@ -0,0 +1,238 @@
+mod common;
+
+use common::{database, select, text};
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+
+#[cfg(test)]
+mod join_tests {
+    use super::*;
+
+    fn setup() -> Database {
+        database(&[
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);",
+            "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER, item TEXT, price REAL);",
+            "CREATE TABLE prices (price REAL, label TEXT);",
+            "INSERT INTO users (name) VALUES ('alice'), ('bob'), ('carol');",
+            "INSERT INTO orders (user_id, item, price) VALUES \
+             (1, 'pen', 1.5), (3, 'ink', 4.0), (1, 'pad', 4.0), (7, 'cap', 2.5), (NULL, 'box', 1.5);",
+            "INSERT INTO prices (price, label) VALUES (1.5, 'cheap'), (4.0, 'dear'), (9.0, 'luxury');",
+        ])
+    }
+
+    #[test]
+    fn test_inner_join_with_aliases() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT u.name, o.item FROM users u JOIN orders AS o ON o.user_id = u.id;"
+            ),
+            vec![
+                vec![text("alice"), text("pen")],
+                vec![text("alice"), text("pad")],
+                vec![text("carol"), text("ink")],
+            ]
+        );
+        // Same rows with the table on the other side and extra conditions in ON.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT users.name, orders.item FROM orders INNER JOIN users ON users.id = orders.user_id AND orders.price > 2;"
+            ),
+            vec![vec![text("carol"), text("ink")], vec![text("alice"), text("pad")]]
+        );
+    }
+
+    #[test]
+    fn test_left_join_pads_with_nulls() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT u.name, o.item FROM users u LEFT JOIN orders o ON o.user_id = u.id;"
+            ),
+            vec![
+                vec![text("alice"), text("pen")],
+                vec![text("alice"), text("pad")],
+                vec![text("bob"), Value::Null],
+                vec![text("carol"), text("ink")],
+            ]
+        );
+        // Users without orders.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT u.name FROM users u LEFT OUTER JOIN orders o ON o.user_id = u.id WHERE o.id IS NULL;"
+            ),
+            vec![vec![text("bob")]]
+        );
+        // A condition in ON keeps the left row, the same condition in WHERE drops it.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT u.name, o.item FROM users u LEFT JOIN orders o ON o.user_id = u.id AND o.price > 3;"
+            )
+            .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_cross_join() {
+        let db = setup();
+        assert_eq!(
+            select(&db, "SELECT COUNT(*) FROM users CROSS JOIN prices;"),
+            vec![vec![Value::Integer(9)]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT u.name, p.label FROM users u, prices p WHERE u.id = 2 ORDER BY p.label DESC;"
+            ),
+            vec![
+                vec![text("bob"), text("luxury")],
+                vec![text("bob"), text("dear")],
+                vec![text("bob"), text("cheap")],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_using_and_natural_join() {
+        let db = setup();
+        let using = run_query("SELECT * FROM orders JOIN prices USING (price);", &db).unwrap();
+        // The USING column appears once.
+        assert_eq!(
+            using.columns,
+            vec!["id", "user_id", "item", "price", "label"]
+        );
+        assert_eq!(using.rows.len(), 4);
+        assert_eq!(
+            select(
+                &db,
+                "SELECT item, price, label FROM orders NATURAL JOIN prices WHERE label = 'dear';"
+            ),
+            vec![
+                vec![text("ink"), Value::Real(4.0), text("dear")],
+                vec![text("pad"), Value::Real(4.0), text("dear")],
+            ]
+        );
+        // The right copy can still be named explicitly.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT prices.price FROM orders LEFT JOIN prices USING (price) WHERE item = 'cap';"
+            ),
+            vec![vec![Value::Null]]
+        );
+        assert!(run_query("SELECT * FROM orders JOIN prices USING (label);", &db).is_err());
+    }
+
+    #[test]
+    fn test_join_strategies_agree() {
+        let db = setup();
+        // Index lookup on orders.user_id, a nested loop over a key expression and a hash
+        // join on the unindexed REAL column must return the same rows.
+        let indexed = select(
+            &db,
+            "SELECT u.id, o.id FROM users u JOIN orders o ON o.user_id = u.id;",
+        );
+        let nested = select(
+            &db,
+            "SELECT u.id, o.id FROM users u JOIN orders o ON o.user_id + 0 = u.id;",
+        );
+        assert_eq!(indexed, nested);
+
+        let hashed = select(
+            &db,
+            "SELECT o.id, p.label FROM orders o JOIN prices p ON p.price = o.price;",
+        );
+        let nested = select(
+            &db,
+            "SELECT o.id, p.label FROM orders o JOIN prices p ON p.price + 0 = o.price;",
+        );
+        assert_eq!(hashed, nested);
+        assert_eq!(hashed.len(), 4);
+    }
+
+    #[test]
+    fn test_join_key_of_another_type() {
+        let mut db = setup();
+        process_command("CREATE TABLE tags (user_ref TEXT, tag TEXT);", &mut db).unwrap();
+        process_command(
+            "INSERT INTO tags (user_ref, tag) VALUES ('1', 'a'), ('2.0', 'b'), ('x', 'c');",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT u.name, t.tag FROM users u JOIN tags t ON t.user_ref = u.id;"
+            ),
+            vec![vec![text("alice"), text("a")], vec![text("bob"), text("b")]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT t.tag, u.name FROM tags t JOIN users u ON u.id = t.user_ref;"
+            ),
+            vec![vec![text("a"), text("alice")], vec![text("b"), text("bob")]]
+        );
+    }
+
+    #[test]
+    fn test_three_table_join_with_aggregate() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT u.name, COUNT(o.id), GROUP_CONCAT(p.label) FROM users u \
+                 LEFT JOIN orders o ON o.user_id = u.id \
+                 LEFT JOIN prices p ON p.price = o.price \
+                 GROUP BY u.name ORDER BY COUNT(o.id) DESC, u.name;"
+            ),
+            vec![
+                vec![text("alice"), Value::Integer(2), text("cheap,dear")],
+                vec![text("carol"), Value::Integer(1), text("dear")],
+                vec![text("bob"), Value::Integer(0), Value::Null],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_self_join_and_qualified_wildcard() {
+        let db = setup();
+        let result = run_query(
+            "SELECT a.*, b.item FROM orders a JOIN orders b ON a.price = b.price AND a.id < b.id;",
+            &db,
+        )
+        .unwrap();
+        assert_eq!(
+            result.columns,
+            vec!["id", "user_id", "item", "price", "item"]
+        );
+        assert_eq!(
+            result
+                .rows
+                .iter()
+                .map(|row| (row[2].clone(), row[4].clone()))
+                .collect::<Vec<_>>(),
+            vec![(text("pen"), text("box")), (text("ink"), text("pad"))]
+        );
+    }
+
+    #[test]
+    fn test_join_errors() {
+        let db = setup();
+        for sql in [
+            "SELECT id FROM users JOIN orders ON orders.user_id = users.id;",
+            "SELECT * FROM users JOIN missing ON 1;",
+            "SELECT * FROM users u JOIN orders o ON o.nope = u.id;",
+            "SELECT * FROM users u RIGHT JOIN orders o ON o.user_id = u.id;",
+        ] {
+            assert!(run_query(sql, &db).is_err(), "{}", sql);
+        }
+    }
+}
@@ -0,0 +1,329 @@
+mod common;
+
+use common::{database, eval, select, text};
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+
+#[cfg(test)]
+mod json_tests {
+    use super::*;
+
+    fn setup() -> Database {
+        database(&[
+            "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer TEXT, payload JSON);",
+            "INSERT INTO orders (customer, payload) VALUES \
+             ('ann', '{\"total\": 30.5, \"items\": [{\"sku\": \"a1\", \"qty\": 2}, {\"sku\": \"b2\", \"qty\": 1}], \"gift\": true}'), \
+             ('bob', '{\"total\": 12, \"items\": [{\"sku\": \"a1\", \"qty\": 5}]}'), \
+             ('cid', NULL);",
+        ])
+    }
+
+    #[test]
+    fn test_json_column() {
+        let mut db = setup();
+        // Documents are validated and stored minified, with members in written order.
+        assert_eq!(
+            select(&db, "SELECT payload FROM orders WHERE id = 2;"),
+            vec![vec![text(r#"{"total":12,"items":[{"sku":"a1","qty":5}]}"#)]]
+        );
+        assert!(process_command(
+            "INSERT INTO orders (customer, payload) VALUES ('dan', '{not json');",
+            &mut db
+        )
+        .is_err());
+        process_command(
+            "INSERT INTO orders (customer, payload) VALUES ('eve', 42);",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT payload, json_type(payload) FROM orders WHERE customer = 'eve';"
+            ),
+            vec![vec![text("42"), text("integer")]]
+        );
+
+        process_command(
+            "UPDATE orders SET payload = json_set(payload, '$.status', 'shipped') WHERE id = 2;",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            select(&db, "SELECT payload ->> 'status' FROM orders WHERE id = 2;"),
+            vec![vec![text("shipped")]]
+        );
+    }
+
+    #[test]
+    fn test_extract_and_arrows() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT customer, json_extract(payload, '$.total'), payload ->> '$.items[0].sku', \
+                 payload -> 'items' -> -1 -> 'qty' FROM orders ORDER BY id;"
+            ),
+            vec![
+                vec![text("ann"), Value::Real(30.5), text("a1"), text("1")],
+                vec![text("bob"), Value::Integer(12), text("a1"), text("5")],
+                vec![text("cid"), Value::Null, Value::Null, Value::Null],
+            ]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT customer FROM orders WHERE payload ->> 'gift' = TRUE OR payload ->> '$.total' < 20 ORDER BY id;"
+            ),
+            vec![vec![text("ann")], vec![text("bob")]]
+        );
+        for (expr, expected) in [
+            (
+                r#"json_extract('{"a":{"b":[1,2]}}', '$.a')"#,
+                text(r#"{"b":[1,2]}"#),
+            ),
+            (
+                r#"json_extract('{"a":"x","b":2}', '$.a', '$.b', '$.c')"#,
+                text(r#"["x",2,null]"#),
+            ),
+            (r#"'{"a":"x"}' -> '$.a'"#, text(r#""x""#)),
+            (r#"'{"a":"x"}' ->> 'a'"#, text("x")),
+            (r#"'[10,20,30]' ->> '$[#-1]'"#, Value::Integer(30)),
+            (r#"'[10,20,30]' -> 5"#, Value::Null),
+            // The arrows bind tighter than arithmetic and group to the left.
+            (r#"'{"a":{"b":2}}' -> 'a' ->> 'b' * 3"#, Value::Integer(6)),
+            (r#"'{"a b":1}' ->> '$."a b"'"#, Value::Integer(1)),
+            (
+                r#"json_array_length('[1,[2,3]]', '$[1]')"#,
+                Value::Integer(2),
+            ),
+            (r#"json_type('{"a":null}', '$.a')"#, text("null")),
+            (r#"json_type('{"a":null}', '$.b')"#, Value::Null),
+            ("json_valid('[1,2')", Value::Bool(false)),
+            (
+                r#"json(' { "a" : [ 1 , 2.0 ] } ')"#,
+                text(r#"{"a":[1,2.0]}"#),
+            ),
+        ] {
+            assert_eq!(eval(expr), expected, "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_building_json() {
+        for (expr, expected) in [
+            (
+                "json_array(1, 'two', 3.5, NULL, 1 = 1)",
+                r#"[1,"two",3.5,null,true]"#,
+            ),
+            // Results of JSON functions are embedded as JSON, other text as strings.
+            (
+                r#"json_object('a', json_array(1, 2), 'b', '[3]', 'c', json('{"d":4}'))"#,
+                r#"{"a":[1,2],"b":"[3]","c":{"d":4}}"#,
+            ),
+            (r#"json_quote('say "hi"')"#, r#""say \"hi\"""#),
+            (
+                r#"json_set('{"a":1,"b":[1]}', '$.a', 2, '$.b[#]', 9, '$.c.d', 'new')"#,
+                r#"{"a":2,"b":[1,9],"c":{"d":"new"}}"#,
+            ),
+            (
+                r#"json_insert('{"a":1}', '$.a', 2, '$.b', 3)"#,
+                r#"{"a":1,"b":3}"#,
+            ),
+            (
+                r#"json_replace('{"a":1}', '$.a', 2, '$.b', 3)"#,
+                r#"{"a":2}"#,
+            ),
+            (
+                r#"json_remove('{"a":1,"b":[1,2,3]}', '$.a', '$.b[1]')"#,
+                r#"{"b":[1,3]}"#,
+            ),
+        ] {
+            assert_eq!(eval(expr), text(expected), "{}", expr);
+        }
+
+        let db = Database::new("test_db".to_string());
+        for sql in [
+            "SELECT json('{bad');",
+            "SELECT json_object('a');",
+            "SELECT json_object(1, 2);",
+            "SELECT json_extract('[]', 'a');",
+            "SELECT json_set('{}', '$.a');",
+        ] {
+            assert!(run_query(sql, &db).is_err(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_json_each_and_tree() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT key, value, type, atom, fullkey FROM json_each('{\"a\":1,\"b\":[2,3]}');"
+            ),
+            vec![
+                vec![
+                    text("a"),
+                    Value::Integer(1),
+                    text("integer"),
+                    Value::Integer(1),
+                    text("$.a")
+                ],
+                vec![
+                    text("b"),
+                    text("[2,3]"),
+                    text("array"),
+                    Value::Null,
+                    text("$.b")
+                ],
+            ]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT key, value FROM json_each('[5,6]', '$') WHERE value > 5;"
+            ),
+            vec![vec![Value::Integer(1), Value::Integer(6)]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT id, parent, key, fullkey, path FROM json_tree('{\"a\":[7]}');"
+            ),
+            vec![
+                vec![
+                    Value::Integer(0),
+                    Value::Null,
+                    Value::Null,
+                    text("$"),
+                    text("$")
+                ],
+                vec![
+                    Value::Integer(1),
+                    Value::Integer(0),
+                    text("a"),
+                    text("$.a"),
+                    text("$")
+                ],
+                vec![
+                    Value::Integer(2),
+                    Value::Integer(1),
+                    Value::Integer(0),
+                    text("$.a[0]"),
+                    text("$.a")
+                ],
+            ]
+        );
+
+        // Each order's items, with the function reading the row it is joined with.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT o.customer, item.value ->> 'sku', item.value ->> 'qty' \
+                 FROM orders o, json_each(o.payload, '$.items') AS item ORDER BY o.id, item.key;"
+            ),
+            vec![
+                vec![text("ann"), text("a1"), Value::Integer(2)],
+                vec![text("ann"), text("b2"), Value::Integer(1)],
+                vec![text("bob"), text("a1"), Value::Integer(5)],
+            ]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT o.customer, COUNT(j.key) FROM orders o \
+                 LEFT JOIN json_each(o.payload, '$.items') j ON j.value ->> 'qty' > 1 \
+                 GROUP BY o.customer ORDER BY o.customer;"
+            ),
+            vec![
+                vec![text("ann"), Value::Integer(1)],
+                vec![text("bob"), Value::Integer(1)],
+                vec![text("cid"), Value::Integer(0)],
+            ]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT customer FROM orders WHERE EXISTS \
+                 (SELECT 1 FROM json_each(payload, '$.items') WHERE value ->> 'sku' = 'b2');"
+            ),
+            vec![vec![text("ann")]]
+        );
+        assert!(run_query("SELECT * FROM no_such_function(1);", &db).is_err());
+    }
+
+    #[test]
+    fn test_json_group_aggregates() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT json_group_array(customer), json_group_array(payload ->> 'total') FROM orders;"
+            ),
+            vec![vec![text(r#"["ann","bob","cid"]"#), text("[30.5,12,null]")]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT item.value ->> 'sku' AS sku, \
+                 json_group_object(o.customer, item.value -> 'qty') \
+                 FROM orders o, json_each(o.payload, '$.items') AS item GROUP BY sku ORDER BY sku;"
+            ),
+            vec![
+                vec![text("a1"), text(r#"{"ann":2,"bob":5}"#)],
+                vec![text("b2"), text(r#"{"ann":1}"#)],
+            ]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT json_group_array(json_object('id', id)) FROM orders WHERE id > 5;"
+            ),
+            vec![vec![text("[]")]]
+        );
+        // Values of a JSON column are embedded as JSON, text of other columns is quoted.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT json_group_array(payload) FROM orders WHERE id <> 1;"
+            ),
+            vec![vec![text(
+                r#"[{"total":12,"items":[{"sku":"a1","qty":5}]},null]"#
+            )]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT json_group_array(payload -> 'gift'), json_group_object(customer, o.payload), \
+                 json_group_array(json_extract(payload, '$.items[0].sku')) FROM orders o WHERE id < 3;"
+            ),
+            vec![vec![
+                text("[true,null]"),
+                text(
+                    r#"{"ann":{"total":30.5,"items":[{"sku":"a1","qty":2},{"sku":"b2","qty":1}],"gift":true},"bob":{"total":12,"items":[{"sku":"a1","qty":5}]}}"#
+                ),
+                text(r#"["a1","a1"]"#),
+            ]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT json_group_array(payload) OVER (ORDER BY id), json_array(payload, customer) \
+                 FROM orders WHERE id = 2;"
+            ),
+            vec![vec![
+                text(r#"[{"total":12,"items":[{"sku":"a1","qty":5}]}]"#),
+                text(r#"[{"total":12,"items":[{"sku":"a1","qty":5}]},"bob"]"#),
+            ]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT json_group_array(doc) FROM (SELECT customer AS doc FROM orders WHERE id = 1);"
+            ),
+            vec![vec![text(r#"["ann"]"#)]]
+        );
+    }
+}
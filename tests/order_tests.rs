@@ -0,0 +1,282 @@
+mod common;
+
+use common::{integers, select};
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+use tempfile::tempdir;
+
+#[cfg(test)]
+mod order_tests {
+    use super::*;
+
+    fn setup_people() -> Database {
+        let mut db = Database::new("test_db".to_string());
+        process_command(
+            "CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT, age INTEGER, score REAL);",
+            &mut db,
+        )
+        .unwrap();
+        process_command(
+            "INSERT INTO people (name, age, score) VALUES \
+             ('Dave', 30, 1.5), ('alice', NULL, 9.0), ('Bob', 25, NULL), \
+             ('Carol', 30, 4.25), (NULL, 41, 2.0);",
+            &mut db,
+        )
+        .unwrap();
+        db
+    }
+
+    #[test]
+    fn test_order_by_indexed_column() {
+        let db = setup_people();
+        // NULL sorts first ascending and last descending; ties keep rowid order.
+        assert_eq!(
+            integers(&db, "SELECT id FROM people ORDER BY age;"),
+            vec![2, 3, 1, 4, 5]
+        );
+        assert_eq!(
+            integers(&db, "SELECT id FROM people ORDER BY age DESC;"),
+            vec![5, 1, 4, 3, 2]
+        );
+        assert_eq!(
+            integers(&db, "SELECT id FROM people ORDER BY name;"),
+            vec![5, 3, 4, 1, 2]
+        );
+    }
+
+    #[test]
+    fn test_nulls_first_and_last() {
+        let db = setup_people();
+        assert_eq!(
+            integers(&db, "SELECT id FROM people ORDER BY age NULLS LAST;"),
+            vec![3, 1, 4, 5, 2]
+        );
+        assert_eq!(
+            integers(&db, "SELECT id FROM people ORDER BY age DESC NULLS FIRST;"),
+            vec![2, 5, 1, 4, 3]
+        );
+        // REAL columns have no index and go through the sorter.
+        assert_eq!(
+            integers(&db, "SELECT id FROM people ORDER BY score NULLS LAST;"),
+            vec![1, 5, 4, 2, 3]
+        );
+        assert_eq!(
+            integers(&db, "SELECT id FROM people ORDER BY score DESC;"),
+            vec![2, 4, 5, 1, 3]
+        );
+    }
+
+    #[test]
+    fn test_index_walk_matches_sorting() {
+        let mut db = Database::new("test_db".to_string());
+        process_command(
+            "CREATE TABLE nums (id INTEGER PRIMARY KEY, n INTEGER, t TEXT);",
+            &mut db,
+        )
+        .unwrap();
+        let mut seed: u64 = 42;
+        for _ in 0..200 {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            let n = (seed >> 33) % 50;
+            let value = if n.is_multiple_of(7) {
+                "NULL".to_string()
+            } else {
+                n.to_string()
+            };
+            process_command(
+                &format!(
+                    "INSERT INTO nums (n, t) VALUES ({}, 'v{}');",
+                    value,
+                    (seed >> 40) % 30
+                ),
+                &mut db,
+            )
+            .unwrap();
+        }
+
+        // `+n` is not a plain column reference, so it is sorted instead of walked.
+        for order in ["", " DESC", " NULLS LAST", " DESC NULLS FIRST"] {
+            for limit in ["", " LIMIT 17", " LIMIT 10 OFFSET 95"] {
+                let walked = format!("SELECT id FROM nums ORDER BY n{}{};", order, limit);
+                let sorted = format!("SELECT id FROM nums ORDER BY +n{}{};", order, limit);
+                assert_eq!(integers(&db, &walked), integers(&db, &sorted), "{}", walked);
+            }
+        }
+        assert_eq!(
+            integers(
+                &db,
+                "SELECT id FROM nums WHERE n > 10 ORDER BY t DESC LIMIT 5;"
+            ),
+            integers(
+                &db,
+                "SELECT id FROM nums WHERE n > 10 ORDER BY t || '' DESC LIMIT 5;"
+            )
+        );
+    }
+
+    #[test]
+    fn test_index_walk_tracks_nulls_through_changes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("nums.db");
+        let mut db = Database::new("nums".to_string());
+        process_command(
+            "CREATE TABLE nums (id INTEGER PRIMARY KEY, n INTEGER);",
+            &mut db,
+        )
+        .unwrap();
+        process_command(
+            "INSERT INTO nums (n) VALUES (3), (NULL), (1), (NULL), (2), (NULL);",
+            &mut db,
+        )
+        .unwrap();
+        process_command("UPDATE nums SET n = 0 WHERE id = 2;", &mut db).unwrap();
+        process_command("UPDATE nums SET n = NULL WHERE id = 1;", &mut db).unwrap();
+        process_command("UPDATE nums SET id = 10 WHERE id = 4;", &mut db).unwrap();
+        process_command("DELETE FROM nums WHERE id = 6;", &mut db).unwrap();
+        process_command("BEGIN;", &mut db).unwrap();
+        process_command("DELETE FROM nums WHERE n IS NULL;", &mut db).unwrap();
+        process_command("ROLLBACK;", &mut db).unwrap();
+
+        let check = |db: &Database| {
+            for order in ["", " DESC", " NULLS LAST", " DESC NULLS FIRST"] {
+                let walked = format!("SELECT id FROM nums ORDER BY n{};", order);
+                let sorted = format!("SELECT id FROM nums ORDER BY +n{};", order);
+                assert_eq!(integers(db, &walked), integers(db, &sorted), "{}", walked);
+            }
+            assert_eq!(
+                integers(db, "SELECT id FROM nums ORDER BY n LIMIT 3;"),
+                vec![1, 10, 2]
+            );
+        };
+        check(&db);
+        db.save(&path).unwrap();
+        check(&Database::open(&path).unwrap());
+    }
+
+    #[test]
+    fn test_order_by_multiple_keys() {
+        let db = setup_people();
+        assert_eq!(
+            integers(&db, "SELECT id FROM people ORDER BY age DESC, name;"),
+            vec![5, 4, 1, 3, 2]
+        );
+        assert_eq!(
+            integers(&db, "SELECT id FROM people ORDER BY age, score DESC;"),
+            vec![2, 3, 4, 1, 5]
+        );
+    }
+
+    #[test]
+    fn test_order_by_alias_position_and_expression() {
+        let db = setup_people();
+        assert_eq!(
+            integers(&db, "SELECT id, age * -1 AS neg FROM people ORDER BY neg;"),
+            vec![2, 5, 1, 4, 3]
+        );
+        assert_eq!(
+            integers(&db, "SELECT id, score FROM people ORDER BY 2 DESC;"),
+            vec![2, 4, 5, 1, 3]
+        );
+        // Sort keys may use columns that are not selected.
+        assert_eq!(
+            integers(&db, "SELECT id FROM people ORDER BY score * 2 LIMIT 2;"),
+            vec![3, 1]
+        );
+        assert!(run_query("SELECT id FROM people ORDER BY 3;", &db).is_err());
+    }
+
+    #[test]
+    fn test_text_sorts_by_bytes() {
+        let mut db = Database::new("test_db".to_string());
+        process_command("CREATE TABLE t (id INTEGER PRIMARY KEY, v TEXT);", &mut db).unwrap();
+        process_command(
+            "INSERT INTO t (v) VALUES ('10'), ('9'), ('b'), ('B'), (NULL);",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            select(&db, "SELECT v FROM t ORDER BY v;"),
+            vec![
+                vec![Value::Null],
+                vec![Value::Text("10".to_string())],
+                vec![Value::Text("9".to_string())],
+                vec![Value::Text("B".to_string())],
+                vec![Value::Text("b".to_string())],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_limit_and_offset() {
+        let db = setup_people();
+        assert_eq!(
+            integers(&db, "SELECT id FROM people ORDER BY id LIMIT 2;"),
+            vec![1, 2]
+        );
+        assert_eq!(
+            integers(
+                &db,
+                "SELECT id FROM people ORDER BY id DESC LIMIT 2 OFFSET 1;"
+            ),
+            vec![4, 3]
+        );
+        assert_eq!(
+            integers(&db, "SELECT id FROM people LIMIT 3;"),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            integers(&db, "SELECT id FROM people LIMIT 10 OFFSET 4;"),
+            vec![5]
+        );
+        assert_eq!(
+            integers(&db, "SELECT id FROM people LIMIT 0;"),
+            Vec::<i64>::new()
+        );
+        assert_eq!(
+            integers(
+                &db,
+                "SELECT id FROM people WHERE age IS NOT NULL LIMIT 1 + 1 OFFSET 2;"
+            ),
+            vec![4, 5]
+        );
+        // A negative LIMIT means no limit.
+        assert_eq!(
+            integers(&db, "SELECT id FROM people LIMIT -1 OFFSET 3;"),
+            vec![4, 5]
+        );
+        assert!(run_query("SELECT id FROM people LIMIT 'many';", &db).is_err());
+    }
+
+    #[test]
+    fn test_top_n_keeps_first_rows_of_ties() {
+        let db = setup_people();
+        assert_eq!(
+            integers(
+                &db,
+                "SELECT id FROM people ORDER BY score IS NULL, age LIMIT 3;"
+            ),
+            vec![2, 1, 4]
+        );
+        assert_eq!(
+            integers(
+                &db,
+                "SELECT id FROM people ORDER BY age + 0 DESC LIMIT 2 OFFSET 1;"
+            ),
+            vec![1, 4]
+        );
+    }
+
+    #[test]
+    fn test_order_by_on_parenthesized_query() {
+        let db = setup_people();
+        assert_eq!(
+            integers(
+                &db,
+                "(SELECT id, age FROM people) ORDER BY age DESC LIMIT 2;"
+            ),
+            vec![5, 1]
+        );
+        assert!(run_query("(SELECT id FROM people) ORDER BY score;", &db).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use rusql::sql::parser::create::{CreateQuery, ParsedColumn};
+use rusql::sql::parser::create::{CreateQuery, ParsedColumn, ParsedConstraint};
 use sqlparser::dialect::SQLiteDialect;
 use sqlparser::parser::Parser;
 
@@ -98,6 +98,25 @@ mod parser_tests {
         assert_eq!(create_query.columns[4].datatype, "Text");
     }
 
+    #[test]
+    fn test_parse_data_types_by_affinity() {
+        let sql =
+            "CREATE TABLE test (a TINYINT, b CHARACTER VARYING(5), c FLOAT8, d BOOL, e NUMERIC);";
+        let dialect = SQLiteDialect {};
+        let statement = Parser::parse_sql(&dialect, sql).unwrap().pop().unwrap();
+
+        let create_query = CreateQuery::new(&statement).unwrap();
+        let datatypes: Vec<&str> = create_query
+            .columns
+            .iter()
+            .map(|col| col.datatype.as_str())
+            .collect();
+        assert_eq!(
+            datatypes,
+            vec!["Integer", "Text", "Real", "Bool", "Decimal"]
+        );
+    }
+
     #[test]
     fn test_parsed_column_equality() {
         let col1 = ParsedColumn {
@@ -106,6 +125,7 @@ mod parser_tests {
             is_pk: true,
             not_null: true,
             is_unique: true,
+            default: None,
         };
 
         let col2 = ParsedColumn {
@@ -114,6 +134,7 @@ mod parser_tests {
             is_pk: true,
             not_null: true,
             is_unique: true,
+            default: None,
         };
 
         assert_eq!(col1, col2);
@@ -133,4 +154,52 @@ mod parser_tests {
         assert_eq!(create_query.columns.len(), 2);
         assert!(!create_query.columns.iter().any(|col| col.is_pk));
     }
+
+    #[test]
+    fn test_parse_table_constraints() {
+        let sql = "CREATE TABLE members (
+            tenant_id INTEGER,
+            user_id INTEGER,
+            email TEXT,
+            handle TEXT,
+            PRIMARY KEY (tenant_id, user_id),
+            CONSTRAINT one_email UNIQUE (tenant_id, email),
+            UNIQUE (handle)
+        );";
+        let dialect = SQLiteDialect {};
+        let mut ast = Parser::parse_sql(&dialect, sql).unwrap();
+        let statement = ast.pop().unwrap();
+
+        let create_query = CreateQuery::new(&statement).unwrap();
+        assert_eq!(
+            create_query.constraints,
+            vec![
+                ParsedConstraint {
+                    name: None,
+                    columns: vec!["tenant_id".to_string(), "user_id".to_string()],
+                    is_primary: true,
+                },
+                ParsedConstraint {
+                    name: Some("one_email".to_string()),
+                    columns: vec!["tenant_id".to_string(), "email".to_string()],
+                    is_primary: false,
+                },
+            ]
+        );
+        // Composite primary key columns are NOT NULL; a single-column constraint is
+        // stored on its column.
+        assert!(create_query.columns[0].not_null && create_query.columns[1].not_null);
+        assert!(!create_query.columns.iter().any(|col| col.is_pk));
+        assert!(create_query.columns[3].is_unique);
+
+        for sql in [
+            "CREATE TABLE t (a INTEGER, PRIMARY KEY (a, b));",
+            "CREATE TABLE t (a INTEGER, b INTEGER, UNIQUE (a, a));",
+            "CREATE TABLE t (a INTEGER PRIMARY KEY, b INTEGER, PRIMARY KEY (a, b));",
+            "CREATE TABLE t (a INTEGER, b INTEGER, PRIMARY KEY (a, b), PRIMARY KEY (b));",
+        ] {
+            let statement = Parser::parse_sql(&dialect, sql).unwrap().pop().unwrap();
+            assert!(CreateQuery::new(&statement).is_err(), "{}", sql);
+        }
+    }
 }
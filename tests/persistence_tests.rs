@@ -0,0 +1,346 @@
+use rusql::sql::db::database::Database;
+use rusql::sql::db::file_format::{
+    FORMAT_VERSION, LEGACY_JSON_VERSION, MAGIC, NARROW_NUMBERS_VERSION,
+};
+use rusql::sql::db::table::Row;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+use std::fs;
+use tempfile::tempdir;
+
+#[cfg(test)]
+mod persistence_tests {
+    use super::*;
+
+    fn populate(db: &mut Database) {
+        process_command(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE NOT NULL, score REAL);",
+            db,
+        )
+        .unwrap();
+        process_command(
+            "INSERT INTO users (email, score) VALUES ('a@x.com', 1.5), ('b@x.com', NULL);",
+            db,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_save_and_open_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("users.db");
+
+        let mut db = Database::new("users".to_string());
+        populate(&mut db);
+        db.save(&path).unwrap();
+
+        let reopened = Database::open(&path).unwrap();
+        assert_eq!(reopened.db_name, "users");
+        assert_eq!(reopened.path.as_deref(), Some(path.as_path()));
+        assert_eq!(
+            run_query("SELECT id, email, score FROM users;", &reopened)
+                .unwrap()
+                .rows,
+            vec![
+                vec![
+                    Value::Integer(1),
+                    Value::Text("a@x.com".to_string()),
+                    Value::Real(1.5)
+                ],
+                vec![
+                    Value::Integer(2),
+                    Value::Text("b@x.com".to_string()),
+                    Value::Null
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_constraints_and_rowids_survive_reopen() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("users.db");
+
+        let mut db = Database::open(&path).unwrap();
+        populate(&mut db);
+        db.save_to_path().unwrap();
+
+        let mut reopened = Database::open(&path).unwrap();
+        let duplicate = "INSERT INTO users (email) VALUES ('a@x.com');";
+        assert!(process_command(duplicate, &mut reopened).is_err());
+
+        process_command(
+            "INSERT INTO users (email) VALUES ('c@x.com');",
+            &mut reopened,
+        )
+        .unwrap();
+        assert_eq!(
+            run_query("SELECT id FROM users WHERE email = 'c@x.com';", &reopened)
+                .unwrap()
+                .rows,
+            vec![vec![Value::Integer(3)]]
+        );
+    }
+
+    #[test]
+    fn test_open_missing_file_creates_empty_database() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("fresh.db");
+
+        let db = Database::open(&path).unwrap();
+        assert_eq!(db.db_name, "fresh");
+        assert_eq!(db.table_count(), 0);
+        assert!(path.exists());
+
+        let reopened = Database::open(&path).unwrap();
+        assert_eq!(reopened.db_name, "fresh");
+        assert_eq!(reopened.table_count(), 0);
+    }
+
+    #[test]
+    fn test_file_starts_with_header() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("users.db");
+        Database::new("users".to_string()).save(&path).unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(&bytes[..8], MAGIC);
+        assert_eq!(
+            u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            FORMAT_VERSION
+        );
+    }
+
+    #[test]
+    fn test_open_rejects_foreign_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "just some text").unwrap();
+        assert!(Database::open(&path).is_err());
+    }
+
+    #[test]
+    fn test_open_rejects_newer_format_version() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("users.db");
+        Database::new("users".to_string()).save(&path).unwrap();
+
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[8..12].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+
+        let err = Database::open(&path).unwrap_err();
+        assert!(err.to_string().contains("format version"));
+    }
+
+    #[test]
+    fn test_open_rejects_truncated_payload() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("users.db");
+        let mut db = Database::new("users".to_string());
+        populate(&mut db);
+        db.save(&path).unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(Database::open(&path).is_err());
+    }
+
+    #[test]
+    fn test_open_migrates_legacy_json_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("users.db");
+        let mut db = Database::new("users".to_string());
+        populate(&mut db);
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&LEGACY_JSON_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&serde_json::to_vec(&db).unwrap());
+        fs::write(&path, bytes).unwrap();
+
+        let mut reopened = Database::open(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(
+            u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            FORMAT_VERSION
+        );
+        assert_eq!(
+            run_query("SELECT email FROM users WHERE score IS NULL;", &reopened)
+                .unwrap()
+                .rows,
+            vec![vec![Value::Text("b@x.com".to_string())]]
+        );
+        assert!(process_command(
+            "INSERT INTO users (email) VALUES ('a@x.com');",
+            &mut reopened
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_small_page_cache_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("big.db");
+
+        let mut db = Database::open_with_cache(&path, 4).unwrap();
+        process_command(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT NOT NULL);",
+            &mut db,
+        )
+        .unwrap();
+        for i in 0..300 {
+            let query = format!(
+                "INSERT INTO notes (body) VALUES ('note {} {}');",
+                i,
+                "x".repeat(i % 50)
+            );
+            process_command(&query, &mut db).unwrap();
+        }
+        process_command("DELETE FROM notes WHERE id > 250;", &mut db).unwrap();
+        assert!(db.pager().unwrap().borrow().cached_pages() <= 4);
+        db.save_to_path().unwrap();
+        drop(db);
+
+        let reopened = Database::open_with_cache(&path, 4).unwrap();
+        let rows = run_query("SELECT id, body FROM notes;", &reopened)
+            .unwrap()
+            .rows;
+        assert_eq!(rows.len(), 250);
+        assert_eq!(
+            rows[99],
+            vec![
+                Value::Integer(100),
+                Value::Text(format!("note 99 {}", "x".repeat(49)))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drop_table_frees_pages_for_reuse() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("users.db");
+
+        let mut db = Database::open(&path).unwrap();
+        populate(&mut db);
+        db.save_to_path().unwrap();
+        let page_count = db.pager().unwrap().borrow().page_count();
+
+        process_command("DROP TABLE users;", &mut db).unwrap();
+        populate(&mut db);
+        db.save_to_path().unwrap();
+        assert_eq!(db.pager().unwrap().borrow().page_count(), page_count);
+    }
+
+    #[test]
+    fn test_save_copy_to_other_path() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("users.db");
+        let copy_path = dir.path().join("copy.db");
+
+        let mut db = Database::open(&path).unwrap();
+        populate(&mut db);
+        db.save(&copy_path).unwrap();
+
+        let copy = Database::open(&copy_path).unwrap();
+        assert_eq!(
+            run_query("SELECT email FROM users;", &copy).unwrap().rows,
+            vec![
+                vec![Value::Text("a@x.com".to_string())],
+                vec![Value::Text("b@x.com".to_string())],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_open_migrates_narrow_number_cells() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("users.db");
+        let mut db = Database::open(&path).unwrap();
+        populate(&mut db);
+
+        // Rewrite the cells the way version 2 stored them: 4-byte i32 and f32 values.
+        {
+            let pager = db.pager().unwrap();
+            let table = &db.tables["users"];
+            let rows = table.rows.borrow();
+            let narrow_cell = |column: &str, payload: [u8; 4]| {
+                let Row::Paged(column) = &rows[column] else {
+                    panic!("column {} is not paged", column);
+                };
+                let cell = [&[1], &payload[..]].concat();
+                column
+                    .tree
+                    .insert(&mut pager.borrow_mut(), 1, &cell)
+                    .unwrap();
+            };
+            narrow_cell("id", 1i32.to_le_bytes());
+            narrow_cell("score", 1.5f32.to_le_bytes());
+        }
+        db.close().unwrap();
+        drop(db);
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[8..12].copy_from_slice(&NARROW_NUMBERS_VERSION.to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+
+        let reopened = Database::open(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(
+            u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            FORMAT_VERSION
+        );
+        assert_eq!(
+            run_query(
+                "SELECT id, email, score FROM users ORDER BY email;",
+                &reopened
+            )
+            .unwrap()
+            .rows,
+            vec![
+                vec![
+                    Value::Integer(1),
+                    Value::Text("a@x.com".to_string()),
+                    Value::Real(1.5)
+                ],
+                vec![
+                    Value::Integer(2),
+                    Value::Text("b@x.com".to_string()),
+                    Value::Null
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_64_bit_values_survive_reopen() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("ledger.db");
+        let mut db = Database::open(&path).unwrap();
+        process_command(
+            "CREATE TABLE ledger (id INTEGER PRIMARY KEY, cents BIGINT, rate DOUBLE);",
+            &mut db,
+        )
+        .unwrap();
+        process_command(
+            "INSERT INTO ledger VALUES (5000000000, -9000000000000000000, 0.1);",
+            &mut db,
+        )
+        .unwrap();
+        db.close().unwrap();
+        drop(db);
+
+        let reopened = Database::open(&path).unwrap();
+        assert_eq!(
+            run_query(
+                "SELECT id, cents, rate FROM ledger WHERE id = 5000000000;",
+                &reopened
+            )
+            .unwrap()
+            .rows,
+            vec![vec![
+                Value::Integer(5_000_000_000),
+                Value::Integer(-9_000_000_000_000_000_000),
+                Value::Real(0.1)
+            ]]
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+
+#[cfg(test)]
+mod select_tests {
+    use super::*;
+
+    fn setup_users() -> Database {
+        let mut db = Database::new("test_db".to_string());
+        process_command(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, age INTEGER);",
+            &mut db,
+        )
+        .unwrap();
+        process_command(
+            "INSERT INTO users (name, age) VALUES ('Alice', 30);",
+            &mut db,
+        )
+        .unwrap();
+        process_command("INSERT INTO users (name, age) VALUES ('Bob', 25);", &mut db).unwrap();
+        process_command(
+            "INSERT INTO users (name, age) VALUES ('Carol', 41);",
+            &mut db,
+        )
+        .unwrap();
+        db
+    }
+
+    #[test]
+    fn test_select_star() {
+        let db = setup_users();
+        let result = run_query("SELECT * FROM users;", &db).unwrap();
+
+        assert_eq!(result.columns, vec!["id", "name", "age"]);
+        assert_eq!(result.rows.len(), 3);
+        assert_eq!(
+            result.rows[0],
+            vec![
+                Value::Integer(1),
+                Value::Text("Alice".to_string()),
+                Value::Integer(30)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_column_list() {
+        let db = setup_users();
+        let result = run_query("SELECT age, name FROM users;", &db).unwrap();
+
+        assert_eq!(result.columns, vec!["age", "name"]);
+        assert_eq!(
+            result.rows[1],
+            vec![Value::Integer(25), Value::Text("Bob".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_select_with_aliases() {
+        let db = setup_users();
+        let result = run_query("SELECT u.name AS username, age years FROM users u;", &db).unwrap();
+
+        assert_eq!(result.columns, vec!["username", "years"]);
+        assert_eq!(result.rows.len(), 3);
+        assert_eq!(result.rows[2][0], Value::Text("Carol".to_string()));
+    }
+
+    #[test]
+    fn test_select_qualified_wildcard() {
+        let db = setup_users();
+        let result = run_query("SELECT users.* FROM users;", &db).unwrap();
+
+        assert_eq!(result.columns.len(), 3);
+        assert_eq!(result.rows.len(), 3);
+    }
+
+    #[test]
+    fn test_select_rows_ordered_by_rowid() {
+        let mut db = Database::new("test_db".to_string());
+        process_command(
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT);",
+            &mut db,
+        )
+        .unwrap();
+        process_command("INSERT INTO items (id, name) VALUES (20, 'b');", &mut db).unwrap();
+        process_command("INSERT INTO items (id, name) VALUES (10, 'a');", &mut db).unwrap();
+
+        let result = run_query("SELECT id FROM items;", &db).unwrap();
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Integer(10)], vec![Value::Integer(20)]]
+        );
+    }
+
+    #[test]
+    fn test_select_from_empty_table() {
+        let mut db = Database::new("test_db".to_string());
+        process_command("CREATE TABLE empty (id INTEGER);", &mut db).unwrap();
+
+        let result = run_query("SELECT * FROM empty;", &db).unwrap();
+        assert_eq!(result.columns, vec!["id"]);
+        assert!(result.rows.is_empty());
+    }
+
+    #[test]
+    fn test_select_unknown_column() {
+        let db = setup_users();
+        assert!(run_query("SELECT email FROM users;", &db).is_err());
+        assert!(run_query("SELECT other.name FROM users;", &db).is_err());
+    }
+
+    #[test]
+    fn test_select_unknown_table() {
+        let db = setup_users();
+        assert!(run_query("SELECT * FROM nonexistent;", &db).is_err());
+    }
+
+    #[test]
+    fn test_process_command_select() {
+        let mut db = setup_users();
+        let result = process_command("SELECT name FROM users;", &mut db);
+        assert!(result.is_ok());
+        assert!(result.unwrap().contains("3 row(s)"));
+    }
+
+    #[test]
+    fn test_select_where_comparison() {
+        let db = setup_users();
+        let result = run_query("SELECT name FROM users WHERE age > 28;", &db).unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Text("Alice".to_string())],
+                vec![Value::Text("Carol".to_string())]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_where_compound_condition() {
+        let db = setup_users();
+        let result = run_query(
+            "SELECT id FROM users WHERE (age BETWEEN 20 AND 35 AND name LIKE '%o%') OR id IN (3);",
+            &db,
+        )
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Integer(2)], vec![Value::Integer(3)]]
+        );
+    }
+
+    #[test]
+    fn test_select_where_no_match() {
+        let db = setup_users();
+        let result = run_query("SELECT * FROM users WHERE name GLOB 'z*';", &db).unwrap();
+        assert!(result.rows.is_empty());
+    }
+
+    #[test]
+    fn test_select_expressions() {
+        let db = setup_users();
+        let result = run_query(
+            "SELECT name || '!' AS shout, age + 1 FROM users WHERE id = 1;",
+            &db,
+        )
+        .unwrap();
+        assert_eq!(result.columns, vec!["shout", "age + 1"]);
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Text("Alice!".to_string()), Value::Integer(31)]]
+        );
+    }
+
+    #[test]
+    fn test_select_where_unknown_column() {
+        let db = setup_users();
+        assert!(run_query("SELECT * FROM users WHERE salary > 1;", &db).is_err());
+    }
+}
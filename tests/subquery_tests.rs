@@ -0,0 +1,237 @@
+mod common;
+
+use common::{column, database, select, text};
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+
+#[cfg(test)]
+mod subquery_tests {
+    use super::*;
+
+    fn setup() -> Database {
+        database(&[
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, team INTEGER);",
+            "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER, amount INTEGER);",
+            "INSERT INTO users (name, team) VALUES ('alice', 1), ('bob', 2), ('carol', 1), ('dave', NULL);",
+            "INSERT INTO orders (user_id, amount) VALUES (1, 10), (1, 30), (3, 5), (NULL, 7);",
+        ])
+    }
+
+    #[test]
+    fn test_scalar_subquery() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT (SELECT MAX(amount) FROM orders), (SELECT name FROM users WHERE id = 2);"
+            ),
+            vec![vec![Value::Integer(30), text("bob")]]
+        );
+        // No rows gives NULL, several rows give the first.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT (SELECT name FROM users WHERE id > 10), (SELECT name FROM users ORDER BY name DESC);"
+            ),
+            vec![vec![Value::Null, text("dave")]]
+        );
+        assert_eq!(
+            column(
+                &db,
+                "SELECT user_id FROM orders WHERE amount = (SELECT MIN(amount) FROM orders);"
+            ),
+            vec![Value::Integer(3)]
+        );
+    }
+
+    #[test]
+    fn test_in_subquery() {
+        let db = setup();
+        assert_eq!(
+            column(
+                &db,
+                "SELECT name FROM users WHERE id IN (SELECT user_id FROM orders);"
+            ),
+            vec![text("alice"), text("carol")]
+        );
+        // The NULL user_id makes NOT IN unknown for every other row.
+        assert!(column(
+            &db,
+            "SELECT name FROM users WHERE id NOT IN (SELECT user_id FROM orders);"
+        )
+        .is_empty());
+        assert_eq!(
+            column(
+                &db,
+                "SELECT name FROM users WHERE id NOT IN (SELECT user_id FROM orders WHERE user_id IS NOT NULL);"
+            ),
+            vec![text("bob"), text("dave")]
+        );
+    }
+
+    #[test]
+    fn test_exists() {
+        let db = setup();
+        assert_eq!(
+            column(
+                &db,
+                "SELECT name FROM users u WHERE EXISTS (SELECT 1 FROM orders o WHERE o.user_id = u.id);"
+            ),
+            vec![text("alice"), text("carol")]
+        );
+        assert_eq!(
+            column(
+                &db,
+                "SELECT name FROM users u WHERE NOT EXISTS (SELECT * FROM orders WHERE user_id = u.id);"
+            ),
+            vec![text("bob"), text("dave")]
+        );
+    }
+
+    #[test]
+    fn test_correlated_subqueries() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT name, (SELECT SUM(amount) FROM orders WHERE user_id = users.id) FROM users;"
+            ),
+            vec![
+                vec![text("alice"), Value::Integer(40)],
+                vec![text("bob"), Value::Null],
+                vec![text("carol"), Value::Integer(5)],
+                vec![text("dave"), Value::Null],
+            ]
+        );
+        // Orders above their user's average, with inner names shadowing outer ones.
+        assert_eq!(
+            column(
+                &db,
+                "SELECT id FROM orders o WHERE amount > (SELECT AVG(amount) FROM orders WHERE user_id = o.user_id);"
+            ),
+            vec![Value::Integer(2)]
+        );
+        // Teammates, two levels deep.
+        assert_eq!(
+            column(
+                &db,
+                "SELECT name FROM users a WHERE EXISTS (SELECT 1 FROM users b WHERE b.team = a.team AND b.id <> a.id \
+                 AND EXISTS (SELECT 1 FROM orders WHERE user_id = b.id AND amount > a.id));"
+            ),
+            vec![text("alice"), text("carol")]
+        );
+    }
+
+    #[test]
+    fn test_subquery_in_aggregate_query() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT team, COUNT(*), (SELECT COUNT(*) FROM orders WHERE user_id IN \
+                 (SELECT id FROM users WHERE team = t.team)) FROM users t WHERE team IS NOT NULL GROUP BY team;"
+            ),
+            vec![
+                vec![Value::Integer(1), Value::Integer(2), Value::Integer(3)],
+                vec![Value::Integer(2), Value::Integer(1), Value::Integer(0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_derived_tables() {
+        let db = setup();
+        let result = run_query(
+            "SELECT t.who, t.total FROM (SELECT user_id AS who, SUM(amount) AS total FROM orders GROUP BY user_id) AS t \
+             WHERE t.total > 6 ORDER BY total DESC;",
+            &db,
+        )
+        .unwrap();
+        assert_eq!(result.columns, vec!["who", "total"]);
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Integer(1), Value::Integer(40)],
+                vec![Value::Null, Value::Integer(7)],
+            ]
+        );
+        // Columns renamed by the alias, and a derived table without one.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT b FROM (SELECT id, name FROM users) t(a, b) WHERE a = 3;"
+            ),
+            vec![vec![text("carol")]]
+        );
+        assert_eq!(
+            select(
+                &db,
+                "SELECT COUNT(*) FROM (SELECT * FROM orders WHERE amount > 6);"
+            ),
+            vec![vec![Value::Integer(3)]]
+        );
+        assert!(run_query("SELECT * FROM (SELECT id, name FROM users) t(a);", &db).is_err());
+    }
+
+    #[test]
+    fn test_derived_table_in_join() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT u.name, s.total FROM users u \
+                 LEFT JOIN (SELECT user_id, SUM(amount) AS total FROM orders GROUP BY user_id) s \
+                 ON s.user_id = u.id;"
+            ),
+            vec![
+                vec![text("alice"), Value::Integer(40)],
+                vec![text("bob"), Value::Null],
+                vec![text("carol"), Value::Integer(5)],
+                vec![text("dave"), Value::Null],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subqueries_in_update_and_delete() {
+        let mut db = setup();
+        process_command(
+            "UPDATE users SET team = (SELECT SUM(amount) FROM orders WHERE user_id = users.id) \
+             WHERE id IN (SELECT user_id FROM orders);",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            select(&db, "SELECT team FROM users ORDER BY id;"),
+            vec![
+                vec![Value::Integer(40)],
+                vec![Value::Integer(2)],
+                vec![Value::Integer(5)],
+                vec![Value::Null],
+            ]
+        );
+        process_command(
+            "DELETE FROM orders WHERE amount < (SELECT AVG(amount) FROM orders);",
+            &mut db,
+        )
+        .unwrap();
+        assert_eq!(
+            column(&db, "SELECT amount FROM orders;"),
+            vec![Value::Integer(30)]
+        );
+    }
+
+    #[test]
+    fn test_subquery_errors() {
+        let db = setup();
+        for sql in [
+            "SELECT (SELECT id, name FROM users);",
+            "SELECT name FROM users WHERE id IN (SELECT id, team FROM users);",
+            "SELECT * FROM (SELECT missing FROM users) t;",
+            "SELECT name FROM users WHERE EXISTS (SELECT 1 FROM orders WHERE nope = 1);",
+        ] {
+            assert!(run_query(sql, &db).is_err(), "{}", sql);
+        }
+    }
+}
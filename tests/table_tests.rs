@@ -1,4 +1,6 @@
+use rusql::error::RUSQLError;
 use rusql::sql::db::table::{Column, DataType, Table};
+use rusql::sql::db::value::Value;
 use rusql::sql::parser::create::{CreateQuery, ParsedColumn};
 
 #[cfg(test)]
@@ -27,6 +29,7 @@ mod table_tests {
                     is_pk: true,
                     not_null: true,
                     is_unique: true,
+                    default: None,
                 },
                 ParsedColumn {
                     name: "name".to_string(),
@@ -34,8 +37,10 @@ mod table_tests {
                     is_pk: false,
                     not_null: false,
                     is_unique: false,
+                    default: None,
                 },
             ],
+            constraints: vec![],
         };
 
         let table = Table::new(create_query);
@@ -56,6 +61,7 @@ mod table_tests {
                     is_pk: true,
                     not_null: true,
                     is_unique: true,
+                    default: None,
                 },
                 ParsedColumn {
                     name: "email".to_string(),
@@ -63,8 +69,10 @@ mod table_tests {
                     is_pk: false,
                     not_null: false,
                     is_unique: true,
+                    default: None,
                 },
             ],
+            constraints: vec![],
         };
 
         let table = Table::new(create_query);
@@ -79,10 +87,10 @@ mod table_tests {
 
         assert_eq!(column.column_name, "id");
         assert_eq!(column.datatype, DataType::Integer);
-        assert_eq!(column.is_pk, true);
-        assert_eq!(column.not_null, true);
-        assert_eq!(column.is_unique, true);
-        assert_eq!(column.is_indexed, true);
+        assert!(column.is_pk);
+        assert!(column.not_null);
+        assert!(column.is_unique);
+        assert!(column.is_indexed);
     }
 
     #[test]
@@ -96,6 +104,7 @@ mod table_tests {
                     is_pk: true,
                     not_null: true,
                     is_unique: true,
+                    default: None,
                 },
                 ParsedColumn {
                     name: "name".to_string(),
@@ -103,6 +112,7 @@ mod table_tests {
                     is_pk: false,
                     not_null: false,
                     is_unique: false,
+                    default: None,
                 },
                 ParsedColumn {
                     name: "age".to_string(),
@@ -110,15 +120,17 @@ mod table_tests {
                     is_pk: false,
                     not_null: false,
                     is_unique: false,
+                    default: None,
                 },
             ],
+            constraints: vec![],
         };
 
         let mut table = Table::new(create_query);
         let cols = vec!["name".to_string(), "age".to_string()];
-        let values = vec!["John".to_string(), "25".to_string()];
+        let values = vec![Value::Text("John".to_string()), Value::Integer(25)];
 
-        table.insert_row(&cols, &values);
+        table.insert_row(&cols, &values).unwrap();
         assert_eq!(table.last_rowid, 1);
     }
 
@@ -133,6 +145,7 @@ mod table_tests {
                     is_pk: true,
                     not_null: true,
                     is_unique: true,
+                    default: None,
                 },
                 ParsedColumn {
                     name: "name".to_string(),
@@ -140,15 +153,17 @@ mod table_tests {
                     is_pk: false,
                     not_null: false,
                     is_unique: false,
+                    default: None,
                 },
             ],
+            constraints: vec![],
         };
 
         let mut table = Table::new(create_query);
         let cols = vec!["id".to_string(), "name".to_string()];
-        let values = vec!["100".to_string(), "Alice".to_string()];
+        let values = vec![Value::Integer(100), Value::Text("Alice".to_string())];
 
-        table.insert_row(&cols, &values);
+        table.insert_row(&cols, &values).unwrap();
         assert_eq!(table.last_rowid, 100);
     }
 
@@ -163,6 +178,7 @@ mod table_tests {
                     is_pk: true,
                     not_null: true,
                     is_unique: true,
+                    default: None,
                 },
                 ParsedColumn {
                     name: "email".to_string(),
@@ -170,20 +186,79 @@ mod table_tests {
                     is_pk: false,
                     not_null: false,
                     is_unique: true,
+                    default: None,
                 },
             ],
+            constraints: vec![],
         };
 
         let mut table = Table::new(create_query);
 
         // Insert first row
         let cols = vec!["email".to_string()];
-        let values = vec!["test@example.com".to_string()];
-        table.insert_row(&cols, &values);
+        let values = vec![Value::Text("test@example.com".to_string())];
+        table.insert_row(&cols, &values).unwrap();
 
         // Try to insert duplicate email - should fail
         let result = table.validate_unique_constraint(&cols, &values);
         assert!(result.is_err());
+
+        // Inserting it reports a key violation, as does reusing the primary key.
+        assert!(matches!(
+            table.insert_row(&cols, &values),
+            Err(RUSQLError::KeyViolation(_))
+        ));
+        let cols = vec!["id".to_string(), "email".to_string()];
+        let values = vec![
+            Value::Integer(1),
+            Value::Text("other@example.com".to_string()),
+        ];
+        assert!(matches!(
+            table.insert_row(&cols, &values),
+            Err(RUSQLError::KeyViolation(_))
+        ));
+    }
+
+    #[test]
+    fn test_insert_row_type_mismatch() {
+        let create_query = CreateQuery {
+            table_name: "users".to_string(),
+            columns: vec![
+                ParsedColumn {
+                    name: "id".to_string(),
+                    datatype: "Integer".to_string(),
+                    is_pk: true,
+                    not_null: true,
+                    is_unique: true,
+                    default: None,
+                },
+                ParsedColumn {
+                    name: "score".to_string(),
+                    datatype: "Real".to_string(),
+                    is_pk: false,
+                    not_null: false,
+                    is_unique: false,
+                    default: None,
+                },
+            ],
+            constraints: vec![],
+        };
+
+        let mut table = Table::new(create_query);
+        let cols = vec!["score".to_string()];
+
+        let result = table.insert_row(&cols, &[Value::Text("abc".to_string())]);
+        assert!(result.is_err());
+        assert_eq!(table.last_rowid, 0);
+        assert!(table.rowids().unwrap().is_empty());
+
+        table
+            .insert_row(&cols, &[Value::Text("1.5".to_string())])
+            .unwrap();
+        assert_eq!(
+            table.get_row_values(1).unwrap(),
+            vec![Value::Integer(1), Value::Real(1.5)]
+        );
     }
 
     #[test]
@@ -196,7 +271,9 @@ mod table_tests {
                 is_pk: true,
                 not_null: true,
                 is_unique: true,
+                default: None,
             }],
+            constraints: vec![],
         };
 
         let table = Table::new(create_query);
@@ -218,6 +295,7 @@ mod table_tests {
                     is_pk: true,
                     not_null: true,
                     is_unique: true,
+                    default: None,
                 },
                 ParsedColumn {
                     name: "name".to_string(),
@@ -225,8 +303,10 @@ mod table_tests {
                     is_pk: false,
                     not_null: false,
                     is_unique: false,
+                    default: None,
                 },
             ],
+            constraints: vec![],
         };
 
         let table = Table::new(create_query);
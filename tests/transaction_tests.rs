@@ -0,0 +1,245 @@
+mod common;
+
+use common::{run, select};
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::process_command;
+use tempfile::tempdir;
+
+#[cfg(test)]
+mod transaction_tests {
+    use super::*;
+
+    fn setup_users() -> Database {
+        let mut db = Database::new("test_db".to_string());
+        process_command(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE, age INTEGER NOT NULL);",
+            &mut db,
+        )
+        .unwrap();
+        process_command(
+            "INSERT INTO users (email, age) VALUES ('a@x.com', 30), ('b@x.com', 25);",
+            &mut db,
+        )
+        .unwrap();
+        db
+    }
+
+    fn emails(db: &Database) -> Vec<String> {
+        select(db, "SELECT email FROM users;")
+            .into_iter()
+            .map(|row| row[0].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_failed_multi_row_insert_leaves_table_unchanged() {
+        let mut db = setup_users();
+        let insert =
+            "INSERT INTO users (email, age) VALUES ('c@x.com', 1), ('d@x.com', 2), ('a@x.com', 3);";
+        assert!(process_command(insert, &mut db).is_err());
+        assert_eq!(emails(&db), vec!["a@x.com", "b@x.com"]);
+
+        // The rowids handed out by the failed statement are reused.
+        run(
+            &mut db,
+            "INSERT INTO users (email, age) VALUES ('c@x.com', 1);",
+        );
+        assert_eq!(
+            select(&db, "SELECT id FROM users WHERE email = 'c@x.com';"),
+            vec![vec![Value::Integer(3)]]
+        );
+    }
+
+    #[test]
+    fn test_failed_update_leaves_every_row_unchanged() {
+        let mut db = setup_users();
+        // The first row takes the new email, the second one then violates UNIQUE.
+        let update = "UPDATE users SET email = 'same@x.com', age = age + 1;";
+        assert!(process_command(update, &mut db).is_err());
+        assert_eq!(
+            select(&db, "SELECT email, age FROM users;"),
+            vec![
+                vec![Value::Text("a@x.com".to_string()), Value::Integer(30)],
+                vec![Value::Text("b@x.com".to_string()), Value::Integer(25)],
+            ]
+        );
+        run(
+            &mut db,
+            "UPDATE users SET email = 'same@x.com' WHERE id = 2;",
+        );
+    }
+
+    #[test]
+    fn test_commit_keeps_changes() {
+        let mut db = setup_users();
+        run(&mut db, "BEGIN;");
+        assert!(db.in_transaction());
+        run(
+            &mut db,
+            "INSERT INTO users (email, age) VALUES ('c@x.com', 40);",
+        );
+        run(&mut db, "DELETE FROM users WHERE email = 'a@x.com';");
+        run(&mut db, "COMMIT;");
+        assert!(!db.in_transaction());
+        assert_eq!(emails(&db), vec!["b@x.com", "c@x.com"]);
+    }
+
+    #[test]
+    fn test_rollback_undoes_every_statement() {
+        let mut db = setup_users();
+        run(&mut db, "BEGIN TRANSACTION;");
+        run(
+            &mut db,
+            "INSERT INTO users (email, age) VALUES ('c@x.com', 40);",
+        );
+        run(
+            &mut db,
+            "UPDATE users SET id = 10, age = 31 WHERE email = 'a@x.com';",
+        );
+        run(&mut db, "DELETE FROM users WHERE email = 'b@x.com';");
+        run(
+            &mut db,
+            "CREATE TABLE posts (id INTEGER PRIMARY KEY, title TEXT);",
+        );
+        run(&mut db, "ROLLBACK;");
+
+        assert!(!db.contains_table("posts".to_string()));
+        assert_eq!(
+            select(&db, "SELECT id, email, age FROM users;"),
+            vec![
+                vec![
+                    Value::Integer(1),
+                    Value::Text("a@x.com".to_string()),
+                    Value::Integer(30)
+                ],
+                vec![
+                    Value::Integer(2),
+                    Value::Text("b@x.com".to_string()),
+                    Value::Integer(25)
+                ],
+            ]
+        );
+        // Indexes were restored along with the rows.
+        assert!(process_command(
+            "INSERT INTO users (email, age) VALUES ('b@x.com', 1);",
+            &mut db
+        )
+        .is_err());
+        run(
+            &mut db,
+            "INSERT INTO users (email, age) VALUES ('c@x.com', 1);",
+        );
+    }
+
+    #[test]
+    fn test_rollback_restores_dropped_table() {
+        let mut db = setup_users();
+        run(&mut db, "BEGIN;");
+        run(&mut db, "DROP TABLE users;");
+        assert!(!db.contains_table("users".to_string()));
+        run(&mut db, "ROLLBACK;");
+        assert_eq!(emails(&db), vec!["a@x.com", "b@x.com"]);
+    }
+
+    #[test]
+    fn test_failed_statement_inside_transaction_keeps_earlier_work() {
+        let mut db = setup_users();
+        run(&mut db, "BEGIN;");
+        run(
+            &mut db,
+            "INSERT INTO users (email, age) VALUES ('c@x.com', 40);",
+        );
+        let insert = "INSERT INTO users (email, age) VALUES ('d@x.com', 1), ('e@x.com', NULL);";
+        assert!(process_command(insert, &mut db).is_err());
+        assert!(db.in_transaction());
+        run(&mut db, "COMMIT;");
+        assert_eq!(emails(&db), vec!["a@x.com", "b@x.com", "c@x.com"]);
+    }
+
+    #[test]
+    fn test_savepoints() {
+        let mut db = setup_users();
+        run(&mut db, "BEGIN;");
+        run(
+            &mut db,
+            "INSERT INTO users (email, age) VALUES ('c@x.com', 1);",
+        );
+        run(&mut db, "SAVEPOINT one;");
+        run(
+            &mut db,
+            "INSERT INTO users (email, age) VALUES ('d@x.com', 2);",
+        );
+        run(&mut db, "SAVEPOINT two;");
+        run(
+            &mut db,
+            "INSERT INTO users (email, age) VALUES ('e@x.com', 3);",
+        );
+
+        run(&mut db, "ROLLBACK TO one;");
+        assert_eq!(emails(&db), vec!["a@x.com", "b@x.com", "c@x.com"]);
+        // Rolling back to a savepoint keeps it but forgets later ones.
+        assert!(process_command("RELEASE two;", &mut db).is_err());
+        run(
+            &mut db,
+            "INSERT INTO users (email, age) VALUES ('f@x.com', 4);",
+        );
+        run(&mut db, "ROLLBACK TO SAVEPOINT one;");
+        run(
+            &mut db,
+            "INSERT INTO users (email, age) VALUES ('g@x.com', 5);",
+        );
+        run(&mut db, "RELEASE SAVEPOINT one;");
+        assert!(db.in_transaction());
+
+        run(&mut db, "ROLLBACK;");
+        assert_eq!(emails(&db), vec!["a@x.com", "b@x.com"]);
+    }
+
+    #[test]
+    fn test_savepoint_outside_transaction_commits_on_release() {
+        let mut db = setup_users();
+        run(&mut db, "SAVEPOINT outer;");
+        assert!(db.in_transaction());
+        run(
+            &mut db,
+            "INSERT INTO users (email, age) VALUES ('c@x.com', 1);",
+        );
+        run(&mut db, "RELEASE outer;");
+        assert!(!db.in_transaction());
+        assert_eq!(emails(&db), vec!["a@x.com", "b@x.com", "c@x.com"]);
+    }
+
+    #[test]
+    fn test_transaction_control_errors() {
+        let mut db = setup_users();
+        assert!(process_command("COMMIT;", &mut db).is_err());
+        assert!(process_command("ROLLBACK;", &mut db).is_err());
+        assert!(process_command("ROLLBACK TO nowhere;", &mut db).is_err());
+        run(&mut db, "BEGIN;");
+        assert!(process_command("BEGIN;", &mut db).is_err());
+        assert!(process_command("RELEASE nowhere;", &mut db).is_err());
+        assert!(db.in_transaction());
+    }
+
+    #[test]
+    fn test_close_rolls_back_open_transaction() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("users.db");
+
+        let mut db = Database::open(&path).unwrap();
+        run(
+            &mut db,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT);",
+        );
+        run(&mut db, "INSERT INTO users (email) VALUES ('a@x.com');");
+        run(&mut db, "BEGIN;");
+        run(&mut db, "INSERT INTO users (email) VALUES ('b@x.com');");
+        run(&mut db, "DROP TABLE users;");
+        db.close().unwrap();
+        drop(db);
+
+        let reopened = Database::open(&path).unwrap();
+        assert_eq!(emails(&reopened), vec!["a@x.com"]);
+    }
+}
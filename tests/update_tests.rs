@@ -0,0 +1,146 @@
+mod common;
+
+use common::column;
+use rusql::sql::db::database::Database;
+use rusql::sql::db::table::Index;
+use rusql::sql::db::value::Value;
+use rusql::sql::{process_command, run_query};
+
+#[cfg(test)]
+mod update_tests {
+    use super::*;
+
+    fn setup_users() -> Database {
+        let mut db = Database::new("test_db".to_string());
+        process_command(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE NOT NULL, visits INTEGER);",
+            &mut db,
+        )
+        .unwrap();
+        for (email, visits) in [("a@x.com", 1), ("b@x.com", 5), ("c@x.com", 9)] {
+            process_command(
+                &format!(
+                    "INSERT INTO users (email, visits) VALUES ('{}', {});",
+                    email, visits
+                ),
+                &mut db,
+            )
+            .unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn test_update_with_where() {
+        let mut db = setup_users();
+        let result =
+            process_command("UPDATE users SET visits = 0 WHERE visits > 3;", &mut db).unwrap();
+        assert!(result.contains("2 row(s) affected"));
+        assert_eq!(
+            column(&db, "SELECT visits FROM users;"),
+            vec![Value::Integer(1), Value::Integer(0), Value::Integer(0)]
+        );
+    }
+
+    #[test]
+    fn test_update_references_old_row() {
+        let mut db = setup_users();
+        process_command(
+            "UPDATE users SET visits = visits + 1, email = 'new-' || email WHERE id = 2;",
+            &mut db,
+        )
+        .unwrap();
+
+        let result = run_query("SELECT email, visits FROM users WHERE id = 2;", &db).unwrap();
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::Text("new-b@x.com".to_string()),
+                Value::Integer(6)
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_update_moves_index_entries() {
+        let mut db = setup_users();
+        process_command("UPDATE users SET email = 'z@x.com' WHERE id = 1;", &mut db).unwrap();
+
+        let table = db.get_table("users".to_string()).unwrap();
+        match &table.get_column("email".to_string()).unwrap().index {
+            Index::Text(index) => {
+                assert!(!index.contains_key("a@x.com"));
+                assert!(index.get("z@x.com").unwrap().contains(&1));
+            }
+            _ => panic!("email column should have a text index"),
+        }
+
+        let insert = "INSERT INTO users (email, visits) VALUES ('a@x.com', 0);";
+        assert!(process_command(insert, &mut db).is_ok());
+    }
+
+    #[test]
+    fn test_update_unique_violation() {
+        let mut db = setup_users();
+        let result = process_command("UPDATE users SET email = 'c@x.com' WHERE id = 1;", &mut db);
+        assert!(result.is_err());
+        assert_eq!(
+            column(&db, "SELECT email FROM users WHERE id = 1;"),
+            vec![Value::Text("a@x.com".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_update_same_unique_value_on_same_row() {
+        let mut db = setup_users();
+        assert!(
+            process_command("UPDATE users SET email = 'a@x.com' WHERE id = 1;", &mut db).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_update_not_null_violation() {
+        let mut db = setup_users();
+        let result = process_command("UPDATE users SET email = NULL WHERE id = 1;", &mut db);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_update_nullable_column_to_null() {
+        let mut db = setup_users();
+        process_command("UPDATE users SET visits = NULL WHERE id = 3;", &mut db).unwrap();
+        assert_eq!(
+            column(&db, "SELECT id FROM users WHERE visits IS NULL;"),
+            vec![Value::Integer(3)]
+        );
+    }
+
+    #[test]
+    fn test_update_primary_key() {
+        let mut db = setup_users();
+        process_command("UPDATE users SET id = 10 WHERE id = 2;", &mut db).unwrap();
+        assert_eq!(
+            column(&db, "SELECT id FROM users;"),
+            vec![Value::Integer(1), Value::Integer(3), Value::Integer(10)]
+        );
+        assert_eq!(
+            column(&db, "SELECT email FROM users WHERE id = 10;"),
+            vec![Value::Text("b@x.com".to_string())]
+        );
+
+        assert!(process_command("UPDATE users SET id = 1 WHERE id = 3;", &mut db).is_err());
+    }
+
+    #[test]
+    fn test_update_type_mismatch() {
+        let mut db = setup_users();
+        let result = process_command("UPDATE users SET visits = 'many' WHERE id = 1;", &mut db);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_update_unknown_column() {
+        let mut db = setup_users();
+        assert!(process_command("UPDATE users SET missing = 1;", &mut db).is_err());
+    }
+}
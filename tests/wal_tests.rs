@@ -0,0 +1,212 @@
+mod common;
+
+use common::run;
+use rusql::sql::db::database::Database;
+use rusql::sql::db::pager::wal_path;
+use rusql::sql::db::value::Value;
+use rusql::sql::run_query;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+#[cfg(test)]
+mod wal_tests {
+    use super::*;
+
+    const ROWS: usize = 8;
+
+    fn names(db: &Database) -> Vec<String> {
+        if !db.contains_table("items".to_string()) {
+            return vec![];
+        }
+        run_query("SELECT name FROM items;", db)
+            .unwrap()
+            .rows
+            .into_iter()
+            .map(|row| row[0].to_string())
+            .collect()
+    }
+
+    fn expected(count: usize) -> Vec<String> {
+        (1..=count).map(|i| format!("item {}", i)).collect()
+    }
+
+    /// Creates a database with `ROWS` separately committed inserts and drops it without
+    /// closing, as if the process had crashed. The changes are only in the log.
+    fn crashed_database(path: &Path) {
+        let mut db = Database::open(path).unwrap();
+        run(
+            &mut db,
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT UNIQUE);",
+        );
+        for i in 1..=ROWS {
+            run(
+                &mut db,
+                &format!("INSERT INTO items (name) VALUES ('item {}');", i),
+            );
+        }
+    }
+
+    /// Copies the database file and the first `wal_len` bytes of its log to `copy`.
+    fn copy_with_wal(path: &Path, copy: &Path, wal_len: usize) {
+        fs::copy(path, copy).unwrap();
+        let wal = fs::read(wal_path(path)).unwrap();
+        fs::write(wal_path(copy), &wal[..wal_len]).unwrap();
+    }
+
+    #[test]
+    fn test_committed_changes_survive_crash() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("items.db");
+        crashed_database(&path);
+        assert!(fs::metadata(wal_path(&path)).unwrap().len() > 0);
+
+        let db = Database::open(&path).unwrap();
+        assert_eq!(names(&db), expected(ROWS));
+        // Recovery checkpointed the log into the database file.
+        assert_eq!(db.pager().unwrap().borrow().wal_frames(), 0);
+    }
+
+    #[test]
+    fn test_wal_truncated_at_any_offset_opens_consistently() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("items.db");
+        crashed_database(&path);
+        let wal_len = fs::metadata(wal_path(&path)).unwrap().len() as usize;
+
+        let frame_len = 20 + 4096;
+        let mut offsets: Vec<usize> = (0..wal_len).step_by(1231).collect();
+        for frame in 0..=(wal_len - 20) / frame_len {
+            let boundary = 20 + frame * frame_len;
+            offsets.extend([boundary - 1, boundary, boundary + 1, boundary + 24]);
+        }
+        offsets.push(wal_len);
+        offsets.retain(|offset| *offset <= wal_len);
+        offsets.sort_unstable();
+        offsets.dedup();
+
+        let mut recovered = 0;
+        for (case, offset) in offsets.into_iter().enumerate() {
+            let copy = dir.path().join(format!("case{}.db", case));
+            copy_with_wal(&path, &copy, offset);
+
+            let db = Database::open(&copy).unwrap();
+            let rows = names(&db);
+            // Each commit either made it completely or not at all, in order.
+            assert_eq!(rows, expected(rows.len()), "WAL cut at {}", offset);
+            assert!(rows.len() >= recovered, "WAL cut at {}", offset);
+            recovered = rows.len();
+
+            // The recovered database keeps working.
+            let mut db = db;
+            if db.contains_table("items".to_string()) {
+                run(&mut db, "INSERT INTO items (name) VALUES ('after');");
+            }
+        }
+        assert_eq!(recovered, ROWS);
+    }
+
+    #[test]
+    fn test_corrupt_frame_discards_its_transaction() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("items.db");
+        crashed_database(&path);
+
+        let copy = dir.path().join("copy.db");
+        let mut wal = fs::read(wal_path(&path)).unwrap();
+        let last = wal.len() - 100;
+        wal[last] ^= 0xff;
+        fs::copy(&path, &copy).unwrap();
+        fs::write(wal_path(&copy), wal).unwrap();
+
+        let db = Database::open(&copy).unwrap();
+        assert_eq!(names(&db), expected(ROWS - 1));
+    }
+
+    #[test]
+    fn test_uncommitted_pages_are_discarded() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("items.db");
+        {
+            // A tiny cache evicts uncommitted pages into the log.
+            let mut db = Database::open_with_cache(&path, 2).unwrap();
+            run(
+                &mut db,
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT UNIQUE);",
+            );
+            run(&mut db, "INSERT INTO items (name) VALUES ('item 1');");
+            run(&mut db, "BEGIN;");
+            for i in 2..200 {
+                run(
+                    &mut db,
+                    &format!("INSERT INTO items (name) VALUES ('item {}');", i),
+                );
+            }
+        }
+
+        let db = Database::open(&path).unwrap();
+        assert_eq!(names(&db), expected(1));
+    }
+
+    #[test]
+    fn test_close_checkpoints_into_database_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("items.db");
+        let mut db = Database::open(&path).unwrap();
+        run(
+            &mut db,
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT UNIQUE);",
+        );
+        run(&mut db, "INSERT INTO items (name) VALUES ('item 1');");
+        assert!(db.pager().unwrap().borrow().wal_frames() > 0);
+        db.close().unwrap();
+        assert_eq!(db.pager().unwrap().borrow().wal_frames(), 0);
+        drop(db);
+
+        fs::remove_file(wal_path(&path)).unwrap();
+        let db = Database::open(&path).unwrap();
+        assert_eq!(names(&db), expected(1));
+    }
+
+    #[test]
+    fn test_wal_of_another_database_is_ignored() {
+        let dir = tempdir().unwrap();
+        let crashed = dir.path().join("crashed.db");
+        crashed_database(&crashed);
+
+        let other = dir.path().join("other.db");
+        let mut db = Database::open(&other).unwrap();
+        run(&mut db, "CREATE TABLE notes (body TEXT);");
+        db.close().unwrap();
+        drop(db);
+        fs::copy(wal_path(&crashed), wal_path(&other)).unwrap();
+
+        let db = Database::open(&other).unwrap();
+        assert!(db.contains_table("notes".to_string()));
+        assert!(!db.contains_table("items".to_string()));
+    }
+
+    #[test]
+    fn test_commit_statement_is_durable() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("items.db");
+        {
+            let mut db = Database::open(&path).unwrap();
+            run(
+                &mut db,
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT);",
+            );
+            run(&mut db, "BEGIN;");
+            run(&mut db, "INSERT INTO items (name) VALUES ('item 1');");
+            run(&mut db, "COMMIT;");
+            run(&mut db, "BEGIN;");
+            run(&mut db, "INSERT INTO items (name) VALUES ('item 2');");
+        }
+
+        let db = Database::open(&path).unwrap();
+        assert_eq!(
+            run_query("SELECT id, name FROM items;", &db).unwrap().rows,
+            vec![vec![Value::Integer(1), Value::Text("item 1".to_string())]]
+        );
+    }
+}
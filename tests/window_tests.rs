@@ -0,0 +1,406 @@
+mod common;
+
+use common::{database, ints, select, text};
+use rusql::sql::db::database::Database;
+use rusql::sql::db::value::Value;
+use rusql::sql::run_query;
+
+#[cfg(test)]
+mod window_tests {
+    use super::*;
+
+    fn setup() -> Database {
+        database(&[
+            "CREATE TABLE emp (id INTEGER PRIMARY KEY, name TEXT, dept TEXT, salary INTEGER);",
+            "INSERT INTO emp (name, dept, salary) VALUES \
+             ('ann', 'eng', 100), ('bob', 'eng', 80), ('cid', 'ops', 50), ('dee', 'eng', 100), \
+             ('eve', 'ops', 70), ('fay', 'eng', 60), ('gus', NULL, 40);",
+        ])
+    }
+
+    /// The second column of every row.
+    fn values(db: &Database, sql: &str) -> Vec<Value> {
+        select(db, sql)
+            .into_iter()
+            .map(|row| row[1].clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_ranking_functions() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT name, ROW_NUMBER() OVER (ORDER BY salary DESC), RANK() OVER (ORDER BY salary DESC), \
+                 DENSE_RANK() OVER (ORDER BY salary DESC) FROM emp WHERE dept = 'eng' ORDER BY id;"
+            ),
+            vec![
+                vec![
+                    Value::Text("ann".to_string()),
+                    Value::Integer(1),
+                    Value::Integer(1),
+                    Value::Integer(1)
+                ],
+                vec![
+                    Value::Text("bob".to_string()),
+                    Value::Integer(3),
+                    Value::Integer(3),
+                    Value::Integer(2)
+                ],
+                vec![
+                    Value::Text("dee".to_string()),
+                    Value::Integer(2),
+                    Value::Integer(1),
+                    Value::Integer(1)
+                ],
+                vec![
+                    Value::Text("fay".to_string()),
+                    Value::Integer(4),
+                    Value::Integer(4),
+                    Value::Integer(3)
+                ],
+            ]
+        );
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, PERCENT_RANK() OVER (ORDER BY salary DESC) FROM emp WHERE dept = 'eng' ORDER BY id;"
+            ),
+            vec![
+                Value::Real(0.0),
+                Value::Real(2.0 / 3.0),
+                Value::Real(0.0),
+                Value::Real(1.0)
+            ]
+        );
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, CUME_DIST() OVER (ORDER BY salary DESC) FROM emp WHERE dept = 'eng' ORDER BY id;"
+            ),
+            vec![
+                Value::Real(0.5),
+                Value::Real(0.75),
+                Value::Real(0.5),
+                Value::Real(1.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_partition_by() {
+        let db = setup();
+        // Partitions are numbered separately; the NULL department is a partition too.
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, ROW_NUMBER() OVER (PARTITION BY dept ORDER BY salary) FROM emp ORDER BY id;"
+            ),
+            ints(&[Some(3), Some(2), Some(1), Some(4), Some(2), Some(1), Some(1)])
+        );
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, COUNT(*) OVER (PARTITION BY dept) FROM emp ORDER BY id;"
+            ),
+            ints(&[
+                Some(4),
+                Some(4),
+                Some(2),
+                Some(4),
+                Some(2),
+                Some(4),
+                Some(1)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_ntile() {
+        let db = setup();
+        // Seven rows in three buckets: the first bucket takes the extra row.
+        assert_eq!(
+            values(&db, "SELECT id, NTILE(3) OVER (ORDER BY id) FROM emp;"),
+            ints(&[
+                Some(1),
+                Some(1),
+                Some(1),
+                Some(2),
+                Some(2),
+                Some(3),
+                Some(3)
+            ])
+        );
+        assert_eq!(
+            values(&db, "SELECT id, NTILE(10) OVER (ORDER BY id) FROM emp;"),
+            ints(&[
+                Some(1),
+                Some(2),
+                Some(3),
+                Some(4),
+                Some(5),
+                Some(6),
+                Some(7)
+            ])
+        );
+        assert!(run_query("SELECT NTILE(0) OVER (ORDER BY id) FROM emp;", &db).is_err());
+    }
+
+    #[test]
+    fn test_lag_and_lead() {
+        let db = setup();
+        assert_eq!(
+            values(&db, "SELECT id, LAG(salary) OVER (ORDER BY id) FROM emp;"),
+            ints(&[
+                None,
+                Some(100),
+                Some(80),
+                Some(50),
+                Some(100),
+                Some(70),
+                Some(60)
+            ])
+        );
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, LEAD(salary, 2, -1) OVER (PARTITION BY dept ORDER BY id) FROM emp ORDER BY id;"
+            ),
+            ints(&[Some(100), Some(60), Some(-1), Some(-1), Some(-1), Some(-1), Some(-1)])
+        );
+        // Change from the previous row.
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, salary - LAG(salary, 1, salary) OVER (ORDER BY id) FROM emp;"
+            ),
+            ints(&[
+                Some(0),
+                Some(-20),
+                Some(-30),
+                Some(50),
+                Some(-30),
+                Some(-10),
+                Some(-20)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_running_aggregates() {
+        let db = setup();
+        // The default frame ends at the last peer, so tied salaries share a total.
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, SUM(salary) OVER (ORDER BY salary DESC) FROM emp ORDER BY id;"
+            ),
+            ints(&[
+                Some(200),
+                Some(280),
+                Some(460),
+                Some(200),
+                Some(350),
+                Some(410),
+                Some(500)
+            ])
+        );
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, SUM(salary) OVER (ORDER BY id ROWS UNBOUNDED PRECEDING) FROM emp;"
+            ),
+            ints(&[
+                Some(100),
+                Some(180),
+                Some(230),
+                Some(330),
+                Some(400),
+                Some(460),
+                Some(500)
+            ])
+        );
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, MAX(salary) OVER (PARTITION BY dept ORDER BY id) FROM emp ORDER BY id;"
+            ),
+            ints(&[
+                Some(100),
+                Some(100),
+                Some(50),
+                Some(100),
+                Some(70),
+                Some(100),
+                Some(40)
+            ])
+        );
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, AVG(salary) OVER () FROM emp WHERE dept = 'ops';"
+            ),
+            vec![Value::Real(60.0), Value::Real(60.0)]
+        );
+    }
+
+    #[test]
+    fn test_sliding_frames() {
+        let db = setup();
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, SUM(salary) OVER (ORDER BY id ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING) FROM emp;"
+            ),
+            ints(&[Some(180), Some(230), Some(230), Some(220), Some(230), Some(170), Some(100)])
+        );
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, COUNT(*) OVER (ORDER BY id ROWS BETWEEN 2 FOLLOWING AND UNBOUNDED FOLLOWING) FROM emp;"
+            ),
+            ints(&[Some(5), Some(4), Some(3), Some(2), Some(1), Some(0), Some(0)])
+        );
+        // GROUPS counts peer groups: salaries 100, 80, 70, 60, 50, 40 in descending order.
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, SUM(salary) OVER (ORDER BY salary DESC GROUPS BETWEEN 1 PRECEDING AND CURRENT ROW) \
+                 FROM emp ORDER BY id;"
+            ),
+            ints(&[Some(200), Some(280), Some(110), Some(200), Some(150), Some(130), Some(90)])
+        );
+    }
+
+    #[test]
+    fn test_first_last_and_nth_value() {
+        let db = setup();
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, FIRST_VALUE(name) OVER (PARTITION BY dept ORDER BY salary) FROM emp WHERE dept = 'eng';"
+            ),
+            vec![Value::Text("fay".to_string()); 4]
+        );
+        // The default frame stops at the current row's peers.
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, LAST_VALUE(salary) OVER (ORDER BY id) FROM emp WHERE dept = 'ops';"
+            ),
+            ints(&[Some(50), Some(70)])
+        );
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, LAST_VALUE(salary) OVER (ORDER BY id ROWS BETWEEN CURRENT ROW AND UNBOUNDED FOLLOWING) \
+                 FROM emp WHERE dept = 'ops';"
+            ),
+            ints(&[Some(70), Some(70)])
+        );
+        assert_eq!(
+            values(
+                &db,
+                "SELECT id, NTH_VALUE(name, 2) OVER (ORDER BY id) FROM emp WHERE dept = 'eng';"
+            ),
+            vec![
+                Value::Null,
+                Value::Text("bob".to_string()),
+                Value::Text("bob".to_string()),
+                Value::Text("bob".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_named_windows_and_ordering_by_window_values() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT name, RANK() OVER w AS r FROM emp WHERE dept IS NOT NULL \
+                 WINDOW w AS (PARTITION BY dept ORDER BY salary DESC) ORDER BY r, name LIMIT 4;"
+            ),
+            vec![
+                vec![Value::Text("ann".to_string()), Value::Integer(1)],
+                vec![Value::Text("dee".to_string()), Value::Integer(1)],
+                vec![Value::Text("eve".to_string()), Value::Integer(1)],
+                vec![Value::Text("cid".to_string()), Value::Integer(2)],
+            ]
+        );
+        // Top earner per department, through a derived table.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT dept, name FROM (SELECT dept, name, ROW_NUMBER() OVER (PARTITION BY dept ORDER BY salary DESC, id) AS n \
+                 FROM emp) WHERE n = 1 AND dept IS NOT NULL;"
+            ),
+            vec![
+                vec![Value::Text("eng".to_string()), Value::Text("ann".to_string())],
+                vec![Value::Text("ops".to_string()), Value::Text("eve".to_string())],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_windows_over_groups() {
+        let db = setup();
+        assert_eq!(
+            select(
+                &db,
+                "SELECT dept, SUM(salary), RANK() OVER (ORDER BY SUM(salary) DESC) FROM emp \
+                 GROUP BY dept;"
+            ),
+            vec![
+                vec![Value::Null, Value::Integer(40), Value::Integer(3)],
+                vec![text("eng"), Value::Integer(340), Value::Integer(1)],
+                vec![text("ops"), Value::Integer(120), Value::Integer(2)],
+            ]
+        );
+        // Windows run after HAVING and can be sorted on by their alias.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT dept, SUM(SUM(salary)) OVER (ORDER BY dept) AS running FROM emp \
+                 GROUP BY dept HAVING COUNT(*) > 1 ORDER BY running DESC;"
+            ),
+            vec![
+                vec![text("ops"), Value::Integer(460)],
+                vec![text("eng"), Value::Integer(340)],
+            ]
+        );
+        // An aggregate inside the window alone makes the query an aggregate query.
+        assert_eq!(
+            select(
+                &db,
+                "SELECT COUNT(*), ROW_NUMBER() OVER (ORDER BY MAX(salary)) FROM emp;"
+            ),
+            vec![vec![Value::Integer(7), Value::Integer(1)]]
+        );
+        assert_eq!(
+            values(
+                &db,
+                "SELECT dept, LAG(dept, 1, 'none') OVER (ORDER BY dept) FROM emp \
+                 WHERE dept IS NOT NULL GROUP BY dept;"
+            ),
+            vec![text("none"), text("eng")]
+        );
+    }
+
+    #[test]
+    fn test_window_errors() {
+        let db = setup();
+        for sql in [
+            "SELECT id FROM emp WHERE ROW_NUMBER() OVER (ORDER BY id) > 1;",
+            "SELECT RANK(1) OVER (ORDER BY id) FROM emp;",
+            "SELECT LENGTH(name) OVER () FROM emp;",
+            "SELECT RANK() OVER missing FROM emp;",
+            "SELECT COUNT(DISTINCT dept) OVER () FROM emp;",
+            "SELECT SUM(salary) OVER (ORDER BY id RANGE BETWEEN 1 PRECEDING AND CURRENT ROW) FROM emp;",
+            "SELECT dept FROM emp GROUP BY dept HAVING RANK() OVER (ORDER BY dept) > 1;",
+        ] {
+            assert!(run_query(sql, &db).is_err(), "{}", sql);
+        }
+    }
+}